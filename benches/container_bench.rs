@@ -0,0 +1,166 @@
+//! `Container` 的基准测试
+//!
+//! 各项分别对两种底层映射运行: `hash_map` (默认的 `Container`) 与 `btree_map` (`SortedContainer`)，
+//! 用于比较二者，并在修改后发现性能退化。
+//!
+//! ```bash
+//! cargo bench --bench container_bench
+//! cargo bench --bench container_bench -- get_by_id # 只运行名称匹配的项
+//! ```
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use rust_http_demo::container::{Container, MapBackend};
+use std::{
+    collections::{BTreeMap, HashMap},
+    hint::black_box,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Barrier,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// 读写测试中预先写入的项数
+const PRELOADED: usize = 1000;
+
+fn key(index: usize) -> String {
+    format!("key-{}", index)
+}
+
+/// 写入 `count` 项的容器
+fn filled<M: MapBackend<u64>>(count: usize) -> Arc<Container<u64, M>> {
+    let container = Container::new_arc();
+    for index in 0..count {
+        container.put_by_id(&key(index), index as u64);
+    }
+    container
+}
+
+/// 单线程写入 (覆盖已有的键，不计扩容)
+fn bench_put<M: MapBackend<u64>>(c: &mut Criterion, backend: &str) {
+    let container = filled::<M>(PRELOADED);
+    let keys: Vec<String> = (0..PRELOADED).map(key).collect();
+    let mut group = c.benchmark_group(format!("{}/put_by_id", backend));
+    group.throughput(Throughput::Elements(1));
+    let mut index = 0;
+    group.bench_function("single_thread", |b| b.iter(|| {
+        index = (index + 1) % PRELOADED;
+        container.put_by_id(&keys[index], black_box(index as u64))
+    }));
+    group.finish();
+}
+
+/// `readers` 个线程同时读取，每次迭代各读一次
+fn bench_get<M: MapBackend<u64> + Send + Sync + 'static>(c: &mut Criterion, backend: &str) {
+    let container = filled::<M>(PRELOADED);
+    let mut group = c.benchmark_group(format!("{}/get_by_id", backend));
+    for readers in [1, 10, 100, 1000] {
+        group.throughput(Throughput::Elements(readers as u64));
+        group.bench_with_input(BenchmarkId::new("readers", readers), &readers, |b, &readers| {
+            b.iter_custom(|iters| run_threads(readers, 0, iters, &container))
+        });
+    }
+    group.finish();
+}
+
+/// 取全部项 (复制整个映射)
+fn bench_get_all<M: MapBackend<u64> + Clone>(c: &mut Criterion, backend: &str) {
+    let mut group = c.benchmark_group(format!("{}/get_all", backend));
+    for count in [100, 10_000] {
+        let container = filled::<M>(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("items", count), &count, |b, _| b.iter(|| container.get_all()));
+    }
+    group.finish();
+}
+
+/// 其他线程持续读取时删除 (每次删除前重新写入该项，写入不计时)
+fn bench_delete<M: MapBackend<u64> + Send + Sync + 'static>(c: &mut Criterion, backend: &str) {
+    let container = filled::<M>(PRELOADED);
+    let stop = Arc::new(AtomicBool::new(false));
+    let readers: Vec<_> = (0..4).map(|reader| {
+        let (container, stop) = (container.clone(), stop.clone());
+        thread::spawn(move || {
+            let mut index = reader;
+            while !stop.load(Ordering::Relaxed) {
+                index = (index + 7) % PRELOADED;
+                black_box(container.get_by_id(&key(index)));
+            }
+        })
+    }).collect();
+
+    let mut group = c.benchmark_group(format!("{}/delete_by_id", backend));
+    let mut index = 0;
+    group.bench_function("concurrent_reads", |b| b.iter_batched(
+        || {
+            index = (index + 1) % PRELOADED;
+            let key = key(index);
+            container.put_by_id(&key, index as u64);
+            key
+        },
+        |key| container.delete_by_id(&key),
+        BatchSize::SmallInput,
+    ));
+    group.finish();
+
+    stop.store(true, Ordering::Relaxed);
+    for reader in readers {
+        reader.join().expect("reader panicked");
+    }
+}
+
+/// 8 个写线程与 24 个读线程同时运行，每次迭代各操作一次
+fn bench_concurrent_rw<M: MapBackend<u64> + Send + Sync + 'static>(c: &mut Criterion, backend: &str) {
+    let container = filled::<M>(PRELOADED);
+    let mut group = c.benchmark_group(format!("{}/concurrent_rw", backend));
+    group.throughput(Throughput::Elements(8 + 24));
+    group.bench_function("8w_24r", |b| b.iter_custom(|iters| run_threads(24, 8, iters, &container)));
+    group.finish();
+}
+
+/// 同时启动读、写线程，各执行 `iters` 次操作，返回全部完成的用时
+fn run_threads<M>(readers: usize, writers: usize, iters: u64, container: &Arc<Container<u64, M>>) -> Duration
+where
+    M: MapBackend<u64> + Send + Sync + 'static,
+{
+    let barrier = Barrier::new(readers + writers + 1);
+    thread::scope(|scope| {
+        for thread in 0..readers + writers {
+            let barrier = &barrier;
+            scope.spawn(move || {
+                let write = thread < writers;
+                let keys: Vec<String> = (0..64).map(|offset| key((thread * 64 + offset) % PRELOADED)).collect();
+                barrier.wait();
+                for iter in 0..iters {
+                    let key = &keys[iter as usize % keys.len()];
+                    if write {
+                        container.put_by_id(key, iter);
+                    } else {
+                        black_box(container.get_by_id(key));
+                    }
+                }
+            });
+        }
+        barrier.wait(); // 线程与键都准备好后开始计时
+        let start = Instant::now();
+        // scope 结束时等待所有线程
+        start
+    }).elapsed()
+}
+
+fn container_benches(c: &mut Criterion) {
+    bench_put::<HashMap<String, u64>>(c, "hash_map");
+    bench_put::<BTreeMap<String, u64>>(c, "btree_map");
+    bench_get::<HashMap<String, u64>>(c, "hash_map");
+    bench_get::<BTreeMap<String, u64>>(c, "btree_map");
+    bench_get_all::<HashMap<String, u64>>(c, "hash_map");
+    bench_get_all::<BTreeMap<String, u64>>(c, "btree_map");
+    bench_delete::<HashMap<String, u64>>(c, "hash_map");
+    bench_delete::<BTreeMap<String, u64>>(c, "btree_map");
+    bench_concurrent_rw::<HashMap<String, u64>>(c, "hash_map");
+    bench_concurrent_rw::<BTreeMap<String, u64>>(c, "btree_map");
+}
+
+criterion_group!(benches, container_benches);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+//! 构建脚本
+//!
+//! 把构建信息写入编译期环境变量，由 `src/api/version.rs` 读取:
+//!
+//! - `BUILD_DATE` 构建时间 (UTC，RFC 3339)。设置了 `SOURCE_DATE_EPOCH` 时使用之 (可复现构建)
+//! - `BUILD_RUSTC_VERSION` 编译器版本 (`rustc --version`)
+//! - `BUILD_FEATURES` 启用的 Cargo 特性 (不含 `default`)，逗号分隔
+//! - `VERGEN_GIT_SHA` 当前提交。环境中已设置 (如 CI) 时不覆盖，不在 git 仓库中时不设置
+//!
+//! 未声明 `rerun-if-changed`，包内任一文件改变时重新执行 (构建时间随之更新)
+
+use std::process::Command;
+
+fn main() {
+    let date = std::env::var("SOURCE_DATE_EPOCH").ok()
+        .and_then(|secs| secs.parse().ok())
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .unwrap_or_else(chrono::Utc::now);
+    println!("cargo:rustc-env=BUILD_DATE={}", date.to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=BUILD_RUSTC_VERSION={}", rustc_version);
+
+    // 特性名中的 `-` 在环境变量中为 `_`，本项目的特性名都不含二者
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(name, _)| name.strip_prefix("CARGO_FEATURE_").map(str::to_lowercase))
+        .filter(|feature| feature != "default")
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+
+    if std::env::var("VERGEN_GIT_SHA").is_err()
+        && let Some(sha) = command_output("git", &["rev-parse", "HEAD"])
+    {
+        println!("cargo:rustc-env=VERGEN_GIT_SHA={}", sha);
+    }
+}
+
+/// 执行命令，成功时返回去掉首尾空白的标准输出
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
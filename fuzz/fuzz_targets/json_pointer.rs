@@ -0,0 +1,26 @@
+//! JSON Pointer 的解析与取值
+//!
+//! 输入的第一行为路径，其余为 JSON 文档 (不是合法的 JSON 时用固定的文档)
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_http_demo::api::json_pointer::{parse_field, parse_pointer, value_at_pointer};
+use serde_json::{json, Value};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else { return };
+    let (pointer, document) = input.split_once('\n').unwrap_or((input, ""));
+    let root = serde_json::from_str::<Value>(document)
+        .unwrap_or_else(|_| json!({ "data": { "tags": ["a", "b"], "a/b": 1, "m~n": [null, { "x": true }] } }));
+
+    let parsed = parse_pointer(pointer);
+    let value = value_at_pointer(&root, pointer);
+    assert!(parsed.is_ok() || value.is_none(), "invalid pointer {:?} resolved", pointer);
+
+    // 字段转换的结果总是合法的 JSON Pointer
+    if let Ok(converted) = parse_field(pointer) {
+        assert!(parse_pointer(&converted).is_ok(), "field {:?} converted to invalid pointer {:?}", pointer, converted);
+        let _ = value_at_pointer(&root, &converted);
+    }
+});
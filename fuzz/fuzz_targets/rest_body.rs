@@ -0,0 +1,43 @@
+//! 任意字节作为 `POST /rest` 的 JSON 请求体
+//!
+//! 格式错误应得到 `4xx`，不能 panic，也不能返回 `500`
+
+#![no_main]
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request},
+    Router,
+};
+use libfuzzer_sys::fuzz_target;
+use once_cell::sync::Lazy;
+use rust_http_demo::{
+    api::{auth::UserRole, middleware::jwt, rest_store::factory_rest_router},
+    container::Container,
+};
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+
+static RUNTIME: Lazy<Runtime> = Lazy::new(|| {
+    tokio::runtime::Builder::new_current_thread().enable_all().build().expect("runtime")
+});
+
+/// 路由与令牌，所有输入共用
+static APP: Lazy<(Router, String)> = Lazy::new(|| {
+    let router = RUNTIME.block_on(factory_rest_router(Container::new_arc()));
+    let token = jwt::issue("fuzz", "fuzz", UserRole::User).expect("token");
+    (router, token)
+});
+
+fuzz_target!(|data: &[u8]| {
+    let (router, token) = &*APP;
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/rest")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(data.to_vec()))
+        .expect("valid request");
+    let response = RUNTIME.block_on(router.clone().oneshot(request)).expect("router is infallible");
+    assert!(!response.status().is_server_error(), "status {} for body {:?}", response.status(), String::from_utf8_lossy(data));
+});
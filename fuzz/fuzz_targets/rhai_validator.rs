@@ -0,0 +1,12 @@
+//! 任意文本作为 Rhai 脚本校验 (仅编译)，不能 panic
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_http_demo::node::script::validate_script;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(script) = std::str::from_utf8(data) {
+        let _ = validate_script(script);
+    }
+});
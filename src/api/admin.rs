@@ -0,0 +1,311 @@
+//! 管理接口
+//!
+//! - `GET /admin/tasks`: 后台任务状态
+//! - `GET /admin/stats`: 各路由的请求计数
+//! - `GET /admin/memory`: 各存储的数据量
+//! - `GET /admin/snapshot`: 导出各存储的全部数据 (JSON)
+//! - `GET /admin/schema-version`: 持久化后端的数据库版本
+//! - `POST /admin/fingerprint/rotate-secret`: 更换心跳指纹的密钥
+//! - `GET /admin/sessions`: 在线用户 (心跳会话)
+//! - `DELETE /admin/sessions/{id}`: 使会话立即过期
+//! - `PATCH /admin/users/{id}/role`: 修改用户角色
+//! - `GET /admin/node/circuit-breakers`: Http 节点的熔断器状态
+//! - `GET /admin/debug/routes`: 已注册的路由
+//!
+//! 均需以管理员登录 (`Authorization: Bearer <token>`)，未登录返回 `401`，非管理员返回 `403`
+
+use axum::{
+    extract::{FromRef, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use validator::Validate;
+
+use crate::api::{auth::{self, UserRole}, extractors::{AdminRequired, ValidatedJson}, fingerprint, heartbeat, route_registry};
+use crate::api::middleware::{ip_filter::IpFilterLayer, jwt::JwtAuthLayer, user_rate_limit};
+use crate::api::middleware::stats::RequestCounter;
+use crate::container::{Container, MapBackend, Timestamped};
+use crate::documented_route;
+use crate::migrations;
+use crate::node::circuit_breaker;
+use crate::supervisor::TaskSupervisor;
+
+/// 可报告数据量的存储
+pub trait MemoryReport: Send + Sync {
+    fn item_count(&self) -> usize;
+    /// 估算的字节数，见 [`Container::estimated_bytes`]
+    fn estimated_bytes(&self) -> usize;
+    /// 最早与最晚的创建时间
+    fn created_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)>;
+    /// 全部数据 (键到值的 JSON 对象)
+    fn snapshot(&self) -> Value;
+}
+
+impl<T, M> MemoryReport for Container<T, M>
+where
+    T: Timestamped + Send + Sync,
+    M: MapBackend<T> + Serialize + Send + Sync,
+{
+    fn item_count(&self) -> usize {
+        self.len()
+    }
+
+    fn estimated_bytes(&self) -> usize {
+        Container::estimated_bytes(self)
+    }
+
+    fn created_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        Container::created_range(self)
+    }
+
+    fn snapshot(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+type MemoryReports = Arc<Vec<(&'static str, Arc<dyn MemoryReport>)>>;
+
+/// 路由共享状态
+#[derive(Clone)]
+struct AdminState {
+    counter: Arc<RequestCounter>,
+    stores: MemoryReports,
+}
+
+impl FromRef<AdminState> for Arc<RequestCounter> {
+    fn from_ref(state: &AdminState) -> Self {
+        state.counter.clone()
+    }
+}
+
+impl FromRef<AdminState> for MemoryReports {
+    fn from_ref(state: &AdminState) -> Self {
+        state.stores.clone()
+    }
+}
+
+/// 管理路由
+///
+/// - `counter` 请求计数器，需与挂载的计数中间件为同一个
+/// - `stores` 以名称标识的各存储，见 [`crate::api::Stores::memory_reports`]
+pub fn factory_admin_router(counter: Arc<RequestCounter>, stores: Vec<(&'static str, Arc<dyn MemoryReport>)>) -> Router {
+    Router::new()
+        .merge(documented_route!(get, "/admin/tasks", get_admin_tasks, "后台任务状态"))
+        .merge(documented_route!(get, "/admin/stats", get_admin_stats, "各路由的请求计数"))
+        .merge(documented_route!(get, "/admin/memory", get_admin_memory, "各存储的数据量"))
+        .merge(documented_route!(get, "/admin/snapshot", get_admin_snapshot, "导出各存储的全部数据"))
+        .merge(documented_route!(get, "/admin/schema-version", get_admin_schema_version, "持久化后端的数据库版本"))
+        .merge(documented_route!(post, "/admin/fingerprint/rotate-secret", post_admin_rotate_fingerprint_secret, "更换心跳指纹的密钥"))
+        .merge(documented_route!(get, "/admin/sessions", get_admin_sessions, "在线用户 (心跳会话)"))
+        .merge(documented_route!(delete, "/admin/sessions/{id}", delete_admin_session, "使会话立即过期"))
+        .merge(documented_route!(patch, "/admin/users/{id}/role", patch_admin_user_role, "修改用户角色"))
+        .merge(documented_route!(patch, "/admin/users/{id}/rate-limit", patch_admin_user_rate_limit, "修改用户的限流配额"))
+        .merge(documented_route!(get, "/admin/node/circuit-breakers", get_admin_circuit_breakers, "Http 节点的熔断器状态"))
+        .merge(documented_route!(get, "/admin/debug/routes", get_admin_debug_routes, "已注册的路由"))
+        .route_layer(JwtAuthLayer) // 各处理函数再用 AdminRequired 检查角色
+        .route_layer(IpFilterLayer::admin_from_env()) // 在鉴权之前，不在白名单内的请求不接触鉴权逻辑
+        .with_state(AdminState { counter, stores: Arc::new(stores) })
+}
+
+/// GET /admin/tasks, 后台任务列表
+pub async fn get_admin_tasks(_admin: AdminRequired) -> impl IntoResponse {
+    Json(TaskSupervisor::list())
+}
+
+/// GET /admin/stats, 请求统计
+///
+/// `routes` 按请求次数降序排列
+pub async fn get_admin_stats(_admin: AdminRequired, State(counter): State<Arc<RequestCounter>>) -> impl IntoResponse {
+    Json(json!({
+        "uptime_secs": counter.uptime_secs(),
+        "total_requests": counter.total_requests(),
+        "error_rate_5m": counter.error_rate_5m(),
+        "routes": counter.routes(),
+    }))
+}
+
+/// GET /admin/memory, 各存储的数据量
+///
+/// - `query` 查询参数，`format=human` 时字节数显示为 `4.2 KiB` 形式
+async fn get_admin_memory(
+    _admin: AdminRequired,
+    Query(query): Query<MemoryQuery>,
+    State(stores): State<MemoryReports>,
+) -> impl IntoResponse {
+    let human = query.format.as_deref() == Some("human");
+
+    let mut result = Map::new();
+    for (name, store) in stores.iter() {
+        let bytes = store.estimated_bytes();
+        let range = store.created_range();
+        result.insert(name.to_string(), json!({
+            "item_count": store.item_count(),
+            "estimated_bytes": if human { Value::from(format_bytes(bytes)) } else { Value::from(bytes) },
+            "oldest_item_created_at": range.map(|(oldest, _)| oldest),
+            "newest_item_created_at": range.map(|(_, newest)| newest),
+        }));
+    }
+    Json(result)
+}
+
+/// GET /admin/snapshot, 导出各存储的全部数据
+///
+/// 返回 `{ "todos": { "<id>": {...} }, "rest": {...}, "nodes": {...} }`，
+/// `?store=todos` 时只返回该存储 (`{ "<id>": {...} }`)，存储不存在时返回 `404`
+async fn get_admin_snapshot(
+    _admin: AdminRequired,
+    Query(query): Query<SnapshotQuery>,
+    State(stores): State<MemoryReports>,
+) -> Response {
+    match query.store {
+        Some(name) => match stores.iter().find(|(store_name, _)| *store_name == name) {
+            Some((_, store)) => Json(store.snapshot()).into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        },
+        None => {
+            let result: Map<String, Value> = stores.iter()
+                .map(|(name, store)| (name.to_string(), store.snapshot()))
+                .collect();
+            Json(result).into_response()
+        }
+    }
+}
+
+/// GET /admin/schema-version, 持久化后端的数据库版本
+///
+/// 键为后端名 (`sqlite`/`sled`)，未启用持久化时为空对象
+async fn get_admin_schema_version(_admin: AdminRequired) -> impl IntoResponse {
+    Json(migrations::versions())
+}
+
+/// POST /admin/fingerprint/rotate-secret, 更换心跳指纹的密钥
+///
+/// 旧指纹全部失效，在线用户清空，客户端下次心跳时以新指纹重新计入
+async fn post_admin_rotate_fingerprint_secret(_admin: AdminRequired) -> impl IntoResponse {
+    fingerprint::rotate_secret();
+    let cleared = heartbeat::clear_sessions();
+    Json(json!({ "cleared_sessions": cleared }))
+}
+
+/// GET /admin/sessions, 在线用户 (心跳会话)，按最近活跃时间降序
+async fn get_admin_sessions(_admin: AdminRequired) -> impl IntoResponse {
+    Json(heartbeat::list_sessions())
+}
+
+/// GET /admin/debug/routes, 已注册的路由
+///
+/// 返回 `[{ "method": "GET", "path_pattern": "/todos/{id}", "module": "api::rest_todos", "description": "..." }]`，
+/// 按路径与方法排序。只含经 [`documented_route!`] 注册的路由
+async fn get_admin_debug_routes(_admin: AdminRequired) -> impl IntoResponse {
+    Json(route_registry::list())
+}
+
+/// GET /admin/node/circuit-breakers, 熔断器状态
+///
+/// 只列出有失败记录的 URL，恢复正常的不再列出
+async fn get_admin_circuit_breakers(_admin: AdminRequired) -> impl IntoResponse {
+    let breakers: Vec<Value> = circuit_breaker::list().iter().map(|breaker| breaker.summary()).collect();
+    Json(breakers)
+}
+
+/// DELETE /admin/sessions/{id}, 使会话立即过期
+async fn delete_admin_session(_admin: AdminRequired, Path(id): Path<String>) -> impl IntoResponse {
+    if heartbeat::expire_session(&id) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        (StatusCode::NOT_FOUND, Json(json!({ "error": "session not found" }))).into_response()
+    }
+}
+
+/// PATCH /admin/users/{id}/role, 修改用户角色 `{ "role": "admin" }`
+///
+/// 用户需重新登录或刷新令牌后新角色才生效
+async fn patch_admin_user_role(
+    AdminRequired(admin): AdminRequired,
+    Path(id): Path<String>,
+    ValidatedJson(input): ValidatedJson<RoleRequest>,
+) -> impl IntoResponse {
+    // 避免唯一的管理员误把自己降级后无人可管理
+    if id == admin.sub && input.role != UserRole::Admin {
+        return (StatusCode::CONFLICT, Json(json!({ "error": "cannot demote yourself" }))).into_response();
+    }
+    match auth::set_role(&id, input.role) {
+        Some(user) => Json(user.profile()).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(json!({ "error": "user not found" }))).into_response(),
+    }
+}
+
+/// PATCH /admin/users/{id}/rate-limit, 修改用户的限流配额 `{ "capacity": 200, "refill_per_sec": 50, "unlimited": false }`
+///
+/// 未给出的字段保持当前值 (未设置过时为按角色的默认值)，返回修改后的配额。修改后该用户的令牌桶重新开始
+async fn patch_admin_user_rate_limit(
+    _admin: AdminRequired,
+    Path(id): Path<String>,
+    ValidatedJson(input): ValidatedJson<RateLimitRequest>,
+) -> impl IntoResponse {
+    let Some(user) = auth::find_user(&id) else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "user not found" }))).into_response();
+    };
+    let mut quota = user_rate_limit::quota(&id, user.role);
+    if let Some(capacity) = input.capacity {
+        quota.capacity = capacity;
+    }
+    if let Some(refill_per_sec) = input.refill_per_sec {
+        quota.refill_per_sec = refill_per_sec;
+    }
+    if let Some(unlimited) = input.unlimited {
+        quota.unlimited = unlimited;
+    }
+    user_rate_limit::set_quota(&id, quota);
+    Json(quota).into_response()
+}
+
+/// 字节数转为可读形式，如 `4.2 KiB`
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+// #region api struct
+
+#[derive(Debug, Deserialize, Validate)]
+struct RoleRequest {
+    role: UserRole,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct RateLimitRequest {
+    #[validate(range(min = 1))]
+    capacity: Option<u32>,
+    #[validate(range(exclusive_min = 0.0))]
+    refill_per_sec: Option<f64>,
+    unlimited: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MemoryQuery {
+    /// `human` 为可读格式
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapshotQuery {
+    /// 只导出该存储，如 `todos`
+    store: Option<String>,
+}
+
+// #endregion
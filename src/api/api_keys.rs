@@ -0,0 +1,50 @@
+//! API Key 存储
+//!
+//! 供机器客户端 (如 webhook 调用方) 使用，每个 Key 对应一个签名密钥，见 [`crate::api::middleware::hmac`]。
+//! 目前仅从配置文件加载，不提供增删改接口
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::config::CONFIG;
+use crate::container::Container;
+
+/// API Key 记录
+///
+/// - `key` 客户端通过 `X-API-Key` 头发送
+/// - `secret` 签名用的共享密钥，不会在网络上传输
+#[derive(Clone, Deserialize)]
+pub struct ApiKeyRecord {
+    pub key: String,
+    pub secret: String,
+}
+
+impl std::fmt::Debug for ApiKeyRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKeyRecord")
+            .field("key", &self.key)
+            .field("secret", &"***")
+            .finish()
+    }
+}
+
+/// 全局 API Key 存储，以 key 为索引
+static API_KEYS: Lazy<Arc<Container<ApiKeyRecord>>> = Lazy::new(|| {
+    let container = Container::new_arc();
+    for record in &CONFIG.api_keys {
+        container.put_by_id(&record.key, record.clone());
+    }
+    container
+});
+
+/// 查找 API Key
+pub fn find(key: &str) -> Option<ApiKeyRecord> {
+    API_KEYS.get_by_id(key)
+}
+
+/// 添加 API Key (仅测试用，运行时只从配置文件加载)
+#[cfg(test)]
+pub(crate) fn insert(record: ApiKeyRecord) {
+    API_KEYS.put_by_id(&record.key, record.clone());
+}
@@ -0,0 +1,388 @@
+//! 用户注册与登录
+//!
+//! - `POST /auth/register`: 注册 `{ "username": "...", "password": "..." }`
+//! - `POST /auth/login`: 登录，返回 JWT (见 [`crate::api::middleware::jwt`]) 与刷新令牌 (见 [`crate::api::refresh_token`])
+//! - `POST /auth/refresh`: 用刷新令牌换取新的 JWT
+//! - `DELETE /auth/logout`: 使刷新令牌失效
+//! - `GET /auth/me`: 当前用户 (需 `Authorization: Bearer <token>`)
+//! - `DELETE /auth/account`: 注销当前用户，并删除其所有数据 (todos、rest、node)
+//! - `GET /auth/github`: GitHub 登录，见 [`crate::api::oauth`]
+//!
+//! 用户仅存于内存，重启后清空。密码以 argon2 哈希存储。
+//! 设置环境变量 `RUST_DEMO_ADMIN_PASSWORD` 时，启动时创建管理员 `admin` (已有管理员时跳过)
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    extract::State,
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::api::{extractors::ValidatedJson, oauth, refresh_token, rest_node, webhooks, Stores};
+use crate::api::middleware::jwt::{self, Claims, JwtAuthLayer};
+use crate::container::Container;
+use crate::documented_route;
+
+// #region 相关类型
+
+/// 初始管理员密码的环境变量
+const ADMIN_PASSWORD_ENV: &str = "RUST_DEMO_ADMIN_PASSWORD";
+/// 初始管理员的用户名
+const ADMIN_USERNAME: &str = "admin";
+
+/// 用户角色
+/// 
+/// - `Admin` 可访问 `/admin/...` 管理接口
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    Admin,
+    #[default]
+    User,
+}
+
+/// 用户记录
+///
+/// - `password_hash` argon2 哈希 (PHC 字符串，含盐与参数)。经 GitHub 注册的用户为空，不能用密码登录
+/// - `github_id` 关联的 GitHub 用户ID
+#[derive(Clone)]
+pub struct UserRecord {
+    pub id: Uuid,
+    pub username: String,
+    pub password_hash: String,
+    pub github_id: Option<u64>,
+    pub role: UserRole,
+    pub created_at: DateTime<Utc>,
+}
+
+impl std::fmt::Debug for UserRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserRecord")
+            .field("id", &self.id)
+            .field("username", &self.username)
+            .field("password_hash", &"***")
+            .field("github_id", &self.github_id)
+            .field("role", &self.role)
+            .field("created_at", &self.created_at)
+            .finish()
+    }
+}
+
+impl UserRecord {
+    /// 对外展示的信息 (不含密码哈希)
+    pub fn profile(&self) -> serde_json::Value {
+        json!({
+            "id": self.id,
+            "username": self.username,
+            "github_id": self.github_id,
+            "role": self.role,
+            "created_at": self.created_at,
+        })
+    }
+}
+
+/// 全局用户存储，以用户ID为索引
+static USERS: Lazy<Arc<Container<UserRecord>>> = Lazy::new(Container::new_arc);
+
+/// 注册时检查用户名与写入需互斥，避免并发注册同名用户
+static REGISTER_LOCK: Mutex<()> = Mutex::new(());
+
+/// 用户不存在时用于校验的哈希，使登录耗时与用户是否存在无关 (防止枚举用户名)
+static DUMMY_HASH: Lazy<String> = Lazy::new(|| hash_password("dummy password").expect("failed to hash dummy password"));
+
+// #endregion
+
+/// 认证路由
+/// 
+/// - `stores` 各资源的存储，注销用户时删除其数据
+pub fn factory_auth_router(stores: Stores) -> Router {
+    ensure_admin();
+
+    Router::new()
+        .merge(documented_route!(get, "/auth/me", get_auth_me, "当前用户"))
+        .merge(documented_route!(delete, "/auth/account", delete_auth_account, "注销当前用户"))
+        .route_layer(JwtAuthLayer) // 仅作用于以上路由
+        .merge(documented_route!(post, "/auth/register", post_auth_register, "注册"))
+        .merge(documented_route!(post, "/auth/login", post_auth_login, "登录"))
+        .merge(documented_route!(post, "/auth/refresh", post_auth_refresh, "用刷新令牌换取新的 JWT"))
+        .merge(documented_route!(delete, "/auth/logout", delete_auth_logout, "退出登录 (使刷新令牌失效)"))
+        .with_state(stores)
+        .merge(oauth::factory_github_router())
+}
+
+/// POST /auth/register, 注册
+///
+/// 用户名已存在时返回 `409`
+async fn post_auth_register(ValidatedJson(input): ValidatedJson<AuthRequest>) -> Response {
+    let password = input.password;
+    let password_hash = match tokio::task::spawn_blocking(move || hash_password(&password)).await {
+        Ok(Ok(hash)) => hash,
+        _ => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "failed to hash password" }))).into_response(),
+    };
+
+    let user = UserRecord {
+        id: Uuid::new_v4(),
+        username: input.username,
+        password_hash,
+        github_id: None,
+        role: UserRole::User,
+        created_at: Utc::now(),
+    };
+    {
+        let _guard = REGISTER_LOCK.lock().unwrap();
+        if find_by_username(&user.username).is_some() {
+            return (StatusCode::CONFLICT, Json(json!({ "error": "username already exists" }))).into_response();
+        }
+        USERS.put_by_id(&user.id.to_string(), user.clone());
+    }
+    (StatusCode::CREATED, Json(user.profile())).into_response()
+}
+
+/// POST /auth/login, 登录
+///
+/// 用户名或密码错误时统一返回 `401`
+async fn post_auth_login(Json(input): Json<LoginRequest>) -> Response {
+    let user = find_by_username(&input.username);
+    let password_hash = user.as_ref().map_or_else(|| DUMMY_HASH.clone(), |user| user.password_hash.clone());
+    let password = input.password;
+    let verified = tokio::task::spawn_blocking(move || verify_password(&password, &password_hash))
+        .await
+        .unwrap_or(false);
+
+    let Some(user) = user.filter(|_| verified) else {
+        return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "invalid username or password" }))).into_response();
+    };
+    token_response(&user)
+}
+
+/// POST /auth/refresh, 用刷新令牌换取新的 JWT
+///
+/// 默认轮换: 旧的刷新令牌失效，响应中带新的刷新令牌。`"rotate": false` 时沿用旧的刷新令牌。
+/// 令牌无效、过期或用户已不存在时返回 `401`
+async fn post_auth_refresh(Json(input): Json<RefreshRequest>) -> Response {
+    let user = refresh_token::verify(&input.refresh_token, input.rotate)
+        .and_then(|record| USERS.get_by_id(&record.user_id));
+    let Some(user) = user else {
+        return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "invalid refresh token" }))).into_response();
+    };
+    let refresh = input.rotate.then(|| refresh_token::issue(&user.id.to_string()));
+    issue_tokens(&user, refresh)
+}
+
+/// DELETE /auth/logout, 退出登录: 使刷新令牌失效
+///
+/// 令牌不存在时同样返回 `204`。已签发的 JWT 在过期前仍然有效
+async fn delete_auth_logout(Json(input): Json<LogoutRequest>) -> StatusCode {
+    refresh_token::revoke(&input.refresh_token);
+    StatusCode::NO_CONTENT
+}
+
+/// 登录成功的响应: 为用户签发 JWT 与刷新令牌
+pub(crate) fn token_response(user: &UserRecord) -> Response {
+    let refresh = refresh_token::issue(&user.id.to_string());
+    issue_tokens(user, Some(refresh))
+}
+
+/// 签发 JWT，`refresh_token` 非空时一并返回
+fn issue_tokens(user: &UserRecord, refresh_token: Option<String>) -> Response {
+    match jwt::issue(&user.id.to_string(), &user.username, user.role) {
+        Ok(token) => {
+            let mut resp = json!({
+                "access_token": token,
+                "token_type": "Bearer",
+                "expires_in": jwt::TOKEN_TTL_SECS,
+            });
+            if let Some(refresh_token) = refresh_token {
+                resp["refresh_token"] = json!(refresh_token);
+            }
+            Json(resp).into_response()
+        }
+        Err(err) => {
+            tracing::error!("failed to issue token: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "failed to issue token" }))).into_response()
+        }
+    }
+}
+
+/// GET /auth/me, 当前用户
+///
+/// 令牌有效但用户已不存在时返回 `404`
+async fn get_auth_me(Extension(claims): Extension<Claims>) -> Response {
+    match USERS.get_by_id(&claims.sub) {
+        Some(user) => Json(user.profile()).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(json!({ "error": "user not found" }))).into_response(),
+    }
+}
+
+/// DELETE /auth/account, 注销当前用户
+///
+/// 删除用户记录及其所有数据，并使其刷新令牌失效。已签发的 JWT 在过期前仍可通过校验，但不再对应任何用户
+async fn delete_auth_account(
+    State(stores): State<Stores>,
+    Extension(claims): Extension<Claims>,
+) -> Response {
+    if USERS.delete_by_id(&claims.sub).is_none() {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "user not found" }))).into_response();
+    }
+    let user_id = claims.sub.as_str();
+    let todos = stores.todos.delete_where(|item| item.owner_id == user_id).len();
+    let rest = stores.rest.delete_where(|item| item.owner_id == user_id).len();
+    let nodes = rest_node::delete_nodes_by_owner(user_id);
+    let webhooks = webhooks::delete_webhooks_by_owner(user_id);
+    refresh_token::revoke_all(user_id);
+    tracing::info!("deleted user {} ({} todos, {} rest items, {} nodes, {} webhooks)", user_id, todos, rest, nodes, webhooks);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// 查找或创建 GitHub 用户
+///
+/// 已关联该 GitHub 账号时返回原用户，否则以 GitHub 用户名新建; 用户名已被占用时加上 GitHub 用户ID作为后缀
+pub(crate) fn upsert_github_user(github_id: u64, login: &str) -> UserRecord {
+    let _guard = REGISTER_LOCK.lock().unwrap();
+    if let Some(user) = USERS.find_where(|user| user.github_id == Some(github_id)).into_iter().next() {
+        return user;
+    }
+    let username = match find_by_username(login) {
+        Some(_) => format!("{}-{}", login, github_id),
+        None => login.to_string(),
+    };
+    let user = UserRecord {
+        id: Uuid::new_v4(),
+        username,
+        password_hash: String::new(),
+        github_id: Some(github_id),
+        role: UserRole::User,
+        created_at: Utc::now(),
+    };
+    USERS.put_by_id(&user.id.to_string(), user.clone());
+    tracing::info!("created user {} for github user {}", user.username, github_id);
+    user
+}
+
+/// 按ID查找用户
+pub fn find_user(user_id: &str) -> Option<UserRecord> {
+    USERS.get_by_id(user_id)
+}
+
+/// 修改用户角色，返回修改后的用户，用户不存在时返回 None
+pub fn set_role(user_id: &str, role: UserRole) -> Option<UserRecord> {
+    let mut user = USERS.get_by_id(user_id)?;
+    user.role = role;
+    USERS.put_by_id(user_id, user.clone());
+    tracing::info!("set role of user {} to {:?}", user.username, role);
+    Some(user)
+}
+
+/// 设置了 `RUST_DEMO_ADMIN_PASSWORD` 且没有管理员时，创建初始管理员
+fn ensure_admin() {
+    let Ok(password) = std::env::var(ADMIN_PASSWORD_ENV) else {
+        return;
+    };
+    let _guard = REGISTER_LOCK.lock().unwrap();
+    if !USERS.find_where(|user| user.role == UserRole::Admin).is_empty() {
+        return;
+    }
+    if find_by_username(ADMIN_USERNAME).is_some() {
+        tracing::error!("cannot create admin: username {} is taken", ADMIN_USERNAME);
+        return;
+    }
+    let password_hash = match hash_password(&password) {
+        Ok(hash) => hash,
+        Err(err) => {
+            tracing::error!("cannot create admin: {}", err);
+            return;
+        }
+    };
+    let user = UserRecord {
+        id: Uuid::new_v4(),
+        username: ADMIN_USERNAME.to_string(),
+        password_hash,
+        github_id: None,
+        role: UserRole::Admin,
+        created_at: Utc::now(),
+    };
+    USERS.put_by_id(&user.id.to_string(), user);
+    tracing::info!("created admin user {}", ADMIN_USERNAME);
+}
+
+/// 按用户名查找 (区分大小写)
+fn find_by_username(username: &str) -> Option<UserRecord> {
+    USERS.find_where(|user| user.username == username).into_iter().next()
+}
+
+/// 计算密码的 argon2 哈希 (耗时，应在阻塞线程中调用)
+fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| err.to_string())
+}
+
+/// 校验密码 (耗时，应在阻塞线程中调用)
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+// #region api struct
+
+/// 注册的请求体
+#[derive(Debug, Deserialize, Validate)]
+struct AuthRequest {
+    #[validate(
+        length(min = 3, max = 32, message = "username must be 3-32 characters"),
+        custom(function = "validate_username"),
+    )]
+    username: String,
+    #[validate(length(min = 8, message = "password must be at least 8 characters"))]
+    password: String,
+}
+
+/// 登录的请求体 (不校验格式，错误时统一返回 401)
+#[derive(Debug, Deserialize)]
+struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+/// 刷新的请求体
+#[derive(Debug, Deserialize)]
+struct RefreshRequest {
+    refresh_token: String,
+    /// 是否轮换刷新令牌
+    #[serde(default = "default_rotate")]
+    rotate: bool,
+}
+
+fn default_rotate() -> bool {
+    true
+}
+
+/// 退出登录的请求体
+#[derive(Debug, Deserialize)]
+struct LogoutRequest {
+    refresh_token: String,
+}
+
+/// 校验: 用户名只含 ASCII 字母与数字
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if username.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("alphanumeric").with_message("username must be alphanumeric".into()))
+    }
+}
+
+// #endregion
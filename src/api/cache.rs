@@ -0,0 +1,124 @@
+//! 进程内的响应缓存
+//!
+//! 缓存成功 (`200`) 的响应体，在有效期内直接返回，不再执行处理函数。响应带 `X-Cache: HIT|MISS` 与 `ETag`。
+//! 通过容器的事件钩子，在资源被修改或删除时使对应的缓存失效 (见 [`ResponseCache::invalidate_on_change`])
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::container::{Container, HookEvent, MapBackend};
+
+/// 缓存状态头
+pub const X_CACHE: HeaderName = HeaderName::from_static("x-cache");
+/// 可缓存的响应体上限，更大的响应不缓存
+const MAX_CACHED_BODY: usize = 1024 * 1024;
+
+/// 缓存的响应
+///
+/// - `resource_id` 对应资源的ID，资源变化时据此失效 (同一资源可能因用户不同有多个缓存项)
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub resource_id: String,
+    pub body: Bytes,
+    pub etag: String,
+    pub content_type: String,
+    pub expires_at: Instant,
+}
+
+/// 响应缓存，可 clone 共享
+#[derive(Clone, Default)]
+pub struct ResponseCache {
+    entries: Arc<Container<CachedResponse>>,
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self { entries: Container::new_arc() }
+    }
+
+    /// 使资源的所有缓存项失效
+    pub fn invalidate(&self, resource_id: &str) {
+        self.entries.delete_where(|entry| entry.resource_id == resource_id);
+    }
+
+    /// 在 `container` 的项被修改或删除时使对应的缓存失效 (缓存的 `resource_id` 即容器的键)
+    pub fn invalidate_on_change<T, M: MapBackend<T>>(&self, container: &Container<T, M>) {
+        let cache = self.clone();
+        container.add_hook(move |event| {
+            let (HookEvent::Put { key, .. } | HookEvent::Delete { key, .. }) = event;
+            cache.invalidate(key);
+        });
+    }
+}
+
+/// 带缓存地执行处理函数
+///
+/// - `key` 缓存键，响应因用户等而不同时需包含这些信息
+/// - `resource_id` 响应对应的资源，见 [`ResponseCache::invalidate`]
+/// - `ttl` 有效期
+/// - `handler` 未命中时执行，仅缓存 `200` 的响应
+pub async fn cached_response<F, Fut>(
+    cache: &ResponseCache,
+    key: &str,
+    resource_id: &str,
+    ttl: Duration,
+    handler: F,
+) -> Response
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Response>,
+{
+    if let Some(entry) = cache.entries.get_by_id(key).filter(|entry| entry.expires_at > Instant::now()) {
+        return build_response(&entry, "HIT");
+    }
+
+    let response = handler().await;
+    if response.status() != StatusCode::OK {
+        return with_cache_header(response, "MISS");
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(body) = to_bytes(body, MAX_CACHED_BODY).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let content_type = parts.headers.get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let now = Instant::now();
+    let entry = CachedResponse {
+        resource_id: resource_id.to_string(),
+        etag: format!("\"{}\"", &hex::encode(Sha256::digest(&body))[..16]),
+        body,
+        content_type,
+        expires_at: now + ttl,
+    };
+    cache.entries.delete_where(|entry| entry.expires_at <= now); // 顺带清理过期项
+    cache.entries.put_by_id(key, entry.clone());
+    build_response(&entry, "MISS")
+}
+
+fn build_response(entry: &CachedResponse, status: &'static str) -> Response {
+    let mut response = Response::new(Body::from(entry.body.clone()));
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&entry.content_type) {
+        headers.insert(header::CONTENT_TYPE, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&entry.etag) {
+        headers.insert(header::ETAG, value);
+    }
+    with_cache_header(response, status)
+}
+
+fn with_cache_header(mut response: Response, status: &'static str) -> Response {
+    response.headers_mut().insert(X_CACHE, HeaderValue::from_static(status));
+    response
+}
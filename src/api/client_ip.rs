@@ -0,0 +1,139 @@
+//! 客户端IP
+//!
+//! 从反向代理设置的请求头中取客户端的真实地址:
+//!
+//! 1. `X-Real-IP`: 单个地址，由最近一层 (可信的) 代理设置，代理须覆盖客户端发来的值
+//! 2. `X-Forwarded-For`: `client, proxy1, proxy2`，每经过一层代理在末尾追加一项 (该代理所见的连接地址)。
+//!    配置 `trusted_proxy_depth = N` 时取右起第 N 项，即最外层的可信代理所见的地址，其左侧的项都可能由客户端伪造;
+//!    项数不足 N 时视为无效 (中间件退回连接地址)。为0时 (无可信代理) 取最左侧的一项，仅可用于指纹等不涉及安全的场合
+//!
+//! 取到的值须为合法的IP地址。私有、回环、链路本地等地址不是真实的客户端地址，默认视为无效
+//! (配置 `client_ip_allow_private = true` 后接受，如内网部署)。
+//!
+//! 请求头可被客户端伪造，只应在确有代理时信任，中间件用 [`request_client_ip`]
+
+use axum::{extract::{ConnectInfo, Request}, http::HeaderMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::config::CONFIG;
+
+/// 中间件使用的客户端IP
+///
+/// 配置了可信代理 (`trusted_proxy_depth > 0`) 时优先取代理转发的地址，否则连接地址都是代理的。
+/// 未配置、或请求头无效 (如 `X-Forwarded-For` 的项数少于代理层数) 时取连接地址 `ConnectInfo<SocketAddr>`，
+/// 不信任可伪造的请求头。都取不到时为 `None`
+pub fn request_client_ip(req: &Request) -> Option<IpAddr> {
+    let forwarded = (CONFIG.trusted_proxy_depth > 0).then(|| extract_client_ip(req.headers())).flatten();
+    forwarded.or_else(|| req.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip()))
+}
+
+/// 从请求头取客户端IP，按配置 (`trusted_proxy_depth`、`client_ip_allow_private`) 解析，见模块说明
+pub fn extract_client_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    extract_client_ip_with(headers, CONFIG.trusted_proxy_depth, CONFIG.client_ip_allow_private)
+}
+
+/// 从请求头取客户端IP
+///
+/// - `trusted_proxy_depth` 可信代理的层数，取 `X-Forwarded-For` 右起第 N 项
+/// - `allow_private` 是否接受私有、回环等地址
+pub fn extract_client_ip_with(headers: &HeaderMap, trusted_proxy_depth: usize, allow_private: bool) -> Option<IpAddr> {
+    let accept = |ip: IpAddr| (allow_private || is_public(ip)).then_some(ip);
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(ip) = header("x-real-ip").and_then(parse_ip).and_then(accept) {
+        return Some(ip);
+    }
+    let forwarded: Vec<&str> = header("x-forwarded-for")?.split(',').map(str::trim).collect();
+    let index = match trusted_proxy_depth {
+        0 => 0,
+        depth => forwarded.len().checked_sub(depth)?,
+    };
+    parse_ip(forwarded[index]).and_then(accept)
+}
+
+/// 解析IP地址，允许 `[IPv6]` 与带端口的形式 (部分代理会带上端口)
+fn parse_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    if let Ok(ip) = value.parse() {
+        return Some(ip);
+    }
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    value.strip_prefix('[')?.strip_suffix(']')?.parse().ok()
+}
+
+/// 是否为公网地址 (非私有、回环、链路本地、未指定等)
+pub(crate) fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    !(ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast())
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    !(ip.is_loopback() || ip.is_unspecified() || ip.is_unique_local() || ip.is_unicast_link_local())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn ip(value: &str) -> Option<IpAddr> {
+        Some(value.parse().unwrap())
+    }
+
+    #[test]
+    fn prefers_x_real_ip() {
+        let headers = headers(&[("x-real-ip", "203.0.113.7"), ("x-forwarded-for", "198.51.100.1")]);
+        assert_eq!(extract_client_ip_with(&headers, 0, false), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn forwarded_for_skips_trusted_proxies() {
+        let headers = headers(&[("x-forwarded-for", "198.51.100.1, 203.0.113.2 ,203.0.113.3")]);
+        assert_eq!(extract_client_ip_with(&headers, 0, false), ip("198.51.100.1"));
+        assert_eq!(extract_client_ip_with(&headers, 1, false), ip("203.0.113.3"));
+        assert_eq!(extract_client_ip_with(&headers, 2, false), ip("203.0.113.2"));
+        assert_eq!(extract_client_ip_with(&headers, 3, false), ip("198.51.100.1"));
+        // 项数少于代理层数，不可信
+        assert_eq!(extract_client_ip_with(&headers, 5, false), None);
+    }
+
+    #[test]
+    fn forged_forwarded_for_is_ignored() {
+        // 客户端伪造了 X-Forwarded-For，唯一的一层代理在末尾追加真实的连接地址
+        let forged = headers(&[("x-forwarded-for", "127.0.0.1, 10.0.0.1, 203.0.113.66")]);
+        assert_eq!(extract_client_ip_with(&forged, 1, true), ip("203.0.113.66"));
+        // 客户端绕过代理直连时，伪造的单项不足两层代理
+        let direct = headers(&[("x-forwarded-for", "10.0.0.1")]);
+        assert_eq!(extract_client_ip_with(&direct, 2, true), None);
+    }
+
+    #[test]
+    fn rejects_invalid_and_private() {
+        assert_eq!(extract_client_ip_with(&headers(&[("x-forwarded-for", "unknown")]), 0, false), None);
+        assert_eq!(extract_client_ip_with(&headers(&[("x-forwarded-for", "10.0.0.1")]), 0, false), None);
+        assert_eq!(extract_client_ip_with(&headers(&[("x-forwarded-for", "10.0.0.1")]), 0, true), ip("10.0.0.1"));
+        assert_eq!(extract_client_ip_with(&headers(&[("x-forwarded-for", "::ffff:127.0.0.1")]), 0, false), None);
+        // X-Real-IP 无效时退回 X-Forwarded-For
+        let headers = headers(&[("x-real-ip", "127.0.0.1"), ("x-forwarded-for", "[2001:db8::1]:443")]);
+        assert_eq!(extract_client_ip_with(&headers, 0, false), ip("2001:db8::1"));
+        assert_eq!(extract_client_ip_with(&HeaderMap::new(), 0, false), None);
+    }
+}
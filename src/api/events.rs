@@ -0,0 +1,232 @@
+//! 数据变更的实时通知 (Server-Sent Events)
+//!
+//! - `GET /events?resource=todos`: 订阅变更事件 (需登录，令牌可放在 `access_token` 查询参数中)，`resource` 可选，用于过滤
+//! - `GET /events/ping`: 查看事件通道状态
+//!
+//! 事件来源于各容器的变更订阅 ([`Container::watch`])，见 [`publish_changes`]。
+//! 各用户只收到自己的数据的事件 (见 [`SseEvent::visible_to`])。
+//! 客户端断线重连时若带 `Last-Event-ID`，会先补发最近缓存的事件 (最多100条)
+
+use axum::{
+    extract::{Extension, Query},
+    http::HeaderMap,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json,
+    },
+    Router,
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::api::middleware::jwt::{BrowserJwtAuthLayer, Claims};
+use crate::container::{Container, ContainerEvent, MapBackend};
+use crate::supervisor::TaskSupervisor;
+use crate::documented_route;
+
+/// 重连补发的事件缓存数量
+const REPLAY_BUFFER_SIZE: usize = 100;
+/// 保活间隔
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// #region 类型
+
+/// 变更事件
+///
+/// - `resource` 资源类别，如 `todos`，用于过滤
+/// - `event_type` 事件类型，如 `todo.created`、`node.deleted`
+/// - `resource_id` 被修改项的ID
+/// - `owner_id` 数据所有者的用户ID (取自 `payload` 的 `owner_id`)，没有所有者的数据 (如匿名创建的节点) 为 None
+/// - `payload` 修改后的值 (删除时为被删除的值)
+#[derive(Debug, Serialize, Clone)]
+pub struct SseEvent {
+    pub resource: String,
+    pub event_type: String,
+    pub resource_id: String,
+    pub owner_id: Option<String>,
+    pub payload: Value,
+}
+
+impl SseEvent {
+    /// 用户能否收到该事件: 有所有者的只发给所有者，没有所有者的发给所有人
+    pub fn visible_to(&self, user_id: &str) -> bool {
+        self.owner_id.as_deref().is_none_or(|owner| owner == user_id)
+    }
+}
+
+/// 事件中心
+///
+/// 所有事件带递增的ID，并缓存最近的若干条以便重连补发
+struct EventHub {
+    sender: broadcast::Sender<(u64, SseEvent)>,
+    buffer: Mutex<VecDeque<(u64, SseEvent)>>,
+    next_id: AtomicU64,
+}
+
+/// 全局事件中心
+static EVENT_HUB: Lazy<EventHub> = Lazy::new(|| EventHub {
+    sender: broadcast::channel(1024).0,
+    buffer: Mutex::new(VecDeque::with_capacity(REPLAY_BUFFER_SIZE)),
+    next_id: AtomicU64::new(1),
+});
+
+// #endregion
+
+/// 事件路由
+pub fn factory_events_router() -> Router {
+    Router::new()
+        .merge(documented_route!(get, "/events", get_events, "订阅变更事件 (SSE)"))
+        .route_layer(BrowserJwtAuthLayer) // 仅作用于以上路由，可用 `?access_token=` (EventSource 无法设置请求头)
+        .merge(documented_route!(get, "/events/ping", get_events_ping, "事件通道状态"))
+}
+
+/// 发布事件
+pub fn publish(event: SseEvent) {
+    let id = EVENT_HUB.next_id.fetch_add(1, Ordering::Relaxed);
+    {
+        let mut buffer = EVENT_HUB.buffer.lock().unwrap();
+        if buffer.len() >= REPLAY_BUFFER_SIZE {
+            buffer.pop_front();
+        }
+        buffer.push_back((id, event.clone()));
+    }
+    // 没有订阅者时发送会失败，忽略即可
+    let _ = EVENT_HUB.sender.send((id, event));
+}
+
+/// 订阅实时事件 (不含缓存的历史事件)
+///
+/// 元素为 `(事件ID, 事件)`
+pub fn subscribe() -> broadcast::Receiver<(u64, SseEvent)> {
+    EVENT_HUB.sender.subscribe()
+}
+
+/// 订阅容器的变更，将增删改转为变更事件
+///
+/// 由受监管的任务 `events.{resource}` 转发 (见 `GET /admin/tasks`)。
+/// 调用时即订阅，之后的修改都不会遗漏
+///
+/// - `resource` 资源类别 (过滤用)，如 `todos`
+/// - `name` 事件名前缀，如 `todo`，生成 `todo.created`/`todo.updated`/`todo.deleted`
+pub fn publish_changes<T, M>(container: &Container<T, M>, resource: &'static str, name: &'static str)
+where
+    T: Serialize + Clone + Send + Sync + 'static,
+    M: MapBackend<T> + Clone + Send + Sync + 'static,
+{
+    let container = container.clone();
+    let initial = Mutex::new(Some(container.watch()));
+    TaskSupervisor::spawn(&format!("events.{}", resource), move || {
+        // 重启时重新订阅，期间的修改会丢失
+        let receiver = initial.lock().unwrap().take().unwrap_or_else(|| container.watch());
+        forward_changes(receiver, resource, name)
+    });
+}
+
+/// 把容器的变更转为变更事件并发布，容器被释放时退出
+async fn forward_changes<T: Serialize + Clone>(
+    mut receiver: broadcast::Receiver<ContainerEvent<T>>,
+    resource: &'static str,
+    name: &'static str,
+) {
+    loop {
+        let (action, key, value) = match receiver.recv().await {
+            Ok(ContainerEvent::Inserted { key, value }) => ("created", key, value),
+            Ok(ContainerEvent::Updated { key, new, .. }) => ("updated", key, new),
+            Ok(ContainerEvent::Deleted { key, value }) => ("deleted", key, value),
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!("{} change events lagged, {} events skipped", resource, skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        let payload = serde_json::to_value(value).unwrap_or_default();
+        publish(SseEvent {
+            resource: resource.to_string(),
+            event_type: format!("{}.{}", name, action),
+            resource_id: key,
+            owner_id: payload.get("owner_id").and_then(Value::as_str).map(String::from),
+            payload,
+        });
+    }
+}
+
+/// GET /events, 订阅变更事件 (SSE)
+///
+/// args:
+/// - `claims` 当前用户，只接收其可见的事件 (补发的与实时的都是)
+/// - `query` 查询参数，`resource` 仅接收该类别的事件
+/// - `headers` 重连时读取 `Last-Event-ID`，补发其后的缓存事件
+pub async fn get_events(
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let last_event_id = headers.get("last-event-id")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.parse::<u64>().ok());
+
+    // 先订阅再读缓存，避免两者之间的事件丢失。重复的部分按ID去重
+    let receiver = subscribe();
+    let replay: Vec<(u64, SseEvent)> = match last_event_id {
+        Some(last) => EVENT_HUB.buffer.lock().unwrap()
+            .iter()
+            .filter(|(id, _)| *id > last)
+            .cloned()
+            .collect(),
+        None => Vec::new(),
+    };
+    let replayed_until = replay.last().map(|(id, _)| *id).or(last_event_id).unwrap_or(0);
+
+    let resource = query.resource;
+    let matches = move |event: &SseEvent| {
+        event.visible_to(&claims.sub) && resource.as_deref().is_none_or(|r| r == event.resource)
+    };
+
+    let matches_replay = matches.clone();
+    let replay = stream::iter(replay)
+        .filter(move |(_, event)| std::future::ready(matches_replay(event)));
+    let live = BroadcastStream::new(receiver)
+        .filter_map(|result| std::future::ready(result.ok())) // 落后太多时会丢失部分事件
+        .filter(move |(id, event)| std::future::ready(*id > replayed_until && matches(event)));
+
+    let stream = replay.chain(live).map(|(id, event)| {
+        Ok(Event::default()
+            .id(id.to_string())
+            .event(event.event_type.clone())
+            .json_data(&event)
+            .unwrap_or_default())
+    });
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL).text("ping"))
+}
+
+/// GET /events/ping, 事件通道状态
+pub async fn get_events_ping() -> impl IntoResponse {
+    Json(json!({
+        "status": "ok",
+        "subscribers": EVENT_HUB.sender.receiver_count(),
+        "last_event_id": EVENT_HUB.next_id.load(Ordering::Relaxed) - 1,
+    }))
+}
+
+// #region api struct
+
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// 资源类别，如 `todos`
+    resource: Option<String>,
+}
+
+// #endregion
@@ -0,0 +1,113 @@
+//! 自定义提取器
+//!
+//! - [`ValidatedJson`] 解析JSON请求体后按 `validator` 规则校验
+//! - [`AdminRequired`] 要求当前用户为管理员
+//! - [`OptionalClaims`] 可选的登录用户
+
+use axum::{
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::api::auth::UserRole;
+use crate::api::middleware::jwt::{self, Claims};
+
+/// 字符串类型的 `data` 的大小上限
+const MAX_STRING_DATA_BYTES: usize = 64 * 1024;
+
+/// 带校验的JSON请求体，用法同 `Json<T>`
+///
+/// - 请求体不是合法JSON或类型不符: 与 `Json<T>` 相同的状态码 (`400`/`415`/`422`)，`{ "error": "..." }`
+/// - 校验失败: `422`，`{ "error": "validation failed", "fields": { "text": [{ "code": "length", "message": "..." }] } }`。
+///   不回显字段值 (可能很大)。涉及多个字段的校验 (结构体级别) 的错误在 `__all__` 下
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await.map_err(|rejection| {
+            (rejection.status(), Json(json!({ "error": rejection.body_text() }))).into_response()
+        })?;
+        value.validate().map_err(validation_failed)?;
+        Ok(Self(value))
+    }
+}
+
+/// 校验失败的响应，格式见 [`ValidatedJson`] (供在处理函数中补充校验的接口使用)
+pub fn validation_failed(errors: ValidationErrors) -> Response {
+    let fields: Map<String, Value> = errors.field_errors().into_iter()
+        .map(|(field, errors)| {
+            let errors = errors.iter()
+                .map(|error| json!({ "code": error.code, "message": error.message }))
+                .collect();
+            (field.to_string(), Value::Array(errors))
+        })
+        .collect();
+    let response = json!({ "error": "validation failed", "fields": fields });
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(response)).into_response()
+}
+
+/// 要求当前用户为管理员，用于管理接口
+///
+/// 需与 [`crate::api::middleware::jwt::JwtAuthLayer`] 一同使用 (由其放入 `Claims`)。
+/// 未登录返回 `401`，非管理员返回 `403`。角色取自令牌，修改角色后需重新签发令牌才生效
+#[derive(Debug, Clone)]
+pub struct AdminRequired(pub Claims);
+
+impl<S> FromRequestParts<S> for AdminRequired
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(claims) = parts.extensions.get::<Claims>() else {
+            return Err((StatusCode::UNAUTHORIZED, Json(json!({ "error": "authentication required" }))).into_response());
+        };
+        if claims.role != UserRole::Admin {
+            return Err((StatusCode::FORBIDDEN, Json(json!({ "error": "admin role required" }))).into_response());
+        }
+        Ok(Self(claims.clone()))
+    }
+}
+
+/// 可选的登录用户，用于无需登录、但登录时记录当前用户的路由 (不经过 `JwtAuthLayer`)
+///
+/// 不带 `Authorization` 时为 `None`。带了但令牌无效或过期时返回 `401`，不当作未登录处理
+#[derive(Debug, Clone)]
+pub struct OptionalClaims(pub Option<Claims>);
+
+impl<S> FromRequestParts<S> for OptionalClaims
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match jwt::bearer_token(&parts.headers) {
+            None => Ok(Self(None)),
+            Some(token) => jwt::verify(token).map(|claims| Self(Some(claims))).ok_or_else(jwt::unauthorized),
+        }
+    }
+}
+
+/// 校验: 字符串类型的 `data` 不超过 64 KiB (其他类型的大小由请求体大小限制)
+pub fn validate_data_size(data: &Value) -> Result<(), ValidationError> {
+    match data {
+        Value::String(text) if text.len() > MAX_STRING_DATA_BYTES => {
+            Err(ValidationError::new("size").with_message("string data must not exceed 64 KiB".into()))
+        }
+        _ => Ok(()),
+    }
+}
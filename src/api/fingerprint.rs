@@ -0,0 +1,110 @@
+//! 客户端指纹
+//!
+//! 由请求头等信息拼接后计算 HMAC-SHA256，用于在无 cookie 的情况下粗略区分客户端 (如心跳的在线人数统计)。
+//! 不同客户端可能得到相同的指纹，不能用于鉴权。
+//!
+//! 密钥由环境变量 `FINGERPRINT_SECRET` 指定，使指纹在重启后及多个实例间保持一致，且客户端无法由请求头推算他人的指纹。
+//! 未设置时启动时随机生成 (指纹在重启后改变)。可用 `POST /admin/fingerprint/rotate-secret` 更换
+
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha2::Sha256;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+use crate::api::client_ip::extract_client_ip;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 密钥的环境变量
+const FINGERPRINT_SECRET_ENV: &str = "FINGERPRINT_SECRET";
+/// 指纹长度 (十六进制字符数)
+const FINGERPRINT_LEN: usize = 16;
+
+/// 当前密钥
+static SECRET: Lazy<RwLock<Vec<u8>>> = Lazy::new(|| {
+    let secret = match std::env::var(FINGERPRINT_SECRET_ENV) {
+        Ok(secret) if !secret.is_empty() => secret.into_bytes(),
+        _ => {
+            tracing::warn!("{} not set, using a random secret (fingerprints change on restart)", FINGERPRINT_SECRET_ENV);
+            random_secret()
+        }
+    };
+    RwLock::new(secret)
+});
+
+/// 更换为随机密钥，此后同一客户端的指纹与之前不同
+pub fn rotate_secret() {
+    *SECRET.write().unwrap() = random_secret();
+    tracing::info!("fingerprint secret rotated");
+}
+
+/// 随机的 32 字节密钥 (两个 UUID v4，共 244 位随机)
+fn random_secret() -> Vec<u8> {
+    [Uuid::new_v4(), Uuid::new_v4()].iter().flat_map(|uuid| *uuid.as_bytes()).collect()
+}
+
+/// 请求是否带有可区分客户端的信息: 客户端地址 (见 [`extract_client_ip`])、`User-Agent` 或 `Accept-Language`
+///
+/// 都没有时 (如简单的 HTTP 客户端) 所有这样的客户端指纹相同，指纹没有意义
+pub fn has_client_signals(headers: &HeaderMap) -> bool {
+    extract_client_ip(headers).is_some()
+        || ["user-agent", "accept-language"].iter().any(|name| headers.get(*name).is_some_and(|value| !value.is_empty()))
+}
+
+/// 指纹构造器
+///
+/// ```ignore
+/// let fingerprint = FingerprintBuilder::from_headers(&headers)
+///     .field("extra", "value")
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FingerprintBuilder {
+    /// (字段名, 值)，按加入顺序参与哈希
+    fields: Vec<(&'static str, String)>,
+}
+
+impl FingerprintBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 默认的字段: 客户端地址 (`X-Real-IP`/`X-Forwarded-For`，见 [`extract_client_ip`]) 与浏览器特征
+    /// (`User-Agent`、`Accept-Language`、`Accept`、`DNT`)
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let ip = extract_client_ip(headers).map_or_else(|| "unknown-ip".to_string(), |ip| ip.to_string());
+        Self::new()
+            .field("ip", ip)
+            .header(headers, "user-agent")
+            .header(headers, "accept-language")
+            .header(headers, "accept")
+            .header(headers, "dnt")
+    }
+
+    /// 加入字段
+    pub fn field(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.fields.push((name, value.into()));
+        self
+    }
+
+    /// 加入请求头，缺失或非 ASCII 时为空
+    pub fn header(self, headers: &HeaderMap, name: &'static str) -> Self {
+        let value = headers.get(name).and_then(|h| h.to_str().ok()).unwrap_or_default();
+        self.field(name, value)
+    }
+
+    /// 生成指纹 (HMAC-SHA256 的前16位十六进制)
+    pub fn build(&self) -> String {
+        let mut mac = HmacSha256::new_from_slice(&SECRET.read().unwrap())
+            .expect("HMAC can take key of any size");
+        // 带上字段名并逐行分隔，避免不同字段的值拼接后相同
+        for (name, value) in &self.fields {
+            mac.update(format!("{}={}\n", name, value).as_bytes());
+        }
+        let mut fingerprint = hex::encode(mac.finalize().into_bytes());
+        fingerprint.truncate(FINGERPRINT_LEN);
+        fingerprint
+    }
+}
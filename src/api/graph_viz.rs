@@ -0,0 +1,93 @@
+//! Graphviz DOT 格式输出
+//!
+//! 仅做字符串拼接，不依赖外部 graphviz 库。输出可直接交给 `dot -Tsvg` 渲染
+
+use std::fmt::Write;
+
+/// 边的样式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeStyle {
+    /// 实线，如 `next_id`
+    Solid,
+    /// 虚线，如分支节点的 `else_id`
+    Dashed,
+}
+
+/// 有向图
+///
+/// 用法:
+///
+/// ```ignore
+/// let mut graph = DotGraph::new("nodes");
+/// graph.add_node("a", "a\n(basic)");
+/// graph.add_edge("a", "b", EdgeStyle::Solid);
+/// let dot = graph.to_dot();
+/// ```
+#[derive(Debug, Default)]
+pub struct DotGraph {
+    name: String,
+    nodes: Vec<(String, String)>,
+    edges: Vec<(String, String, EdgeStyle)>,
+}
+
+impl DotGraph {
+    pub fn new(name: &str) -> Self {
+        DotGraph {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 添加节点
+    ///
+    /// - `id` 节点ID
+    /// - `label` 显示文本 (可含换行)
+    pub fn add_node(&mut self, id: &str, label: &str) {
+        self.nodes.push((id.to_string(), label.to_string()));
+    }
+
+    /// 添加边
+    pub fn add_edge(&mut self, from: &str, to: &str, style: EdgeStyle) {
+        self.edges.push((from.to_string(), to.to_string(), style));
+    }
+
+    /// 序列化为 DOT 字符串
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        // 写入 String 不会失败，忽略 fmt::Result
+        let _ = writeln!(out, "digraph {} {{", quote(&self.name));
+        let _ = writeln!(out, "    node [shape=box];");
+        for (id, label) in &self.nodes {
+            let _ = writeln!(out, "    {} [label={}];", quote(id), quote(label));
+        }
+        for (from, to, style) in &self.edges {
+            match style {
+                EdgeStyle::Solid => {
+                    let _ = writeln!(out, "    {} -> {};", quote(from), quote(to));
+                }
+                EdgeStyle::Dashed => {
+                    let _ = writeln!(out, "    {} -> {} [style=dashed];", quote(from), quote(to));
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// 转为 DOT 的双引号字符串，转义引号、反斜杠和换行
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
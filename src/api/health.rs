@@ -0,0 +1,139 @@
+//! 健康检查
+//!
+//! - `GET /health/live`: 存活探针，立即返回，不做任何检查
+//! - `GET /health/ready`: 就绪探针，运行所有已注册的检查，任一失败则返回 `503`
+//!
+//! 各子系统在初始化时通过 [`register`] 注册自己的检查项。
+//! 存活探针同时返回实际监听的地址，见 [`set_listen_addrs`]
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use once_cell::sync::{Lazy, OnceCell};
+use serde_json::{json, Map, Value};
+use std::sync::RwLock;
+
+use crate::container::{Container, MapBackend};
+
+/// 检查项
+pub trait HealthCheck: Send + Sync {
+    /// 检查项名称，作为响应中 `checks` 的键
+    fn name(&self) -> &str;
+
+    /// 执行检查，失败时返回原因
+    fn check(&self) -> Result<(), String>;
+}
+
+/// 检查项注册表
+#[derive(Default)]
+pub struct HealthCheckRegistry {
+    checks: RwLock<Vec<Box<dyn HealthCheck>>>,
+}
+
+impl HealthCheckRegistry {
+    pub fn register(&self, check: impl HealthCheck + 'static) {
+        self.checks.write().unwrap().push(Box::new(check));
+    }
+
+    /// 运行所有检查，返回 (是否全部通过, 各项结果)
+    pub fn run(&self) -> (bool, Map<String, Value>) {
+        let mut all_ok = true;
+        let mut results = Map::new();
+        for check in self.checks.read().unwrap().iter() {
+            let status = match check.check() {
+                Ok(()) => "ok",
+                Err(reason) => {
+                    tracing::warn!("health check {} failed: {}", check.name(), reason);
+                    all_ok = false;
+                    "failed"
+                }
+            };
+            results.insert(check.name().to_string(), Value::from(status));
+        }
+        (all_ok, results)
+    }
+}
+
+/// 全局注册表
+static HEALTH_CHECKS: Lazy<HealthCheckRegistry> = Lazy::new(HealthCheckRegistry::default);
+
+/// 实际监听的地址
+static LISTEN_ADDRS: OnceCell<Vec<String>> = OnceCell::new();
+
+/// 记录实际监听的地址 (启动时调用一次)
+pub fn set_listen_addrs(addrs: Vec<String>) {
+    let _ = LISTEN_ADDRS.set(addrs);
+}
+
+/// 注册检查项
+pub fn register(check: impl HealthCheck + 'static) {
+    HEALTH_CHECKS.register(check);
+}
+
+// #region 检查项
+
+/// 以闭包实现的检查项
+pub struct FnCheck<F> {
+    name: String,
+    check: F,
+}
+
+impl<F> FnCheck<F>
+where
+    F: Fn() -> Result<(), String> + Send + Sync,
+{
+    pub fn new(name: impl Into<String>, check: F) -> Self {
+        Self { name: name.into(), check }
+    }
+}
+
+impl<F> HealthCheck for FnCheck<F>
+where
+    F: Fn() -> Result<(), String> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self) -> Result<(), String> {
+        (self.check)()
+    }
+}
+
+/// 注册容器的存储检查 (`storage.{name}`)
+///
+/// 数据目前仅在内存中，只需检查锁是否中毒
+pub fn register_storage<T, M>(container: &Container<T, M>, name: &str)
+where
+    T: Clone + Send + Sync + 'static,
+    M: MapBackend<T> + Clone + Send + Sync + 'static,
+{
+    let container = container.clone();
+    register(FnCheck::new(format!("storage.{}", name), move || {
+        if container.is_poisoned() {
+            Err("container lock poisoned".to_string())
+        } else {
+            Ok(())
+        }
+    }));
+}
+
+// #endregion
+
+/// GET /health/live, 存活探针
+/// 
+/// `listening` 为实际监听的地址
+pub async fn get_health_live() -> impl IntoResponse {
+    Json(json!({ "status": "alive", "listening": LISTEN_ADDRS.get() }))
+}
+
+/// GET /health/ready, 就绪探针
+pub async fn get_health_ready() -> impl IntoResponse {
+    let (ok, checks) = HEALTH_CHECKS.run();
+    if ok {
+        (StatusCode::OK, Json(json!({ "status": "ready", "checks": checks })))
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "status": "not_ready", "checks": checks })))
+    }
+}
@@ -1,120 +1,183 @@
 //! 用于心跳检测的API
 
 use axum::{
+    extract::Query,
     http::{HeaderMap, StatusCode},
     response::{IntoResponse, Json},
-    routing::get,
     Router,
-    // extract::ConnectInfo,
-    // Extension,
 };
 use axum_extra::extract::{
-    // cookie::Cookie,
+    cookie::{Cookie, SameSite},
     CookieJar,
 };
+use serde::{Deserialize, Serialize};
 use serde_json::{json};
 use once_cell::sync::Lazy;
-use tokio::sync::RwLock;
-// use uuid::Uuid;
+use uuid::Uuid;
 use std::{
-    collections::HashMap, sync::atomic::{AtomicU32, Ordering}, time::{Duration, Instant}
+    collections::VecDeque,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
 };
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use sysinfo::{Pid, ProcessesToUpdate, System};
 
-use crate::node::utils::NODE_LIST;
+use crate::api::client_ip::extract_client_ip;
+use crate::api::extractors::AdminRequired;
+use crate::api::fingerprint::{self, FingerprintBuilder};
+use crate::api::middleware::jwt::JwtAuthLayer;
+use crate::api::health::{self, FnCheck};
+use crate::container::{Container, HookEvent};
+use crate::node::registry::NODE_LIST;
+use crate::supervisor::TaskSupervisor;
+use crate::documented_route;
+
+/// 会话超时 (秒) 的环境变量
+const SESSION_TIMEOUT_ENV: &str = "SESSION_TIMEOUT_SECS";
+/// 清理间隔 (秒) 的环境变量
+const CLEANUP_INTERVAL_ENV: &str = "CLEANUP_INTERVAL_SECS";
+/// 会话识别方式的环境变量，见 [`SessionStrategy`]
+const SESSION_STRATEGY_ENV: &str = "SESSION_STRATEGY";
+/// 会话ID的 cookie 名
+const SESSION_COOKIE: &str = "session_id";
+/// 默认会话超时 (秒)
+const DEFAULT_SESSION_TIMEOUT_SECS: u64 = 5;
+/// 默认清理间隔 (秒)
+const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 5;
+/// 在线用户数历史的样本数 (每10秒一个，共1小时)
+const HISTORY_CAPACITY: usize = 360;
+/// 在线用户数历史的采样间隔
+const HISTORY_SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
 
 /// 工具路由
 /// 
 /// 包括心跳检测和常用工具等
 pub fn factory_utils_router() -> Router {
     // 启动清理任务
-    start_cleanup_task(None);
+    let config = SessionConfig {
+        timeout_secs: env_secs(SESSION_TIMEOUT_ENV, DEFAULT_SESSION_TIMEOUT_SECS),
+        interval_secs: env_secs(CLEANUP_INTERVAL_ENV, DEFAULT_CLEANUP_INTERVAL_SECS),
+        strategy: SessionStrategy::from_env(),
+    };
+    start_session_cleanup_task(Duration::from_secs(config.timeout_secs), Duration::from_secs(config.interval_secs));
+    health::register(FnCheck::new("background_tasks", TaskSupervisor::check_all_running));
 
-    let app = Router::new()
-        .route("/heartbeat", get(get_heartbeat))
-        .route("/nodelist", get(get_nodelist));
-    app
+    Router::new()
+        .merge(documented_route!(get, "/heartbeat/config", move |admin: AdminRequired| get_heartbeat_config(admin, config), "会话清理的配置"))
+        .route_layer(JwtAuthLayer) // 仅作用于以上路由
+        .merge(documented_route!(get, "/heartbeat", move |cookie_jar: CookieJar, headers: HeaderMap| get_heartbeat(cookie_jar, headers, config.strategy), "心跳 (在线用户数)"))
+        .merge(documented_route!(get, "/heartbeat/history", get_heartbeat_history, "在线用户数的历史"))
+        .merge(documented_route!(get, "/health/live", health::get_health_live, "存活探针"))
+        .merge(documented_route!(get, "/health/ready", health::get_health_ready, "就绪探针"))
+        .merge(documented_route!(get, "/nodelist", get_nodelist, "节点列表"))
 }
 
 /// GET /heartbeat, 心脏检测
 /// 
 /// 可能有一些额外的服务器信息，如:
-/// - 在线用户数 (cookie/fingerprint)
+/// - 在线用户数 (按会话计，会话的识别方式见 [`SessionStrategy`])
 /// - 服务器时间
-/// - 设备信息 (内存、CPU使用率等)
+/// - 设备信息 (内存、CPU使用率等)，每5秒刷新一次。平台不支持时不返回这些字段
 /// - 等
 /// 
 /// args:
-/// - `cookie_jar` 用于获取或设置会话ID。
-///   弊端: 如果客户端是非浏览器环境，而是自定义客户端，则需要该自定义客户端支持cookie
+/// - `cookie_jar` 用于获取或设置会话ID (cookie 方式)
+/// - `strategy` 会话的识别方式
 pub async fn get_heartbeat(
-    _cookie_jar: CookieJar,
+    cookie_jar: CookieJar,
     headers: HeaderMap,
+    strategy: SessionStrategy,
 ) -> impl IntoResponse {
-    /*// 获取会话id
-    let (new_session_id, new_cookie_jar) =
-        if let Some(cookie) = cookie_jar.get("session_id") { // 客户端带会话id
-            let cookie_value = cookie.value().to_string();
-            let user_activity = ONLINE_STATE.user_activity_time.read().await;
-
-            if user_activity.contains_key(&cookie_value) { // 服务器有此id，沿用
-                tracing::debug!("GET /heartbeat, cookies get {}", cookie_value);
-                (cookie_value, cookie_jar)
-            }
-            else { // 服务端无此id，需重新分配
-                let new_id = Uuid::new_v4().to_string();
-                let cookie = Cookie::new("session_id", new_id.clone());
-                tracing::warn!("GET /heartbeat, cookies reCreate {}", new_id);
-                (new_id, cookie_jar.add(cookie.clone()))
-            }
-        }
-        else { // 客户端不带会话id
-            let new_id = Uuid::new_v4().to_string();
-            let cookie = Cookie::new("session_id", new_id.clone());
-            tracing::debug!("GET /heartbeat, Cookies: create {}", new_id);
-            (new_id, cookie_jar.add(cookie.clone()))
-        };*/
-
-    // 获取浏览器指纹
-    let new_session_id = {
-        let user_agent = headers.get("user-agent").map(|h| h.to_str().unwrap_or("")).unwrap_or("");
-        let accept_language = headers.get("accept-language").map(|h| h.to_str().unwrap_or("")).unwrap_or("");
-        let ip = headers.get("x-forwarded-for").map(|h| h.to_str().unwrap_or(""))
-            .unwrap_or("unknown-ip");
-        
-        // 根据信息创建指纹 (可以加入更多因素或使用哈希算法)
-        let fingerprint = format!("{}:{}:{}", ip, user_agent, accept_language);
-        let mut hasher = DefaultHasher::new();
-        fingerprint.hash(&mut hasher);
-        let hash = hasher.finish().to_string();
-        tracing::debug!("GET /heartbeat, hash {}", hash);
-        hash
-    };
+    let (session_id, cookie_jar) = resolve_session(strategy, cookie_jar, &headers);
 
     // 更新用户活跃时间
-    {
-        let mut user_activity_time = ONLINE_STATE.user_activity_time.write().await;
-        // insert会返回被替换值，若None则表示之前没有这个键，即这是个新用户
-        let old_value = user_activity_time.insert(new_session_id.clone(), Instant::now());
-        if old_value.is_none() {
-            ONLINE_STATE.user_activity_count.fetch_add(1, Ordering::Relaxed);
-        }
-    };
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok()).unwrap_or_default().to_string();
+    SESSIONS.put_by_id(&session_id, SessionRecord {
+        id: session_id.clone(),
+        last_active: now_millis(),
+        ip: extract_client_ip(&headers).map_or_else(|| "unknown-ip".to_string(), |ip| ip.to_string()),
+        user_agent: header("user-agent"),
+    });
 
-    let resp = json!({
+    let mut resp = json!({
         "status": "alive",
         "timestamp": chrono::Local::now().to_rfc3339(), // 本地时间
             // chrono::Utc::now().to_rfc3339(), // 零区
             // chrono::FixedOffset::east_opt(8 * 3600).unwrap(), // 东八区
-        "online_user_count": ONLINE_STATE.user_activity_count.load(Ordering::Relaxed),
+        "online_user_count": SESSIONS.atomic_counter(ONLINE_COUNTER).get(),
     });
+    if let Some(snapshot) = SYSTEM_INFO.as_ref().and_then(SystemInfo::snapshot) {
+        resp["system_cpu_usage_percent"] = json!(snapshot.cpu_usage_percent);
+        resp["system_memory_used_bytes"] = json!(snapshot.memory_used_bytes);
+        if let Some(bytes) = snapshot.process_memory_bytes {
+            resp["process_memory_bytes"] = json!(bytes);
+        }
+    }
+
+    (cookie_jar, (StatusCode::OK, Json(resp)))
+}
+
+/// 按识别方式取得会话ID，需要新的 cookie 时加入 `cookie_jar`
+fn resolve_session(strategy: SessionStrategy, cookie_jar: CookieJar, headers: &HeaderMap) -> (String, CookieJar) {
+    let use_cookie = match strategy {
+        SessionStrategy::Cookie => true,
+        SessionStrategy::Fingerprint => false,
+        // 已持有会话 cookie 的客户端沿用之，否则有可区分的请求头时用指纹
+        SessionStrategy::Best => session_cookie(&cookie_jar).is_some() || !fingerprint::has_client_signals(headers),
+    };
+    if !use_cookie {
+        // 需要更多因素时在 FingerprintBuilder 中添加
+        return (FingerprintBuilder::from_headers(headers).build(), cookie_jar);
+    }
+    if let Some(id) = session_cookie(&cookie_jar) {
+        return (id, cookie_jar);
+    }
+    let id = Uuid::new_v4().to_string();
+    tracing::debug!("GET /heartbeat, new session cookie {}", id);
+    let cookie = Cookie::build((SESSION_COOKIE, id.clone()))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .build();
+    (id, cookie_jar.add(cookie))
+}
+
+/// 请求带的会话ID，须为 UUID (由服务器生成)，否则视为没有
+fn session_cookie(cookie_jar: &CookieJar) -> Option<String> {
+    let value = cookie_jar.get(SESSION_COOKIE)?.value();
+    Uuid::parse_str(value).ok().map(|uuid| uuid.to_string())
+}
+
+/// GET /heartbeat/history, 在线用户数的历史
+/// 
+/// `?since_secs=600` 只返回最近600秒内的样本，缺省时返回全部 (最近1小时)。样本按时间升序:
+/// `{ "samples": [{ "at": "2024-01-01T00:00:00+08:00", "count": 3 }] }`
+async fn get_heartbeat_history(Query(query): Query<HistoryQuery>) -> impl IntoResponse {
+    let now = Instant::now();
+    let now_local = chrono::Local::now();
+    let window = query.since_secs.map_or(Duration::MAX, Duration::from_secs);
+    let samples: Vec<_> = ONLINE_HISTORY.lock().unwrap()
+        .since(now, window)
+        .map(|(at, count)| {
+            let at = now_local - chrono::Duration::from_std(now - at).unwrap_or_default();
+            json!({ "at": at.to_rfc3339(), "count": count })
+        })
+        .collect();
+    Json(json!({ "samples": samples }))
+}
+
+/// GET /heartbeat/history 的查询参数
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    since_secs: Option<u64>,
+}
 
-    // (new_cookie_jar, (StatusCode::OK, Json(resp)))
-    (StatusCode::OK, Json(resp))
+/// GET /heartbeat/config, 会话清理的配置 (管理接口，需管理员)
+async fn get_heartbeat_config(_admin: AdminRequired, config: SessionConfig) -> impl IntoResponse {
+    Json(config)
 }
 
 /// GET /nodelist, 获取节点列表
+#[allow(clippy::iter_kv_map)]
 pub async fn get_nodelist() -> impl IntoResponse {
     let result = NODE_LIST
         .iter()
@@ -125,50 +188,310 @@ pub async fn get_nodelist() -> impl IntoResponse {
 
 // #region 类型
 
-/// 用户活跃状态结构
+/// 会话清理的配置
 /// 
-/// TODO 感觉可以连同里面的操作方法封装成一个对象
-struct OnlineState {
-    // user_activity_time: RwLock<HashMap<SocketAddr, Instant>>, // 存储用户最后活跃时间 (Ip)
-    user_activity_time: RwLock<HashMap<String, Instant>>, // 存储用户最后活跃时间 (会话ID)
-    user_activity_count: AtomicU32, // 原子计数器用于快速查询
+/// - `timeout_secs` 超过该时长无心跳的用户视为离线
+/// - `interval_secs` 清理任务的执行间隔
+/// - `strategy` 会话的识别方式
+#[derive(Debug, Clone, Copy, Serialize)]
+struct SessionConfig {
+    timeout_secs: u64,
+    interval_secs: u64,
+    strategy: SessionStrategy,
 }
-/// 全局在线状态
+
+/// 心跳会话的识别方式，由环境变量 `SESSION_STRATEGY` (`cookie`/`fingerprint`/`best`) 指定，默认 `best`
+/// 
+/// - `Cookie` 首次心跳时分配随机的 UUID，以 `session_id` cookie 下发。识别准确，
+///   但 cookie 是持久的客户端标识 (部分地区的法规要求事先征得用户同意)，且客户端须支持 cookie
+/// - `Fingerprint` 由请求头计算指纹 (见 [`FingerprintBuilder`])，不在客户端存储任何内容。
+///   但同一网络下配置相同的浏览器会被计为一个，缺少这些请求头的客户端 (如简单的 HTTP 客户端) 全部相同
+/// - `Best` 有可区分的请求头时用指纹，都缺失时退回 cookie (见 [`fingerprint::has_client_signals`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStrategy {
+    Cookie,
+    Fingerprint,
+    #[default]
+    Best,
+}
+
+impl SessionStrategy {
+    /// 读取 `SESSION_STRATEGY`，未设置或无效时使用默认值
+    fn from_env() -> Self {
+        match std::env::var(SESSION_STRATEGY_ENV).as_deref() {
+            Ok("cookie") => Self::Cookie,
+            Ok("fingerprint") => Self::Fingerprint,
+            Ok("best") | Err(_) => Self::Best,
+            Ok(value) => {
+                tracing::warn!("invalid {}={}, using default best", SESSION_STRATEGY_ENV, value);
+                Self::Best
+            }
+        }
+    }
+}
+
+/// 在线用户 (会话) 记录
 /// 
-/// type: 线程安全只初始化一次<自定义结构体>
-static ONLINE_STATE: Lazy<OnlineState> = Lazy::new(|| OnlineState {
-    user_activity_time: RwLock::new(HashMap::new()),
-    user_activity_count: AtomicU32::new(0),
+/// - `id` 会话ID (浏览器指纹或 cookie 中的 UUID)
+/// - `last_active` 最近一次心跳的时间 (Unix 毫秒)
+/// - `ip` 客户端地址 (`X-Real-IP`/`X-Forwarded-For`，见 [`extract_client_ip`])
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRecord {
+    pub id: String,
+    pub last_active: u64,
+    pub ip: String,
+    pub user_agent: String,
+}
+
+/// 全局在线状态，以会话ID为索引。在线用户数即容器的项数
+static SESSIONS: Lazy<Arc<Container<SessionRecord>>> = Lazy::new(|| {
+    let sessions = Container::new_arc();
+    // 在线用户数由钩子维护 (钩子在写锁内执行，与项数一致)，心跳读取时无需加锁
+    let online = sessions.atomic_counter(ONLINE_COUNTER);
+    sessions.add_hook(move |event| match event {
+        HookEvent::Put { old: None, .. } => { online.fetch_add(1, Ordering::Relaxed); }
+        HookEvent::Put { .. } => {}
+        HookEvent::Delete { .. } => { online.fetch_sub(1, Ordering::Relaxed); }
+    });
+    sessions
+});
+/// 在线用户数的计数器
+const ONLINE_COUNTER: &str = "online";
+
+/// 系统资源信息
+/// 
+/// `System` 的刷新较慢 (需读取 /proc 等)，由清理任务定时刷新，心跳只读取缓存的快照
+struct SystemInfo {
+    system: std::sync::Mutex<System>,
+    /// 本进程
+    pid: Option<Pid>,
+    snapshot: std::sync::Mutex<Option<SystemSnapshot>>,
+}
+
+/// 系统资源快照
+/// 
+/// - `process_memory_bytes` 本进程的常驻内存 (RSS)
+#[derive(Debug, Clone, Copy)]
+struct SystemSnapshot {
+    cpu_usage_percent: f32,
+    memory_used_bytes: u64,
+    process_memory_bytes: Option<u64>,
+}
+
+impl SystemInfo {
+    /// 刷新并更新快照
+    /// 
+    /// CPU 使用率由两次刷新之间的差值计算，因此首次快照的 CPU 使用率为0
+    fn refresh(&self) {
+        let mut system = self.system.lock().unwrap();
+        system.refresh_cpu_usage();
+        system.refresh_memory();
+        if let Some(pid) = self.pid {
+            system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        }
+        let snapshot = SystemSnapshot {
+            cpu_usage_percent: system.global_cpu_usage(),
+            memory_used_bytes: system.used_memory(),
+            process_memory_bytes: self.pid.and_then(|pid| system.process(pid)).map(|process| process.memory()),
+        };
+        *self.snapshot.lock().unwrap() = Some(snapshot);
+    }
+
+    /// 最近一次的快照，尚未刷新过时为 None
+    fn snapshot(&self) -> Option<SystemSnapshot> {
+        *self.snapshot.lock().unwrap()
+    }
+}
+
+/// 全局系统资源信息，平台不支持时为 None
+static SYSTEM_INFO: Lazy<Option<SystemInfo>> = Lazy::new(|| {
+    sysinfo::IS_SUPPORTED_SYSTEM.then(|| SystemInfo {
+        system: std::sync::Mutex::new(System::new()),
+        pid: sysinfo::get_current_pid().ok(),
+        snapshot: std::sync::Mutex::new(None),
+    })
 });
 
+/// 在线用户数的历史，最多 [`HISTORY_CAPACITY`] 个样本的环形缓冲，最早的在前
+/// 
+/// 由清理任务在每次清理后记录，距上一个样本不足 [`HISTORY_SAMPLE_INTERVAL`] 时跳过
+/// (清理间隔较短时不致过快覆盖早先的样本)
+#[derive(Debug, Default)]
+struct OnlineHistory {
+    samples: VecDeque<(Instant, u32)>,
+}
+
+impl OnlineHistory {
+    /// 记录一个样本，缓冲已满时丢弃最早的
+    fn record(&mut self, at: Instant, count: u32) {
+        // 容许1秒的误差，清理任务的执行时刻并不精确
+        let too_soon = self.samples.back()
+            .is_some_and(|(last, _)| at.saturating_duration_since(*last) + Duration::from_secs(1) < HISTORY_SAMPLE_INTERVAL);
+        if too_soon {
+            return;
+        }
+        if self.samples.len() == HISTORY_CAPACITY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((at, count));
+    }
+
+    /// `now` 之前 `window` 内的样本
+    fn since(&self, now: Instant, window: Duration) -> impl Iterator<Item = (Instant, u32)> + '_ {
+        self.samples.iter()
+            .copied()
+            .filter(move |(at, _)| now.saturating_duration_since(*at) <= window)
+    }
+}
+
+/// 全局的在线用户数历史
+static ONLINE_HISTORY: Lazy<Mutex<OnlineHistory>> = Lazy::new(Mutex::default);
+
 // #endregion
 
+/// 所有在线用户，按最近活跃时间降序
+pub fn list_sessions() -> Vec<SessionRecord> {
+    SESSIONS.get_all_sorted_by(|session| std::cmp::Reverse(session.last_active))
+}
+
+/// 使会话立即过期，返回是否存在
+/// 
+/// 客户端下次心跳时会重新计入
+pub fn expire_session(id: &str) -> bool {
+    SESSIONS.delete_by_id(id).is_some()
+}
+
+/// 清空在线用户，返回清除的数量
+/// 
+/// 更换指纹密钥后调用，此后客户端以新指纹重新计入
+pub fn clear_sessions() -> usize {
+    SESSIONS.delete_where(|_| true).len()
+}
+
+/// 当前时间 (Unix 毫秒)
+fn now_millis() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+/// 读取秒数类型的环境变量，未设置或无效 (非正整数) 时使用默认值
+fn env_secs(name: &str, default: u64) -> u64 {
+    match std::env::var(name) {
+        Ok(value) => match value.parse() {
+            Ok(secs) if secs > 0 => secs,
+            _ => {
+                tracing::warn!("invalid {}={}, using default {}", name, value, default);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
 /// 后台任务，定时清理不活跃用户
 /// 
-/// 检测到成出时间的用户，删除之，并使活跃用户数-1
+/// 检测到成出时间的用户，删除之，并使活跃用户数-1。同时刷新系统资源信息，记录在线用户数的历史
 /// 
 /// args
-/// - `timeout_time` 超时时间 (默认5)
-/// - `interval_time` 检测频率 (略，默认5)
+/// - `timeout` 超时时间 (精确到毫秒)
+/// - `interval` 检测频率
 /// - 补充:
-///   最快刷新频率 = timeout_time，最慢刷新频率 = timeout_time + interval_time
-pub fn start_cleanup_task(timeout: Option<u64>) {
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(5));
-        loop {
-            interval.tick().await;
-            
-            // 移除超过30秒不活跃的用户
-            let mut user_activity_time = ONLINE_STATE.user_activity_time.write().await;
-            let before_count = user_activity_time.len();
-            let now = Instant::now();
-            user_activity_time.retain(|_, &mut last_active| now.duration_since(last_active) < Duration::from_secs(timeout.unwrap_or(5)));
-            let after_count = user_activity_time.len();
-            
-            // 如果有变化则更新计数器
-            if before_count != after_count {
-                ONLINE_STATE.user_activity_count .store(after_count as u32, Ordering::Relaxed);
-            }
+///   最快刷新频率 = timeout，最慢刷新频率 = timeout + interval
+/// 
+/// 由 [`TaskSupervisor`] 监管，意外退出时自动重启
+pub fn start_session_cleanup_task(timeout: Duration, interval: Duration) {
+    TaskSupervisor::spawn("heartbeat_cleanup", move || cleanup_loop(timeout, interval));
+}
+
+/// 清理任务的主循环
+async fn cleanup_loop(timeout: Duration, interval: Duration) {
+    let timeout_millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+
+        if SYSTEM_INFO.is_some() {
+            let _ = tokio::task::spawn_blocking(|| SYSTEM_INFO.as_ref().map(SystemInfo::refresh)).await;
         }
-    });
+        
+        // 移除超时不活跃的用户
+        let threshold = now_millis().saturating_sub(timeout_millis);
+        SESSIONS.delete_where(|session| session.last_active < threshold);
+
+        let online = SESSIONS.atomic_counter(ONLINE_COUNTER).get();
+        ONLINE_HISTORY.lock().unwrap().record(Instant::now(), u32::try_from(online).unwrap_or(u32::MAX));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn online_history() {
+        let start = Instant::now();
+        let mut history = OnlineHistory::default();
+        history.record(start, 1);
+        history.record(start + Duration::from_secs(5), 2); // 不足采样间隔，跳过
+        history.record(start + Duration::from_millis(9500), 3);
+        assert_eq!(history.samples.iter().map(|(_, count)| *count).collect::<Vec<_>>(), [1, 3]);
+
+        for i in 2..=HISTORY_CAPACITY as u64 {
+            history.record(start + HISTORY_SAMPLE_INTERVAL * i as u32, i as u32);
+        }
+        assert_eq!(history.samples.len(), HISTORY_CAPACITY);
+        assert_eq!(history.samples.front().map(|(_, count)| *count), Some(3));
+
+        let now = start + HISTORY_SAMPLE_INTERVAL * HISTORY_CAPACITY as u32;
+        let recent: Vec<u32> = history.since(now, Duration::from_secs(30)).map(|(_, count)| count).collect();
+        assert_eq!(recent, [357, 358, 359, 360]);
+    }
+
+    #[test]
+    fn session_strategies() {
+        let set_cookie = |jar: &CookieJar| jar.get(SESSION_COOKIE).map(|cookie| cookie.value().to_string());
+        let mut browser = HeaderMap::new();
+        browser.insert("user-agent", "Mozilla/5.0".parse().unwrap());
+
+        // 有请求头时用指纹，不下发 cookie
+        let (id, jar) = resolve_session(SessionStrategy::Best, CookieJar::new(), &browser);
+        assert_eq!(id, FingerprintBuilder::from_headers(&browser).build());
+        assert_eq!(set_cookie(&jar), None);
+
+        // 请求头都缺失时退回 cookie，之后沿用
+        let (id, jar) = resolve_session(SessionStrategy::Best, CookieJar::new(), &HeaderMap::new());
+        assert_eq!(set_cookie(&jar), Some(id.clone()));
+        let (again, _) = resolve_session(SessionStrategy::Best, jar.clone(), &HeaderMap::new());
+        assert_eq!(again, id);
+        let (again, _) = resolve_session(SessionStrategy::Best, jar, &browser);
+        assert_eq!(again, id);
+
+        // 非 UUID 的 cookie 视为没有
+        let forged = CookieJar::new().add(Cookie::new(SESSION_COOKIE, "forged"));
+        let (id, jar) = resolve_session(SessionStrategy::Cookie, forged, &browser);
+        assert_ne!(id, "forged");
+        assert_eq!(set_cookie(&jar), Some(id));
+
+        let (id, _) = resolve_session(SessionStrategy::Fingerprint, CookieJar::new(), &HeaderMap::new());
+        assert_eq!(id, FingerprintBuilder::from_headers(&HeaderMap::new()).build());
+    }
+
+    #[tokio::test]
+    async fn sessions_expire() {
+        start_session_cleanup_task(Duration::from_millis(100), Duration::from_millis(50));
+        let id = uuid::Uuid::new_v4().to_string();
+        let jar = CookieJar::new().add(Cookie::new(SESSION_COOKIE, id.clone()));
+        let started = Instant::now();
+        let _ = get_heartbeat(jar, HeaderMap::new(), SessionStrategy::Cookie).await;
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(SESSIONS.get_by_id(&id).is_some(), "expired before the timeout");
+
+        // 超时后的下一次清理时删除 (清理任务的首次执行需刷新系统资源信息，可能较慢，因此轮询)
+        while SESSIONS.get_by_id(&id).is_some() {
+            assert!(started.elapsed() < Duration::from_secs(5), "not expired after the timeout");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
 }
@@ -0,0 +1,229 @@
+//! 节点链的后台执行
+//!
+//! `POST /node/{id}/run/async` 把执行请求放入队列后立即返回任务ID，由固定数量的工作任务依次取出执行，
+//! `GET /node/jobs/{job_id}` 查询状态与结果，`DELETE /node/jobs/{job_id}` 取消。
+//!
+//! 工作任务数由环境变量 `NODE_WORKER_THREADS` 指定，默认为 CPU 核数。
+//! 工作任务受 [`TaskSupervisor`] 监管，可在 `GET /admin/tasks` 中查看。
+//! 任务记录只存于内存，完成 1 小时后清理
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::api::rest_node;
+use crate::container::Container;
+use crate::supervisor::TaskSupervisor;
+
+/// 工作任务数的环境变量
+const WORKER_THREADS_ENV: &str = "NODE_WORKER_THREADS";
+/// 队列容量，满时拒绝提交
+const QUEUE_CAPACITY: usize = 1024;
+/// 已完成的任务记录的保留时长 (秒)
+const RECORD_TTL_SECS: i64 = 60 * 60;
+
+/// 全局队列，首次访问时启动工作任务
+static QUEUE: Lazy<JobQueue> = Lazy::new(JobQueue::start);
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// 任务记录
+///
+/// - `result` 执行记录 (同 `POST /node/{id}/run` 的响应)，失败时含出错前已完成的步骤，
+///   执行中取消时为 `{ "executed_nodes": [...], "running": [...] }`
+/// - `error` 失败时的错误信息
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRecord {
+    pub id: String,
+    pub node_id: String,
+    pub status: JobStatus,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    cancel: CancellationToken,
+}
+
+/// 队列中的任务
+#[derive(Debug)]
+struct Job {
+    id: String,
+    node_id: String,
+    input: Value,
+    cancel: CancellationToken,
+}
+
+/// 任务队列
+pub struct JobQueue {
+    sender: mpsc::Sender<Job>,
+    records: Arc<Container<JobRecord>>,
+}
+
+impl JobQueue {
+    /// 创建队列并启动工作任务 (各工作任务共享同一个接收端)
+    fn start() -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let records = Container::new_arc();
+        let workers = worker_count();
+        for index in 0..workers {
+            let receiver = receiver.clone();
+            let records = records.clone();
+            TaskSupervisor::spawn(&format!("node_worker.{}", index), move || worker(receiver.clone(), records.clone()));
+        }
+        tracing::info!("node job queue started with {} workers", workers);
+        Self { sender, records }
+    }
+}
+
+/// 启动工作任务 (否则在首次提交时启动)
+pub fn start_workers() {
+    Lazy::force(&QUEUE);
+}
+
+/// 提交任务，返回任务记录。队列已满时返回 `Err`
+pub fn submit(node_id: &str, input: Value) -> Result<JobRecord, String> {
+    let now = Utc::now();
+    QUEUE.records.delete_where(|record| record.finished_at.is_some_and(|at| (now - at).num_seconds() > RECORD_TTL_SECS)); // 顺带清理
+    let record = JobRecord {
+        id: Uuid::new_v4().to_string(),
+        node_id: node_id.to_string(),
+        status: JobStatus::Queued,
+        result: None,
+        error: None,
+        created_at: now,
+        started_at: None,
+        finished_at: None,
+        cancel: CancellationToken::new(),
+    };
+    QUEUE.records.put_by_id(&record.id, record.clone());
+    let job = Job { id: record.id.clone(), node_id: node_id.to_string(), input, cancel: record.cancel.clone() };
+    if QUEUE.sender.try_send(job).is_err() {
+        QUEUE.records.delete_by_id(&record.id);
+        return Err("job queue is full".to_string());
+    }
+    Ok(record)
+}
+
+/// 查询任务
+pub fn get(job_id: &str) -> Option<JobRecord> {
+    QUEUE.records.get_by_id(job_id)
+}
+
+/// 取消任务，返回取消后的记录，不存在时返回 `None`
+/// 
+/// 排队中的任务不再执行，执行中的任务在当前节点完成前中断 (同步执行的脚本除外)。
+/// 已结束的任务不变，返回 `Err(其状态)`
+pub fn cancel(job_id: &str) -> Option<Result<JobRecord, JobStatus>> {
+    let updated = QUEUE.records.update_by_id(job_id, |record| {
+        if record.finished_at.is_some() {
+            return Err(record.status);
+        }
+        record.cancel.cancel();
+        let mut record = record.clone();
+        if record.status == JobStatus::Queued {
+            record.finished_at = Some(Utc::now());
+        }
+        record.status = JobStatus::Cancelled;
+        Ok(record)
+    })?;
+    Some(updated.map(|(_, record)| record))
+}
+
+/// 工作任务: 依次取出并执行任务，通道关闭时退出
+async fn worker(receiver: Arc<Mutex<mpsc::Receiver<Job>>>, records: Arc<Container<JobRecord>>) {
+    loop {
+        // 只在取任务时持有锁，执行期间其他工作任务可继续取
+        let Some(job) = receiver.lock().await.recv().await else { return };
+        let Some(mut record) = records.get_by_id(&job.id) else { continue };
+        if job.cancel.is_cancelled() {
+            continue; // 排队时已取消
+        }
+        record.status = JobStatus::Running;
+        record.started_at = Some(Utc::now());
+        records.put_by_id(&job.id, record.clone());
+
+        tracing::debug!("node job {} started, node:{}", job.id, job.node_id);
+        let (result, error) = rest_node::run_chain_job(&job.node_id, job.input, job.cancel.clone()).await;
+        record.status = match (job.cancel.is_cancelled(), error.is_some()) {
+            (true, _) => JobStatus::Cancelled,
+            (false, true) => JobStatus::Failed,
+            (false, false) => JobStatus::Completed,
+        };
+        record.result = result;
+        record.error = error;
+        record.finished_at = Some(Utc::now());
+        records.put_by_id(&job.id, record);
+    }
+}
+
+/// 工作任务数，见 [`WORKER_THREADS_ENV`]
+fn worker_count() -> usize {
+    std::env::var(WORKER_THREADS_ENV).ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|&count| count > 0)
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |count| count.get()))
+}
+
+// 测试用 Script 节点模拟长时间执行的任务
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    /// 轮询任务直到满足条件 (最多约1秒)
+    async fn wait_for(job_id: &str, done: impl Fn(&JobRecord) -> bool) -> JobRecord {
+        for _ in 0..50 {
+            let record = get(job_id).expect("job record");
+            if done(&record) {
+                return record;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        get(job_id).expect("job record")
+    }
+
+    // 工作任务运行在首个使用队列的测试的运行时中，所以只有这一个测试提交任务
+    #[tokio::test]
+    async fn cancel_running_job() {
+        // 死循环，直到用完操作数才结束，使 Script 节点一直执行
+        let script = "fn run(input) { let s = \"\"; loop { s = s + \"x\"; if s.len() > 10000 { s = \"\"; } } }";
+        let nodes = rest_node::node_container();
+        for (id, node) in [
+            ("jobs-start", json!({ "id": "jobs-start", "next_id": "jobs-slow", "children_ids": ["jobs-slow"] })),
+            ("jobs-slow", json!({ "id": "jobs-slow", "kind": "script", "config": { "script": script } })),
+        ] {
+            nodes.put_by_id(id, serde_json::from_value(node).unwrap());
+        }
+
+        start_workers();
+        let job = submit("jobs-start", Value::Null).unwrap();
+        wait_for(&job.id, |record| record.status == JobStatus::Running).await;
+        let cancelled = cancel(&job.id).unwrap().unwrap();
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+
+        let record = wait_for(&job.id, |record| record.finished_at.is_some()).await;
+        assert_eq!(record.status, JobStatus::Cancelled);
+        assert_eq!(record.error.as_deref(), Some("cancelled"));
+        assert_eq!(record.result, Some(json!({ "executed_nodes": ["jobs-start"], "running": ["jobs-slow"] })));
+        assert_eq!(cancel(&job.id).unwrap().unwrap_err(), JobStatus::Cancelled);
+        assert!(cancel("missing").is_none());
+    }
+}
@@ -0,0 +1,113 @@
+//! JSON 路径
+//!
+//! 简单的 JSON Pointer (RFC 6901): `/` 分隔各级，数组用下标，如 `/data/tags/0`。
+//! 键中的 `~`、`/` 分别写作 `~0`、`~1`。
+//! 查询参数中也可用 `.` 分隔的写法 (如 `data.name`)，见 [`parse_field`]
+
+use serde_json::Value;
+
+/// 解析 JSON Pointer 为各级的键
+///
+/// 空串表示根。非空时须以 `/` 开头，`~` 后只能是 `0` 或 `1`
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err("pointer must start with '/'".to_string());
+    };
+    rest.split('/').map(unescape).collect()
+}
+
+/// 把查询参数中的字段转为 JSON Pointer: 以 `/` 开头的原样 (校验格式)，否则按 `.` 分隔 (各级不能为空)
+pub fn parse_field(field: &str) -> Result<String, String> {
+    if field.is_empty() || field.starts_with('/') {
+        return parse_pointer(field).map(|_| field.to_string());
+    }
+    field.split('.').try_fold(String::new(), |mut pointer, key| {
+        if key.is_empty() {
+            return Err("field must not contain empty segments".to_string());
+        }
+        pointer.push('/');
+        pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+        Ok(pointer)
+    })
+}
+
+/// 按 JSON Pointer 取值，路径不存在或格式有误时为 `None`
+pub fn value_at_pointer<'a>(root: &'a Value, pointer: &str) -> Option<&'a Value> {
+    parse_pointer(pointer).ok()?.iter().try_fold(root, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        // 下标不允许前导零 (RFC 6901)
+        Value::Array(items) if key == "0" || !key.starts_with('0') => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => None,
+    })
+}
+
+/// 按 JSON Pointer 写入值，返回被替换的旧值
+///
+/// 中间缺失的键 (及 `null`) 按对象补全。数组下标须已存在，或为数组长度/`-` (追加)。
+/// 经过的值不是对象或数组、下标越界或格式有误时返回错误
+pub fn set_at_pointer(root: &mut Value, pointer: &str, value: Value) -> Result<Option<Value>, String> {
+    let keys = parse_pointer(pointer)?;
+    let Some((last, parents)) = keys.split_last() else {
+        return Ok(Some(std::mem::replace(root, value)));
+    };
+    let mut current = root;
+    for key in parents {
+        if current.is_null() {
+            *current = Value::Object(Default::default());
+        }
+        current = match current {
+            Value::Object(map) => map.entry(key.clone()).or_insert(Value::Null),
+            Value::Array(items) => {
+                let index = array_index(key)?;
+                items.get_mut(index).ok_or_else(|| format!("index out of bounds: {}", key))?
+            }
+            _ => return Err(format!("cannot set '{}': parent is not an object or array", key)),
+        };
+    }
+    if current.is_null() {
+        *current = Value::Object(Default::default());
+    }
+    match current {
+        Value::Object(map) => Ok(map.insert(last.clone(), value)),
+        Value::Array(items) => {
+            let index = if last == "-" { items.len() } else { array_index(last)? };
+            match index.cmp(&items.len()) {
+                std::cmp::Ordering::Less => Ok(Some(std::mem::replace(&mut items[index], value))),
+                std::cmp::Ordering::Equal => {
+                    items.push(value);
+                    Ok(None)
+                }
+                std::cmp::Ordering::Greater => Err(format!("index out of bounds: {}", last)),
+            }
+        }
+        _ => Err(format!("cannot set '{}': parent is not an object or array", last)),
+    }
+}
+
+/// 解析数组下标 (不允许前导零)
+fn array_index(key: &str) -> Result<usize, String> {
+    if key != "0" && key.starts_with('0') {
+        return Err(format!("invalid array index: {}", key));
+    }
+    key.parse().map_err(|_| format!("invalid array index: {}", key))
+}
+
+/// 还原 `~0`/`~1`
+fn unescape(key: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(key.len());
+    let mut chars = key.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next() {
+                Some('0') => result.push('~'),
+                Some('1') => result.push('/'),
+                _ => return Err("'~' must be followed by '0' or '1'".to_string()),
+            },
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
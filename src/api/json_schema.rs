@@ -0,0 +1,55 @@
+//! JSON Schema 的简单校验
+//!
+//! 只支持内部定义的 schema (如节点的 `config`) 用到的关键字:
+//! `type` (单个或数组)、`required`、`properties`、`enum`，其余关键字忽略
+
+use serde_json::Value;
+
+/// 按 schema 校验，不符合时返回第一处错误，如 `config.url: expected string`
+///
+/// - `name` 值的名称，用于错误信息
+pub fn validate(schema: &Value, value: &Value, name: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|expected| is_type(value, expected)) {
+            return Err(format!("{}: expected {}", name, types.join(" or ")));
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+        && !allowed.contains(value)
+    {
+        return Err(format!("{}: must be one of {}", name, Value::Array(allowed.clone())));
+    }
+    let Value::Object(object) = value else {
+        return Ok(());
+    };
+    for key in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+        if !object.contains_key(key) {
+            return Err(format!("{}.{}: required", name, key));
+        }
+    }
+    for (key, property) in schema.get("properties").and_then(Value::as_object).into_iter().flatten() {
+        if let Some(value) = object.get(key) {
+            validate(property, value, &format!("{}.{}", name, key))?;
+        }
+    }
+    Ok(())
+}
+
+/// 值是否为该类型 (`integer` 为没有小数部分的数字)
+fn is_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true, // 未知的类型不限制
+    }
+}
@@ -0,0 +1,117 @@
+//! 请求体大小限制
+//!
+//! `Content-Length` 超过上限的请求直接返回 `413 { "error": "payload too large" }`，不读取请求体。
+//! 没有 `Content-Length` 的 (分块传输) 由 axum 的提取器 (`Json`、`Bytes` 等) 在读取时按同一上限截断并返回 `413`。
+//! 内层可再用 `DefaultBodyLimit` 为个别路由设置不同的上限
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{DefaultBodyLimit, Request},
+    http::{header::CONTENT_LENGTH, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::{future::BoxFuture, stream, StreamExt};
+use serde_json::json;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// 默认上限 (字节)，与 axum 提取器的默认值相同
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// 请求体大小限制中间件
+#[derive(Debug, Clone, Copy)]
+pub struct BodySizeLimitLayer {
+    limit: usize,
+}
+
+impl BodySizeLimitLayer {
+    /// `limit` 为请求体的字节数上限
+    pub fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+}
+
+impl Default for BodySizeLimitLayer {
+    fn default() -> Self {
+        Self::new(DEFAULT_BODY_LIMIT)
+    }
+}
+
+impl<S> Layer<S> for BodySizeLimitLayer {
+    type Service = BodySizeLimit<<DefaultBodyLimit as Layer<S>>::Service>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BodySizeLimit { inner: DefaultBodyLimit::max(self.limit).layer(inner), limit: self.limit }
+    }
+}
+
+/// 见 [`BodySizeLimitLayer`]
+#[derive(Debug, Clone)]
+pub struct BodySizeLimit<S> {
+    inner: S,
+    limit: usize,
+}
+
+impl<S> Service<Request> for BodySizeLimit<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let length = req.headers().get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if length.is_some_and(|length| length > self.limit as u64) {
+            let response = (StatusCode::PAYLOAD_TOO_LARGE, Json(json!({ "error": "payload too large" }))).into_response();
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+/// 读取响应体，不超过 `limit` 时返回完整内容。
+/// 超过时 (或读取出错时) 返回与原响应体内容相同的 `Body` (已读取的部分加上剩余部分)，以便照常返回。
+/// 供需要缓冲响应体的中间件使用 (幂等键、响应体日志)
+pub(crate) async fn buffer_body(body: Body, limit: usize) -> Result<Bytes, Body> {
+    let mut data = body.into_data_stream();
+    let mut chunks = Vec::new();
+    let mut size = 0;
+    while let Some(chunk) = data.next().await {
+        let Ok(chunk) = chunk else {
+            return Err(Body::from_stream(stream::iter(chunks.into_iter().map(Ok)).chain(stream::once(async { chunk }))));
+        };
+        size += chunk.len();
+        chunks.push(chunk);
+        if size > limit {
+            return Err(Body::from_stream(stream::iter(chunks.into_iter().map(Ok)).chain(data)));
+        }
+    }
+    Ok(chunks.concat().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn buffer_body_passes_large_bodies_through() {
+        let chunks = || stream::iter(["ab", "cd", "ef"].map(|chunk| Ok::<_, std::io::Error>(Bytes::from(chunk))));
+        let small = buffer_body(Body::from_stream(chunks()), 6).await.unwrap();
+        assert_eq!(small, "abcdef");
+
+        let Err(large) = buffer_body(Body::from_stream(chunks()), 3).await else {
+            panic!("expected the body to exceed the limit");
+        };
+        assert_eq!(to_bytes(large, usize::MAX).await.unwrap(), "abcdef");
+    }
+}
@@ -0,0 +1,104 @@
+//! 请求体/响应体日志 (仅调试构建)
+//!
+//! 设置环境变量 `RUST_DEMO_LOG_BODIES=1` 时，缓冲请求体与响应体，以 trace 级别连同请求ID记录，
+//! 再以缓冲的字节重新构造请求体/响应体交给处理函数与客户端。每个记录最多 4 KiB，超出部分截断。
+//!
+//! 本模块只在 `debug_assertions` 下编译，发布构建中不存在。
+//! SSE (`text/event-stream`) 的响应不会结束，不缓冲。超过 2 MiB 的响应不记录，原样返回。需挂载在 [`super::request_id::RequestIdLayer`] 内层
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::Request,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures_util::future::BoxFuture;
+use once_cell::sync::Lazy;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+use super::{body_limit::{buffer_body, DEFAULT_BODY_LIMIT}, request_id::RequestId};
+
+/// 启用开关的环境变量
+const LOG_BODIES_ENV: &str = "RUST_DEMO_LOG_BODIES";
+/// 每个请求体/响应体记录的字节数上限
+const MAX_LOGGED_BYTES: usize = 4 * 1024;
+
+/// 是否启用，启动后首次使用时读取
+static ENABLED: Lazy<bool> = Lazy::new(|| std::env::var(LOG_BODIES_ENV).is_ok_and(|value| value == "1"));
+
+/// 请求体/响应体日志中间件
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BodyLogLayer;
+
+impl<S> Layer<S> for BodyLogLayer {
+    type Service = BodyLog<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BodyLog { inner }
+    }
+}
+
+/// 见 [`BodyLogLayer`]
+#[derive(Debug, Clone)]
+pub struct BodyLog<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for BodyLog<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        if !*ENABLED || !tracing::enabled!(tracing::Level::TRACE) {
+            return Box::pin(self.inner.call(req));
+        }
+        // 已 poll_ready 的是 self.inner，换出来使用，留下一个克隆
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let request_id = req.extensions().get::<RequestId>().map_or_else(String::new, |id| id.as_str().to_string());
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let Ok(body) = to_bytes(body, DEFAULT_BODY_LIMIT).await else {
+                return Ok(StatusCode::PAYLOAD_TOO_LARGE.into_response());
+            };
+            tracing::trace!(request_id = %request_id, "request body ({} bytes): {}", body.len(), preview(&body));
+            let response = inner.call(Request::from_parts(parts, Body::from(body))).await?;
+
+            let streaming = response.headers().get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("text/event-stream"));
+            if streaming {
+                return Ok(response);
+            }
+            let (parts, body) = response.into_parts();
+            let body = match buffer_body(body, DEFAULT_BODY_LIMIT).await {
+                Ok(body) => body,
+                Err(body) => {
+                    // 原样返回 (已读取的部分加上剩余部分)，不记录
+                    tracing::trace!(request_id = %request_id, "response body too large to log");
+                    return Ok(Response::from_parts(parts, body));
+                }
+            };
+            tracing::trace!(request_id = %request_id, "response body ({} bytes): {}", body.len(), preview(&body));
+            Ok(Response::from_parts(parts, Body::from(body)))
+        })
+    }
+}
+
+/// 取前 [`MAX_LOGGED_BYTES`] 字节，非 UTF-8 的部分以替换字符显示
+fn preview(body: &Bytes) -> String {
+    if body.len() <= MAX_LOGGED_BYTES {
+        return String::from_utf8_lossy(body).into_owned();
+    }
+    format!("{}... ({} bytes truncated)", String::from_utf8_lossy(&body[..MAX_LOGGED_BYTES]), body.len() - MAX_LOGGED_BYTES)
+}
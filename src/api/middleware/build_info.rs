@@ -0,0 +1,86 @@
+//! 构建信息响应头
+//!
+//! 在每个响应中加入 `X-Server-Version: 0.1.0` 与 `X-Build-Date: 2024-01-01T00:00:00Z`，
+//! 便于排查线上问题时确认客户端所连的服务器版本。更多信息见 `GET /version` ([`crate::api::version`])
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+use crate::api::version::{BUILD_DATE, SERVER_VERSION};
+
+/// 服务器版本头
+pub const SERVER_VERSION_HEADER: HeaderName = HeaderName::from_static("x-server-version");
+/// 构建时间头
+pub const BUILD_DATE_HEADER: HeaderName = HeaderName::from_static("x-build-date");
+
+/// 构建信息中间件
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuildInfoLayer;
+
+impl<S> Layer<S> for BuildInfoLayer {
+    type Service = BuildInfo<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BuildInfo { inner }
+    }
+}
+
+/// 见 [`BuildInfoLayer`]
+#[derive(Debug, Clone)]
+pub struct BuildInfo<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for BuildInfo<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let mut response = future.await?;
+            let headers = response.headers_mut();
+            headers.insert(SERVER_VERSION_HEADER, HeaderValue::from_static(SERVER_VERSION));
+            headers.insert(BUILD_DATE_HEADER, HeaderValue::from_static(BUILD_DATE));
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::{to_bytes, Body}, routing::get, Router};
+    use tower::ServiceExt;
+
+    use crate::api::version::get_version;
+
+    #[tokio::test]
+    async fn adds_headers() {
+        let app = BuildInfoLayer.layer(Router::new().route("/version", get(get_version)));
+        let response = app.oneshot(Request::get("/version").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.headers()[SERVER_VERSION_HEADER], env!("CARGO_PKG_VERSION"));
+        assert_eq!(response.headers()[BUILD_DATE_HEADER], BUILD_DATE);
+
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["version"], SERVER_VERSION);
+        let features = body["features"].as_array().unwrap();
+        assert!(features.is_sorted_by_key(|feature| feature.as_str()));
+        assert_eq!(features.iter().any(|feature| feature == "scripting"), cfg!(feature = "scripting"));
+    }
+}
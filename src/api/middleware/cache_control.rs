@@ -0,0 +1,123 @@
+//! `Cache-Control` 响应头
+//!
+//! - `POST`/`PUT`/`PATCH`/`DELETE` 的响应: `no-store`
+//! - `GET`/`HEAD` 的成功响应: 默认策略，可按路径前缀覆盖 (最长前缀优先)
+//!
+//! 处理函数自行设置了 `Cache-Control` 的 (如 SSE 的 `no-cache`) 不会被覆盖
+//!
+//! ```ignore
+//! let layer = CacheControlLayer::new("max-age=5, stale-while-revalidate=60")
+//!     .prefix("/heartbeat", "max-age=2")
+//!     .prefix("/todos", "max-age=0");
+//! ```
+
+use axum::{
+    extract::Request,
+    http::{header::CACHE_CONTROL, HeaderValue, Method},
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// 缓存策略中间件
+#[derive(Debug, Clone)]
+pub struct CacheControlLayer {
+    config: Arc<CacheConfig>,
+}
+
+#[derive(Debug)]
+struct CacheConfig {
+    default: HeaderValue,
+    /// (路径前缀, 策略)
+    prefixes: Vec<(String, HeaderValue)>,
+}
+
+impl CacheControlLayer {
+    /// `default` 为 GET 响应的默认策略
+    ///
+    /// panic: 策略含有非法的头部字符时 (下同)
+    pub fn new(default: &str) -> Self {
+        Self {
+            config: Arc::new(CacheConfig {
+                default: HeaderValue::from_str(default).expect("invalid Cache-Control"),
+                prefixes: Vec::new(),
+            }),
+        }
+    }
+
+    /// 为路径前缀单独设置 GET 响应的策略。按路径段匹配，`/todos` 匹配 `/todos/1` 但不匹配 `/todos2`
+    pub fn prefix(mut self, prefix: &str, policy: &str) -> Self {
+        let config = Arc::get_mut(&mut self.config).expect("configure before cloning");
+        config.prefixes.push((
+            prefix.trim_end_matches('/').to_string(),
+            HeaderValue::from_str(policy).expect("invalid Cache-Control"),
+        ));
+        self
+    }
+}
+
+impl CacheConfig {
+    /// GET 请求使用的策略
+    fn policy_for(&self, path: &str) -> &HeaderValue {
+        self.prefixes.iter()
+            .filter(|(prefix, _)| {
+                path.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, policy)| policy)
+            .unwrap_or(&self.default)
+    }
+}
+
+impl<S> Layer<S> for CacheControlLayer {
+    type Service = CacheControl<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CacheControl { inner, config: self.config.clone() }
+    }
+}
+
+/// 见 [`CacheControlLayer`]
+#[derive(Debug, Clone)]
+pub struct CacheControl<S> {
+    inner: S,
+    config: Arc<CacheConfig>,
+}
+
+impl<S> Service<Request> for CacheControl<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let policy = match *req.method() {
+            Method::GET | Method::HEAD => Some(self.config.policy_for(req.uri().path()).clone()),
+            Method::POST | Method::PUT | Method::PATCH | Method::DELETE => None,
+            _ => return Box::pin(self.inner.call(req)),
+        };
+
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let mut response = future.await?;
+            let value = match policy {
+                Some(_) if !response.status().is_success() => return Ok(response),
+                Some(policy) => policy,
+                None => HeaderValue::from_static("no-store"),
+            };
+            response.headers_mut().entry(CACHE_CONTROL).or_insert(value);
+            Ok(response)
+        })
+    }
+}
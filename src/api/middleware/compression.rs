@@ -0,0 +1,105 @@
+//! 响应压缩
+//!
+//! 根据 `Accept-Encoding` 协商 brotli/gzip/deflate (权重相同时优先 brotli)。
+//! 仅压缩大于 1 KiB 的响应，小响应压缩的开销大于收益。
+//! SSE 流不压缩，否则事件会被压缩器缓冲而无法及时送达
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    http::{
+        header::{ACCEPT_ENCODING, VARY},
+        HeaderValue, Response,
+    },
+    BoxError,
+};
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate, SizeAbove},
+    CompressionLayer,
+};
+
+/// 压缩阈值 (字节)
+const MIN_COMPRESS_SIZE: u16 = 1024;
+
+/// 压缩中间件
+pub fn compression_layer() -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(MIN_COMPRESS_SIZE)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE);
+    CompressionLayer::new().compress_when(predicate)
+}
+
+/// 为所有响应添加 `Vary: Accept-Encoding`，并将响应体转回 axum 的 `Body`
+///
+/// `CompressionLayer` 只在实际压缩时添加，但未压缩的响应同样因 `Accept-Encoding` 而异，缓存需要知道这一点。
+/// 需放在 `CompressionLayer` 外层 (作为最外层，`axum::serve` 要求响应体为 `Body`)，已有该值时不重复添加
+pub fn vary_accept_encoding<B>(response: Response<B>) -> axum::response::Response
+where
+    B: HttpBody<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    let mut response = response.map(Body::new);
+    let present = response.headers().get_all(VARY).iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|v| v.trim().eq_ignore_ascii_case(ACCEPT_ENCODING.as_str()));
+    if !present {
+        response.headers_mut().append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::to_bytes, extract::Request, http::header::CONTENT_ENCODING, routing::get, Json, Router};
+    use chrono::Utc;
+    use std::{io::Read, sync::Arc};
+    use tower::{util::MapResponseLayer, ServiceExt};
+
+    use crate::{api::rest_todos::Item, container::hash_store::Container};
+
+    async fn get_todos(app: Router, accept_encoding: Option<&str>) -> axum::response::Response {
+        let mut request = Request::get("/todos");
+        if let Some(value) = accept_encoding {
+            request = request.header(ACCEPT_ENCODING, value);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn gzip_large_store() {
+        let store: Arc<Container<Item>> = Container::new_arc();
+        let owner_id = uuid::Uuid::new_v4().to_string();
+        for i in 0..10_000 {
+            let id = uuid::Uuid::new_v4().to_string();
+            let item = serde_json::from_value(serde_json::json!({
+                "id": id, "owner_id": owner_id, "text": format!("todo {}", i), "completed": i % 3 == 0,
+                "position": i * 1024, "created_at": Utc::now(), "updated_at": Utc::now(),
+            })).unwrap();
+            store.put_by_id(&id, item);
+        }
+        let app = Router::new()
+            .route("/todos", get(move || async move { Json(store.get_all().into_values().collect::<Vec<_>>()) }))
+            .layer(compression_layer())
+            .layer(MapResponseLayer::new(vary_accept_encoding));
+
+        let plain = get_todos(app.clone(), None).await;
+        assert!(!plain.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(plain.headers()[VARY], "accept-encoding");
+        let plain = to_bytes(plain.into_body(), usize::MAX).await.unwrap();
+
+        let compressed = get_todos(app, Some("gzip")).await;
+        assert_eq!(compressed.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(compressed.headers()[VARY], "accept-encoding");
+        let compressed = to_bytes(compressed.into_body(), usize::MAX).await.unwrap();
+        assert!(
+            compressed.len() * 5 <= plain.len(),
+            "gzip {} bytes, uncompressed {} bytes", compressed.len(), plain.len()
+        );
+
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(&compressed[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, plain);
+    }
+}
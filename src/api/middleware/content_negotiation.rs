@@ -0,0 +1,255 @@
+//! 内容协商 (MessagePack)
+//!
+//! - 请求带 `Accept: application/msgpack` 时，JSON 响应体改用 MessagePack 编码，`Content-Type: application/msgpack`
+//! - 请求体为 `Content-Type: application/msgpack` 时解码后转为 JSON 交给处理函数 (处理函数无需改动)，格式错误返回 `400`
+//!
+//! `application/x-msgpack` 同样接受。JSON 响应带 `Vary: Accept`，SSE 等其他响应原样返回
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{FromRequestParts, Request},
+    http::{
+        header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, VARY},
+        request::Parts,
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// MessagePack 的媒体类型
+pub const MSGPACK: &str = "application/msgpack";
+/// 缓冲的请求体上限 (与 axum 默认的请求体限制一致)
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// 协商出的响应格式
+///
+/// 中间件将其放入请求扩展，处理函数可直接作为提取器使用 (未挂载中间件时按 `Accept` 判断)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+impl ResponseFormat {
+    /// 由 `Accept` 选择: 列出了 MessagePack (且权重不为0) 时用 MessagePack，否则为 JSON
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let accepts_msgpack = headers.get_all(ACCEPT).iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|range| {
+                let mut params = range.split(';');
+                let media_type = params.next().unwrap_or_default().trim();
+                is_msgpack(media_type) && !params.any(|param| {
+                    param.trim().strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()) == Some(0.0)
+                })
+            });
+        if accepts_msgpack { Self::MsgPack } else { Self::Json }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ResponseFormat {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().copied().unwrap_or_else(|| Self::from_headers(&parts.headers)))
+    }
+}
+
+/// 内容协商中间件
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContentNegotiationLayer;
+
+impl<S> Layer<S> for ContentNegotiationLayer {
+    type Service = ContentNegotiation<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ContentNegotiation { inner }
+    }
+}
+
+/// 见 [`ContentNegotiationLayer`]
+#[derive(Debug, Clone)]
+pub struct ContentNegotiation<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for ContentNegotiation<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        // 已 poll_ready 的是 self.inner，换出来使用，留下一个克隆
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let format = ResponseFormat::from_headers(req.headers());
+        req.extensions_mut().insert(format);
+        Box::pin(async move {
+            let req = match has_msgpack_body(req.headers()) {
+                true => msgpack_to_json(req).await,
+                false => Some(req),
+            };
+            // 无法解码的请求体不交给处理函数，400 的错误信息同样按 Accept 编码
+            let response = match req {
+                Some(req) => inner.call(req).await?,
+                None => (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid msgpack body" }))).into_response(),
+            };
+            if !has_json_body(response.headers()) {
+                return Ok(response);
+            }
+            let mut response = match format {
+                ResponseFormat::MsgPack => json_to_msgpack(response).await,
+                ResponseFormat::Json => response,
+            };
+            response.headers_mut().append(VARY, HeaderValue::from_static("accept"));
+            Ok(response)
+        })
+    }
+}
+
+/// 是否为 MessagePack 的媒体类型
+fn is_msgpack(media_type: &str) -> bool {
+    media_type.eq_ignore_ascii_case(MSGPACK) || media_type.eq_ignore_ascii_case("application/x-msgpack")
+}
+
+/// `Content-Type` 的媒体类型 (去掉参数)
+fn media_type(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    value.split(';').next().map(str::trim)
+}
+
+fn has_msgpack_body(headers: &HeaderMap) -> bool {
+    media_type(headers).is_some_and(is_msgpack)
+}
+
+fn has_json_body(headers: &HeaderMap) -> bool {
+    media_type(headers).is_some_and(|media_type| media_type.eq_ignore_ascii_case("application/json"))
+}
+
+/// 将 MessagePack 请求体转为 JSON，无法解码时返回 `None`
+async fn msgpack_to_json(req: Request) -> Option<Request> {
+    let (mut parts, body) = req.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_SIZE).await.ok()?;
+    let value: Value = rmp_serde::from_slice(&bytes).ok()?;
+    let json = serde_json::to_vec(&value).ok()?;
+    parts.headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    parts.headers.remove(CONTENT_LENGTH);
+    Some(Request::from_parts(parts, Body::from(json)))
+}
+
+/// 将 JSON 响应体转为 MessagePack，响应体不是合法的 JSON 时原样返回
+async fn json_to_msgpack(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            tracing::error!("failed to read response body: {}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    let bytes = match rmp_serde::to_vec_named(&value) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            tracing::error!("failed to encode msgpack response: {}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.insert(CONTENT_TYPE, HeaderValue::from_static(MSGPACK));
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::{get, post}, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/item", get(|| async { Json(json!({ "id": "a", "count": 1 })) }))
+            .route("/echo", post(|Json(value): Json<Value>| async move { Json(value) }))
+            .route("/text", get(|| async { "plain" }))
+            .layer(ContentNegotiationLayer)
+    }
+
+    async fn send(req: axum::http::request::Builder, body: Body) -> Response {
+        app().oneshot(req.body(body).unwrap()).await.unwrap()
+    }
+
+    async fn body_bytes(response: Response) -> Vec<u8> {
+        to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()
+    }
+
+    #[test]
+    fn accept_selects_format() {
+        let format = |accept: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT, accept.parse().unwrap());
+            ResponseFormat::from_headers(&headers)
+        };
+        assert_eq!(ResponseFormat::from_headers(&HeaderMap::new()), ResponseFormat::Json);
+        assert_eq!(format("application/json"), ResponseFormat::Json);
+        assert_eq!(format("application/msgpack"), ResponseFormat::MsgPack);
+        assert_eq!(format("text/html, Application/X-MsgPack;q=0.5"), ResponseFormat::MsgPack);
+        assert_eq!(format("application/msgpack;q=0, application/json"), ResponseFormat::Json);
+    }
+
+    #[tokio::test]
+    async fn encodes_json_responses_by_accept() {
+        let response = send(Request::get("/item"), Body::empty()).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers()[VARY], "accept");
+        assert_eq!(serde_json::from_slice::<Value>(&body_bytes(response).await).unwrap(), json!({ "id": "a", "count": 1 }));
+
+        let response = send(Request::get("/item").header(ACCEPT, MSGPACK), Body::empty()).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], MSGPACK);
+        assert_eq!(response.headers()[VARY], "accept");
+        assert_eq!(rmp_serde::from_slice::<Value>(&body_bytes(response).await).unwrap(), json!({ "id": "a", "count": 1 }));
+
+        // 非 JSON 的响应原样返回
+        let response = send(Request::get("/text").header(ACCEPT, MSGPACK), Body::empty()).await;
+        assert!(!response.headers().contains_key(VARY));
+        assert_eq!(body_bytes(response).await, b"plain");
+    }
+
+    #[tokio::test]
+    async fn decodes_msgpack_requests() {
+        let body = rmp_serde::to_vec_named(&json!({ "name": "node", "tags": ["a", "b"] })).unwrap();
+        let response = send(Request::post("/echo").header(CONTENT_TYPE, "application/x-msgpack"), Body::from(body)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(serde_json::from_slice::<Value>(&body_bytes(response).await).unwrap(), json!({ "name": "node", "tags": ["a", "b"] }));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_msgpack() {
+        let response = send(Request::post("/echo").header(CONTENT_TYPE, MSGPACK), Body::from(vec![0xc1])).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(serde_json::from_slice::<Value>(&body_bytes(response).await).unwrap(), json!({ "error": "invalid msgpack body" }));
+
+        let response = send(Request::post("/echo").header(CONTENT_TYPE, MSGPACK).header(ACCEPT, MSGPACK), Body::from(vec![0xc1])).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[CONTENT_TYPE], MSGPACK);
+        assert_eq!(rmp_serde::from_slice::<Value>(&body_bytes(response).await).unwrap(), json!({ "error": "invalid msgpack body" }));
+    }
+}
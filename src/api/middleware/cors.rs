@@ -0,0 +1,289 @@
+//! 按路由的跨域 (CORS) 策略
+//!
+//! 按请求路径依次匹配各路由的模式，使用第一个匹配的策略，都不匹配时不处理跨域。
+//! 模式以 `/` 分隔: `*` 匹配一级中的任意字符 (不含 `/`)，`**` 匹配任意多级 (含零级):
+//!
+//! ```ignore
+//! let layer = PerRouteCorsLayer::new()
+//!     .route("/auth/login", frontend_cors)
+//!     .route("/admin/**", admin_cors)
+//!     .route("/**", public_cors)
+//!     .build();
+//! ```
+//!
+//! 需以 `Router::layer` 挂载 (作用于各路由内部)，非预检的 `OPTIONS` 由外层的
+//! [`super::options::OptionsMethodLayer`] 处理
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, Method},
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service, ServiceExt};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Cors, CorsLayer};
+
+use crate::api::{cache::X_CACHE, pagination::TOTAL_COUNT_HEADER};
+use crate::config::ServerConfig;
+use super::{idempotency::REPLAYED_HEADER, request_id::REQUEST_ID_HEADER, response_time::RESPONSE_TIME_HEADER};
+
+/// 表示任意值的通配符
+pub const CORS_ANY: &str = "*";
+
+/// 跨域时前端可读取的响应头 (不可配置，由各功能决定)
+const EXPOSE_HEADERS: [HeaderName; 6] = [
+    TOTAL_COUNT_HEADER,
+    header::LINK,
+    X_CACHE,
+    REPLAYED_HEADER,
+    REQUEST_ID_HEADER,
+    RESPONSE_TIME_HEADER,
+];
+
+/// 一条跨域策略
+///
+/// - `allowed_origins`/`allowed_methods`/`allowed_headers` 允许的来源、方法、请求头，`["*"]` 表示任意
+/// - `allow_credentials` 是否允许携带凭证 (cookies等)，开启时以上三项都不能为 `*`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+}
+
+impl CorsConfig {
+    /// 配置文件中的全局策略 (`cors_*`)
+    pub fn from_server_config(config: &ServerConfig) -> Self {
+        Self {
+            allowed_origins: config.cors_allowed_origins.clone(),
+            allowed_methods: config.cors_allowed_methods.clone(),
+            allowed_headers: config.cors_allowed_headers.clone(),
+            allow_credentials: config.cors_allow_credentials,
+        }
+    }
+
+    /// 校验: 来源、方法、请求头须能解析，`*` 须单独使用。允许凭证时不能为 `*`
+    /// (浏览器不接受，且任意网站都能以用户身份发起跨域请求)
+    pub fn validate(&self) -> Result<(), String> {
+        let lists = [
+            ("allowed_origins", &self.allowed_origins),
+            ("allowed_methods", &self.allowed_methods),
+            ("allowed_headers", &self.allowed_headers),
+        ];
+        for (name, values) in lists {
+            if values.len() > 1 && values.iter().any(|value| value == CORS_ANY) {
+                return Err(format!("{}: \"*\" cannot be combined with other values", name));
+            }
+            if self.allow_credentials && is_any(values) {
+                return Err(format!("{}: \"*\" is not allowed when allow_credentials is true", name));
+            }
+        }
+        if !is_any(&self.allowed_origins) {
+            for origin in &self.allowed_origins {
+                HeaderValue::from_str(origin).map_err(|_| format!("allowed_origins: invalid origin {}", origin))?;
+            }
+        }
+        if !is_any(&self.allowed_methods) {
+            for method in &self.allowed_methods {
+                Method::from_bytes(method.as_bytes()).map_err(|_| format!("allowed_methods: invalid method {}", method))?;
+            }
+        }
+        if !is_any(&self.allowed_headers) {
+            for header in &self.allowed_headers {
+                HeaderName::from_bytes(header.as_bytes()).map_err(|_| format!("allowed_headers: invalid header {}", header))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 转为 `CorsLayer`
+    ///
+    /// panic: 校验不通过时
+    fn to_layer(&self) -> CorsLayer {
+        if let Err(err) = self.validate() {
+            panic!("invalid CORS config: {}", err);
+        }
+        let allow_origin = if is_any(&self.allowed_origins) {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(self.allowed_origins.iter().map(|origin| HeaderValue::from_str(origin).expect("validated origin")))
+        };
+        let allow_methods = if is_any(&self.allowed_methods) {
+            AllowMethods::any()
+        } else {
+            AllowMethods::list(self.allowed_methods.iter().map(|method| Method::from_bytes(method.as_bytes()).expect("validated method")))
+        };
+        let allow_headers = if is_any(&self.allowed_headers) {
+            AllowHeaders::any()
+        } else {
+            AllowHeaders::list(self.allowed_headers.iter().map(|header| HeaderName::from_bytes(header.as_bytes()).expect("validated header")))
+        };
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(allow_methods)
+            .allow_headers(allow_headers)
+            .expose_headers(EXPOSE_HEADERS)
+            .allow_credentials(self.allow_credentials)
+    }
+}
+
+/// 列表是否为 `["*"]` (任意)
+fn is_any(values: &[String]) -> bool {
+    matches!(values, [value] if value == CORS_ANY)
+}
+
+// #region 路径模式
+
+/// 路径模式，按 `/` 分为各级
+#[derive(Debug, Clone)]
+struct PathPattern {
+    pattern: String,
+    segments: Vec<String>,
+}
+
+impl PathPattern {
+    fn new(pattern: &str) -> Self {
+        Self { pattern: pattern.to_string(), segments: split_path(pattern).map(String::from).collect() }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let path: Vec<&str> = split_path(path).collect();
+        match_segments(&self.segments, &path)
+    }
+}
+
+/// 去掉开头的 `/` 后按 `/` 分割
+fn split_path(path: &str) -> std::str::Split<'_, char> {
+    path.strip_prefix('/').unwrap_or(path).split('/')
+}
+
+fn match_segments(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((first, rest)) => path.split_first()
+            .is_some_and(|(segment, path)| match_segment(first, segment) && match_segments(rest, path)),
+    }
+}
+
+/// 一级内的匹配，`*` 匹配任意字符
+fn match_segment(pattern: &str, segment: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == segment;
+    };
+    let Some(segment) = segment.strip_prefix(prefix) else {
+        return false;
+    };
+    // `*` 依次尝试匹配 0..=len 个字符
+    segment.char_indices().map(|(index, _)| index).chain([segment.len()])
+        .any(|index| match_segment(rest, &segment[index..]))
+}
+
+// #endregion
+
+/// 按路由的 CORS 中间件，见模块说明
+#[derive(Debug, Clone, Default)]
+pub struct PerRouteCorsLayer {
+    routes: Vec<(PathPattern, CorsConfig, CorsLayer)>,
+}
+
+impl PerRouteCorsLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一条路由的策略，按添加顺序匹配
+    ///
+    /// panic: 策略校验不通过时，见 [`CorsConfig::validate`]
+    pub fn route(mut self, pattern: &str, config: CorsConfig) -> Self {
+        let layer = config.to_layer();
+        self.routes.push((PathPattern::new(pattern), config, layer));
+        self
+    }
+
+    /// 完成构造，并记录各路由生效的策略
+    pub fn build(self) -> Self {
+        for (pattern, config, _) in &self.routes {
+            tracing::info!(
+                "CORS {}: origins {:?}, methods {:?}, headers {:?}, allow credentials {}",
+                pattern.pattern, config.allowed_origins, config.allowed_methods, config.allowed_headers, config.allow_credentials,
+            );
+        }
+        self
+    }
+}
+
+impl<S: Clone> Layer<S> for PerRouteCorsLayer {
+    type Service = PerRouteCors<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let routes = self.routes.iter()
+            .map(|(pattern, _, layer)| (pattern.clone(), layer.layer(inner.clone())))
+            .collect();
+        PerRouteCors { inner, routes: Arc::new(routes) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PerRouteCors<S> {
+    inner: S,
+    routes: Arc<Vec<(PathPattern, Cors<S>)>>,
+}
+
+impl<S> Service<Request> for PerRouteCors<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let path = req.uri().path();
+        match self.routes.iter().find(|(pattern, _)| pattern.matches(path)) {
+            Some((_, cors)) => Box::pin(cors.clone().oneshot(req)), // 与 inner 是各自的克隆，需单独等待就绪
+            None => Box::pin(self.inner.call(req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_patterns() {
+        let matches = |pattern: &str, path: &str| PathPattern::new(pattern).matches(path);
+        assert!(matches("/**", "/"));
+        assert!(matches("/**", "/todos/1"));
+        assert!(matches("/admin/**", "/admin"));
+        assert!(matches("/admin/**", "/admin/users/1/role"));
+        assert!(!matches("/admin/**", "/administrator"));
+        assert!(matches("/todos/*", "/todos/1"));
+        assert!(!matches("/todos/*", "/todos/1/share"));
+        assert!(matches("/*/todos", "/v1/todos"));
+        assert!(matches("/auth/log*", "/auth/login"));
+        assert!(matches("/**/history", "/rest/a/history"));
+        assert!(!matches("/auth/login", "/auth/login/x"));
+    }
+
+    #[test]
+    fn validation() {
+        let config = CorsConfig::from_server_config(&ServerConfig::default());
+        assert_eq!(config.validate(), Ok(()));
+        let credentials = CorsConfig { allow_credentials: true, ..config.clone() };
+        assert!(credentials.validate().unwrap_err().starts_with("allowed_origins"));
+        let mixed = CorsConfig { allowed_methods: vec!["GET".to_string(), "*".to_string()], ..config };
+        assert!(mixed.validate().is_err());
+    }
+}
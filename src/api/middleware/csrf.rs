@@ -0,0 +1,175 @@
+//! CSRF 防护 (同步令牌模式，供使用 cookie 的浏览器客户端)
+//!
+//! - `GET` 请求若没有 `csrf_token` cookie，则在响应中设置一个 (32字节随机数，十六进制)
+//! - `POST`/`PUT`/`PATCH`/`DELETE` 请求必须带 `X-CSRF-Token` 头，且与 cookie 一致，否则返回 `403`
+//! - 带 `Authorization: Bearer` 的请求 (API 客户端) 不做检查
+//!
+//! cookie 不设 `HttpOnly`，前端需用 JS 读取后放入请求头
+
+use axum::{
+    extract::Request,
+    http::{
+        header::{AUTHORIZATION, SET_COOKIE},
+        HeaderValue, Method, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::task::{Context, Poll};
+use subtle::ConstantTimeEq;
+use tower::{Layer, Service};
+
+/// 令牌 cookie 名
+pub const CSRF_COOKIE: &str = "csrf_token";
+/// 令牌请求头
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// CSRF 防护中间件
+#[derive(Debug, Clone, Default)]
+pub struct CsrfLayer;
+
+impl<S> Layer<S> for CsrfLayer {
+    type Service = Csrf<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Csrf { inner }
+    }
+}
+
+/// 见 [`CsrfLayer`]
+#[derive(Debug, Clone)]
+pub struct Csrf<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for Csrf<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let is_bearer = req.headers().get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .is_some_and(|h| h.starts_with("Bearer "));
+        if is_bearer {
+            return Box::pin(self.inner.call(req));
+        }
+
+        let jar = CookieJar::from_headers(req.headers());
+        let token = jar.get(CSRF_COOKIE).map(|c| c.value().to_string());
+        let method = req.method().clone();
+
+        let is_mutating = matches!(method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE);
+        if is_mutating {
+            let header = req.headers().get(CSRF_HEADER).map(|h| h.as_bytes());
+            let valid = match (&token, header) {
+                (Some(token), Some(header)) => bool::from(token.as_bytes().ct_eq(header)),
+                _ => false,
+            };
+            if !valid {
+                tracing::debug!("{} {}, csrf token invalid", method, req.uri());
+                return Box::pin(async {
+                    Ok((StatusCode::FORBIDDEN, Json(json!({ "error": "csrf token invalid" }))).into_response())
+                });
+            }
+        }
+
+        let future = self.inner.call(req);
+        let issue_token = method == Method::GET && token.is_none();
+        Box::pin(async move {
+            let mut response = future.await?;
+            if issue_token {
+                let cookie = Cookie::build((CSRF_COOKIE, new_token()))
+                    .path("/")
+                    .same_site(SameSite::Strict)
+                    .build();
+                if let Ok(value) = HeaderValue::from_str(&cookie.to_string()) {
+                    response.headers_mut().append(SET_COOKIE, value);
+                }
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// 生成新令牌
+fn new_token() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header::COOKIE, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn send(method: Method, headers: &[(&str, &str)]) -> Response {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }).post(|| async { "ok" }).delete(|| async { "ok" }))
+            .layer(CsrfLayer);
+        let mut req = Request::builder().method(method).uri("/");
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_issues_cookie_once() {
+        let response = send(Method::GET, &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = Cookie::parse(response.headers()[SET_COOKIE].to_str().unwrap().to_string()).unwrap();
+        assert_eq!(cookie.name(), CSRF_COOKIE);
+        assert_eq!(cookie.value().len(), 64);
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(cookie.http_only(), None); // 前端需读取
+
+        // 已有 cookie 时不再设置
+        let response = send(Method::GET, &[(COOKIE.as_str(), &format!("{}=abc", CSRF_COOKIE))]).await;
+        assert!(!response.headers().contains_key(SET_COOKIE));
+    }
+
+    #[tokio::test]
+    async fn mutating_requests_need_matching_token() {
+        let cookie = format!("{}=abc", CSRF_COOKIE);
+        let cases: [&[(&str, &str)]; 3] = [
+            &[],
+            &[(COOKIE.as_str(), &cookie)],
+            &[(COOKIE.as_str(), &cookie), (CSRF_HEADER, "abd")],
+        ];
+        for method in [Method::POST, Method::DELETE] {
+            for headers in cases {
+                let response = send(method.clone(), headers).await;
+                assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} {:?}", method, headers);
+            }
+            // 只有头没有 cookie 同样拒绝
+            let response = send(method.clone(), &[(CSRF_HEADER, "abc")]).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+            let response = send(method, &[(COOKIE.as_str(), &cookie), (CSRF_HEADER, "abc")]).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn bearer_requests_skip_check() {
+        let response = send(Method::POST, &[(AUTHORIZATION.as_str(), "Bearer token")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(Method::GET, &[(AUTHORIZATION.as_str(), "Bearer token")]).await;
+        assert!(!response.headers().contains_key(SET_COOKIE));
+    }
+}
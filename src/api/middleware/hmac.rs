@@ -0,0 +1,210 @@
+//! 请求签名校验 (webhook 类机器客户端)
+//!
+//! 客户端发送:
+//!
+//! - `X-API-Key`: API Key，用于查找签名密钥，见 [`crate::api::api_keys`]
+//! - `X-Signature-SHA256`: 原始请求体的 HMAC-SHA256 (十六进制，可带 `sha256=` 前缀)
+//!
+//! 签名不匹配、Key 不存在时返回 `401`。
+//! 可选模式下，不带签名头的请求不做校验; 必需模式下则一律拒绝
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use std::task::{Context, Poll};
+use subtle::ConstantTimeEq;
+use tower::{Layer, Service};
+
+use crate::api::api_keys;
+
+/// 签名头
+pub const SIGNATURE_HEADER: &str = "x-signature-sha256";
+/// API Key 头
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// 校验签名时缓冲的请求体上限 (与 axum 默认的请求体限制一致)
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 校验模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureMode {
+    /// 仅校验带签名头的请求
+    Optional,
+    /// 所有请求都必须带签名
+    Required,
+}
+
+/// 请求签名校验中间件
+#[derive(Debug, Clone)]
+pub struct HmacSignatureLayer {
+    mode: SignatureMode,
+}
+
+impl HmacSignatureLayer {
+    pub fn new(mode: SignatureMode) -> Self {
+        Self { mode }
+    }
+}
+
+impl<S> Layer<S> for HmacSignatureLayer {
+    type Service = HmacSignature<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HmacSignature { inner, mode: self.mode }
+    }
+}
+
+/// 见 [`HmacSignatureLayer`]
+#[derive(Debug, Clone)]
+pub struct HmacSignature<S> {
+    inner: S,
+    mode: SignatureMode,
+}
+
+impl<S> Service<Request> for HmacSignature<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        // 已 poll_ready 的是 self.inner，换出来使用，留下一个克隆
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let mode = self.mode;
+        Box::pin(async move {
+            match verify(req, mode).await {
+                Ok(req) => inner.call(req).await,
+                Err(response) => Ok(response),
+            }
+        })
+    }
+}
+
+/// 校验签名，成功时返回重建的请求 (请求体已被读取)
+async fn verify(req: Request, mode: SignatureMode) -> Result<Request, Response> {
+    let Some(signature) = req.headers().get(SIGNATURE_HEADER) else {
+        return match mode {
+            SignatureMode::Optional => Ok(req),
+            SignatureMode::Required => Err(invalid_signature()),
+        };
+    };
+    let signature = signature.to_str().ok()
+        .and_then(|s| hex::decode(s.trim().trim_start_matches("sha256=")).ok());
+    let record = req.headers().get(API_KEY_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .and_then(api_keys::find);
+    let (Some(signature), Some(record)) = (signature, record) else {
+        return Err(invalid_signature());
+    };
+
+    let (parts, body) = req.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_SIZE).await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+
+    let mut mac = HmacSha256::new_from_slice(record.secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(&bytes);
+    let expected = mac.finalize().into_bytes();
+    if !bool::from(expected.as_slice().ct_eq(&signature)) {
+        tracing::debug!("invalid signature, api key:{}", record.key);
+        return Err(invalid_signature());
+    }
+    Ok(Request::from_parts(parts, Body::from(bytes)))
+}
+
+fn invalid_signature() -> Response {
+    (StatusCode::UNAUTHORIZED, Json(json!({ "error": "invalid signature" }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Router};
+    use tower::ServiceExt;
+
+    use crate::api::api_keys::ApiKeyRecord;
+
+    const KEY: &str = "hmac-test-key";
+    const SECRET: &str = "hmac-test-secret";
+    const BODY: &str = r#"{"event":"ping"}"#;
+
+    fn sign(body: &str) -> String {
+        let mut mac = HmacSha256::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(body.as_bytes());
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    /// 发送请求，返回状态码与处理函数收到的请求体
+    async fn send(mode: SignatureMode, headers: &[(&str, &str)]) -> (StatusCode, String) {
+        api_keys::insert(ApiKeyRecord { key: KEY.to_string(), secret: SECRET.to_string() });
+        let app = Router::new()
+            .route("/hook", post(|body: String| async move { body }))
+            .layer(HmacSignatureLayer::new(mode));
+        let mut req = Request::post("/hook");
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        let response = app.oneshot(req.body(Body::from(BODY)).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_signature() {
+        let signature = sign(BODY);
+        for mode in [SignatureMode::Optional, SignatureMode::Required] {
+            let (status, body) = send(mode, &[(API_KEY_HEADER, KEY), (SIGNATURE_HEADER, &signature)]).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body, BODY); // 请求体校验后原样交给处理函数
+        }
+        // 不带前缀的十六进制同样接受
+        let (status, _) = send(SignatureMode::Required, &[(API_KEY_HEADER, KEY), (SIGNATURE_HEADER, signature.trim_start_matches("sha256="))]).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_signature_or_unknown_key() {
+        let wrong = sign("another body");
+        let valid = sign(BODY);
+        for mode in [SignatureMode::Optional, SignatureMode::Required] {
+            let (status, _) = send(mode, &[(API_KEY_HEADER, KEY), (SIGNATURE_HEADER, &wrong)]).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            let (status, _) = send(mode, &[(API_KEY_HEADER, KEY), (SIGNATURE_HEADER, "not-hex")]).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            let (status, _) = send(mode, &[(API_KEY_HEADER, "unknown-key"), (SIGNATURE_HEADER, &valid)]).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            let (status, _) = send(mode, &[(SIGNATURE_HEADER, &valid)]).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_signature_depends_on_mode() {
+        let (status, body) = send(SignatureMode::Optional, &[]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, BODY);
+        let (status, _) = send(SignatureMode::Optional, &[(API_KEY_HEADER, KEY)]).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = send(SignatureMode::Required, &[(API_KEY_HEADER, KEY)]).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap(), json!({ "error": "invalid signature" }));
+    }
+}
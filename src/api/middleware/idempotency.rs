@@ -0,0 +1,314 @@
+//! 幂等键 (防止重试造成重复创建)
+//!
+//! 客户端为 `POST` 请求带上 `Idempotency-Key: <uuid>`，网络失败后用同一个键重试。
+//! 首次请求的响应 (状态码、响应头、响应体) 保存 24 小时，期间同一个键的请求直接返回保存的响应，
+//! 不再执行处理函数，并带 `X-Idempotent-Replayed: true`。
+//!
+//! - 键按 `Authorization` 区分，不同用户的键互不影响
+//! - 同一个键用于不同的请求 (方法、路径、查询参数或请求体不同) 时返回 `422`
+//! - 首次请求尚未完成时重试返回 `409`。首次请求中断 (客户端断开、处理函数 panic) 时删除记录，可用同一个键重试
+//! - `5xx` 的响应与超过 2 MiB 的响应不保存 (后者照常返回)，可用同一个键重试
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::Request,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use once_cell::sync::Lazy;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{
+    sync::{Arc, TryLockError},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+use uuid::Uuid;
+
+use super::body_limit::buffer_body;
+use crate::container::Container;
+
+/// 幂等键头
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+/// 重放的响应带此头
+pub const REPLAYED_HEADER: HeaderName = HeaderName::from_static("x-idempotent-replayed");
+/// 保存时长
+const RECORD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// 缓冲的请求体/响应体上限 (与 axum 默认的请求体限制一致)
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// 已保存的请求，以 `Authorization` 与幂等键的哈希为索引
+static RECORDS: Lazy<Arc<Container<IdempotencyRecord>>> = Lazy::new(Container::new_arc);
+
+/// 幂等键对应的请求与响应
+///
+/// - `fingerprint` 方法、路径 (含查询参数) 与请求体的哈希，用于发现键被用于不同的请求
+/// - `response` 首次请求完成前为 `None`
+#[derive(Debug, Clone)]
+pub struct IdempotencyRecord {
+    pub fingerprint: String,
+    pub response: Option<StoredResponse>,
+    pub created_at: Instant,
+}
+
+/// 保存的响应
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+/// 幂等键中间件，仅处理带 `Idempotency-Key` 的 `POST` 请求
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IdempotencyLayer;
+
+impl<S> Layer<S> for IdempotencyLayer {
+    type Service = Idempotency<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Idempotency { inner }
+    }
+}
+
+/// 见 [`IdempotencyLayer`]
+#[derive(Debug, Clone)]
+pub struct Idempotency<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for Idempotency<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        // 已 poll_ready 的是 self.inner，换出来使用，留下一个克隆
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let idempotency_key = req.headers().get(IDEMPOTENCY_KEY_HEADER).cloned();
+        Box::pin(async move {
+            let Some(idempotency_key) = idempotency_key.filter(|_| req.method() == Method::POST) else {
+                return inner.call(req).await;
+            };
+            let Some(idempotency_key) = idempotency_key.to_str().ok().and_then(|key| Uuid::parse_str(key.trim()).ok()) else {
+                return Ok(error_response(StatusCode::BAD_REQUEST, "invalid idempotency key"));
+            };
+
+            let (parts, body) = req.into_parts();
+            let Ok(body) = to_bytes(body, MAX_BODY_SIZE).await else {
+                return Ok(StatusCode::PAYLOAD_TOO_LARGE.into_response());
+            };
+            let authorization = parts.headers.get(header::AUTHORIZATION).map(HeaderValue::as_bytes).unwrap_or_default();
+            let key = hex::encode(Sha256::new()
+                .chain_update(authorization)
+                .chain_update(b"\n")
+                .chain_update(idempotency_key.as_bytes())
+                .finalize());
+            let fingerprint = hex::encode(Sha256::new()
+                .chain_update(parts.method.as_str())
+                .chain_update(b" ")
+                .chain_update(parts.uri.path_and_query().map_or(parts.uri.path(), |path| path.as_str()))
+                .chain_update(b"\n")
+                .chain_update(&body)
+                .finalize());
+
+            let now = Instant::now();
+            RECORDS.delete_where(|record| now.duration_since(record.created_at) >= RECORD_TTL); // 顺带清理过期项
+            let record = IdempotencyRecord { fingerprint: fingerprint.clone(), response: None, created_at: now };
+            if let Err(existing) = RECORDS.put_if_absent(&key, record) {
+                return Ok(match existing {
+                    IdempotencyRecord { fingerprint: existing, .. } if existing != fingerprint =>
+                        error_response(StatusCode::UNPROCESSABLE_ENTITY, "idempotency key reused for a different request"),
+                    IdempotencyRecord { response: Some(stored), .. } => replay(stored),
+                    IdempotencyRecord { response: None, .. } =>
+                        error_response(StatusCode::CONFLICT, "request with this idempotency key is in progress"),
+                });
+            }
+            // 未保存响应就结束 (出错、中断、不保存的响应) 时删除记录
+            let pending = PendingRecord(Some(key));
+
+            let response = inner.call(Request::from_parts(parts, Body::from(body))).await?;
+            if response.status().is_server_error() {
+                return Ok(response);
+            }
+            let (parts, body) = response.into_parts();
+            let body = match buffer_body(body, MAX_BODY_SIZE).await {
+                Ok(body) => body,
+                Err(body) => {
+                    tracing::warn!("response too large to store for idempotency key {}", idempotency_key);
+                    return Ok(Response::from_parts(parts, body));
+                }
+            };
+            let key = pending.keep();
+            let stored = StoredResponse { status: parts.status, headers: parts.headers.clone(), body: body.clone() };
+            let record = IdempotencyRecord { fingerprint, response: Some(stored), created_at: now };
+            // 写竞争时不等待，交给后台保存，先返回响应
+            match RECORDS.try_put_by_id(&key, record.clone()) {
+                Ok(_) => {}
+                Err(TryLockError::WouldBlock) => {
+                    tracing::debug!("idempotency store busy, saving response for {} in background", idempotency_key);
+                    tokio::task::spawn_blocking(move || RECORDS.put_by_id(&key, record));
+                }
+                // 锁已中毒，保存失败；响应照常返回，客户端重试时会被当作新请求
+                Err(TryLockError::Poisoned(_)) => {
+                    tracing::error!("idempotency store poisoned, response for {} not saved", idempotency_key);
+                }
+            }
+            Ok(Response::from_parts(parts, Body::from(body)))
+        })
+    }
+}
+
+/// 首次请求的占位记录，析构时删除，除非已调用 [`PendingRecord::keep`]
+struct PendingRecord(Option<String>);
+
+impl PendingRecord {
+    /// 保留记录，返回其键
+    fn keep(mut self) -> String {
+        self.0.take().unwrap_or_default()
+    }
+}
+
+impl Drop for PendingRecord {
+    fn drop(&mut self) {
+        if let Some(key) = self.0.take() {
+            RECORDS.delete_by_id(&key);
+        }
+    }
+}
+
+/// 返回保存的响应
+fn replay(stored: StoredResponse) -> Response {
+    let mut response = Response::new(Body::from(stored.body));
+    *response.status_mut() = stored.status;
+    *response.headers_mut() = stored.headers;
+    response.headers_mut().insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    /// 每次执行计数加一，返回 `201 {"calls": n}`
+    fn counting_app() -> (Router, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new()
+            .route("/items", post(move || async move {
+                let calls = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                (StatusCode::CREATED, Json(json!({ "calls": calls })))
+            }))
+            .layer(IdempotencyLayer);
+        (app, calls)
+    }
+
+    async fn send(app: &Router, uri: &str, key: &str, body: &str) -> (StatusCode, HeaderMap, Bytes) {
+        let request = Request::post(uri)
+            .header(IDEMPOTENCY_KEY_HEADER, key)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let (parts, body) = response.into_parts();
+        (parts.status, parts.headers, to_bytes(body, usize::MAX).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn replays_stored_response() {
+        let (app, calls) = counting_app();
+        let key = Uuid::new_v4().to_string();
+        let (status, headers, body) = send(&app, "/items", &key, "{}").await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(!headers.contains_key(REPLAYED_HEADER));
+
+        let (replayed_status, headers, replayed_body) = send(&app, "/items", &key, "{}").await;
+        assert_eq!(replayed_status, status);
+        assert_eq!(replayed_body, body);
+        assert_eq!(headers[REPLAYED_HEADER], "true");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let (status, _, _) = send(&app, "/items", "not-a-uuid", "{}").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rejects_key_reused_for_different_request() {
+        let (app, calls) = counting_app();
+        let key = Uuid::new_v4().to_string();
+        assert_eq!(send(&app, "/items?kind=a", &key, "{}").await.0, StatusCode::CREATED);
+        assert_eq!(send(&app, "/items?kind=a", &key, r#"{"x":1}"#).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(send(&app, "/items?kind=b", &key, "{}").await.0, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn in_progress_and_cancelled() {
+        // 首次执行时等待 (直到被取消)，之后立即返回
+        let started = Arc::new(Notify::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (notify, counter) = (started.clone(), calls.clone());
+        let app = Router::new()
+            .route("/items", post(move || async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    notify.notify_one();
+                    std::future::pending::<()>().await;
+                }
+                StatusCode::CREATED
+            }))
+            .layer(IdempotencyLayer);
+        let key = Uuid::new_v4().to_string();
+
+        let first = tokio::spawn({
+            let (app, key) = (app.clone(), key.clone());
+            async move { send(&app, "/items", &key, "{}").await }
+        });
+        started.notified().await;
+        assert_eq!(send(&app, "/items", &key, "{}").await.0, StatusCode::CONFLICT);
+
+        // 客户端断开 (请求的 future 被丢弃) 后可用同一个键重试
+        first.abort();
+        assert!(first.await.unwrap_err().is_cancelled());
+        assert_eq!(send(&app, "/items", &key, "{}").await.0, StatusCode::CREATED);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn passes_through_large_response() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new()
+            .route("/items", post(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                vec![b'x'; MAX_BODY_SIZE + 1]
+            }))
+            .layer(IdempotencyLayer);
+        let key = Uuid::new_v4().to_string();
+
+        let (status, _, body) = send(&app, "/items", &key, "{}").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.len(), MAX_BODY_SIZE + 1);
+        // 未保存，重试时重新执行
+        assert_eq!(send(&app, "/items", &key, "{}").await.0, StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,173 @@
+//! 按客户端IP的访问控制
+//!
+//! 白名单: 不在任一网段内的请求返回 `403 { "error": "forbidden" }`;
+//! 黑名单: 在任一网段内的请求返回 `403`。拒绝的请求以 WARN 级别记录来源IP。
+//!
+//! 客户端IP见 [`request_client_ip`]。取不到时 (如 Unix socket) 白名单拒绝、黑名单放行。
+//!
+//! 管理接口 (`/admin/*`) 用 [`IpFilterLayer::admin_from_env`]，网段列表来自环境变量 `ADMIN_ALLOWED_CIDRS`:
+//!
+//! ```ignore
+//! // ADMIN_ALLOWED_CIDRS="127.0.0.1/8,10.0.0.0/8,::1"
+//! router.route_layer(IpFilterLayer::admin_from_env())
+//! ```
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use ipnet::IpNet;
+use serde_json::json;
+use std::{
+    net::IpAddr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+use crate::api::client_ip::request_client_ip;
+
+/// 管理接口允许的网段的环境变量，逗号分隔
+const ADMIN_ALLOWED_CIDRS_ENV: &str = "ADMIN_ALLOWED_CIDRS";
+
+/// 过滤方式
+#[derive(Debug, Clone)]
+enum Rule {
+    /// 不过滤
+    AllowAll,
+    Allow(Vec<IpNet>),
+    Deny(Vec<IpNet>),
+}
+
+/// IP过滤中间件
+#[derive(Debug, Clone)]
+pub struct IpFilterLayer {
+    rule: Arc<Rule>,
+}
+
+impl IpFilterLayer {
+    /// 白名单，只允许这些网段
+    pub fn allowlist(cidrs: Vec<IpNet>) -> Self {
+        Self { rule: Arc::new(Rule::Allow(cidrs)) }
+    }
+
+    /// 黑名单，拒绝这些网段
+    pub fn denylist(cidrs: Vec<IpNet>) -> Self {
+        Self { rule: Arc::new(Rule::Deny(cidrs)) }
+    }
+
+    /// 管理接口的白名单，读取 `ADMIN_ALLOWED_CIDRS`
+    ///
+    /// 未设置时允许所有IP，并记录警告
+    ///
+    /// panic: 网段格式有误时 (配置错误应在启动时暴露)
+    pub fn admin_from_env() -> Self {
+        let Ok(value) = std::env::var(ADMIN_ALLOWED_CIDRS_ENV) else {
+            tracing::warn!("{} not set, admin routes are reachable from any IP", ADMIN_ALLOWED_CIDRS_ENV);
+            return Self { rule: Arc::new(Rule::AllowAll) };
+        };
+        let cidrs = parse_cidrs(&value)
+            .unwrap_or_else(|err| panic!("invalid {}: {}", ADMIN_ALLOWED_CIDRS_ENV, err));
+        tracing::info!("admin routes restricted to {:?}", cidrs);
+        Self::allowlist(cidrs)
+    }
+}
+
+/// 解析逗号分隔的网段 (IPv4/IPv6)，单个地址视为只含该地址的网段 (`/32`、`/128`)
+pub fn parse_cidrs(value: &str) -> Result<Vec<IpNet>, String> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|cidr| !cidr.is_empty())
+        .map(|cidr| {
+            cidr.parse::<IpNet>()
+                .or_else(|_| cidr.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| format!("invalid CIDR: {}", cidr))
+        })
+        .collect()
+}
+
+impl Rule {
+    fn allows(&self, ip: Option<IpAddr>) -> bool {
+        match (self, ip) {
+            (Rule::AllowAll, _) => true,
+            (Rule::Allow(cidrs), Some(ip)) => cidrs.iter().any(|cidr| cidr.contains(&ip)),
+            (Rule::Allow(_), None) => false,
+            (Rule::Deny(cidrs), Some(ip)) => !cidrs.iter().any(|cidr| cidr.contains(&ip)),
+            (Rule::Deny(_), None) => true,
+        }
+    }
+}
+
+impl<S> Layer<S> for IpFilterLayer {
+    type Service = IpFilter<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        IpFilter { inner, rule: self.rule.clone() }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IpFilter<S> {
+    inner: S,
+    rule: Arc<Rule>,
+}
+
+impl<S> Service<Request> for IpFilter<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let ip = request_client_ip(&req);
+        if !self.rule.allows(ip) {
+            let source = ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+            tracing::warn!("blocked {} {} from {}", req.method(), req.uri().path(), source);
+            let response = (StatusCode::FORBIDDEN, Json(json!({ "error": "forbidden" }))).into_response();
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> Option<IpAddr> {
+        Some(value.parse().unwrap())
+    }
+
+    #[test]
+    fn allowlist_and_denylist() {
+        let cidrs = parse_cidrs("127.0.0.1/8, 10.0.0.0/8,fd00::/8,::1").unwrap();
+        let allow = Rule::Allow(cidrs.clone());
+        assert!(allow.allows(ip("127.0.0.2")));
+        assert!(allow.allows(ip("10.1.2.3")));
+        assert!(allow.allows(ip("fd12::1")));
+        assert!(allow.allows(ip("::1")));
+        assert!(!allow.allows(ip("203.0.113.1")));
+        assert!(!allow.allows(None));
+
+        let deny = Rule::Deny(cidrs);
+        assert!(!deny.allows(ip("10.1.2.3")));
+        assert!(deny.allows(ip("203.0.113.1")));
+        assert!(deny.allows(None));
+    }
+
+    #[test]
+    fn rejects_invalid_cidr() {
+        assert_eq!(parse_cidrs("10.0.0.0/33"), Err("invalid CIDR: 10.0.0.0/33".to_string()));
+        assert_eq!(parse_cidrs(""), Ok(Vec::new()));
+    }
+}
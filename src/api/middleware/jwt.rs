@@ -0,0 +1,189 @@
+//! JWT 鉴权 (用户登录)
+//!
+//! 登录后由 [`issue`] 签发 HS256 令牌，客户端以 `Authorization: Bearer <token>` 发送。
+//! 中间件校验令牌，通过后把 [`Claims`] 放入请求扩展，处理函数用 `Extension<Claims>` 取得当前用户。
+//! 缺少令牌、令牌无效或过期时返回 `401`。
+//!
+//! 浏览器的 `EventSource`/`WebSocket` 无法设置请求头，这类路由改用 [`BrowserJwtAuthLayer`]，
+//! 另外接受查询参数 `access_token` 与 WebSocket 子协议中的令牌
+//!
+//! 密钥由环境变量 `JWT_SECRET` 指定，未设置时启动时随机生成 (重启后已签发的令牌全部失效)
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use uuid::Uuid;
+
+use crate::api::auth::UserRole;
+
+/// 密钥的环境变量
+const JWT_SECRET_ENV: &str = "JWT_SECRET";
+/// 令牌有效期 (秒)，过期后用刷新令牌换取新的，见 [`crate::api::refresh_token`]
+pub const TOKEN_TTL_SECS: u64 = 15 * 60;
+/// 携带令牌的查询参数，见 [`BrowserJwtAuthLayer`]
+pub const TOKEN_QUERY_PARAM: &str = "access_token";
+/// 携带令牌的 WebSocket 子协议: `Sec-WebSocket-Protocol: bearer, <token>`，见 [`BrowserJwtAuthLayer`]
+pub const TOKEN_SUBPROTOCOL: &str = "bearer";
+
+/// 签名与校验用的密钥
+static KEYS: Lazy<(EncodingKey, DecodingKey)> = Lazy::new(|| {
+    let secret = match std::env::var(JWT_SECRET_ENV) {
+        Ok(secret) if !secret.is_empty() => secret.into_bytes(),
+        _ => {
+            tracing::warn!("{} not set, using a random secret (tokens are invalidated on restart)", JWT_SECRET_ENV);
+            [Uuid::new_v4(), Uuid::new_v4()].iter().flat_map(|uuid| *uuid.as_bytes()).collect()
+        }
+    };
+    (EncodingKey::from_secret(&secret), DecodingKey::from_secret(&secret))
+});
+
+/// 令牌携带的信息
+///
+/// - `sub` 用户ID
+/// - `role` 签发时的角色，修改角色后需重新登录或刷新才生效
+/// - `iat`/`exp` 签发与过期时间 (Unix 秒)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub username: String,
+    #[serde(default)]
+    pub role: UserRole,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+/// 签发令牌
+pub fn issue(user_id: &str, username: &str, role: UserRole) -> Result<String, String> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let claims = Claims {
+        sub: user_id.to_string(),
+        username: username.to_string(),
+        role,
+        iat: now,
+        exp: now + TOKEN_TTL_SECS,
+    };
+    jsonwebtoken::encode(&Header::default(), &claims, &KEYS.0).map_err(|err| err.to_string())
+}
+
+/// 校验令牌 (签名与过期时间)
+pub fn verify(token: &str) -> Option<Claims> {
+    jsonwebtoken::decode::<Claims>(token, &KEYS.1, &Validation::default())
+        .map(|data| data.claims)
+        .ok()
+}
+
+/// JWT 鉴权中间件，用 `route_layer` 挂载到需要登录的路由上
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JwtAuthLayer;
+
+impl<S> Layer<S> for JwtAuthLayer {
+    type Service = JwtAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        JwtAuth { inner, browser: false }
+    }
+}
+
+/// 同 [`JwtAuthLayer`]，没有 `Authorization` 头时另外接受:
+///
+/// - 查询参数 `?access_token=<token>` (`EventSource`)
+/// - 子协议 `Sec-WebSocket-Protocol: bearer, <token>` (`new WebSocket(url, ["bearer", token])`)，
+///   处理函数需以 [`TOKEN_SUBPROTOCOL`] 应答 (`WebSocketUpgrade::protocols`)，否则浏览器会断开
+///
+/// URL 中的令牌可能被代理、浏览器历史等记录，只用于无法设置请求头的 SSE/WebSocket 路由
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BrowserJwtAuthLayer;
+
+impl<S> Layer<S> for BrowserJwtAuthLayer {
+    type Service = JwtAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        JwtAuth { inner, browser: true }
+    }
+}
+
+/// 见 [`JwtAuthLayer`]、[`BrowserJwtAuthLayer`]
+#[derive(Debug, Clone)]
+pub struct JwtAuth<S> {
+    inner: S,
+    browser: bool,
+}
+
+impl<S> Service<Request> for JwtAuth<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        // 已 poll_ready 的是 self.inner，换出来使用，留下一个克隆
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let token = match bearer_token(req.headers()) {
+            None if self.browser => query_token(req.uri().query()).or_else(|| subprotocol_token(req.headers())),
+            token => token,
+        };
+        let claims = token.and_then(verify);
+        if let Some(claims) = &claims {
+            tracing::Span::current().record("user_id", claims.sub.as_str()); // 记录到请求的 span (见 TracingLayer)
+        }
+        Box::pin(async move {
+            match claims {
+                Some(claims) => {
+                    req.extensions_mut().insert(claims);
+                    inner.call(req).await
+                }
+                None => Ok(unauthorized()),
+            }
+        })
+    }
+}
+
+/// 请求头 `Authorization: Bearer <token>` 中的令牌
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers.get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// 查询参数 `access_token` 中的令牌 (JWT 只含 URL 安全的字符，无需解码)
+fn query_token(query: Option<&str>) -> Option<&str> {
+    query?.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == TOKEN_QUERY_PARAM)
+        .map(|(_, token)| token)
+}
+
+/// `Sec-WebSocket-Protocol: bearer, <token>` 中的令牌
+fn subprotocol_token(headers: &HeaderMap) -> Option<&str> {
+    let mut protocols = headers.get(header::SEC_WEBSOCKET_PROTOCOL)?.to_str().ok()?.split(',').map(str::trim);
+    protocols.find(|protocol| *protocol == TOKEN_SUBPROTOCOL)?;
+    protocols.next()
+}
+
+/// 缺少令牌或令牌无效时的响应
+pub(crate) fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(json!({ "error": "invalid or missing token" })),
+    ).into_response()
+}
@@ -0,0 +1,211 @@
+//! 中间件
+//!
+//! 以 tower 的 `Layer` 形式提供，在 main.rs 中挂载到路由上。
+//! 通用的几项由 [`MiddlewareStack`] 按固定顺序组合，只需挂载一次:
+//!
+//! ```ignore
+//! let app = router.layer(MiddlewareStack::default().layer());
+//! let app = router.layer(MiddlewareStack::new().without_compression().with_rate_limit(config).layer());
+//! ```
+
+pub mod body_limit;
+#[cfg(debug_assertions)]
+pub mod body_log;
+pub mod build_info;
+pub mod cache_control;
+pub mod compression;
+pub mod cors;
+#[cfg(feature = "msgpack")]
+pub mod content_negotiation;
+#[cfg(feature = "csrf")]
+pub mod csrf;
+pub mod hmac;
+pub mod idempotency;
+pub mod ip_filter;
+pub mod jwt;
+pub mod options;
+pub mod rate_limit;
+pub mod request_id;
+pub mod response_time;
+pub mod security_headers;
+pub mod stats;
+pub mod tracing;
+pub mod user_rate_limit;
+
+use axum::{extract::Request, response::Response};
+use tower::{
+    util::{BoxCloneSyncService, MapResponseLayer},
+    Layer, Service,
+};
+
+use self::{
+    body_limit::{BodySizeLimitLayer, DEFAULT_BODY_LIMIT},
+    cache_control::CacheControlLayer,
+    compression::{compression_layer, vary_accept_encoding},
+    rate_limit::{RateLimitConfig, RateLimitLayer},
+    request_id::RequestIdLayer,
+    response_time::ResponseTimeLayer,
+    security_headers::SecurityHeadersLayer,
+    tracing::TracingLayer,
+};
+
+/// GET 响应默认的缓存策略
+pub const DEFAULT_CACHE_CONTROL: &str = "max-age=5, stale-while-revalidate=60";
+
+// #region 中间件栈
+
+/// 通用中间件的组合，由外到内依次为:
+///
+/// 1. [`SecurityHeadersLayer`] 安全响应头
+/// 2. 压缩 ([`compression_layer`]，外加 [`vary_accept_encoding`] 把响应体转回 `Body`)
+/// 3. [`RequestIdLayer`] 请求ID
+/// 4. [`TracingLayer`] 请求追踪 (需在请求ID内层)
+/// 5. [`RateLimitLayer`] 按IP限流
+/// 6. [`BodySizeLimitLayer`] 请求体大小限制
+/// 7. [`CacheControlLayer`] `Cache-Control` 响应头
+/// 8. [`ResponseTimeLayer`] 响应耗时
+///
+/// 请求ID与追踪总是启用，其余可关闭或替换。`new()` 与 `default()` 相同，都是推荐的配置。
+/// 需用 `Router::layer` 挂载 (追踪需取得 `MatchedPath`)
+#[derive(Debug, Clone)]
+pub struct MiddlewareStack {
+    security_headers: Option<SecurityHeadersLayer>,
+    compression: bool,
+    rate_limit: Option<RateLimitConfig>,
+    body_limit: Option<usize>,
+    cache_control: Option<CacheControlLayer>,
+    response_time: bool,
+}
+
+impl MiddlewareStack {
+    pub fn new() -> Self {
+        Self {
+            security_headers: Some(SecurityHeadersLayer::default()),
+            compression: true,
+            rate_limit: Some(RateLimitConfig::default()),
+            body_limit: Some(DEFAULT_BODY_LIMIT),
+            cache_control: Some(CacheControlLayer::new(DEFAULT_CACHE_CONTROL)),
+            response_time: true,
+        }
+    }
+
+    /// 替换安全响应头的配置 (如启用 HSTS)
+    pub fn with_security_headers(mut self, layer: SecurityHeadersLayer) -> Self {
+        self.security_headers = Some(layer);
+        self
+    }
+
+    pub fn without_security_headers(mut self) -> Self {
+        self.security_headers = None;
+        self
+    }
+
+    pub fn without_compression(mut self) -> Self {
+        self.compression = false;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limit = Some(config);
+        self
+    }
+
+    pub fn without_rate_limit(mut self) -> Self {
+        self.rate_limit = None;
+        self
+    }
+
+    /// 请求体的字节数上限
+    pub fn with_body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = Some(limit);
+        self
+    }
+
+    /// 替换缓存策略 (如按路径前缀设置)
+    pub fn with_cache_control(mut self, layer: CacheControlLayer) -> Self {
+        self.cache_control = Some(layer);
+        self
+    }
+
+    pub fn without_cache_control(mut self) -> Self {
+        self.cache_control = None;
+        self
+    }
+
+    pub fn without_response_time(mut self) -> Self {
+        self.response_time = false;
+        self
+    }
+
+    /// 生成可挂载的 `Layer`
+    ///
+    /// panic: 限流参数无效时，见 [`RateLimitLayer::new`]
+    pub fn layer(self) -> MiddlewareStackLayer {
+        MiddlewareStackLayer {
+            security_headers: self.security_headers,
+            compression: self.compression,
+            rate_limit: self.rate_limit.map(RateLimitLayer::new),
+            body_limit: self.body_limit.map(BodySizeLimitLayer::new),
+            cache_control: self.cache_control,
+            response_time: self.response_time,
+        }
+    }
+}
+
+impl Default for MiddlewareStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 见 [`MiddlewareStack::layer`]
+///
+/// 各项可关闭，组合后的类型不固定，因此装箱为 `BoxCloneSyncService`。
+/// 限流的令牌桶在此创建，同一个 `MiddlewareStackLayer` 挂载到多处时共享
+#[derive(Debug, Clone)]
+pub struct MiddlewareStackLayer {
+    security_headers: Option<SecurityHeadersLayer>,
+    compression: bool,
+    rate_limit: Option<RateLimitLayer>,
+    body_limit: Option<BodySizeLimitLayer>,
+    cache_control: Option<CacheControlLayer>,
+    response_time: bool,
+}
+
+impl<S> Layer<S> for MiddlewareStackLayer
+where
+    S: Service<Request, Response = Response> + Clone + Send + Sync + 'static,
+    S::Future: Send + 'static,
+    S::Error: 'static,
+{
+    type Service = BoxCloneSyncService<Request, Response, S::Error>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        // 由内到外
+        let mut service = BoxCloneSyncService::new(inner);
+        if self.response_time {
+            service = BoxCloneSyncService::new(ResponseTimeLayer.layer(service));
+        }
+        if let Some(layer) = &self.cache_control {
+            service = BoxCloneSyncService::new(layer.layer(service));
+        }
+        if let Some(layer) = &self.body_limit {
+            service = BoxCloneSyncService::new(layer.layer(service));
+        }
+        if let Some(layer) = &self.rate_limit {
+            service = BoxCloneSyncService::new(layer.layer(service));
+        }
+        service = BoxCloneSyncService::new(TracingLayer.layer(service));
+        service = BoxCloneSyncService::new(RequestIdLayer.layer(service));
+        if self.compression {
+            let compressed = compression_layer().layer(service);
+            service = BoxCloneSyncService::new(MapResponseLayer::new(vary_accept_encoding).layer(compressed));
+        }
+        if let Some(layer) = &self.security_headers {
+            service = BoxCloneSyncService::new(layer.layer(service));
+        }
+        service
+    }
+}
+
+// #endregion
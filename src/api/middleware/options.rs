@@ -0,0 +1,88 @@
+//! OPTIONS 请求处理 (RFC 7231 §4.3.7)
+//!
+//! 对非 CORS 预检的 `OPTIONS` 请求，返回 `200 OK` 及 `Allow` 头，列出该路径支持的方法。
+//!
+//! 支持的方法直接取自 axum 的路由表，而非另外维护一份: 把请求方法改为一个没有任何路由处理的探测方法，
+//! 再交给内层服务。路径存在时 axum 会返回带 `Allow` 头的 `405`，路径不存在则返回 `404`。
+//! 探测请求不会命中任何处理函数，因此没有副作用
+//!
+//! 注意:
+//! - 需放在 `CorsLayer` 外层，因为 `CorsLayer` 会把所有 `OPTIONS` 当作预检请求直接应答
+//! - 需包裹整个 `Router`，不能用 `Router::layer`: 后者作用于各路由内部，`Allow` 头在其外层才被加上
+
+use axum::{
+    extract::Request,
+    http::{
+        header::{ACCESS_CONTROL_REQUEST_METHOD, ALLOW},
+        HeaderValue, Method, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use futures_util::future::BoxFuture;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// 探测用的方法名，不应有任何路由处理该方法
+const PROBE_METHOD: &[u8] = b"X-ALLOW-PROBE";
+
+/// OPTIONS 处理中间件
+#[derive(Debug, Clone, Default)]
+pub struct OptionsMethodLayer;
+
+impl<S> Layer<S> for OptionsMethodLayer {
+    type Service = OptionsMethod<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OptionsMethod { inner }
+    }
+}
+
+/// 见 [`OptionsMethodLayer`]
+#[derive(Debug, Clone)]
+pub struct OptionsMethod<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for OptionsMethod<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        // 预检请求 (带 Access-Control-Request-Method) 交给 CorsLayer
+        let is_plain_options = req.method() == Method::OPTIONS
+            && !req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+        if is_plain_options {
+            *req.method_mut() = Method::from_bytes(PROBE_METHOD).expect("valid method token");
+        }
+
+        // 已 poll_ready 的是 self.inner，换出来使用，留下一个克隆
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let response = inner.call(req).await?;
+            if !is_plain_options || response.status() != StatusCode::METHOD_NOT_ALLOWED {
+                return Ok(response);
+            }
+
+            let methods = response.headers().get(ALLOW)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            let allow = if methods.is_empty() {
+                "OPTIONS".to_string()
+            } else {
+                format!("{},OPTIONS", methods)
+            };
+            let allow = HeaderValue::from_str(&allow).unwrap_or(HeaderValue::from_static("OPTIONS"));
+            Ok((StatusCode::OK, [(ALLOW, allow)]).into_response())
+        })
+    }
+}
@@ -0,0 +1,198 @@
+//! 按客户端IP限流
+//!
+//! 每个IP一个令牌桶: 容量 `capacity`，每秒补充 `refill_per_sec` 个，每个请求消耗一个。
+//! 令牌不足时返回 `429 { "error": "too many requests" }`，`Retry-After` 为补足一个令牌所需的秒数。
+//!
+//! 客户端IP取自 `ConnectInfo<SocketAddr>` (需以 `into_make_service_with_connect_info` 启动)，
+//! 取不到时 (如 Unix socket、测试中直接调用路由) 不限流。
+//! 配置了可信代理 (`trusted_proxy_depth > 0`) 时优先取代理转发的地址 (见 [`request_client_ip`])，
+//! 否则连接地址都是代理的，所有客户端共用一个桶。未配置时不信任这些请求头 (客户端可伪造以绕过限流)
+
+use axum::{
+    extract::Request,
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+use tower::{Layer, Service};
+
+use crate::api::client_ip::request_client_ip;
+use crate::container::Container;
+
+/// 桶数超过此值时清理已补满的桶 (补满的桶与新建的桶等价)
+const MAX_BUCKETS: usize = 10_000;
+
+/// 限流参数
+///
+/// - `capacity` 桶容量，即允许的突发请求数
+/// - `refill_per_sec` 每秒补充的令牌数，即持续的请求速率
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    pub capacity: u32,
+    pub refill_per_sec: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self { capacity: 100, refill_per_sec: 20.0 }
+    }
+}
+
+/// 令牌桶，也用于按用户限流 ([`super::user_rate_limit`])
+#[derive(Debug, Clone)]
+pub(crate) struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// 满的桶
+    pub(crate) fn full(config: &RateLimitConfig, now: Instant) -> Self {
+        Self { tokens: config.capacity as f64, updated: now }
+    }
+
+    /// 补充到 `now` 时的令牌数
+    fn refill(&mut self, config: &RateLimitConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.refill_per_sec).min(config.capacity as f64);
+        self.updated = now;
+    }
+
+    /// 取一个令牌，不足时返回需等待的秒数
+    pub(crate) fn take(&mut self, config: &RateLimitConfig, now: Instant) -> Result<(), u64> {
+        self.refill(config, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(((1.0 - self.tokens) / config.refill_per_sec).ceil().max(1.0) as u64)
+    }
+}
+
+/// 限流中间件
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<Limiter>,
+}
+
+#[derive(Debug)]
+struct Limiter {
+    config: RateLimitConfig,
+    /// 键为客户端IP
+    buckets: Container<TokenBucket>,
+}
+
+impl RateLimitLayer {
+    /// panic: `capacity` 为0或 `refill_per_sec` 不为正数时
+    pub fn new(config: RateLimitConfig) -> Self {
+        assert!(config.capacity > 0 && config.refill_per_sec > 0.0, "invalid rate limit config");
+        Self { limiter: Arc::new(Limiter { config, buckets: Container::default() }) }
+    }
+}
+
+impl Default for RateLimitLayer {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+impl Limiter {
+    /// 为 `key` 取一个令牌
+    fn check(&self, key: &str) -> Result<(), u64> {
+        let now = Instant::now();
+        let config = &self.config;
+        if self.buckets.len() > MAX_BUCKETS {
+            self.buckets.delete_where(|bucket| {
+                let mut bucket = bucket.clone();
+                bucket.refill(config, now);
+                bucket.tokens >= config.capacity as f64
+            });
+        }
+        self.buckets.transaction(|tx| {
+            let mut bucket = tx.get_by_id(key)
+                .unwrap_or_else(|| TokenBucket::full(config, now));
+            let result = bucket.take(config, now);
+            tx.insert(key, bucket);
+            result
+        })
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit { inner, limiter: self.limiter.clone() }
+    }
+}
+
+/// 见 [`RateLimitLayer`]
+#[derive(Debug, Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Arc<Limiter>,
+}
+
+impl<S> Service<Request> for RateLimit<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        if let Some(ip) = request_client_ip(&req)
+            && let Err(retry_after) = self.limiter.check(&ip.to_string())
+        {
+            let response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, retry_after.to_string())],
+                Json(json!({ "error": "too many requests" })),
+            ).into_response();
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let config = RateLimitConfig { capacity: 2, refill_per_sec: 1.0 };
+        let start = Instant::now();
+        let mut bucket = TokenBucket { tokens: 2.0, updated: start };
+        assert_eq!(bucket.take(&config, start), Ok(()));
+        assert_eq!(bucket.take(&config, start), Ok(()));
+        assert_eq!(bucket.take(&config, start), Err(1));
+        assert_eq!(bucket.take(&config, start + Duration::from_millis(1500)), Ok(()));
+        // 补充不超过容量
+        let mut bucket = TokenBucket { tokens: 0.0, updated: start };
+        bucket.refill(&config, start + Duration::from_secs(60));
+        assert_eq!(bucket.tokens, 2.0);
+    }
+
+    #[test]
+    fn limits_each_key_separately() {
+        let layer = RateLimitLayer::new(RateLimitConfig { capacity: 1, refill_per_sec: 0.001 });
+        assert!(layer.limiter.check("10.0.0.1").is_ok());
+        assert!(layer.limiter.check("10.0.0.1").is_err());
+        assert!(layer.limiter.check("10.0.0.2").is_ok());
+    }
+}
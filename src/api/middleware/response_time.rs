@@ -0,0 +1,64 @@
+//! 响应耗时
+//!
+//! 在响应头 `X-Response-Time` 中返回处理耗时 (毫秒，保留3位小数)，如 `X-Response-Time: 1.234ms`。
+//! 只计到内层返回响应头为止，不含响应体 (如 SSE 流) 的发送时间
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use std::{
+    task::{Context, Poll},
+    time::Instant,
+};
+use tower::{Layer, Service};
+
+/// 响应耗时头
+pub const RESPONSE_TIME_HEADER: HeaderName = HeaderName::from_static("x-response-time");
+
+/// 响应耗时中间件
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResponseTimeLayer;
+
+impl<S> Layer<S> for ResponseTimeLayer {
+    type Service = ResponseTime<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseTime { inner }
+    }
+}
+
+/// 见 [`ResponseTimeLayer`]
+#[derive(Debug, Clone)]
+pub struct ResponseTime<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for ResponseTime<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let start = Instant::now();
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let mut response = future.await?;
+            let elapsed = format!("{:.3}ms", start.elapsed().as_secs_f64() * 1000.0);
+            if let Ok(value) = HeaderValue::from_str(&elapsed) {
+                response.headers_mut().insert(RESPONSE_TIME_HEADER, value);
+            }
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,182 @@
+//! 安全相关的响应头
+//!
+//! 为所有响应添加:
+//!
+//! - `X-Content-Type-Options: nosniff`
+//! - `X-Frame-Options: DENY`
+//! - `X-XSS-Protection: 1; mode=block`
+//! - `Referrer-Policy: strict-origin-when-cross-origin`
+//! - `Content-Security-Policy` (可配置)
+//! - `Strict-Transport-Security` (可选，仅应在启用 TLS 时配置)
+//!
+//! 仅在响应中没有该头时添加，个别路由 (如 `/docs`) 可自行设置更宽松的策略
+//!
+//! ```ignore
+//! let layer = SecurityHeadersLayer::builder()
+//!     .csp("default-src 'self'")
+//!     .hsts(Duration::from_secs(31536000))
+//!     .build();
+//! ```
+
+use axum::{
+    extract::Request,
+    http::{
+        header::{
+            CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS,
+            X_FRAME_OPTIONS, X_XSS_PROTECTION,
+        },
+        HeaderName, HeaderValue,
+    },
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::{Layer, Service};
+
+/// 默认的 CSP
+const DEFAULT_CSP: &str = "default-src 'self'";
+
+/// 安全响应头中间件，通过 [`SecurityHeadersLayer::builder`] 构造
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersLayer {
+    headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl SecurityHeadersLayer {
+    pub fn builder() -> SecurityHeadersBuilder {
+        SecurityHeadersBuilder::default()
+    }
+}
+
+impl Default for SecurityHeadersLayer {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl<S> Layer<S> for SecurityHeadersLayer {
+    type Service = SecurityHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SecurityHeaders { inner, headers: self.headers.clone() }
+    }
+}
+
+/// [`SecurityHeadersLayer`] 的构造器
+#[derive(Debug, Clone, Default)]
+pub struct SecurityHeadersBuilder {
+    csp: Option<String>,
+    hsts: Option<Duration>,
+}
+
+impl SecurityHeadersBuilder {
+    /// 设置 `Content-Security-Policy`，默认 `default-src 'self'`
+    pub fn csp(mut self, policy: impl Into<String>) -> Self {
+        self.csp = Some(policy.into());
+        self
+    }
+
+    /// 启用 `Strict-Transport-Security`，`max_age` 为有效期
+    ///
+    /// 浏览器会在有效期内强制使用 HTTPS，因此只应在启用 TLS 时配置
+    pub fn hsts(mut self, max_age: Duration) -> Self {
+        self.hsts = Some(max_age);
+        self
+    }
+
+    /// panic: CSP 含有非法的头部字符时
+    pub fn build(self) -> SecurityHeadersLayer {
+        let csp = self.csp.as_deref().unwrap_or(DEFAULT_CSP);
+        let mut headers = vec![
+            (X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+            (X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+            (X_XSS_PROTECTION, HeaderValue::from_static("1; mode=block")),
+            (REFERRER_POLICY, HeaderValue::from_static("strict-origin-when-cross-origin")),
+            (CONTENT_SECURITY_POLICY, HeaderValue::from_str(csp).expect("invalid Content-Security-Policy")),
+        ];
+        if let Some(max_age) = self.hsts {
+            let value = format!("max-age={}; includeSubDomains", max_age.as_secs());
+            headers.push((STRICT_TRANSPORT_SECURITY, HeaderValue::from_str(&value).unwrap()));
+        }
+        SecurityHeadersLayer { headers: Arc::new(headers) }
+    }
+}
+
+/// 见 [`SecurityHeadersLayer`]
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders<S> {
+    inner: S,
+    headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl<S> Service<Request> for SecurityHeaders<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let future = self.inner.call(req);
+        let headers = self.headers.clone();
+        Box::pin(async move {
+            let mut response = future.await?;
+            for (name, value) in headers.iter() {
+                response.headers_mut().entry(name).or_insert_with(|| value.clone());
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    use crate::api::{openapi::get_docs, test::root};
+
+    async fn send(layer: SecurityHeadersLayer, uri: &str) -> Response {
+        let app = Router::new().route("/", get(root)).route("/docs", get(get_docs)).layer(layer);
+        app.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn adds_headers_to_root() {
+        let layer = SecurityHeadersLayer::builder()
+            .csp("default-src 'self'; img-src 'self' data:")
+            .hsts(Duration::from_secs(31536000))
+            .build();
+        let response = send(layer, "/").await;
+        let headers = response.headers();
+        assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[X_FRAME_OPTIONS], "DENY");
+        assert_eq!(headers[X_XSS_PROTECTION], "1; mode=block");
+        assert_eq!(headers[REFERRER_POLICY], "strict-origin-when-cross-origin");
+        assert_eq!(headers[CONTENT_SECURITY_POLICY], "default-src 'self'; img-src 'self' data:");
+        assert_eq!(headers[STRICT_TRANSPORT_SECURITY], "max-age=31536000; includeSubDomains");
+    }
+
+    #[tokio::test]
+    async fn defaults_without_hsts() {
+        let response = send(SecurityHeadersLayer::default(), "/").await;
+        assert_eq!(response.headers()[CONTENT_SECURITY_POLICY], DEFAULT_CSP);
+        assert!(!response.headers().contains_key(STRICT_TRANSPORT_SECURITY));
+
+        // 路由自行设置的 CSP 不被覆盖
+        let response = send(SecurityHeadersLayer::default(), "/docs").await;
+        assert_ne!(response.headers()[CONTENT_SECURITY_POLICY], DEFAULT_CSP);
+        assert_eq!(response.headers()[X_FRAME_OPTIONS], "DENY");
+    }
+}
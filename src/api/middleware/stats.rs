@@ -0,0 +1,143 @@
+//! 请求计数
+//!
+//! 按路由模板 (如 `GET /todos/{id}`) 统计请求次数，并记录最近5分钟的请求数与错误数 (5xx)，
+//! 结果见 `GET /admin/stats`。
+//!
+//! 需通过 `Router::layer` 挂载 (见 [`track_requests`])，只有路由匹配后才能取得 `MatchedPath`
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::container::Container;
+
+/// 滑动窗口长度 (秒)
+const WINDOW_SECS: usize = 300;
+/// 未匹配任何路由的请求 (404) 统一计入该键，避免任意路径撑大计数表
+const UNMATCHED_ROUTE: &str = "<unmatched>";
+
+/// 请求计数器，作为路由状态注入
+pub struct RequestCounter {
+    started: Instant,
+    total: AtomicU64,
+    /// 只用其计数器 ([`Container::atomic_counter`])，键为 `"METHOD /path"`
+    routes: Container<()>,
+    window: SlidingWindow,
+}
+
+/// 单个路由的统计
+#[derive(Debug, Serialize)]
+pub struct RouteCount {
+    pub route: String,
+    pub count: u64,
+}
+
+impl RequestCounter {
+    pub fn new_arc() -> Arc<Self> {
+        Arc::new(Self {
+            started: Instant::now(),
+            total: AtomicU64::new(0),
+            routes: Container::default(),
+            window: SlidingWindow::new(),
+        })
+    }
+
+    /// 记录一次请求
+    fn record(&self, route: String, is_error: bool) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        self.routes.atomic_counter(&route).fetch_add(1, Ordering::Relaxed);
+        self.window.record(is_error);
+    }
+
+    pub fn uptime_secs(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    pub fn total_requests(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// 各路由的请求数，按次数降序
+    pub fn routes(&self) -> Vec<RouteCount> {
+        let mut routes: Vec<RouteCount> = self.routes.counters().into_iter()
+            .map(|(route, count)| RouteCount { route, count })
+            .collect();
+        routes.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.route.cmp(&b.route)));
+        routes
+    }
+
+    /// 最近5分钟的错误率 (5xx / 全部)，无请求时为0
+    pub fn error_rate_5m(&self) -> f64 {
+        let (requests, errors) = self.window.sum();
+        if requests == 0 { 0.0 } else { errors as f64 / requests as f64 }
+    }
+}
+
+/// 按秒分桶的环形缓冲区
+///
+/// 每个桶记录它所代表的秒 (`stamps`)，写入时发现桶已过期则先清零。
+/// 并发下清零与计数之间可能有极少量误差，对统计用途可以接受
+struct SlidingWindow {
+    stamps: Vec<AtomicU64>,
+    requests: Vec<AtomicU64>,
+    errors: Vec<AtomicU64>,
+}
+
+impl SlidingWindow {
+    fn new() -> Self {
+        let buckets = || (0..WINDOW_SECS).map(|_| AtomicU64::new(0)).collect();
+        Self { stamps: buckets(), requests: buckets(), errors: buckets() }
+    }
+
+    fn record(&self, is_error: bool) {
+        let now = now_secs();
+        let i = (now % WINDOW_SECS as u64) as usize;
+        let stamp = self.stamps[i].load(Ordering::Acquire);
+        if stamp != now
+            && self.stamps[i].compare_exchange(stamp, now, Ordering::AcqRel, Ordering::Acquire).is_ok()
+        {
+            self.requests[i].store(0, Ordering::Release);
+            self.errors[i].store(0, Ordering::Release);
+        }
+        self.requests[i].fetch_add(1, Ordering::Relaxed);
+        if is_error {
+            self.errors[i].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 窗口内的 (请求数, 错误数)
+    fn sum(&self) -> (u64, u64) {
+        let now = now_secs();
+        (0..WINDOW_SECS)
+            .filter(|&i| now.saturating_sub(self.stamps[i].load(Ordering::Acquire)) < WINDOW_SECS as u64)
+            .fold((0, 0), |(requests, errors), i| (
+                requests + self.requests[i].load(Ordering::Relaxed),
+                errors + self.errors[i].load(Ordering::Relaxed),
+            ))
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// 计数中间件，配合 `axum::middleware::from_fn_with_state` 使用
+pub async fn track_requests(State(counter): State<Arc<RequestCounter>>, req: Request, next: Next) -> Response {
+    let route = match req.extensions().get::<MatchedPath>() {
+        Some(path) => format!("{} {}", req.method(), path.as_str()),
+        None => format!("{} {}", req.method(), UNMATCHED_ROUTE),
+    };
+    let response = next.run(req).await;
+    counter.record(route, response.status().is_server_error());
+    response
+}
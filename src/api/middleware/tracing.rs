@@ -0,0 +1,102 @@
+//! 请求追踪
+//!
+//! 为每个请求创建一个 `request` span，处理函数与内层中间件的日志都在其中，带以下字段:
+//!
+//! - `http.method`
+//! - `http.route` 路由模板 (`MatchedPath`，如 `/todos/{id}`)，而非实际路径 (可能含令牌等)
+//! - `http.status_code` 响应时记录
+//! - `request_id` 取自 [`super::request_id::RequestIdLayer`] 放入的请求扩展 (需挂载在其内层)，
+//!   没有时按同样的规则从请求头取或生成
+//! - `user_id` 通过认证后由 [`super::jwt::JwtAuthLayer`] 记录
+//!
+//! 响应时以 debug 级别记录一条 (含耗时)。需用 `Router::layer` 挂载，才能取得 `MatchedPath`。
+//! 启用 `otel` feature 时另接入传入的 `traceparent`，见 [`crate::telemetry`]
+
+use axum::{
+    extract::{MatchedPath, Request},
+    response::Response,
+};
+#[cfg(feature = "otel")]
+use axum::http::{HeaderName, HeaderValue};
+use futures_util::future::BoxFuture;
+use std::{
+    task::{Context, Poll},
+    time::Instant,
+};
+use ::tracing::{field::Empty, Instrument, Span};
+use tower::{Layer, Service};
+
+use super::request_id::RequestId;
+
+/// 追踪上下文的响应头 (W3C Trace Context Level 2)
+#[cfg(feature = "otel")]
+const TRACERESPONSE_HEADER: HeaderName = HeaderName::from_static("traceresponse");
+
+/// 请求追踪中间件
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingLayer;
+
+impl<S> Layer<S> for TracingLayer {
+    type Service = Tracing<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Tracing { inner }
+    }
+}
+
+/// 见 [`TracingLayer`]
+#[derive(Debug, Clone)]
+pub struct Tracing<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for Tracing<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let request_id = req.extensions().get::<RequestId>().cloned()
+            .unwrap_or_else(|| RequestId::from_headers(req.headers()));
+        let route = req.extensions().get::<MatchedPath>().map_or("", MatchedPath::as_str);
+        let span = ::tracing::info_span!(
+            "request",
+            http.method = %req.method(),
+            http.route = route,
+            http.status_code = Empty,
+            request_id = %request_id.as_str(),
+            user_id = Empty,
+        );
+        #[cfg(feature = "otel")]
+        let (req, trace_context) = {
+            let mut req = req;
+            let trace_context = crate::telemetry::attach(&span, &mut req);
+            (req, trace_context)
+        };
+
+        let future = span.in_scope(|| self.inner.call(req));
+        Box::pin(async move {
+            let start = Instant::now();
+            let response = future.await?;
+            Span::current().record("http.status_code", response.status().as_u16());
+            ::tracing::debug!("finished in {}ms", start.elapsed().as_millis());
+            #[cfg(feature = "otel")]
+            let response = {
+                let mut response = response;
+                if let Some(value) = trace_context.traceresponse().and_then(|value| HeaderValue::from_str(&value).ok()) {
+                    response.headers_mut().insert(TRACERESPONSE_HEADER, value);
+                }
+                response
+            };
+            Ok(response)
+        }.instrument(span))
+    }
+}
@@ -0,0 +1,204 @@
+//! 按用户限流
+//!
+//! 按IP限流 ([`super::rate_limit`]) 挡不住同一用户从多个IP发起的请求，登录后的路由再按用户ID限流:
+//! 每个用户一个令牌桶，令牌不足时返回 `429 { "error": "too many requests" }` 与 `Retry-After`。
+//!
+//! 默认配额来自配置文件 (`user_rate_limit_capacity`、`user_rate_limit_refill_per_sec`)，管理员不限流。
+//! 可用 `PATCH /admin/users/{id}/rate-limit` 为单个用户修改 (见 [`set_quota`])。
+//!
+//! 需在 [`super::jwt::JwtAuthLayer`] 内层 (由其放入 `Claims`)，没有 `Claims` 的请求不限流:
+//!
+//! ```ignore
+//! router
+//!     .route_layer(UserRateLimitLayer)
+//!     .route_layer(JwtAuthLayer)
+//! ```
+
+use axum::{
+    extract::Request,
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    task::{Context, Poll},
+    time::Instant,
+};
+use tower::{Layer, Service};
+
+use crate::api::auth::UserRole;
+use crate::config::CONFIG;
+use crate::container::Container;
+use super::jwt::Claims;
+use super::rate_limit::{RateLimitConfig, TokenBucket};
+
+/// 用户的限流配额
+///
+/// - `capacity` 桶容量，即允许的突发请求数
+/// - `refill_per_sec` 每秒补充的令牌数
+/// - `unlimited` 不限流 (管理员默认如此)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct UserQuota {
+    pub capacity: u32,
+    pub refill_per_sec: f64,
+    pub unlimited: bool,
+}
+
+/// 管理员设置的配额，键为用户ID
+static QUOTAS: Lazy<Container<UserQuota>> = Lazy::new(Container::default);
+/// 各用户的令牌桶，键为用户ID
+static BUCKETS: Lazy<Container<TokenBucket>> = Lazy::new(Container::default);
+
+/// 按角色的默认配额
+pub fn default_quota(role: UserRole) -> UserQuota {
+    UserQuota {
+        capacity: CONFIG.user_rate_limit_capacity,
+        refill_per_sec: CONFIG.user_rate_limit_refill_per_sec,
+        unlimited: role == UserRole::Admin,
+    }
+}
+
+/// 用户当前的配额: 管理员设置过的，否则为按角色的默认值
+pub fn quota(user_id: &str, role: UserRole) -> UserQuota {
+    QUOTAS.get_by_id(user_id).unwrap_or_else(|| default_quota(role))
+}
+
+/// 设置用户的配额，该用户的令牌桶重新开始 (满的)
+pub fn set_quota(user_id: &str, quota: UserQuota) {
+    QUOTAS.put_by_id(user_id, quota);
+    BUCKETS.delete_by_id(user_id);
+    tracing::info!("set rate limit of user {} to {:?}", user_id, quota);
+}
+
+/// 为用户取一个令牌，不足时返回需等待的秒数
+fn check(claims: &Claims) -> Result<(), u64> {
+    let quota = quota(&claims.sub, claims.role);
+    if quota.unlimited {
+        return Ok(());
+    }
+    let config = RateLimitConfig { capacity: quota.capacity, refill_per_sec: quota.refill_per_sec };
+    let now = Instant::now();
+    BUCKETS.transaction(|tx| {
+        let mut bucket = tx.get_by_id(&claims.sub).unwrap_or_else(|| TokenBucket::full(&config, now));
+        let result = bucket.take(&config, now);
+        tx.insert(&claims.sub, bucket);
+        result
+    })
+}
+
+/// 按用户限流中间件，各实例共享同一份配额与令牌桶
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserRateLimitLayer;
+
+impl<S> Layer<S> for UserRateLimitLayer {
+    type Service = UserRateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        UserRateLimit { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserRateLimit<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for UserRateLimit<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        if let Some(claims) = req.extensions().get::<Claims>()
+            && let Err(retry_after) = check(claims)
+        {
+            let response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, retry_after.to_string())],
+                Json(json!({ "error": "too many requests" })),
+            ).into_response();
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn claims(user_id: &str, role: UserRole) -> Claims {
+        Claims { sub: user_id.to_string(), username: user_id.to_string(), role, iat: 0, exp: u64::MAX }
+    }
+
+    fn limited(capacity: u32) -> UserQuota {
+        UserQuota { capacity, refill_per_sec: 0.5, unlimited: false }
+    }
+
+    #[tokio::test]
+    async fn rejects_with_retry_after() {
+        set_quota("user-rate-limit-retry", limited(1));
+        let app = Router::new().route("/", get(|| async { "ok" })).layer(UserRateLimitLayer);
+        let send = |claims: Option<Claims>| {
+            let mut req = Request::get("/").body(Body::empty()).unwrap();
+            if let Some(claims) = claims {
+                req.extensions_mut().insert(claims);
+            }
+            app.clone().oneshot(req)
+        };
+
+        let response = send(Some(claims("user-rate-limit-retry", UserRole::User))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(Some(claims("user-rate-limit-retry", UserRole::User))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "2"); // 每秒补充 0.5 个
+        // 没有 Claims 的请求不限流
+        assert_eq!(send(None).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn admins_are_unlimited_by_default() {
+        assert!(default_quota(UserRole::Admin).unlimited);
+        assert!(!default_quota(UserRole::User).unlimited);
+        let admin = claims("user-rate-limit-admin", UserRole::Admin);
+        for _ in 0..CONFIG.user_rate_limit_capacity + 10 {
+            assert_eq!(check(&admin), Ok(()));
+        }
+
+        // 管理员设置的配额优先于按角色的默认值
+        set_quota("user-rate-limit-admin", limited(1));
+        assert_eq!(check(&admin), Ok(()));
+        assert!(check(&admin).is_err());
+    }
+
+    #[test]
+    fn set_quota_resets_bucket() {
+        let user = claims("user-rate-limit-reset", UserRole::User);
+        set_quota(&user.sub, limited(2));
+        assert_eq!(check(&user), Ok(()));
+        assert_eq!(check(&user), Ok(()));
+        assert!(check(&user).is_err());
+
+        // 新配额从满桶开始，不继承已耗尽的令牌
+        set_quota(&user.sub, limited(3));
+        assert_eq!(quota(&user.sub, UserRole::User), limited(3));
+        for _ in 0..3 {
+            assert_eq!(check(&user), Ok(()));
+        }
+        assert!(check(&user).is_err());
+    }
+}
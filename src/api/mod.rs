@@ -2,9 +2,108 @@
 //! 
 //! 区分成多个模块，作为多个API组
 //! 符合 RESTful 风格
+//! 
+//! 资源类API (todos/rest/node) 带版本前缀，如 `/v1/todos`。
+//! 无前缀的路径是当前版本 (v1) 的别名，以兼容旧客户端
+
+use axum::{
+    response::{IntoResponse, Json},
+    Router,
+};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::container::Container;
 
 pub mod test;
 pub mod heartbeat;
 pub mod rest_todos;
 pub mod rest_store;
 pub mod rest_node;
+pub mod graph_viz;
+pub mod events;
+pub mod ws;
+pub mod openapi;
+pub mod middleware;
+pub mod api_keys;
+pub mod health;
+pub mod admin;
+pub mod extractors;
+pub mod pagination;
+pub mod fingerprint;
+pub mod auth;
+pub mod oauth;
+pub mod refresh_token;
+pub mod cache;
+pub mod json_pointer;
+pub mod json_schema;
+pub mod query_engine;
+pub mod jobs;
+pub mod route_registry;
+pub mod webhooks;
+pub mod client_ip;
+pub mod version;
+
+/// 当前版本
+pub const CURRENT_API_VERSION: &str = "v1";
+/// 支持的版本
+pub const SUPPORTED_API_VERSIONS: &[&str] = &["v1"];
+
+/// 各资源的存储
+/// 
+/// 在 main 中创建，分别交给资源路由和管理接口 (`/admin/memory`)
+#[derive(Clone)]
+pub struct Stores {
+    pub todos: rest_todos::ItemContainer,
+    pub rest: rest_store::ItemContainer,
+    pub nodes: rest_node::ItemContainer,
+}
+
+impl Default for Stores {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stores {
+    pub fn new() -> Self {
+        Self {
+            todos: Container::new_arc(),
+            rest: Container::new_arc(),
+            nodes: rest_node::node_container(), // 节点存储是全局的，见 rest_node
+        }
+    }
+
+    /// 以名称标识的各存储，供 `/admin/memory` 使用
+    pub fn memory_reports(&self) -> Vec<(&'static str, Arc<dyn admin::MemoryReport>)> {
+        vec![
+            ("todos", self.todos.clone()),
+            ("rest", self.rest.clone()),
+            ("nodes", self.nodes.clone()),
+        ]
+    }
+}
+
+/// v1 版本的资源路由 (不含前缀)
+/// 
+/// 调用方用 `nest("/v1", ...)` 挂载。同一个 Router 可 clone 后多处挂载，共享同一份数据
+pub async fn v1_router(stores: &Stores) -> Router {
+    rest_todos::factory_todos_router(stores.todos.clone()).await
+        .merge(rest_store::factory_rest_router(stores.rest.clone()).await)
+        .merge(rest_node::factory_node_router().await)
+}
+
+/// v2 版本的资源路由 (占位)
+/// 
+/// 响应结构有不兼容的修改时在此添加
+pub async fn v2_router() -> Router {
+    Router::new()
+}
+
+/// GET /api-versions, 获取API版本信息
+pub async fn get_api_versions() -> impl IntoResponse {
+    Json(json!({
+        "current": CURRENT_API_VERSION,
+        "supported": SUPPORTED_API_VERSIONS,
+    }))
+}
@@ -0,0 +1,222 @@
+//! GitHub 登录 (OAuth2 授权码流程)
+//!
+//! - `GET /auth/github`: 重定向到 GitHub 的授权页 (`scope=read:user`)
+//! - `GET /auth/github/callback?code=&state=`: GitHub 回调，换取访问令牌并读取用户信息，
+//!   查找或创建对应的用户 (见 [`crate::api::auth`])，返回与 `POST /auth/login` 相同的 JWT
+//!
+//! `state` 同时写入 `SameSite=Lax` 的 cookie，回调时须与之一致 (绑定发起登录的浏览器)，并使用 PKCE。
+//! 未完成的授权请求数有上限，超出时返回 `503`
+//!
+//! 需设置环境变量 `GITHUB_CLIENT_ID`、`GITHUB_CLIENT_SECRET`，可选 `GITHUB_REDIRECT_URL`
+//! (未设置时使用 GitHub 应用中配置的回调地址)。未配置时以上接口返回 `503`
+
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Json, Router,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use chrono::{DateTime, Duration, Utc};
+use oauth2::{
+    basic::BasicClient, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointNotSet, EndpointSet,
+    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, TokenResponse, TokenUrl,
+};
+use once_cell::sync::Lazy;
+use reqwest::{header, redirect::Policy};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+
+use crate::api::auth;
+use crate::container::Container;
+use crate::documented_route;
+
+const AUTHORIZE_URL: &str = "https://github.com/login/oauth/authorize";
+const TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const USER_URL: &str = "https://api.github.com/user";
+/// `state` 的有效期 (分钟)
+const STATE_TTL_MINUTES: i64 = 10;
+/// 未完成的授权请求数上限 (每次请求都会保存一个 `state`，防止被刷爆内存)
+const MAX_PENDING_STATES: usize = 10_000;
+/// 保存 `state` 的 cookie 名
+const STATE_COOKIE: &str = "github_oauth_state";
+/// `state` cookie 的路径，只在回调时发送
+const STATE_COOKIE_PATH: &str = "/auth/github";
+
+/// 已设置授权与令牌地址的客户端
+type GithubClient = BasicClient<EndpointSet, EndpointNotSet, EndpointNotSet, EndpointNotSet, EndpointSet>;
+
+/// GitHub 客户端，未配置时为 None
+static GITHUB: Lazy<Option<GithubClient>> = Lazy::new(|| {
+    let (Ok(client_id), Ok(client_secret)) = (std::env::var("GITHUB_CLIENT_ID"), std::env::var("GITHUB_CLIENT_SECRET")) else {
+        return None;
+    };
+    let mut client = BasicClient::new(ClientId::new(client_id))
+        .set_client_secret(ClientSecret::new(client_secret))
+        .set_auth_uri(AuthUrl::new(AUTHORIZE_URL.to_string()).expect("invalid authorize url"))
+        .set_token_uri(TokenUrl::new(TOKEN_URL.to_string()).expect("invalid token url"));
+    if let Ok(redirect_url) = std::env::var("GITHUB_REDIRECT_URL") {
+        match RedirectUrl::new(redirect_url) {
+            Ok(redirect_url) => client = client.set_redirect_uri(redirect_url),
+            Err(err) => tracing::error!("invalid GITHUB_REDIRECT_URL: {}", err),
+        }
+    }
+    Some(client)
+});
+
+/// 请求 GitHub 的HTTP客户端
+///
+/// 不跟随重定向，避免令牌接口被重定向到其他地址 (SSRF)
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .redirect(Policy::none())
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .expect("failed to build http client")
+});
+
+/// 授权请求的 `state` (防 CSRF)，回调时校验并删除
+#[derive(Debug, Clone)]
+struct OAuthState {
+    created_at: DateTime<Utc>,
+    /// PKCE 的 code_verifier，换取令牌时提交
+    pkce_verifier: String,
+}
+
+/// 未完成的授权请求，以 `state` 为索引
+static STATES: Lazy<Arc<Container<OAuthState>>> = Lazy::new(Container::new_arc);
+
+/// GitHub 登录路由
+pub fn factory_github_router() -> Router {
+    Router::new()
+        .merge(documented_route!(get, "/auth/github", get_auth_github, "重定向到 GitHub 授权页"))
+        .merge(documented_route!(get, "/auth/github/callback", get_auth_github_callback, "GitHub 登录回调"))
+}
+
+/// GET /auth/github, 重定向到 GitHub 授权页
+///
+/// 未完成的授权请求过多时返回 `503`
+async fn get_auth_github(jar: CookieJar) -> Response {
+    let Some(client) = GITHUB.as_ref() else {
+        return not_configured();
+    };
+
+    // 顺带清理过期的 state
+    let expired_before = Utc::now() - Duration::minutes(STATE_TTL_MINUTES);
+    STATES.delete_where(|state| state.created_at < expired_before);
+    if STATES.len() >= MAX_PENDING_STATES {
+        tracing::warn!("too many pending github logins");
+        return error(StatusCode::SERVICE_UNAVAILABLE, "too many pending logins, try again later");
+    }
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (url, state) = client
+        .authorize_url(CsrfToken::new_random)
+        .add_scope(Scope::new("read:user".to_string()))
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+    STATES.put_by_id(state.secret(), OAuthState {
+        created_at: Utc::now(),
+        pkce_verifier: pkce_verifier.secret().to_string(),
+    });
+
+    // GitHub 回调是跨站的顶级导航，Lax 的 cookie 会随之发送
+    let cookie = Cookie::build((STATE_COOKIE, state.secret().to_string()))
+        .path(STATE_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .build();
+    (jar.add(cookie), Redirect::to(url.as_str())).into_response()
+}
+
+/// GET /auth/github/callback, GitHub 回调
+///
+/// - `state` 无效、过期、已使用，或与 cookie 中的不一致 (不是本浏览器发起的登录): `400`
+/// - 用户拒绝授权 (`error=access_denied`): `401`
+/// - 与 GitHub 通信失败: `502`
+async fn get_auth_github_callback(jar: CookieJar, Query(query): Query<CallbackQuery>) -> Response {
+    let Some(client) = GITHUB.as_ref() else {
+        return not_configured();
+    };
+
+    let same_browser = jar.get(STATE_COOKIE)
+        .is_some_and(|cookie| bool::from(cookie.value().as_bytes().ct_eq(query.state.as_bytes())));
+    let jar = jar.remove(Cookie::build(STATE_COOKIE).path(STATE_COOKIE_PATH));
+    if !same_browser {
+        return (jar, error(StatusCode::BAD_REQUEST, "invalid or expired state")).into_response();
+    }
+    // state 只能使用一次
+    let state = STATES.delete_by_id(&query.state)
+        .filter(|state| Utc::now() - state.created_at < Duration::minutes(STATE_TTL_MINUTES));
+    let Some(state) = state else {
+        return (jar, error(StatusCode::BAD_REQUEST, "invalid or expired state")).into_response();
+    };
+    let Some(code) = query.code else {
+        tracing::debug!("github authorization denied: {:?}", query.error);
+        return (jar, error(StatusCode::UNAUTHORIZED, "github authorization denied")).into_response();
+    };
+
+    let token = client.exchange_code(AuthorizationCode::new(code))
+        .set_pkce_verifier(PkceCodeVerifier::new(state.pkce_verifier))
+        .request_async(&*HTTP)
+        .await;
+    let token = match token {
+        Ok(token) => token,
+        Err(err) => {
+            tracing::warn!("github token exchange failed: {}", err);
+            return (jar, error(StatusCode::BAD_GATEWAY, "github token exchange failed")).into_response();
+        }
+    };
+    let github_user = match fetch_user(token.access_token().secret()).await {
+        Ok(user) => user,
+        Err(err) => {
+            tracing::warn!("failed to fetch github user: {}", err);
+            return (jar, error(StatusCode::BAD_GATEWAY, "failed to fetch github user")).into_response();
+        }
+    };
+
+    let user = auth::upsert_github_user(github_user.id, &github_user.login);
+    (jar, auth::token_response(&user)).into_response()
+}
+
+/// 读取访问令牌对应的 GitHub 用户
+async fn fetch_user(access_token: &str) -> Result<GithubUser, reqwest::Error> {
+    HTTP.get(USER_URL)
+        .bearer_auth(access_token)
+        .header(header::USER_AGENT, env!("CARGO_PKG_NAME")) // GitHub API 要求带 User-Agent
+        .header(header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+fn not_configured() -> Response {
+    error(StatusCode::SERVICE_UNAVAILABLE, "github login not configured")
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+// #region api struct
+
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: String,
+    /// 授权失败时 GitHub 给出的错误，如 `access_denied`
+    error: Option<String>,
+}
+
+/// GitHub 用户 (只取用到的字段)
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+    id: u64,
+    login: String,
+}
+
+// #endregion
@@ -0,0 +1,1607 @@
+//! OpenAPI 3.1 文档
+//!
+//! - `GET /openapi.json`: 手工构造的 OpenAPI 规范
+//! - `GET /docs`: Swagger UI 页面 (从CDN加载)，指向上面的规范
+//!
+//! 修改路由或请求/响应结构时需同步修改这里
+
+use axum::{
+    http::header::CONTENT_SECURITY_POLICY,
+    response::{Html, IntoResponse, Json},
+    Router,
+};
+use serde_json::{json, Map, Value};
+use crate::documented_route;
+
+/// 文档路由
+pub fn factory_openapi_router() -> Router {
+    Router::new()
+        .merge(documented_route!(get, "/openapi.json", get_openapi, "OpenAPI 规范"))
+        .merge(documented_route!(get, "/docs", get_docs, "Swagger UI"))
+}
+
+/// GET /openapi.json, OpenAPI 规范
+pub async fn get_openapi() -> impl IntoResponse {
+    Json(openapi_spec())
+}
+
+/// GET /docs, Swagger UI
+///
+/// 页面需从CDN加载脚本和样式，并含内联脚本，因此使用单独的 CSP
+pub async fn get_docs() -> impl IntoResponse {
+    ([(CONTENT_SECURITY_POLICY, DOCS_CSP)], Html(SWAGGER_UI_HTML))
+}
+
+const DOCS_CSP: &str = "default-src 'self'; script-src 'self' 'unsafe-inline' https://unpkg.com; \
+    style-src 'self' https://unpkg.com; img-src 'self' data:";
+
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8" />
+    <title>API Docs</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js" crossorigin></script>
+    <script>
+        window.onload = () => {
+            window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+        };
+    </script>
+</body>
+</html>
+"##;
+
+/// 构造完整的规范
+pub fn openapi_spec() -> Value {
+    let mut paths = Map::new();
+    paths.insert("/heartbeat".into(), json!({
+        "get": {
+            "tags": ["utils"],
+            "summary": "心跳检测",
+            "responses": {
+                "200": json_response("服务器状态", json!({ "$ref": "#/components/schemas/Heartbeat" })),
+            },
+        },
+    }));
+    paths.insert("/version".into(), json!({
+        "get": {
+            "tags": ["utils"],
+            "summary": "服务器版本与构建信息",
+            "responses": {
+                "200": json_response("构建信息", json!({
+                    "type": "object",
+                    "properties": {
+                        "version": { "type": "string" },
+                        "build_date": { "type": "string", "format": "date-time" },
+                        "rustc_version": { "type": "string" },
+                        "git_commit_hash": { "type": ["string", "null"], "description": "不在 git 仓库中构建时为 null" },
+                        "features": { "type": "array", "items": { "type": "string" }, "description": "启用的 Cargo 特性，按名称排序" },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/heartbeat/history".into(), json!({
+        "get": {
+            "tags": ["utils"],
+            "summary": "在线用户数的历史 (每10秒一个样本，保留1小时)",
+            "parameters": [query_parameter("since_secs", "integer", "只返回最近N秒内的样本，缺省时返回全部")],
+            "responses": {
+                "200": json_response("按时间升序的样本", json!({
+                    "type": "object",
+                    "properties": {
+                        "samples": { "type": "array", "items": {
+                            "type": "object",
+                            "properties": {
+                                "at": { "type": "string", "format": "date-time" },
+                                "count": { "type": "integer" },
+                            },
+                        } },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/heartbeat/config".into(), json!({
+        "get": {
+            "tags": ["admin"],
+            "summary": "心跳会话的清理配置",
+            "responses": {
+                "200": json_response("当前配置", json!({
+                    "type": "object",
+                    "properties": {
+                        "timeout_secs": { "type": "integer", "description": "会话超时 (秒)" },
+                        "interval_secs": { "type": "integer", "description": "清理间隔 (秒)" },
+                        "strategy": { "enum": ["cookie", "fingerprint", "best"], "description": "会话的识别方式" },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/health/live".into(), json!({
+        "get": {
+            "tags": ["utils"],
+            "summary": "存活探针",
+            "responses": {
+                "200": json_response("存活", json!({
+                    "type": "object",
+                    "properties": {
+                        "status": { "const": "alive" },
+                        "listening": { "type": "array", "items": { "type": "string" }, "description": "实际监听的地址" },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/health/ready".into(), json!({
+        "get": {
+            "tags": ["utils"],
+            "summary": "就绪探针",
+            "responses": {
+                "200": json_response("全部检查通过", json!({ "$ref": "#/components/schemas/Readiness" })),
+                "503": json_response("存在失败的检查项", json!({ "$ref": "#/components/schemas/Readiness" })),
+            },
+        },
+    }));
+    paths.insert("/admin/tasks".into(), json!({
+        "get": {
+            "tags": ["admin"],
+            "summary": "后台任务状态",
+            "responses": { "200": json_response("任务列表", array_of("TaskInfo")) },
+        },
+    }));
+    paths.insert("/admin/stats".into(), json!({
+        "get": {
+            "tags": ["admin"],
+            "summary": "各路由的请求计数",
+            "responses": {
+                "200": json_response("统计", json!({
+                    "type": "object",
+                    "properties": {
+                        "uptime_secs": { "type": "integer" },
+                        "total_requests": { "type": "integer" },
+                        "error_rate_5m": { "type": "number", "description": "最近5分钟 5xx 响应占比" },
+                        "routes": {
+                            "type": "array",
+                            "description": "按次数降序",
+                            "items": {
+                                "type": "object",
+                                "properties": { "route": { "type": "string" }, "count": { "type": "integer" } },
+                            },
+                        },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/admin/memory".into(), json!({
+        "get": {
+            "tags": ["admin"],
+            "summary": "各存储的数据量 (以JSON序列化后的大小估算)",
+            "parameters": [query_parameter("format", "string", "human: 字节数显示为可读形式")],
+            "responses": {
+                "200": json_response("以存储名为键", json!({
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "properties": {
+                            "item_count": { "type": "integer" },
+                            "estimated_bytes": { "type": ["integer", "string"] },
+                            "oldest_item_created_at": { "type": ["string", "null"], "format": "date-time" },
+                            "newest_item_created_at": { "type": ["string", "null"], "format": "date-time" },
+                        },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/admin/snapshot".into(), json!({
+        "get": {
+            "tags": ["admin"],
+            "summary": "导出各存储的全部数据",
+            "parameters": [query_parameter("store", "string", "只导出该存储 (todos/rest/nodes)，此时直接返回以ID为键的对象")],
+            "responses": {
+                "200": json_response("以存储名为键，值为以ID为键的对象", json!({
+                    "type": "object",
+                    "additionalProperties": { "type": "object" },
+                })),
+                "404": empty_response("存储不存在"),
+            },
+        },
+    }));
+    paths.insert("/admin/fingerprint/rotate-secret".into(), json!({
+        "post": {
+            "tags": ["admin"],
+            "summary": "更换心跳指纹的密钥 (清空在线用户)",
+            "responses": {
+                "200": json_response("清除的在线用户数", json!({
+                    "type": "object",
+                    "properties": { "cleared_sessions": { "type": "integer" } },
+                })),
+            },
+        },
+    }));
+    paths.insert("/admin/sessions".into(), json!({
+        "get": {
+            "tags": ["admin"],
+            "summary": "在线用户 (心跳会话)，按最近活跃时间降序",
+            "responses": {
+                "200": json_response("会话列表", json!({
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "string" },
+                            "last_active": { "type": "integer", "description": "Unix 毫秒" },
+                            "ip": { "type": "string" },
+                            "user_agent": { "type": "string" },
+                        },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/admin/sessions/{id}".into(), json!({
+        "delete": {
+            "tags": ["admin"],
+            "summary": "使会话立即过期",
+            "parameters": [id_parameter()],
+            "responses": {
+                "204": empty_response("已删除"),
+                "404": json_response("会话不存在", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/admin/users/{id}/role".into(), json!({
+        "patch": {
+            "tags": ["admin"],
+            "summary": "修改用户角色 (需重新签发令牌才生效)",
+            "parameters": [id_parameter()],
+            "requestBody": json_body(json!({
+                "type": "object",
+                "required": ["role"],
+                "properties": { "role": { "enum": ["admin", "user"] } },
+            })),
+            "responses": {
+                "200": json_response("修改后的用户", json!({ "$ref": "#/components/schemas/User" })),
+                "404": json_response("用户不存在", json!({ "$ref": "#/components/schemas/AppError" })),
+                "409": json_response("不能降级自己", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    let quota = json!({
+        "type": "object",
+        "properties": {
+            "capacity": { "type": "integer", "minimum": 1, "description": "桶容量，即允许的突发请求数" },
+            "refill_per_sec": { "type": "number", "exclusiveMinimum": 0, "description": "每秒补充的令牌数" },
+            "unlimited": { "type": "boolean", "description": "不限流 (管理员默认如此)" },
+        },
+    });
+    paths.insert("/admin/users/{id}/rate-limit".into(), json!({
+        "patch": {
+            "tags": ["admin"],
+            "summary": "修改用户的限流配额 (未给出的字段保持当前值)",
+            "parameters": [id_parameter()],
+            "requestBody": json_body(quota.clone()),
+            "responses": {
+                "200": json_response("修改后的配额", quota),
+                "404": json_response("用户不存在", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/admin/node/circuit-breakers".into(), json!({
+        "get": {
+            "tags": ["admin"],
+            "summary": "Http 节点的熔断器状态 (只列出有失败记录的 URL)",
+            "responses": {
+                "200": json_response("熔断器列表", json!({
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "url": { "type": "string" },
+                            "state": { "enum": ["closed", "open", "half_open"] },
+                            "consecutive_failures": { "type": "integer" },
+                            "retry_after_secs": { "type": ["integer", "null"], "description": "熔断中时，距离试探的秒数" },
+                            "last_error": { "type": ["string", "null"] },
+                        },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/admin/debug/routes".into(), json!({
+        "get": {
+            "tags": ["admin"],
+            "summary": "已注册的路由 (按路径与方法排序)",
+            "responses": {
+                "200": json_response("路由列表", json!({
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "method": { "type": "string" },
+                            "path_pattern": { "type": "string", "description": "路径模板，资源路由为不带 /v1 前缀的路径" },
+                            "module": { "type": "string", "description": "所在模块，如 api::rest_todos" },
+                            "description": { "type": "string" },
+                        },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/admin/schema-version".into(), json!({
+        "get": {
+            "tags": ["admin"],
+            "summary": "持久化后端的数据库版本",
+            "responses": {
+                "200": json_response("以后端名 (sqlite/sled) 为键，未启用持久化时为空", json!({
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "properties": {
+                            "current": { "type": "integer" },
+                            "latest": { "type": "integer" },
+                        },
+                    },
+                })),
+            },
+        },
+    }));
+    crud_paths(&mut paths, "todos", "TodoItem", "TodoRequest");
+    if let Some(Value::Array(parameters)) = paths.get_mut("/todos").and_then(|path| path.pointer_mut("/get/parameters")) {
+        parameters.push(query_parameter("completed", "boolean", "仅返回该完成状态的项"));
+        parameters.push(query_parameter("after", "string", "游标分页: 返回该ID之后的项 (空串表示从头开始)，响应体改为 TodoCursorPage"));
+    }
+    if let Some(response) = paths.get_mut("/todos").and_then(|path| path.pointer_mut("/get/responses/200/content/application~1json")) {
+        response["schema"] = json!({
+            "oneOf": [array_of("TodoItem"), { "$ref": "#/components/schemas/TodoCursorPage" }],
+        });
+    }
+    if let Some(responses) = paths.get_mut("/todos").and_then(|path| path.pointer_mut("/get/responses")) {
+        responses["400"] = json_response("游标对应的项不存在", json!({ "$ref": "#/components/schemas/AppError" }));
+    }
+    if let Some(response) = paths.get_mut("/todos/{id}").and_then(|path| path.pointer_mut("/get/responses/200")) {
+        response["headers"] = json!({
+            "X-Cache": { "description": "进程内缓存是否命中 (缓存5秒，修改时失效)", "schema": { "enum": ["HIT", "MISS"] } },
+            "ETag": { "description": "响应体的哈希", "schema": { "type": "string" } },
+        });
+    }
+    paths.insert("/todos/{id}/toggle".into(), json!({
+        "patch": {
+            "tags": ["todos"],
+            "summary": "切换完成状态 (无需请求体)",
+            "parameters": [id_parameter()],
+            "responses": {
+                "200": json_response("修改后的项", json!({ "$ref": "#/components/schemas/TodoItem" })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/todos/{id}/move".into(), json!({
+        "patch": {
+            "tags": ["todos"],
+            "summary": "调整顺序 (before_id、after_id、position 三选一)",
+            "parameters": [id_parameter()],
+            "requestBody": json_body(json!({
+                "type": "object",
+                "properties": {
+                    "before_id": { "type": "string", "description": "移到该项之前" },
+                    "after_id": { "type": "string", "description": "移到该项之后" },
+                    "position": { "type": "integer", "minimum": 0, "description": "移到第几位 (从0开始)" },
+                },
+            })),
+            "responses": {
+                "200": json_response("移动后的项", json!({ "$ref": "#/components/schemas/TodoItem" })),
+                "400": json_response("参数有误或参照项不存在", json!({ "$ref": "#/components/schemas/AppError" })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/todos/toggle-all".into(), json!({
+        "patch": {
+            "tags": ["todos"],
+            "summary": "把本人的所有项设为指定的完成状态",
+            "parameters": [{ "name": "completed", "in": "query", "required": true, "schema": { "type": "boolean" } }],
+            "responses": {
+                "200": json_response("修改的项数", json!({
+                    "type": "object",
+                    "properties": { "updated": { "type": "integer" } },
+                })),
+            },
+        },
+    }));
+    paths.insert("/todos/{id}/share".into(), json!({
+        "post": {
+            "tags": ["todos"],
+            "summary": "生成只读分享链接 (7天有效，每项最多10个)",
+            "parameters": [id_parameter()],
+            "responses": {
+                "201": json_response("分享令牌 (只在此时可见)", json!({
+                    "type": "object",
+                    "properties": {
+                        "share_token": { "type": "string", "description": "用于 GET /shared/todos/{share_token}" },
+                        "expires_at": { "type": "string", "format": "date-time" },
+                    },
+                })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+        "delete": {
+            "tags": ["todos"],
+            "summary": "撤销该项的所有分享链接",
+            "parameters": [id_parameter()],
+            "responses": {
+                "204": empty_response("已撤销"),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/shared/todos/{share_token}".into(), json!({
+        "get": {
+            "tags": ["todos"],
+            "summary": "通过分享令牌查看 (无需登录)",
+            "parameters": [{ "name": "share_token", "in": "path", "required": true, "schema": { "type": "string" } }],
+            "responses": {
+                "200": json_response("公开的字段", json!({ "$ref": "#/components/schemas/SharedTodoItem" })),
+                "404": empty_response("令牌无效、过期或项已删除"),
+            },
+        },
+    }));
+    crud_paths(&mut paths, "rest", "RestItem", "RestRequest");
+    if let Some(Value::Array(parameters)) = paths.get_mut("/rest").and_then(|path| path.pointer_mut("/get/parameters")) {
+        parameters.push(query_parameter("after_id", "string", "游标分页: 返回ID在此之后的项 (按ID排序，空串表示从头开始)。下一页见 Link 头"));
+    }
+    if let Some(path) = paths.get_mut("/rest/{id}") {
+        add_versioning(path);
+        if let Some(Value::Array(parameters)) = path.pointer_mut("/patch/parameters") {
+            parameters.push(query_parameter(
+                "set.<field>", "string",
+                "只修改该字段 (data 下，. 分隔或 JSON Pointer)，值为JSON，如 set.data.count=42。可有多个，带此参数时不读取请求体",
+            ));
+        }
+    }
+    paths.insert("/rest/{id}/increment".into(), json!({
+        "post": {
+            "tags": ["rest"],
+            "summary": "原子地增减 data 中的数字",
+            "parameters": [id_parameter()],
+            "requestBody": json_body(json!({
+                "type": "object",
+                "required": ["path"],
+                "properties": {
+                    "path": { "type": "string", "description": "data 中的 JSON Pointer，如 /count" },
+                    "by": { "type": "number", "default": 1, "description": "增量，可为负数或小数" },
+                },
+            })),
+            "responses": {
+                "200": json_response("增减前后的值", json!({
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "previous": { "type": "number" },
+                        "current": { "type": "number" },
+                    },
+                })),
+                "400": json_response("路径有误或该值不是数字", json!({ "$ref": "#/components/schemas/AppError" })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/rest/{id}/history".into(), json!({
+        "get": {
+            "tags": ["rest"],
+            "summary": "历史版本 (最近 ITEM_VERSION_HISTORY 个，默认10，按版本号升序，不含当前值)",
+            "parameters": [id_parameter()],
+            "responses": {
+                "200": json_response("历史版本", json!({
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "version": { "type": "integer", "description": "该项的第几次写入" },
+                            "data": {},
+                            "updated_at": { "type": "string", "format": "date-time", "description": "该版本写入的时间" },
+                        },
+                    },
+                })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/rest/{id}/history/{version}/restore".into(), json!({
+        "post": {
+            "tags": ["rest"],
+            "summary": "恢复到历史版本 (作为一次新的修改，当前值存为历史)",
+            "parameters": [
+                id_parameter(),
+                { "name": "version", "in": "path", "required": true, "schema": { "type": "integer" } },
+                { "name": "X-Expected-Version", "in": "header", "required": false, "description": "仅当当前版本号等于该值时写入", "schema": { "type": "integer" } },
+            ],
+            "responses": {
+                "200": json_response("恢复后的项", json!({ "$ref": "#/components/schemas/RestItem" })),
+                "404": json_response("找不到资源或版本", json!({ "$ref": "#/components/schemas/AppError" })),
+                "409": json_response("版本号不匹配", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/rest/search".into(), json!({
+        "get": {
+            "tags": ["rest"],
+            "summary": "按字段过滤 (需 q 或 q_gt，可同时使用)",
+            "parameters": [
+                { "name": "field", "in": "query", "required": true, "description": "字段路径，如 data.name 或 /data/name", "schema": { "type": "string" } },
+                query_parameter("q", "string", "字段值等于"),
+                query_parameter("q_gt", "number", "字段为数字且大于"),
+                query_parameter("offset", "integer", "起始位置"),
+                query_parameter("limit", "integer", "数量限制"),
+            ],
+            "responses": {
+                "200": json_response("匹配的项", array_of("RestItem")),
+                "400": json_response("字段路径有误或缺少条件", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/rest/aggregate".into(), json!({
+        "get": {
+            "tags": ["rest"],
+            "summary": "按字段统计",
+            "parameters": [
+                { "name": "field", "in": "query", "required": true, "description": "字段路径，如 data.status 或 /data/status", "schema": { "type": "string" } },
+                { "name": "op", "in": "query", "required": true, "schema": { "enum": ["count_by_value", "sum", "avg", "min", "max"] } },
+            ],
+            "responses": {
+                "200": json_response("count_by_value 为各值的计数，其余为单个数字", json!({
+                    "oneOf": [
+                        { "type": "object", "additionalProperties": { "type": "integer" } },
+                        { "type": "number" },
+                    ],
+                })),
+                "400": json_response("不支持的统计方式、字段不存在或没有数字", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/rest/query".into(), json!({
+        "post": {
+            "tags": ["rest"],
+            "summary": "按条件树过滤、排序与分页 (条件可用 and/or/not 组合，字段不存在时条件不成立)",
+            "requestBody": json_body(json!({
+                "type": "object",
+                "properties": {
+                    "filter": { "$ref": "#/components/schemas/QueryFilter" },
+                    "sort": {
+                        "type": "object",
+                        "required": ["field"],
+                        "properties": {
+                            "field": { "type": "string", "description": "字段路径，如 data.name 或 /data/name" },
+                            "order": { "enum": ["asc", "desc"], "default": "asc" },
+                        },
+                    },
+                    "limit": { "type": "integer", "minimum": 0 },
+                    "offset": { "type": "integer", "minimum": 0 },
+                },
+            })),
+            "responses": {
+                "200": json_response("匹配的项", json!({
+                    "type": "object",
+                    "properties": {
+                        "items": array_of("RestItem"),
+                        "total": { "type": "integer", "description": "分页前的匹配数" },
+                    },
+                })),
+                "400": json_response("字段路径有误或 in 的值不是数组", json!({ "$ref": "#/components/schemas/AppError" })),
+                "422": json_response("请求体格式有误", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/rest/import".into(), json!({
+        "post": {
+            "tags": ["rest"],
+            "summary": "从远程地址导入 JSON 数组 (后台执行)",
+            "requestBody": json_body(json!({
+                "type": "object",
+                "required": ["url"],
+                "properties": {
+                    "url": { "type": "string", "format": "uri", "description": "须为 https (配置 import_allow_http 后也可为 http)" },
+                    "id_field": { "type": "string", "default": "id", "description": "作为ID的字段，缺失时随机生成" },
+                },
+            })),
+            "responses": {
+                "202": {
+                    "description": "已创建导入任务",
+                    "headers": { "Location": { "description": "任务地址", "schema": { "type": "string" } } },
+                    "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ImportJob" } } },
+                },
+                "400": json_response("地址不被允许", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/rest/import/{job_id}".into(), json!({
+        "get": {
+            "tags": ["rest"],
+            "summary": "查询导入任务",
+            "parameters": [{ "name": "job_id", "in": "path", "required": true, "schema": { "type": "string" } }],
+            "responses": {
+                "200": json_response("导入任务", json!({ "$ref": "#/components/schemas/ImportJob" })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    for root in ["todos", "rest"] {
+        require_user(&mut paths, root);
+    }
+    for path in ["/todos/{id}/toggle", "/todos/{id}/move", "/todos/toggle-all", "/todos/{id}/share", "/rest/{id}/increment", "/rest/{id}/history", "/rest/{id}/history/{version}/restore", "/rest/search", "/rest/aggregate", "/rest/query", "/rest/import", "/rest/import/{job_id}"] {
+        require_auth(&mut paths, path, empty_response("属于其他用户"));
+    }
+    crud_paths(&mut paths, "node", "BasicNode", "NodeRequest");
+    if let Some(Value::Array(parameters)) = paths.get_mut("/node/{id}").and_then(|path| path.pointer_mut("/delete/parameters")) {
+        parameters.push(query_parameter("cascade_unlink", "boolean", "同时清除其他节点指向它的链接"));
+        parameters.push(query_parameter("rewire", "boolean", "把前驱与后继相连 (隐含 cascade_unlink)"));
+    }
+    for (path, method) in [("/node", "put"), ("/node/{id}", "put"), ("/node/{id}", "patch")] {
+        paths[path][method]["responses"]["409"] = json_response("HTTP 端点节点的路由已被其他节点注册", json!({ "$ref": "#/components/schemas/AppError" }));
+    }
+    paths["/node"]["delete"] = json!({
+        "tags": ["node", "admin"],
+        "summary": "删除某创建者的所有节点 (管理员)。不带 owner 时为清空，禁止",
+        "parameters": [query_parameter("owner", "string", "创建者的用户ID")],
+        "responses": {
+            "200": json_response("删除的数量", json!({ "type": "object", "properties": { "deleted": { "type": "integer" } } })),
+        },
+    });
+    require_auth(&mut paths, "/node", empty_response("非管理员，或未给出 owner"));
+    paths.insert("/node/types".into(), json!({
+        "get": {
+            "tags": ["node"],
+            "summary": "列出所有节点类型及其 config 的格式",
+            "responses": {
+                "200": json_response("节点类型", json!({
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "description": { "type": "string" },
+                            "config_schema": { "type": "object", "description": "config 的 JSON Schema" },
+                        },
+                    },
+                })),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/run".into(), json!({
+        "post": {
+            "tags": ["node"],
+            "summary": "从该节点开始链式执行",
+            "parameters": [id_parameter()],
+            "requestBody": json_body(json!({
+                "type": "object",
+                "properties": {
+                    "input": {},
+                    "timeout_ms": { "type": "integer", "description": "执行时间限制 (毫秒)" },
+                },
+            })),
+            "responses": {
+                "200": json_response("执行记录", json!({ "$ref": "#/components/schemas/ChainTrace" })),
+                "404": empty_response("找不到资源"),
+                "408": json_response("超时", json!({
+                    "type": "object",
+                    "properties": {
+                        "error": { "type": "string" },
+                        "executed_nodes": { "type": "array", "items": { "type": "string" }, "description": "已完成的节点，按完成顺序" },
+                        "timed_out_at": { "type": ["string", "null"], "description": "超时时正在执行的节点" },
+                    },
+                })),
+                "409": json_response("存在环", json!({ "$ref": "#/components/schemas/AppError" })),
+                "422": json_response("执行出错，含出错前的执行记录", json!({ "$ref": "#/components/schemas/ChainTrace" })),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/run/async".into(), json!({
+        "post": {
+            "tags": ["node"],
+            "summary": "在后台从该节点开始链式执行",
+            "parameters": [id_parameter()],
+            "requestBody": json_body(json!({
+                "type": "object",
+                "properties": { "input": {} },
+            })),
+            "responses": {
+                "202": {
+                    "description": "已加入队列",
+                    "headers": { "Location": { "description": "任务地址", "schema": { "type": "string" } } },
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "properties": {
+                            "job_id": { "type": "string" },
+                            "status": { "enum": ["queued"] },
+                        },
+                    } } },
+                },
+                "404": empty_response("找不到资源"),
+                "409": json_response("存在环", json!({ "$ref": "#/components/schemas/AppError" })),
+                "503": json_response("队列已满", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/schedule/next".into(), json!({
+        "get": {
+            "tags": ["node"],
+            "summary": "接下来的5次定时执行时间",
+            "parameters": [id_parameter()],
+            "responses": {
+                "200": json_response("定时", json!({
+                    "type": "object",
+                    "properties": {
+                        "schedule": { "type": "string" },
+                        "last_run_at": { "type": ["string", "null"], "format": "date-time" },
+                        "next": { "type": "array", "items": { "type": "string", "format": "date-time" } },
+                    },
+                })),
+                "404": empty_response("找不到资源，或节点没有定时"),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/metadata".into(), json!({
+        "get": {
+            "tags": ["node"],
+            "summary": "节点的注解",
+            "parameters": [id_parameter()],
+            "responses": {
+                "200": json_response("注解", json!({ "type": "object" })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/metadata/{key}".into(), json!({
+        "patch": {
+            "tags": ["node"],
+            "summary": "设置一个注解 (值为 null 时删除)，其余注解不变",
+            "parameters": [
+                id_parameter(),
+                { "name": "key", "in": "path", "required": true, "schema": { "type": "string" } },
+            ],
+            "requestBody": json_body(json!({ "description": "任意JSON值" })),
+            "responses": {
+                "200": json_response("修改后的全部注解", json!({ "type": "object" })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/history".into(), json!({
+        "get": {
+            "tags": ["node"],
+            "summary": "最近的执行历史 (最多100条，按完成顺序)",
+            "parameters": [id_parameter()],
+            "responses": {
+                "200": json_response("执行历史", json!({
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "node_id": { "type": "string" },
+                            "run_id": { "type": "string", "description": "所属的链式执行" },
+                            "timestamp": { "type": "string", "format": "date-time" },
+                            "input": {},
+                            "output": { "description": "成功时的输出" },
+                            "duration_ms": { "type": "integer" },
+                            "error": { "type": ["string", "null"] },
+                        },
+                    },
+                })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/node/jobs/{job_id}".into(), json!({
+        "get": {
+            "tags": ["node"],
+            "summary": "查询后台执行的任务",
+            "parameters": [{ "name": "job_id", "in": "path", "required": true, "schema": { "type": "string" } }],
+            "responses": {
+                "200": json_response("任务", json!({ "$ref": "#/components/schemas/NodeJob" })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+        "delete": {
+            "tags": ["node"],
+            "summary": "取消后台执行的任务 (排队中的不再执行，执行中的被中断)",
+            "parameters": [{ "name": "job_id", "in": "path", "required": true, "schema": { "type": "string" } }],
+            "responses": {
+                "200": json_response("取消后的任务", json!({ "$ref": "#/components/schemas/NodeJob" })),
+                "404": empty_response("找不到资源"),
+                "409": json_response("任务已结束", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/visualize".into(), json!({
+        "get": {
+            "tags": ["node"],
+            "summary": "以 Graphviz DOT 格式输出从该节点出发的图",
+            "parameters": [id_parameter(), query_parameter("depth", "integer", "最大跳数")],
+            "responses": {
+                "200": {
+                    "description": "DOT",
+                    "content": { "text/vnd.graphviz": { "schema": { "type": "string" } } },
+                },
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/subgraph".into(), json!({
+        "get": {
+            "tags": ["node"],
+            "summary": "导出从该节点可达的子图",
+            "parameters": [
+                id_parameter(),
+                query_parameter("direction", "string", "遍历方向 (forward/backward)"),
+                query_parameter("depth", "integer", "最大跳数"),
+            ],
+            "responses": {
+                "200": json_response("节点数组", array_of("BasicNode")),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/ancestors".into(), json!({
+        "get": {
+            "tags": ["node"],
+            "summary": "沿 prev_id 向上直到根的所有节点",
+            "parameters": [id_parameter()],
+            "responses": {
+                "200": json_response("从根到该节点", json!({
+                    "type": "object",
+                    "properties": {
+                        "chain": { "type": "array", "items": { "type": "string" }, "description": "节点ID，从根到该节点" },
+                        "nodes": array_of("BasicNode"),
+                    },
+                })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/clone".into(), json!({
+        "post": {
+            "tags": ["node"],
+            "summary": "复制节点 (副本不与任何节点相连)",
+            "parameters": [
+                id_parameter(),
+                query_parameter("with_chain", "boolean", "一并复制所有可达的下游节点，副本之间保持原有的连接"),
+                query_parameter("metadata", "boolean", "是否复制注解 (默认是)"),
+            ],
+            "responses": {
+                "201": json_response("副本", json!({
+                    "type": "object",
+                    "properties": {
+                        "original_id": { "type": "string" },
+                        "clone_id": { "type": "string" },
+                        "mapping": { "type": "object", "additionalProperties": { "type": "string" }, "description": "旧ID -> 新ID" },
+                        "nodes": array_of("BasicNode"),
+                    },
+                })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/node/{id}/move".into(), json!({
+        "post": {
+            "tags": ["node"],
+            "summary": "在链表中移动节点 (插入到目标节点之后或之前)",
+            "parameters": [id_parameter()],
+            "requestBody": json_body(json!({
+                "type": "object",
+                "description": "after_id 与 before_id 恰好给出其一",
+                "properties": {
+                    "after_id": { "type": "string" },
+                    "before_id": { "type": "string" },
+                },
+            })),
+            "responses": {
+                "200": json_response("移动后的节点", json!({ "$ref": "#/components/schemas/BasicNode" })),
+                "400": json_response("参数有误或目标节点不存在", json!({ "$ref": "#/components/schemas/AppError" })),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/node/subgraph/import".into(), json!({
+        "post": {
+            "tags": ["node"],
+            "summary": "批量导入子图 (重新分配ID)",
+            "requestBody": json_body(array_of("BasicNode")),
+            "responses": {
+                "201": json_response("ID映射与导入后的节点", json!({
+                    "type": "object",
+                    "properties": {
+                        "mapping": { "type": "object", "additionalProperties": { "type": "string" } },
+                        "nodes": array_of("BasicNode"),
+                    },
+                })),
+                "400": json_response("ID重复", json!({ "$ref": "#/components/schemas/AppError" })),
+                "409": json_response("HTTP 端点节点的路由已被其他节点注册", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    let credentials = json_body(json!({
+        "type": "object",
+        "required": ["username", "password"],
+        "properties": {
+            "username": { "type": "string", "minLength": 3, "maxLength": 32, "pattern": "^[A-Za-z0-9]+$" },
+            "password": { "type": "string", "minLength": 8 },
+        },
+    }));
+    paths.insert("/auth/register".into(), json!({
+        "post": {
+            "tags": ["auth"],
+            "summary": "注册",
+            "requestBody": credentials,
+            "responses": {
+                "201": json_response("新用户", json!({ "$ref": "#/components/schemas/User" })),
+                "409": json_response("用户名已存在", json!({ "$ref": "#/components/schemas/AppError" })),
+                "422": json_response("请求体校验失败", json!({ "$ref": "#/components/schemas/ValidationError" })),
+            },
+        },
+    }));
+    paths.insert("/auth/login".into(), json!({
+        "post": {
+            "tags": ["auth"],
+            "summary": "登录，返回 JWT 与刷新令牌",
+            "requestBody": credentials,
+            "responses": {
+                "200": json_response("令牌", json!({ "$ref": "#/components/schemas/Tokens" })),
+                "401": json_response("用户名或密码错误", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/auth/refresh".into(), json!({
+        "post": {
+            "tags": ["auth"],
+            "summary": "用刷新令牌换取新的 JWT",
+            "requestBody": json_body(json!({
+                "type": "object",
+                "required": ["refresh_token"],
+                "properties": {
+                    "refresh_token": { "type": "string" },
+                    "rotate": { "type": "boolean", "default": true, "description": "是否轮换刷新令牌 (旧的失效)" },
+                },
+            })),
+            "responses": {
+                "200": json_response("令牌，不轮换时不含 refresh_token", json!({ "$ref": "#/components/schemas/Tokens" })),
+                "401": json_response("刷新令牌无效或过期", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/auth/logout".into(), json!({
+        "delete": {
+            "tags": ["auth"],
+            "summary": "退出登录，使刷新令牌失效",
+            "requestBody": json_body(json!({
+                "type": "object",
+                "required": ["refresh_token"],
+                "properties": { "refresh_token": { "type": "string" } },
+            })),
+            "responses": { "204": empty_response("已退出") },
+        },
+    }));
+    paths.insert("/auth/github".into(), json!({
+        "get": {
+            "tags": ["auth"],
+            "summary": "GitHub 登录，重定向到授权页",
+            "responses": {
+                "303": empty_response("重定向到 GitHub"),
+                "503": json_response("未配置 GitHub 登录，或未完成的登录过多", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/auth/github/callback".into(), json!({
+        "get": {
+            "tags": ["auth"],
+            "summary": "GitHub 回调，返回 JWT",
+            "parameters": [
+                query_parameter("code", "string", "授权码"),
+                query_parameter("state", "string", "GET /auth/github 生成的 state"),
+            ],
+            "responses": {
+                "200": json_response("令牌，同 POST /auth/login", json!({ "$ref": "#/components/schemas/Tokens" })),
+                "400": json_response("state 无效、过期或与 cookie 不一致", json!({ "$ref": "#/components/schemas/AppError" })),
+                "401": json_response("用户拒绝授权", json!({ "$ref": "#/components/schemas/AppError" })),
+                "502": json_response("与 GitHub 通信失败", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/auth/account".into(), json!({
+        "delete": {
+            "tags": ["auth"],
+            "summary": "注销当前用户，并删除其所有数据",
+            "security": [{ "bearerAuth": [] }],
+            "responses": {
+                "204": empty_response("已注销"),
+                "401": json_response("缺少令牌或令牌无效", json!({ "$ref": "#/components/schemas/AppError" })),
+                "404": json_response("用户不存在", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+    paths.insert("/auth/me".into(), json!({
+        "get": {
+            "tags": ["auth"],
+            "summary": "当前用户",
+            "security": [{ "bearerAuth": [] }],
+            "responses": {
+                "200": json_response("用户信息", json!({ "$ref": "#/components/schemas/User" })),
+                "401": json_response("缺少令牌或令牌无效", json!({ "$ref": "#/components/schemas/AppError" })),
+            },
+        },
+    }));
+
+    paths.insert("/webhooks".into(), json!({
+        "get": {
+            "tags": ["webhooks"],
+            "summary": "列出自己的 webhook",
+            "responses": { "200": json_response("成功", array_of("Webhook")) },
+        },
+        "post": {
+            "tags": ["webhooks"],
+            "summary": "注册 webhook",
+            "requestBody": json_body(json!({
+                "type": "object",
+                "required": ["url", "events", "secret"],
+                "properties": {
+                    "url": { "type": "string", "format": "uri", "description": "须为 https (配置 webhook_allow_http 后也可为 http)" },
+                    "events": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "minItems": 1,
+                        "maxItems": 16,
+                        "description": "如 todo.created、node.deleted，* 表示全部",
+                    },
+                    "secret": { "type": "string", "minLength": 16, "maxLength": 256, "description": "签名密钥 (X-Webhook-Signature: sha256=HMAC)" },
+                },
+            })),
+            "responses": {
+                "201": json_response("已注册", json!({ "$ref": "#/components/schemas/Webhook" })),
+                "400": json_response("地址不被允许或事件名无效", json!({ "$ref": "#/components/schemas/AppError" })),
+                "409": json_response("webhook 数量已达上限", json!({ "$ref": "#/components/schemas/AppError" })),
+                "422": json_response("请求体校验失败", json!({ "$ref": "#/components/schemas/ValidationError" })),
+            },
+        },
+    }));
+    paths.insert("/webhooks/{id}".into(), json!({
+        "delete": {
+            "tags": ["webhooks"],
+            "summary": "删除 webhook",
+            "parameters": [id_parameter()],
+            "responses": {
+                "204": empty_response("已删除"),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    paths.insert("/webhooks/{id}/deliveries".into(), json!({
+        "get": {
+            "tags": ["webhooks"],
+            "summary": "最近的投递记录 (每次尝试一条，新的在前，最多50条)",
+            "parameters": [id_parameter()],
+            "responses": {
+                "200": json_response("成功", array_of("WebhookDelivery")),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+    for path in ["/webhooks", "/webhooks/{id}", "/webhooks/{id}/deliveries"] {
+        require_auth(&mut paths, path, empty_response("属于其他用户"));
+    }
+
+    require_admin(&mut paths);
+    accept_idempotency_key(&mut paths);
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas(),
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer", "bearerFormat": "JWT" },
+            },
+        },
+    })
+}
+
+/// 通用 RESTful 资源的路径 (`/{root}` 与 `/{root}/{id}`)
+///
+/// 各资源的方法语义一致，见 docs/README.api.md
+fn crud_paths(paths: &mut Map<String, Value>, root: &str, item: &str, request: &str) {
+    let item_ref = json!({ "$ref": format!("#/components/schemas/{}", item) });
+    let request_body = json_body(json!({ "$ref": format!("#/components/schemas/{}", request) }));
+    let invalid = json_response("请求体校验失败", json!({ "$ref": "#/components/schemas/ValidationError" }));
+
+    let get_one = json!({
+        "tags": [root],
+        "summary": "获取项",
+        "parameters": [id_parameter()],
+        "responses": {
+            "200": json_response("成功", item_ref.clone()),
+            "404": empty_response("找不到资源"),
+        },
+    });
+    let mut list_response = json_response("成功", array_of(item));
+    list_response["headers"] = json!({
+        "X-Total-Count": { "description": "分页前的总数", "schema": { "type": "integer" } },
+        "Link": { "description": "带 limit 时的翻页地址 (rel=first/prev/next/last)", "schema": { "type": "string" } },
+    });
+    let get_all = json!({
+        "tags": [root],
+        "summary": "获取全部项 (分页)",
+        "parameters": [
+            query_parameter("offset", "integer", "起始位置"),
+            query_parameter("limit", "integer", "数量限制"),
+        ],
+        "responses": { "200": list_response },
+    });
+    let put = |with_id: bool| json!({
+        "tags": [root],
+        "summary": "幂等创建/修改项 (重复策略：覆盖)",
+        "parameters": if with_id { json!([id_parameter()]) } else { json!([]) },
+        "requestBody": request_body.clone(),
+        "responses": {
+            "201": json_response("成功创建", item_ref.clone()),
+            "422": invalid.clone(),
+        },
+    });
+    let post = |with_id: bool| json!({
+        "tags": [root],
+        "summary": "创建新项 (重复策略：409)",
+        "parameters": if with_id { json!([id_parameter()]) } else { json!([]) },
+        "requestBody": request_body.clone(),
+        "responses": {
+            "201": json_response("成功创建", item_ref.clone()),
+            "409": json_response("重复，返回已有项", item_ref.clone()),
+            "422": invalid.clone(),
+        },
+    });
+
+    paths.insert(format!("/{}", root), json!({
+        "get": get_all,
+        "put": put(false),
+        "post": post(false),
+        "delete": {
+            "tags": [root],
+            "summary": "清空 (高危操作，已禁用)",
+            "responses": { "403": empty_response("禁止") },
+        },
+    }));
+    paths.insert(format!("/{}/{{id}}", root), json!({
+        "get": get_one,
+        "put": put(true),
+        "post": post(true),
+        "patch": {
+            "tags": [root],
+            "summary": "更新项 (缺失策略：404)",
+            "parameters": [id_parameter()],
+            "requestBody": request_body,
+            "responses": {
+                "200": json_response("成功", item_ref),
+                "404": empty_response("找不到资源"),
+                "422": invalid,
+            },
+        },
+        "delete": {
+            "tags": [root],
+            "summary": "删除项",
+            "parameters": [id_parameter()],
+            "responses": {
+                "204": empty_response("删除成功"),
+                "404": empty_response("找不到资源"),
+            },
+        },
+    }));
+}
+
+/// 为需登录的资源 (数据按用户隔离) 的各操作加上鉴权要求及 `401`/`403` 响应
+fn require_user(paths: &mut Map<String, Value>, root: &str) {
+    for path in [format!("/{}", root), format!("/{}/{{id}}", root)] {
+        require_auth(paths, &path, empty_response("属于其他用户"));
+    }
+}
+
+/// 为管理接口 (`/admin/...` 等) 的各操作加上鉴权要求及 `401`/`403` 响应
+fn require_admin(paths: &mut Map<String, Value>) {
+    let admin_paths: Vec<String> = paths.keys()
+        .filter(|path| path.starts_with("/admin/") || path.as_str() == "/heartbeat/config")
+        .cloned()
+        .collect();
+    for path in admin_paths {
+        require_auth(paths, &path, json_response("非管理员", json!({ "$ref": "#/components/schemas/AppError" })));
+    }
+}
+
+/// 为各 `POST` 操作加上可选的 `Idempotency-Key` 头，见 [`crate::api::middleware::idempotency`]
+fn accept_idempotency_key(paths: &mut Map<String, Value>) {
+    for post in paths.values_mut().filter_map(|path| path.get_mut("post")) {
+        let parameters = post["parameters"].as_array().cloned().unwrap_or_default();
+        post["parameters"] = Value::Array(parameters.into_iter().chain([json!({
+            "name": "Idempotency-Key",
+            "in": "header",
+            "required": false,
+            "description": "UUID，重试时使用同一个键，24小时内返回首次的响应 (带 X-Idempotent-Replayed: true)",
+            "schema": { "type": "string", "format": "uuid" },
+        })]).collect());
+        if post["responses"].get("422").is_none() { // 已有的 422 (校验失败) 同为 AppError
+            post["responses"]["422"] = json_response("幂等键已用于不同的请求", json!({ "$ref": "#/components/schemas/AppError" }));
+        }
+    }
+}
+
+/// 为路径的各操作加上鉴权要求及 `401`/`403` 响应
+fn require_auth(paths: &mut Map<String, Value>, path: &str, forbidden: Value) {
+    let Some(Value::Object(operations)) = paths.get_mut(path) else { return };
+    for operation in operations.values_mut() {
+        operation["security"] = json!([{ "bearerAuth": [] }]);
+        operation["responses"]["401"] = json_response("缺少令牌或令牌无效", json!({ "$ref": "#/components/schemas/AppError" }));
+        operation["responses"]["403"] = forbidden.clone();
+    }
+}
+
+/// 数据结构定义
+fn schemas() -> Value {
+    json!({
+        "TodoItem": {
+            "type": "object",
+            "required": ["id", "owner_id", "text", "completed", "position", "created_at", "updated_at"],
+            "properties": {
+                "id": { "type": "string" },
+                "owner_id": { "type": "string", "description": "创建者的用户ID" },
+                "text": { "type": "string" },
+                "completed": { "type": "boolean" },
+                "position": { "type": "integer", "description": "排序位置，列表按此升序。新建时为 4294967295 (最后)" },
+                "created_at": { "type": "string", "format": "date-time" },
+                "updated_at": { "type": "string", "format": "date-time" },
+            },
+        },
+        "QueryFilter": {
+            "description": "条件树: and/or 为条件数组，not 为单个条件，叶子为 { field, op, value }",
+            "oneOf": [
+                { "type": "object", "required": ["and"], "properties": { "and": { "type": "array", "items": { "$ref": "#/components/schemas/QueryFilter" } } } },
+                { "type": "object", "required": ["or"], "properties": { "or": { "type": "array", "items": { "$ref": "#/components/schemas/QueryFilter" } } } },
+                { "type": "object", "required": ["not"], "properties": { "not": { "$ref": "#/components/schemas/QueryFilter" } } },
+                {
+                    "type": "object",
+                    "required": ["field", "op", "value"],
+                    "properties": {
+                        "field": { "type": "string", "description": "字段路径，如 data.status 或 /data/status" },
+                        "op": { "enum": ["eq", "neq", "gt", "gte", "lt", "lte", "contains", "in"] },
+                        "value": { "description": "in 时须为数组" },
+                    },
+                },
+            ],
+        },
+        "TodoCursorPage": {
+            "type": "object",
+            "required": ["items", "total", "next_cursor"],
+            "properties": {
+                "items": array_of("TodoItem"),
+                "total": { "type": "integer", "description": "分页前 (过滤后) 的总数" },
+                "next_cursor": { "type": ["string", "null"], "description": "下一页的 after 参数，已是最后一页时为 null" },
+            },
+        },
+        "SharedTodoItem": {
+            "type": "object",
+            "required": ["id", "text", "completed", "created_at", "updated_at"],
+            "properties": {
+                "id": { "type": "string" },
+                "text": { "type": "string" },
+                "completed": { "type": "boolean" },
+                "created_at": { "type": "string", "format": "date-time" },
+                "updated_at": { "type": "string", "format": "date-time" },
+            },
+        },
+        "TodoRequest": {
+            "type": "object",
+            "properties": {
+                "text": { "type": ["string", "null"], "minLength": 1, "maxLength": 2000 },
+                "completed": { "type": ["boolean", "null"] },
+            },
+        },
+        "RestItem": {
+            "type": "object",
+            "required": ["id", "owner_id", "data", "created_at", "updated_at"],
+            "properties": {
+                "id": { "type": "string" },
+                "owner_id": { "type": "string", "description": "创建者的用户ID" },
+                "data": { "description": "任意JSON值" },
+                "created_at": { "type": "string", "format": "date-time" },
+                "updated_at": { "type": "string", "format": "date-time" },
+            },
+        },
+        "RestRequest": {
+            "type": "object",
+            "properties": {
+                "data": { "description": "任意JSON值，为字符串时不超过 64 KiB" },
+            },
+        },
+        "ValidationError": {
+            "type": "object",
+            "properties": {
+                "error": { "type": "string" },
+                "fields": {
+                    "type": "object",
+                    "description": "以字段名为键",
+                    "additionalProperties": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "code": { "type": "string" },
+                                "message": { "type": ["string", "null"] },
+                            },
+                        },
+                    },
+                },
+            },
+        },
+        "NodeKind": {
+            "type": "string",
+            "enum": ["basic", "script", "http", "branch", "http_endpoint"],
+        },
+        "BasicNode": {
+            "type": "object",
+            "required": ["id", "kind"],
+            "properties": {
+                "id": { "type": "string" },
+                "kind": { "$ref": "#/components/schemas/NodeKind" },
+                "content": { "description": "任意JSON值" },
+                "config": { "description": "类型相关的配置，格式由 kind 决定" },
+                "metadata": { "type": "object", "description": "附加的注解 (显示名、颜色等)，不影响执行" },
+                "next_id": { "type": ["string", "null"], "description": "children_ids 的第一项 (兼容旧数据)" },
+                "children_ids": { "type": "array", "items": { "type": "string" }, "description": "所有后继节点，执行时并发执行" },
+                "prev_id": { "type": ["string", "null"] },
+                "else_id": { "type": ["string", "null"] },
+                "owner_id": { "type": ["string", "null"], "description": "创建者 (创建时登录的用户)，未登录创建时为 null" },
+                "schedule": { "type": ["string", "null"], "description": "cron 表达式 (秒 分 时 日 月 周 [年]，UTC)" },
+                "last_output": { "description": "最近一次成功执行的输出" },
+                "last_run_at": { "type": ["string", "null"], "format": "date-time", "description": "最近一次执行的时间" },
+                "run_count": { "type": "integer", "description": "执行次数 (含失败)" },
+                "created_at": { "type": "string", "format": "date-time" },
+                "updated_at": { "type": "string", "format": "date-time" },
+            },
+        },
+        "NodeRequest": {
+            "type": "object",
+            "properties": {
+                "data": { "description": "任意JSON值，对应 content。为字符串时不超过 64 KiB" },
+                "kind": { "$ref": "#/components/schemas/NodeKind" },
+                "config": {},
+                "metadata": { "type": "object", "description": "附加的注解，PATCH 时合并 (值为 null 的键被删除)" },
+                "next_id": { "type": ["string", "null"] },
+                "children_ids": { "type": "array", "items": { "type": "string" }, "description": "所有后继节点，给出时忽略 next_id" },
+                "prev_id": { "type": ["string", "null"] },
+                "else_id": { "type": ["string", "null"] },
+                "schedule": { "type": ["string", "null"], "description": "cron 表达式，如 0 */5 * * * * (每5分钟)，到点时以 null 为输入在后台执行" },
+            },
+        },
+        "ChainTrace": {
+            "type": "object",
+            "required": ["steps"],
+            "properties": {
+                "steps": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "string" },
+                            "branch": { "enum": ["then", "else", null] },
+                            "output": {},
+                        },
+                    },
+                },
+                "error": { "type": "string" },
+            },
+        },
+        "Heartbeat": {
+            "type": "object",
+            "properties": {
+                "status": { "type": "string" },
+                "timestamp": { "type": "string", "format": "date-time" },
+                "online_user_count": { "type": "integer" },
+                "system_cpu_usage_percent": { "type": "number", "description": "平台不支持时无此字段 (下同)" },
+                "system_memory_used_bytes": { "type": "integer" },
+                "process_memory_bytes": { "type": "integer", "description": "本进程的常驻内存" },
+            },
+        },
+        "Readiness": {
+            "type": "object",
+            "properties": {
+                "status": { "enum": ["ready", "not_ready"] },
+                "checks": { "type": "object", "additionalProperties": { "enum": ["ok", "failed"] } },
+            },
+        },
+        "TaskInfo": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "status": { "enum": ["running", "stopped", "restarting"] },
+                "restart_count": { "type": "integer" },
+                "last_exit_reason": { "type": ["string", "null"] },
+            },
+        },
+        "Tokens": {
+            "type": "object",
+            "properties": {
+                "access_token": { "type": "string" },
+                "token_type": { "const": "Bearer" },
+                "expires_in": { "type": "integer", "description": "access_token 的有效期 (秒)" },
+                "refresh_token": { "type": "string", "description": "有效期 7 天，每个用户最多 5 个" },
+            },
+        },
+        "User": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "format": "uuid" },
+                "username": { "type": "string" },
+                "github_id": { "type": ["integer", "null"], "description": "关联的 GitHub 用户ID" },
+                "role": { "enum": ["admin", "user"] },
+                "created_at": { "type": "string", "format": "date-time" },
+            },
+        },
+        "NodeJob": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "node_id": { "type": "string" },
+                "status": { "enum": ["queued", "running", "completed", "failed", "cancelled"] },
+                "result": { "oneOf": [{ "$ref": "#/components/schemas/ChainTrace" }, { "type": "null" }] },
+                "error": { "type": ["string", "null"] },
+                "created_at": { "type": "string", "format": "date-time" },
+                "started_at": { "type": ["string", "null"], "format": "date-time" },
+                "finished_at": { "type": ["string", "null"], "format": "date-time" },
+            },
+        },
+        "ImportJob": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "url": { "type": "string" },
+                "status": { "enum": ["running", "completed", "failed"] },
+                "imported": { "type": "integer", "description": "已导入的项数" },
+                "errors": { "type": "array", "items": { "type": "string" }, "description": "错误 (最多100条)" },
+                "created_at": { "type": "string", "format": "date-time" },
+                "finished_at": { "type": ["string", "null"], "format": "date-time" },
+            },
+        },
+        "Webhook": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "url": { "type": "string" },
+                "events": { "type": "array", "items": { "type": "string" } },
+                "created_at": { "type": "string", "format": "date-time" },
+            },
+        },
+        "WebhookDelivery": {
+            "type": "object",
+            "properties": {
+                "delivery_id": { "type": "string", "description": "同一事件的各次重试相同" },
+                "event": { "type": "string" },
+                "attempt": { "type": "integer", "description": "第几次尝试，从 1 开始" },
+                "success": { "type": "boolean" },
+                "status_code": { "type": ["integer", "null"], "description": "网络错误时为 null" },
+                "error": { "type": ["string", "null"] },
+                "duration_ms": { "type": "integer" },
+                "attempted_at": { "type": "string", "format": "date-time" },
+            },
+        },
+        "AppError": {
+            "type": "object",
+            "required": ["error"],
+            "properties": {
+                "error": { "type": "string" },
+            },
+        },
+    })
+}
+
+// #region 构造辅助
+
+fn id_parameter() -> Value {
+    json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } })
+}
+
+/// `/rest/{id}` 的版本号: 单项响应带 `X-Version`，`PUT`/`PATCH` 可带 `X-Expected-Version`。
+/// 另有 `GET` 的 `Last-Modified` 与 `PUT` 的 `If-Unmodified-Since`
+fn add_versioning(path: &mut Value) {
+    let version_header = json!({ "X-Version": { "description": "版本号，每次修改递增", "schema": { "type": "integer" } } });
+    for pointer in ["/get/responses/200", "/put/responses/201", "/patch/responses/200"] {
+        if let Some(response) = path.pointer_mut(pointer) {
+            response["headers"] = version_header.clone();
+        }
+    }
+    if let Some(response) = path.pointer_mut("/get/responses/200") {
+        response["headers"]["Last-Modified"] = json!({ "description": "最后修改时间 (HTTP-date)", "schema": { "type": "string" } });
+    }
+    if let Some(Value::Array(parameters)) = path.pointer_mut("/put/parameters") {
+        parameters.push(json!({
+            "name": "If-Unmodified-Since", "in": "header", "required": false,
+            "description": "HTTP-date，项在此之后被修改过则不写入 (精确到秒，需要精确时用 X-Expected-Version)",
+            "schema": { "type": "string" },
+        }));
+    }
+    if let Some(Value::Object(responses)) = path.pointer_mut("/put/responses") {
+        responses.insert("412".into(), json_response("项在 If-Unmodified-Since 之后被修改过", json!({
+            "type": "object",
+            "properties": { "error": { "type": "string" }, "updated_at": { "type": "string", "format": "date-time" } },
+        })));
+    }
+    for method in ["put", "patch"] {
+        if let Some(Value::Array(parameters)) = path.pointer_mut(&format!("/{}/parameters", method)) {
+            parameters.push(json!({
+                "name": "X-Expected-Version", "in": "header", "required": false,
+                "description": "仅当当前版本号与之相等时写入 (PUT 为0表示仅在不存在时创建)",
+                "schema": { "type": "integer" },
+            }));
+        }
+        if let Some(Value::Object(responses)) = path.pointer_mut(&format!("/{}/responses", method)) {
+            responses.insert("409".into(), json_response("版本号不匹配", json!({
+                "type": "object",
+                "properties": { "error": { "type": "string" }, "current_version": { "type": "integer" } },
+            })));
+        }
+    }
+}
+
+fn query_parameter(name: &str, ty: &str, description: &str) -> Value {
+    json!({ "name": name, "in": "query", "required": false, "description": description, "schema": { "type": ty } })
+}
+
+fn json_body(schema: Value) -> Value {
+    json!({ "required": true, "content": { "application/json": { "schema": schema } } })
+}
+
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({ "description": description, "content": { "application/json": { "schema": schema } } })
+}
+
+fn empty_response(description: &str) -> Value {
+    json!({ "description": description })
+}
+
+fn array_of(schema: &str) -> Value {
+    json!({ "type": "array", "items": { "$ref": format!("#/components/schemas/{}", schema) } })
+}
+
+// #endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    /// OpenAPI 3.1 的元 schema (https://spec.openapis.org/oas/3.1/schema/2022-10-07)，测试离线运行，因此放在仓库中
+    const OPENAPI_META_SCHEMA: &str = include_str!("../../tests/fixtures/openapi-3.1.schema.json");
+
+    #[tokio::test]
+    async fn spec_matches_meta_schema() {
+        let body = to_bytes(get_openapi().await.into_response().into_body(), usize::MAX).await.unwrap();
+        let spec: Value = serde_json::from_slice(&body).unwrap();
+
+        let meta_schema: Value = serde_json::from_str(OPENAPI_META_SCHEMA).unwrap();
+        let validator = jsonschema::draft202012::options()
+            .should_validate_formats(true)
+            .build(&meta_schema)
+            .unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&spec)
+            .map(|error| format!("{}: {}", error.instance_path(), error))
+            .collect();
+        assert!(errors.is_empty(), "openapi.json does not match the OpenAPI 3.1 meta-schema:\n{}", errors.join("\n"));
+
+        // 元 schema 不校验 schema 对象本身，这里按 JSON Schema 2020-12 单独校验
+        for (name, schema) in spec["components"]["schemas"].as_object().unwrap() {
+            assert!(jsonschema::draft202012::meta::is_valid(schema), "invalid schema: {}", name);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! 列表分页
+//!
+//! 列表接口 (`GET /todos`、`/rest`、`/node`) 用 `?offset=&limit=` 分页，响应带:
+//!
+//! - `X-Total-Count`: 分页前 (过滤后) 的总数
+//! - `Link`: 带 `limit` 时给出 first/prev/next/last 页的地址，如
+//!   `</todos?offset=20&limit=10>; rel="next"`
+//!
+//! 按键排序的存储 (`/rest`) 另支持游标分页 `?after_id=&limit=`，见 [`cursor_response`]
+//! `GET /todos` 的游标分页 `?after=&limit=` 返回带总数与下一页游标的对象，见 [`cursor_page_response`]
+
+use axum::{
+    http::{header::LINK, HeaderName, Uri},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+
+/// 列表响应中的总数头 (分页前)
+pub const TOTAL_COUNT_HEADER: HeaderName = HeaderName::from_static("x-total-count");
+
+/// 列表响应: 分页后的项，并设置 `X-Total-Count` 与 `Link`
+///
+/// - `uri` 请求的原始地址 (`OriginalUri`)，用于生成翻页链接，保留其中除分页外的查询参数
+/// - `items` 已过滤的全部项
+pub fn list_response<T: Serialize>(uri: &Uri, items: Vec<T>, offset: Option<usize>, limit: Option<usize>) -> Response {
+    let total = items.len();
+    let page: Vec<T> = items.into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+    page_response(uri, page, total, offset, limit)
+}
+
+/// 列表响应: 已分页的项，同 [`list_response`]
+///
+/// - `page` 该页的项，如 [`crate::container::Container::get_page_sorted_by`] 的结果
+/// - `total` 分页前 (过滤后) 的总数
+pub fn page_response<T: Serialize>(uri: &Uri, page: Vec<T>, total: usize, offset: Option<usize>, limit: Option<usize>) -> Response {
+    let mut response = ([(TOTAL_COUNT_HEADER, total.to_string())], Json(page)).into_response();
+    if let Some(limit) = limit.filter(|&limit| limit > 0) {
+        let link = build_link_header(&base_path(uri), offset.unwrap_or(0), limit, total);
+        if let Ok(value) = link.parse() {
+            response.headers_mut().insert(LINK, value);
+        }
+    }
+    response
+}
+
+/// 游标分页的列表响应: 本页取满 `limit` 项时设置 `Link: <...?after_id=<最后一项的键>&limit=..>; rel="next"`
+///
+/// 不设置 `X-Total-Count` (统计总数需遍历全部项，失去游标分页的意义)
+///
+/// - `page` 已取出的本页的项
+/// - `last_key` 本页最后一项的键
+pub fn cursor_response<T: Serialize>(uri: &Uri, page: Vec<T>, limit: Option<usize>, last_key: Option<&str>) -> Response {
+    let full = limit.is_some_and(|limit| limit > 0 && page.len() >= limit);
+    let mut response = Json(page).into_response();
+    if let (true, Some(limit), Some(last_key)) = (full, limit, last_key) {
+        let base_path = strip_query(uri, &["after_id", "limit"]);
+        let separator = if base_path.contains('?') { '&' } else { '?' };
+        let link = format!("<{}{}after_id={}&limit={}>; rel=\"next\"", base_path, separator, percent_encode(last_key), limit);
+        if let Ok(value) = link.parse() {
+            response.headers_mut().insert(LINK, value);
+        }
+    }
+    response
+}
+
+/// 游标分页的一页，`GET /todos?after=` 的响应体
+///
+/// - `total` 分页前 (过滤后) 的总数
+/// - `next_cursor` 本页最后一项的ID，已是最后一页时为 `None`
+#[derive(Debug, Serialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub next_cursor: Option<String>,
+}
+
+/// 游标分页的列表响应: 响应体为 [`CursorPage`]，并设置 `X-Total-Count`，
+/// 有下一页时设置 `Link: <...?after=<next_cursor>&limit=..>; rel="next"`
+pub fn cursor_page_response<T: Serialize>(uri: &Uri, page: CursorPage<T>, limit: Option<usize>) -> Response {
+    let next = page.next_cursor.as_deref().zip(limit).map(|(cursor, limit)| {
+        let base_path = strip_query(uri, &["after", "limit"]);
+        let separator = if base_path.contains('?') { '&' } else { '?' };
+        format!("<{}{}after={}&limit={}>; rel=\"next\"", base_path, separator, percent_encode(cursor), limit)
+    });
+    let mut response = ([(TOTAL_COUNT_HEADER, page.total.to_string())], Json(page)).into_response();
+    if let Some(value) = next.and_then(|link| link.parse().ok()) {
+        response.headers_mut().insert(LINK, value);
+    }
+    response
+}
+
+/// 生成 `Link` 头
+///
+/// - `base_path` 不含分页参数的地址，可带其他查询参数 (如 `/todos?completed=true`)
+/// - 第一页没有 prev，最后一页没有 next
+pub fn build_link_header(base_path: &str, offset: usize, limit: usize, total: usize) -> String {
+    let separator = if base_path.contains('?') { '&' } else { '?' };
+    let link = |offset: usize, rel: &str| {
+        format!("<{}{}offset={}&limit={}>; rel=\"{}\"", base_path, separator, offset, limit, rel)
+    };
+    let last = total.saturating_sub(1) / limit * limit;
+
+    let mut links = vec![link(0, "first")];
+    if offset > 0 {
+        links.push(link(offset.saturating_sub(limit), "prev"));
+    }
+    if offset + limit < total {
+        links.push(link(offset + limit, "next"));
+    }
+    links.push(link(last, "last"));
+    links.join(", ")
+}
+
+/// 去掉查询参数中的 `offset`/`limit`
+fn base_path(uri: &Uri) -> String {
+    strip_query(uri, &["offset", "limit"])
+}
+
+/// 去掉查询参数中的指定项
+fn strip_query(uri: &Uri, keys: &[&str]) -> String {
+    let query: Vec<&str> = uri.query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            !pair.is_empty() && !keys.contains(&key)
+        })
+        .collect();
+    if query.is_empty() {
+        uri.path().to_string()
+    } else {
+        format!("{}?{}", uri.path(), query.join("&"))
+    }
+}
+
+/// 查询参数值的百分号编码 (保留 RFC 3986 的非保留字符)
+fn percent_encode(value: &str) -> String {
+    value.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte),
+    }).collect()
+}
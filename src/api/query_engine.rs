@@ -0,0 +1,233 @@
+//! 查询 DSL
+//!
+//! 用于 `POST /rest/query`，按字段的条件树过滤，再排序、分页:
+//!
+//! ```json
+//! {
+//!     "filter": { "and": [
+//!         { "field": "data.status", "op": "eq", "value": "active" },
+//!         { "field": "data.count", "op": "gt", "value": 5 }
+//!     ] },
+//!     "sort": { "field": "data.name", "order": "asc" },
+//!     "limit": 20,
+//!     "offset": 0
+//! }
+//! ```
+//!
+//! - 条件可用 `and`/`or` (数组) 与 `not` (单个条件) 组合
+//! - `field` 为相对于整个项的路径 (`.` 分隔或 JSON Pointer)，见 [`json_pointer::parse_field`]
+//! - 比较: `eq`/`neq` 相等 (数字按数值比较)，`gt`/`gte`/`lt`/`lte` 仅在两侧同为数字或同为字符串时成立，
+//!   `contains` 字符串包含子串，`in` 字段值等于数组中的某一项
+//! - 字段不存在时条件不成立 (含 `neq`)
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use validator::Validate;
+
+use crate::api::json_pointer;
+
+/// 查询请求
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct QueryRequest {
+    /// 过滤条件，缺省时匹配全部
+    pub filter: Option<Filter>,
+    pub sort: Option<Sort>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// 条件树
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Filter {
+    And { and: Vec<Filter> },
+    Or { or: Vec<Filter> },
+    Not { not: Box<Filter> },
+    Condition(Condition),
+}
+
+/// 单个条件
+#[derive(Debug, Clone, Deserialize)]
+pub struct Condition {
+    pub field: String,
+    pub op: Op,
+    pub value: Value,
+}
+
+/// 比较方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    Eq,
+    Neq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Contains,
+    In,
+}
+
+/// 排序
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sort {
+    pub field: String,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// 查询结果，`total` 为分页前的匹配数
+#[derive(Debug, Serialize)]
+pub struct QueryResult<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+}
+
+/// 已校验的查询，字段路径已转为 JSON Pointer
+#[derive(Debug, Clone)]
+pub struct QueryEngine {
+    filter: Option<CompiledFilter>,
+    sort: Option<(String, SortOrder)>,
+    limit: usize,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+enum CompiledFilter {
+    And(Vec<CompiledFilter>),
+    Or(Vec<CompiledFilter>),
+    Not(Box<CompiledFilter>),
+    Condition { pointer: String, op: Op, value: Value },
+}
+
+impl QueryEngine {
+    /// 校验查询: 字段路径的格式，`in` 的值须为数组
+    pub fn new(query: QueryRequest) -> Result<Self, String> {
+        let filter = query.filter.map(compile).transpose()?;
+        let sort = query.sort
+            .map(|sort| parse_field(&sort.field).map(|pointer| (pointer, sort.order)))
+            .transpose()?;
+        Ok(Self {
+            filter,
+            sort,
+            limit: query.limit.unwrap_or(usize::MAX),
+            offset: query.offset.unwrap_or(0),
+        })
+    }
+
+    /// 过滤、排序并分页。`items` 的原有顺序在排序值相同时保持不变，缺少排序字段的项排在最后
+    pub fn execute<T: Serialize>(&self, items: Vec<T>) -> QueryResult<T> {
+        let mut matched: Vec<(T, Value)> = items.into_iter()
+            .filter_map(|item| {
+                let root = serde_json::to_value(&item).ok()?;
+                self.filter.as_ref().is_none_or(|filter| filter.matches(&root)).then_some((item, root))
+            })
+            .collect();
+        if let Some((pointer, order)) = &self.sort {
+            matched.sort_by(|(_, a), (_, b)| {
+                match (json_pointer::value_at_pointer(a, pointer), json_pointer::value_at_pointer(b, pointer)) {
+                    (None, None) => Ordering::Equal,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(_), None) => Ordering::Less,
+                    (Some(a), Some(b)) => match order {
+                        SortOrder::Asc => sort_order(a, b),
+                        SortOrder::Desc => sort_order(b, a),
+                    },
+                }
+            });
+        }
+        let total = matched.len();
+        let items = matched.into_iter().skip(self.offset).take(self.limit).map(|(item, _)| item).collect();
+        QueryResult { items, total }
+    }
+}
+
+impl CompiledFilter {
+    fn matches(&self, root: &Value) -> bool {
+        match self {
+            CompiledFilter::And(filters) => filters.iter().all(|filter| filter.matches(root)),
+            CompiledFilter::Or(filters) => filters.iter().any(|filter| filter.matches(root)),
+            CompiledFilter::Not(filter) => !filter.matches(root),
+            CompiledFilter::Condition { pointer, op, value } => {
+                json_pointer::value_at_pointer(root, pointer).is_some_and(|field| evaluate(field, *op, value))
+            }
+        }
+    }
+}
+
+fn compile(filter: Filter) -> Result<CompiledFilter, String> {
+    Ok(match filter {
+        Filter::And { and } => CompiledFilter::And(and.into_iter().map(compile).collect::<Result<_, _>>()?),
+        Filter::Or { or } => CompiledFilter::Or(or.into_iter().map(compile).collect::<Result<_, _>>()?),
+        Filter::Not { not } => CompiledFilter::Not(Box::new(compile(*not)?)),
+        Filter::Condition(Condition { field, op, value }) => {
+            if op == Op::In && !value.is_array() {
+                return Err(format!("value of 'in' must be an array (field: {})", field));
+            }
+            CompiledFilter::Condition { pointer: parse_field(&field)?, op, value }
+        }
+    })
+}
+
+fn parse_field(field: &str) -> Result<String, String> {
+    json_pointer::parse_field(field).map_err(|err| format!("invalid field {}: {}", field, err))
+}
+
+/// 字段值 `field` 与条件的值 `value` 比较
+fn evaluate(field: &Value, op: Op, value: &Value) -> bool {
+    match op {
+        Op::Eq => json_equals(field, value),
+        Op::Neq => !json_equals(field, value),
+        Op::Gt => compare(field, value) == Some(Ordering::Greater),
+        Op::Gte => matches!(compare(field, value), Some(Ordering::Greater | Ordering::Equal)),
+        Op::Lt => compare(field, value) == Some(Ordering::Less),
+        Op::Lte => matches!(compare(field, value), Some(Ordering::Less | Ordering::Equal)),
+        Op::Contains => matches!((field, value), (Value::String(field), Value::String(value)) if field.contains(value.as_str())),
+        Op::In => value.as_array().is_some_and(|values| values.iter().any(|value| json_equals(field, value))),
+    }
+}
+
+/// 相等，数字按数值比较 (`1` 与 `1.0` 相等)
+fn json_equals(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => a == b,
+    }
+}
+
+/// 同为数字或同为字符串时可比较
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// 排序用的全序: 不同类型之间按 null < 布尔 < 数字 < 字符串 < 数组 < 对象
+fn sort_order(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        }
+    }
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Number(_), Value::Number(_)) | (Value::String(_), Value::String(_)) => compare(a, b).unwrap_or(Ordering::Equal),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
@@ -0,0 +1,88 @@
+//! 刷新令牌存储
+//!
+//! 登录时与 JWT 一同签发，JWT 过期后用 `POST /auth/refresh` 换取新的 JWT，见 [`crate::api::auth`]。
+//!
+//! - 令牌为 32 字节随机数的十六进制，只存其 SHA-256 哈希，存储泄露时无法直接使用
+//! - 有效期 7 天，过期的令牌在签发新令牌时清理
+//! - 每个用户最多 5 个，超出时删除最早签发的 (限制令牌被盗时的影响范围)
+
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use password_hash::rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::container::Container;
+
+/// 有效期 (天)
+const REFRESH_TOKEN_TTL_DAYS: i64 = 7;
+/// 每个用户的令牌数上限
+const MAX_TOKENS_PER_USER: usize = 5;
+
+/// 刷新令牌记录
+///
+/// - `token_hash` 令牌的 SHA-256 (十六进制)，同时是存储的键
+#[derive(Debug, Clone)]
+pub struct RefreshTokenRecord {
+    pub token_hash: String,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 全局刷新令牌存储，以令牌哈希为索引
+static REFRESH_TOKENS: Lazy<Arc<Container<RefreshTokenRecord>>> = Lazy::new(Container::new_arc);
+
+/// 为用户签发刷新令牌，返回令牌原文 (仅此一次可见)
+pub fn issue(user_id: &str) -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+
+    let now = Utc::now();
+    REFRESH_TOKENS.delete_where(|record| record.expires_at <= now);
+    // 超出上限时删除最早的，为新令牌留出位置
+    let mut existing = REFRESH_TOKENS.find_where(|record| record.user_id == user_id);
+    if existing.len() >= MAX_TOKENS_PER_USER {
+        existing.sort_by_key(|record| record.created_at);
+        for record in &existing[..=existing.len() - MAX_TOKENS_PER_USER] {
+            REFRESH_TOKENS.delete_by_id(&record.token_hash);
+        }
+    }
+
+    let record = RefreshTokenRecord {
+        token_hash: hash(&token),
+        user_id: user_id.to_string(),
+        created_at: now,
+        expires_at: now + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+    };
+    REFRESH_TOKENS.put_by_id(&record.token_hash.clone(), record);
+    token
+}
+
+/// 校验令牌，有效时返回其记录
+///
+/// - `consume` 为 true 时同时使令牌失效 (轮换)
+pub fn verify(token: &str, consume: bool) -> Option<RefreshTokenRecord> {
+    let token_hash = hash(token);
+    let record = if consume {
+        REFRESH_TOKENS.delete_by_id(&token_hash)
+    } else {
+        REFRESH_TOKENS.get_by_id(&token_hash)
+    }?;
+    (record.expires_at > Utc::now()).then_some(record)
+}
+
+/// 使令牌失效，返回是否存在
+pub fn revoke(token: &str) -> bool {
+    REFRESH_TOKENS.delete_by_id(&hash(token)).is_some()
+}
+
+/// 使用户的所有令牌失效，返回失效的数量
+pub fn revoke_all(user_id: &str) -> usize {
+    REFRESH_TOKENS.delete_where(|record| record.user_id == user_id).len()
+}
+
+fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
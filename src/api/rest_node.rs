@@ -9,112 +9,389 @@
 //! - 几个重要成员:
 //!   - `id`
 //!   - `next_id/next_obj` / `prev_id/prev_obj` (可能是数组)
+//!   - `children_ids` 所有后继节点，执行时并发执行各后继的子链。`next_id` 为其第一项的别名
 //!   - `script` 可能是如果是脚本型 (lua/python等)，不过这需要相应的后端环境
+//!   - `metadata` 附加的注解 (显示名、颜色、编辑器中的位置等)，不参与执行
+//!
+//! 节点类型、执行与图遍历见 [`crate::node`]，这里只有存储与路由
 
 use axum::{
     // error_handling::HandleErrorLayer,// 错误处理中间件
-    extract::{Path, Query, State},      // 请求提取器（路径参数、查询参数、状态）
-    http::StatusCode,                   // HTTP状态码
-    response::IntoResponse,             // 响应转换trait
-    routing::{get},                     // HTTP方法路由
+    body::Bytes,                        // 原始请求体 (HTTP 端点节点)
+    extract::{FromRef, OriginalUri, Path, Query, State}, // 请求提取器（路径参数、查询参数、状态）
+    http::{header, Method, StatusCode, Uri}, // HTTP状态码
+    response::{IntoResponse, Response}, // 响应转换trait
     Json, Router,                       // JSON处理、路由器
 };
-use serde::{Deserialize, Serialize};    // JSON序列化/反序列化
-use serde_json::Value;                  // 支持任意JSON数据
+use chrono::{DateTime, Utc};            // 时间戳
+use serde::Deserialize;                 // JSON反序列化
+use serde_json::{json, Map, Value};     // 支持任意JSON数据
+use std::collections::{HashMap, HashSet}; // 集合
 use std::sync::Arc;                     // 线程安全共享指针
 use uuid::Uuid;                         // 生成唯一ID
+use validator::{Validate, ValidationError, ValidationErrors}; // 请求体校验
+use once_cell::sync::Lazy;
+use std::str::FromStr;                  // 解析 cron 表达式
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;   // 取消后台任务
 
-use crate::container::rest_store::Container;
+use crate::api::{events, extractors::{validate_data_size, validation_failed, AdminRequired, OptionalClaims, ValidatedJson}, health, jobs, pagination::{list_response, page_response}};
+use crate::api::middleware::jwt::JwtAuthLayer;
+use crate::api::graph_viz::DotGraph;
+use crate::container::{Container, HookEvent, Transaction};
+use crate::node::executor::{run_chain, RunLog};
+use crate::node::graph::{bfs, find_cycle, Direction};
+use crate::node::types::{endpoint_key, BasicNode, NodeKind, NodeKindMetadata};
+use crate::supervisor::TaskSupervisor;
+#[cfg(feature = "scripting")]
+use crate::node::script;
+use crate::documented_route;
 
 // #region Node相关类型
 
-/// Node特征
-/// 
-/// 必须实现线程安全约束
-trait Node: Send + Sync {
-    /// 依次执行脚本 (执行自身，并自动调动下一个节点)
-    fn _run(&self) -> bool;
-
-    /// 创建Node的派生类
-    /// 
-    /// - 自动分发类型
-    fn factory(id: &str, data: Option<Value>) -> BasicNode {
-        match data {
-            Some(value) => {
-                BasicNode {
-                    id: id.to_string(),
-                    content: value,
-                    next_id: None,
-                    prev_id: None,
-                }
-            },
-            None => {
-                BasicNode {
-                    id: id.to_string(),
-                    content: Value::default(),
-                    next_id: None,
-                    prev_id: None,
-                }
-            }
+/// 由请求体创建节点，见 `PUT`/`POST /node/{id}`
+impl BasicNode {
+    /// 创建节点，`owner_id` 为当前登录用户 (未登录时为 None)
+    fn factory(id: &str, input: RequestType, owner_id: Option<String>) -> BasicNode {
+        let now = Utc::now();
+        let children_ids = input.children_ids.unwrap_or_else(|| input.next_id.into_iter().collect());
+        BasicNode {
+            id: id.to_string(),
+            kind: input.kind.unwrap_or_default(),
+            content: input.data.unwrap_or_default(),
+            config: input.config.unwrap_or_default(),
+            metadata: input.metadata.unwrap_or_default(),
+            next_id: children_ids.first().cloned(),
+            children_ids,
+            prev_id: input.prev_id,
+            else_id: input.else_id,
+            owner_id,
+            schedule: input.schedule,
+            last_output: None,
+            last_run_at: None,
+            run_count: 0,
+            created_at: now,
+            updated_at: now,
         }
     }
 
     /// factory() 的自动管理容器的版本
-    fn factory_put(container:ItemContainer, id: &str, data: Option<Value>) -> BasicNode {
-        let new_value = Item::factory(&id, data);
+    fn factory_put(container:ItemContainer, id: &str, input: RequestType, owner_id: Option<String>) -> BasicNode {
+        let mut new_value = Item::factory(id, input, owner_id);
+        if let Some(old_value) = container.get_by_id(id) {
+            new_value.owner_id = old_value.owner_id; // 创建者在创建时确定，覆盖时不变
+            new_value.created_at = old_value.created_at; // 覆盖时保留创建时间
+        }
 
-        container.put_by_id(&id, new_value.clone());
+        container.put_by_id(id, new_value.clone());
         new_value
     }
 
     /// factory() 的自动管理容器的版本
-    fn factory_post(container:ItemContainer, id: &str, data: Option<Value>) -> (bool, BasicNode) {
-        let old_value = container.get_by_id(&id);
+    fn factory_post(container:ItemContainer, id: &str, input: RequestType, owner_id: Option<String>) -> (bool, BasicNode) {
+        let old_value = container.get_by_id(id);
         if let Some(value) = old_value {
             return (false, value);
         }
 
-        let new_value = Item::factory(&id, data);
+        let new_value = Item::factory(id, input, owner_id);
 
-        container.put_by_id(&id, new_value.clone());
+        container.put_by_id(id, new_value.clone());
         (true, new_value)
     }
 }
 
-/// 基础节点结构体，实现Node trait
+type Item = BasicNode;
+pub type ItemContainer = Arc<Container<Item>>;
+/// 二级索引: owner_id -> 节点ID列表
+type OwnerIndex = Arc<Container<Vec<String>>>;
+/// 各节点的执行统计与执行历史
+type Runs = Arc<RunLog>;
+/// HTTP 端点节点注册的路由: `方法 路径` -> 节点ID
+type EndpointIndex = Arc<Container<String>>;
+
+/// 路由共享状态
+#[derive(Clone)]
+struct NodeState {
+    nodes: ItemContainer,
+    owners: OwnerIndex,
+    runs: Runs,
+    endpoints: EndpointIndex,
+}
+
+impl FromRef<NodeState> for ItemContainer {
+    fn from_ref(state: &NodeState) -> Self {
+        state.nodes.clone()
+    }
+}
+
+impl FromRef<NodeState> for OwnerIndex {
+    fn from_ref(state: &NodeState) -> Self {
+        state.owners.clone()
+    }
+}
+
+impl FromRef<NodeState> for EndpointIndex {
+    fn from_ref(state: &NodeState) -> Self {
+        state.endpoints.clone()
+    }
+}
+
+impl FromRef<NodeState> for Runs {
+    fn from_ref(state: &NodeState) -> Self {
+        state.runs.clone()
+    }
+}
+
+/// 全局节点存储
 /// 
-/// 存储项
-#[derive(Debug, Serialize, Clone)]
-struct BasicNode {
-    id: String,
-    content: Value, // type(预设)/运行脚本，或指向对应的对象
-    next_id: Option<String>,
-    prev_id: Option<String>,
+/// 需要全局可访问，以便删除用户时级联删除其节点
+static NODE_STATE: Lazy<NodeState> = Lazy::new(|| {
+    let nodes = Container::<Item>::new_arc();
+    let owners = Container::<Vec<String>>::new_arc();
+    let runs = Arc::new(RunLog::default());
+    let endpoints = Container::<String>::new_arc();
+
+    // 通过事件钩子维护 owner 索引，避免按 owner 查询时全表扫描
+    let index = owners.clone();
+    nodes.add_hook(move |event| match event {
+        HookEvent::Put { key, old, new } => {
+            let old_owner = old.and_then(|node| node.owner_id.as_deref());
+            if old_owner != new.owner_id.as_deref() {
+                if let Some(owner) = old_owner {
+                    unindex_owner(&index, owner, key);
+                }
+                if let Some(owner) = new.owner_id.as_deref() {
+                    let mut ids = index.get_by_id(owner).unwrap_or_default();
+                    ids.push(key.to_string());
+                    index.put_by_id(owner, ids);
+                }
+            }
+        }
+        HookEvent::Delete { key, value } => {
+            if let Some(owner) = value.owner_id.as_deref() {
+                unindex_owner(&index, owner, key);
+            }
+        }
+    });
+
+    // HTTP 端点节点的路由随节点的增删改注册/注销。路由已被其他节点注册时保留先注册的
+    // (保存节点的接口已事先拒绝，见 [`endpoint_conflict`]，这里处理并发保存与复制的节点)
+    let routes = endpoints.clone();
+    nodes.add_hook(move |event| {
+        let (key, old, new) = match event {
+            HookEvent::Put { key, old, new } => (key, old.and_then(BasicNode::endpoint_key), new.endpoint_key()),
+            HookEvent::Delete { key, value } => (key, value.endpoint_key(), None),
+        };
+        if old == new {
+            return;
+        }
+        if let Some(route) = old
+            && routes.get_by_id(&route).as_deref() == Some(*key)
+        {
+            routes.delete_by_id(&route);
+        }
+        if let Some(route) = new {
+            match routes.put_if_absent(&route, key.to_string()) {
+                Ok(()) => tracing::info!("node {} registered endpoint {}", key, route),
+                Err(owner) if owner != *key => tracing::warn!("node {} not registered: endpoint {} belongs to node {}", key, route, owner),
+                Err(_) => {}
+            }
+        }
+    });
+
+    // 删除节点时一并删除其执行统计与历史
+    let log = runs.clone();
+    nodes.add_hook(move |event| {
+        if let HookEvent::Delete { key, .. } = event {
+            log.forget(key);
+        }
+    });
+
+    // 在索引钩子之后加载，以便同时建立索引
+    #[cfg(feature = "sled")]
+    crate::container::sled_store::SledContainer::open("node")
+        .and_then(|sled| sled.attach(&nodes))
+        .unwrap_or_else(|err| panic!("failed to load nodes from sled: {}", err));
+
+    events::publish_changes(&nodes, "node", "node");
+
+    NodeState { nodes, owners, runs, endpoints }
+});
+
+/// 填入节点的执行统计 (不保存在节点中，见 [`RunLog`])
+fn with_stats(node: Item, runs: &RunLog) -> Item {
+    let stats = runs.stats(&node.id);
+    Item {
+        last_output: stats.last_output,
+        last_run_at: stats.last_run_at,
+        run_count: stats.run_count,
+        ..node
+    }
 }
 
-impl Node for BasicNode {
-    fn _run(&self) -> bool {
-        false
+/// 从 owner 索引中移除节点
+fn unindex_owner(index: &OwnerIndex, owner: &str, node_id: &str) {
+    let Some(mut ids) = index.get_by_id(owner) else { return };
+    ids.retain(|id| id != node_id);
+    if ids.is_empty() {
+        index.delete_by_id(owner);
+    } else {
+        index.put_by_id(owner, ids);
     }
 }
 
-type Item = BasicNode;
-type ItemContainer = Arc<Container<Item>>;
+/// 全局节点存储
+pub(crate) fn node_container() -> ItemContainer {
+    NODE_STATE.nodes.clone()
+}
+
+/// 删除某个创建者的所有节点，返回删除数量
+/// 
+/// 供删除用户时级联调用
+pub fn delete_nodes_by_owner(owner_id: &str) -> usize {
+    let removed = NODE_STATE.nodes.delete_where(|node| node.owner_id.as_deref() == Some(owner_id));
+    #[cfg(feature = "scripting")]
+    for node in &removed {
+        script::forget_script(&node.id);
+    }
+    removed.len()
+}
 
 const API_ROOT_STR: &str = "node/";
 
 // #endregion
 
+// #region 执行
+
+/// 从 `start_id` 开始链式执行 (使用全局节点存储)，并通过 `on_step` 实时回报每个节点的结果
+/// 
+/// 供 WebSocket 等外部模块使用。起点不存在或存在环时不执行，直接返回错误
+pub async fn run_chain_reporting<F>(start_id: &str, input: Value, on_step: F) -> Result<(), String>
+where
+    F: FnMut(&str, Result<&Value, &str>) + Send,
+{
+    let data = &NODE_STATE.nodes;
+    if !data._get_is(start_id) {
+        return Err(format!("node not found: {}", start_id));
+    }
+    if let Some(cycle_at) = find_cycle(data, start_id) {
+        return Err(format!("cycle detected at node: {}", cycle_at));
+    }
+    let _ = run_chain(data, &NODE_STATE.runs, start_id, input, std::future::pending(), on_step).await; // 不会被中断
+    Ok(())
+}
+
+/// 从 `start_id` 开始链式执行 (使用全局节点存储)，供后台任务使用，见 [`crate::api::jobs`]
+/// 
+/// 返回执行记录 (同 `POST /node/{id}/run` 的响应) 与错误信息。起点不存在或存在环时不执行，记录为 `None`。
+/// `cancel` 取消时中断执行，记录为 `{ "executed_nodes": [...], "running": [...] }`
+pub(crate) async fn run_chain_job(start_id: &str, input: Value, cancel: CancellationToken) -> (Option<Value>, Option<String>) {
+    let data = &NODE_STATE.nodes;
+    if !data._get_is(start_id) {
+        return (None, Some(format!("node not found: {}", start_id)));
+    }
+    if let Some(cycle_at) = find_cycle(data, start_id) {
+        return (None, Some(format!("cycle detected at node: {}", cycle_at)));
+    }
+    match run_chain(data, &NODE_STATE.runs, start_id, input, cancel.cancelled(), |_, _| {}).await {
+        Ok(trace) => {
+            let error = trace.error.clone();
+            (serde_json::to_value(trace).ok(), error)
+        }
+        Err(interrupted) => (serde_json::to_value(interrupted).ok(), Some("cancelled".to_string())),
+    }
+}
+
+// #endregion
+
+// #region 定时执行
+
+/// 定时执行的检查间隔
+const SCHEDULE_TICK: Duration = Duration::from_secs(1);
+
+/// 解析 cron 表达式 (秒 分 时 日 月 周 [年])
+fn parse_schedule(schedule: &str) -> Result<cron::Schedule, cron::error::Error> {
+    cron::Schedule::from_str(schedule)
+}
+
+/// 启动定时执行任务
+/// 
+/// 每秒检查一次带 `schedule` 的节点，上次检查后到点的提交到后台队列 (见 [`jobs`])。
+/// 随时读取当前节点，新建或修改的定时立即生效。停机期间错过的执行不补
+fn start_scheduler() {
+    TaskSupervisor::spawn("node_scheduler", scheduler_loop);
+}
+
+/// 定时执行任务的主循环
+async fn scheduler_loop() {
+    let mut interval = tokio::time::interval(SCHEDULE_TICK);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut last_tick = Utc::now();
+    loop {
+        interval.tick().await;
+        let now = Utc::now();
+        let due = NODE_STATE.nodes.find_where(|node| {
+            node.schedule.as_deref()
+                .and_then(|schedule| parse_schedule(schedule).ok())
+                .and_then(|schedule| schedule.after(&last_tick).next())
+                .is_some_and(|next| next <= now)
+        });
+        last_tick = now;
+
+        for node in due {
+            tracing::debug!("scheduled run of node {}", node.id);
+            if let Err(err) = jobs::submit(&node.id, Value::Null) {
+                tracing::warn!("failed to submit scheduled run of node {}: {}", node.id, err);
+            }
+        }
+    }
+}
+
+// #endregion
+
+
 /// 创建 Node API 路由
 pub async fn factory_node_router() -> Router {
-    let data = Container::<Item>::new_arc();
+    let data = NODE_STATE.clone();
+    health::register_storage(&data.nodes, "node");
+    jobs::start_workers();
+    start_scheduler();
 
     // axum
     let app = Router::new()
-        .route("/node", get(node_id_get).put(node_id_put).post(node_id_post).delete(node_id_delete))
-        .route("/node/{id}", get(node_id_get).put(node_id_put).post(node_id_post).patch(node_id_patch).delete(node_id_delete))
-        .with_state(data); // 注入共享状态（节点存储）
-    app
+        .merge(documented_route!(get, "/node", node_id_get, "列出节点"))
+        .merge(documented_route!(put, "/node", node_id_put, "以随机ID创建节点"))
+        .merge(documented_route!(post, "/node", node_id_post, "创建节点"))
+        .merge(documented_route!(get, "/node/{id}", node_id_get, "获取节点"))
+        .merge(documented_route!(put, "/node/{id}", node_id_put, "幂等创建/覆盖节点"))
+        .merge(documented_route!(post, "/node/{id}", node_id_post, "以指定ID创建节点 (已存在时 409)"))
+        .merge(documented_route!(patch, "/node/{id}", node_id_patch, "更新节点"))
+        .merge(documented_route!(delete, "/node/{id}", node_id_delete, "删除节点"))
+        .merge(documented_route!(get, "/node/types", node_types_get, "节点类型及其 config 的格式"))
+        .merge(documented_route!(post, "/node/{id}/run", node_id_run, "从该节点开始链式执行"))
+        .merge(documented_route!(post, "/node/{id}/run/async", node_id_run_async, "在后台链式执行"))
+        .merge(documented_route!(post, "/node/{id}/move", node_id_move, "在链表中移动节点"))
+        .merge(documented_route!(get, "/node/{id}/metadata", node_id_metadata_get, "节点的注解"))
+        .merge(documented_route!(patch, "/node/{id}/metadata/{key}", node_id_metadata_patch, "设置一个注解"))
+        .merge(documented_route!(get, "/node/{id}/schedule/next", node_id_schedule_next, "接下来的5次定时执行时间"))
+        .merge(documented_route!(get, "/node/{id}/history", node_id_history, "节点最近的执行历史"))
+        .merge(documented_route!(get, "/node/jobs/{job_id}", node_job_get, "查询后台执行的任务"))
+        .merge(documented_route!(delete, "/node/jobs/{job_id}", node_job_delete, "取消后台执行的任务"))
+        .merge(documented_route!(get, "/node/{id}/visualize", node_id_visualize, "从该节点出发的图 (Graphviz DOT)"))
+        .merge(documented_route!(get, "/node/{id}/subgraph", node_id_subgraph, "导出从该节点可达的子图"))
+        .merge(documented_route!(get, "/node/{id}/ancestors", node_id_ancestors, "到根节点的路径"))
+        .merge(documented_route!(post, "/node/{id}/clone", node_id_clone, "复制节点 (可连同下游)"))
+        .merge(documented_route!(post, "/node/subgraph/import", node_subgraph_import, "批量导入子图"))
+        .merge(
+            Router::new()
+                .merge(documented_route!(delete, "/node", node_owner_delete, "删除某创建者的所有节点 (管理员)"))
+                .route_layer(JwtAuthLayer) // 仅作用于以上路由
+        );
+    #[cfg(feature = "scripting")]
+    let app = app.merge(documented_route!(post, "/node/validate-script", node_validate_script, "校验脚本 (仅编译)"));
+    app.fallback(node_endpoint_fallback) // HTTP 端点节点注册的路由
+        .with_state(data) // 注入共享状态（节点存储）
 }
 
 // #region 具体路由
@@ -129,59 +406,85 @@ pub async fn factory_node_router() -> Router {
 async fn node_id_get(
     id: Option<Path<String>>,
     pagination: Query<GetPagination>,
+    OriginalUri(uri): OriginalUri,
     State(data): State<ItemContainer>,
+    State(owners): State<OwnerIndex>,
+    State(runs): State<Runs>,
 ) -> impl IntoResponse {
     match id {
         // 有id，则查找特定ID项
         Some(Path(id)) => {
-            tracing::debug!("GET /{}{}", API_ROOT_STR, id); // TODO 用统一的中间件来处理
             data.get_by_id(&id)
                 .map_or_else(
                     || StatusCode::NOT_FOUND.into_response(),
-                    |result| Json(result.clone()).into_response()
+                    |result| Json(with_stats(result, &runs)).into_response()
                 )
         }
-        // 无id，返回所有项
-        None => {
-            tracing::debug!("GET /{}", API_ROOT_STR);
-            let result: Vec<Item> = data.get_all()
-                .values()
-                .skip(pagination.offset.unwrap_or(0))
-                .take(pagination.limit.unwrap_or(usize::MAX))
-                .cloned()
+        // 无id，有owner，经索引返回该创建者的项
+        None if pagination.owner.is_some() => {
+            let owner = pagination.owner.as_deref().unwrap_or_default();
+            let mut result: Vec<Item> = owners.get_by_id(owner)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|id| data.get_by_id(id))
+                .map(|node| with_stats(node, &runs))
                 .collect::<Vec<_>>();
-            Json(result).into_response()
+            result.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id))); // 与无 owner 时的顺序一致
+            list_response(&uri, result, pagination.offset, pagination.limit)
+        }
+        // 无id，返回所有项 (按创建时间)
+        None => {
+            let (page, total) = data.get_page_sorted_by(
+                |_| true,
+                |item| (item.created_at, item.id.clone()),
+                pagination.offset.unwrap_or(0),
+                pagination.limit.unwrap_or(usize::MAX),
+            );
+            let page = page.into_iter().map(|node| with_stats(node, &runs)).collect();
+            page_response(&uri, page, total, pagination.offset, pagination.limit)
         }
     }
 }
 
+/**
+ * GET /node/types 列出所有节点类型
+ * 
+ * 返回 `[{ "name": "http", "description": "...", "config_schema": { ... } }]`，`config_schema` 为 `config` 的 JSON Schema
+ */
+async fn node_types_get() -> impl IntoResponse {
+    let types: Vec<Value> = NodeKind::all_variants().iter()
+        .map(|kind| json!({
+            "name": kind.name(),
+            "description": kind.description(),
+            "config_schema": kind.config_schema(),
+        }))
+        .collect();
+    Json(types)
+}
+
 /**
  * PUT /node/{id?} 幂等创建/修改项 (重复策略：覆盖，而非报错)
  * 
  * - `id` 路径中的ID (可选, 无则随机id)
  * - `db` 共享数据库状态
+ * - `claims` 当前登录用户 (可选)，新建时作为创建者
  * - `input` JSON请求体
  */
 async fn node_id_put(
     id: Option<Path<String>>,
     State(data): State<ItemContainer>,
-    Json(input): Json<RequestType>,
+    State(endpoints): State<EndpointIndex>,
+    State(runs): State<Runs>,
+    OptionalClaims(claims): OptionalClaims,
+    ValidatedJson(input): ValidatedJson<RequestType>,
 ) -> impl IntoResponse {
-    let id = id
-        .map_or_else(
-            || {
-                let id = Uuid::new_v4().to_string();
-                tracing::debug!("PUT /{}, create id:{}", API_ROOT_STR, id);
-                id
-            },
-            |p| {
-                tracing::debug!("PUT /{}{}", API_ROOT_STR, p.0);
-                p.0
-            }
-        );
+    let id = id.map_or_else(|| Uuid::new_v4().to_string(), |p| p.0);
+    if let Some(conflict) = endpoint_conflict(&endpoints, &id, &input) {
+        return conflict;
+    }
 
-    let item = Item::factory_put(data, &id, input.data);
-    (StatusCode::CREATED, Json(item.clone()))
+    let item = Item::factory_put(data, &id, input, claims.map(|claims| claims.sub));
+    (StatusCode::CREATED, Json(with_stats(item, &runs))).into_response()
 }
 
 /**
@@ -189,83 +492,714 @@ async fn node_id_put(
  * 
  * - `id` 路径中的ID (可选, 无则随机id)
  * - `db` 共享数据库状态
+ * - `claims` 当前登录用户 (可选)，新建时作为创建者
  * - `input` JSON请求体
  */
 async fn node_id_post(
     id: Option<Path<String>>,
     State(data): State<ItemContainer>,
-    Json(input): Json<RequestType>
+    State(endpoints): State<EndpointIndex>,
+    State(runs): State<Runs>,
+    OptionalClaims(claims): OptionalClaims,
+    ValidatedJson(input): ValidatedJson<RequestType>
 ) -> impl IntoResponse {
-    let id = id
-        .map_or_else(
-            || {
-                let id = Uuid::new_v4().to_string();
-                tracing::debug!("POST /{}, create id:{}", API_ROOT_STR, id);
-                id
-            },
-            |p| {
-                tracing::debug!("POST /{}{}", API_ROOT_STR, p.0);
-                p.0
-            }
-        );
+    let id = id.map_or_else(|| Uuid::new_v4().to_string(), |p| p.0);
+    if let Some(conflict) = endpoint_conflict(&endpoints, &id, &input) {
+        return conflict;
+    }
 
-    let item = Item::factory_post(data, &id, input.data);
-    if item.0 == false {
-        (StatusCode::CONFLICT, Json(item.1.clone()))
+    let item = Item::factory_post(data, &id, input, claims.map(|claims| claims.sub));
+    if !item.0 {
+        (StatusCode::CONFLICT, Json(with_stats(item.1, &runs))).into_response()
     } else {
-        (StatusCode::CREATED, Json(item.1.clone()))
+        (StatusCode::CREATED, Json(item.1)).into_response()
     }
 }
 
 /**
  * PATCH /node/{id} 更新项 (缺失策略: 404, 而非新建)
  * 
- * - `id` 路径中的ID (可选, 无则随机id)
+ * 只修改请求体中给出的字段，`prev_id`/`else_id`/`schedule` 为 `null` 时清除。
+ * `next_id` 只替换第一个后继 (给出 `children_ids` 时忽略)，`config` 按修改后的 `kind` 校验。
+ * `metadata` 合并到原有的注解中 (值为 `null` 的键被删除)，而非替换
+ * 
+ * - `id` 路径中的ID
  * - `db` 共享数据库状态
+ * - `endpoints` 已注册的路由
  * - `input` JSON请求体
  */
 async fn node_id_patch(
     Path(id): Path<String>,
     State(data): State<ItemContainer>,
-    Json(input): Json<RequestType>,
+    State(endpoints): State<EndpointIndex>,
+    State(runs): State<Runs>,
+    ValidatedJson(input): ValidatedJson<PatchRequestType>,
 ) -> impl IntoResponse {
-    tracing::debug!("PATCH /{}{}", API_ROOT_STR, id);
+    let result = data.update_by_id(&id, |old| {
+        let mut node = old.clone();
+        if let Some(content) = input.data {
+            node.content = content;
+        }
+        if let Some(kind) = input.kind {
+            node.kind = kind;
+        }
+        if let Some(config) = input.config {
+            node.config = config;
+        }
+        for (key, value) in input.metadata.unwrap_or_default() {
+            match value {
+                Value::Null => node.metadata.remove(&key),
+                value => node.metadata.insert(key, value),
+            };
+        }
+        match (input.children_ids, input.next_id) {
+            (Some(children_ids), _) => node.set_children_ids(children_ids),
+            (None, Some(next_id)) => node.set_next_id(next_id),
+            (None, None) => {}
+        }
+        if let Some(prev_id) = input.prev_id {
+            node.prev_id = prev_id;
+        }
+        if let Some(else_id) = input.else_id {
+            node.else_id = else_id;
+        }
+        if let Some(schedule) = input.schedule {
+            node.schedule = schedule;
+        }
 
-    let old_value = data.get_by_id(&id);
-    if old_value.is_none() {
-        return StatusCode::NOT_FOUND.into_response()
-    };
+        node.kind.validate_config(&node.config).map_err(PatchError::InvalidConfig)?;
+        if let Some(route) = node.endpoint_key()
+            && endpoints.get_by_id(&route).is_some_and(|owner| owner != id)
+        {
+            return Err(PatchError::EndpointTaken(route));
+        }
+        node.updated_at = Utc::now();
+        Ok(node)
+    });
+    match result {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(Err(PatchError::InvalidConfig(err))) => {
+            let mut errors = ValidationErrors::new();
+            errors.add("__all__", ValidationError::new("config_schema").with_message(err.into()));
+            validation_failed(errors)
+        }
+        Some(Err(PatchError::EndpointTaken(route))) => endpoint_conflict_response(&route),
+        Some(Ok((_, node))) => Json(with_stats(node, &runs)).into_response(),
+    }
+}
 
-    let new_value = Item::factory_put(data, &id, input.data);
-    Json(new_value).into_response()
+/// 修改节点失败的原因
+enum PatchError {
+    /// `config` 不符合修改后的 `kind` 的格式
+    InvalidConfig(String),
+    /// HTTP 端点节点的路由已注册到其他节点
+    EndpointTaken(String),
 }
 
 /**
  * DELETE /node/{id} 删除待办事项
  * 
+ * `?cascade_unlink=true` 同时清除其他节点指向它的链接 (`children_ids` 中的该项与 `prev_id`)。
+ * `?rewire=true` 则把前驱改连到它的 `next_id`、后继的 `prev_id` 改为它的 `prev_id`，如同从链表中摘除 (隐含 `cascade_unlink`)。
+ * 删除与修改在同一事务内完成
+ * 
  * - `id` 路径中的ID
+ * - `query` 查询参数
  * - `db` 共享数据库状态
  */
 async fn node_id_delete(
-    id: Option<Path<String>>,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteQuery>,
     State(data): State<ItemContainer>,
 ) -> impl IntoResponse {
-    let id = if let Some(id) = id {
-        tracing::debug!("DELETE /{}{}", API_ROOT_STR, id.0);
-        id.0
-    } else {
+    let rewire = query.rewire.unwrap_or(false);
+    let result = match query.cascade_unlink.unwrap_or(false) || rewire {
+        true => data.transaction(|tx| delete_and_unlink(tx, &id, rewire, Utc::now())),
+        false => data.delete_by_id(&id),
+    };
+    #[cfg(feature = "scripting")]
+    script::forget_script(&id);
+    match result {
+        Some(_) => StatusCode::NO_CONTENT.into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// DELETE /node?owner={user_id} 删除某创建者的所有项 (管理接口，需管理员)
+///
+/// 不带 `owner` 时为清空，禁止 (返回 403)
+async fn node_owner_delete(
+    AdminRequired(admin): AdminRequired,
+    Query(query): Query<OwnerDeleteQuery>,
+) -> impl IntoResponse {
+    let Some(owner) = query.owner else {
         tracing::warn!("DELETE /{}, clearing is a high-risk operation", API_ROOT_STR);
-        // data._delete_all();
         return StatusCode::FORBIDDEN.into_response();
     };
+    tracing::warn!("DELETE /{}, admin {} deletes all nodes of owner:{}", API_ROOT_STR, admin.sub, owner);
+    let deleted = delete_nodes_by_owner(&owner);
+    Json(json!({ "deleted": deleted })).into_response()
+}
 
-    let result = data.delete_by_id(&id);
-    match result {
-        Some(_) => StatusCode::NO_CONTENT.into_response(),
+/// 在事务中删除节点，并修改指向它的链接，返回被删除的节点，见 `DELETE /node/{id}`
+fn delete_and_unlink(tx: &mut Transaction<'_, Item, HashMap<String, Item>>, id: &str, rewire: bool, now: DateTime<Utc>) -> Option<Item> {
+    let node = tx.remove(id)?;
+    let referrers: Vec<Item> = tx.values()
+        .filter(|other| other.children_ids.iter().any(|child| child == id) || other.prev_id.as_deref() == Some(id))
+        .cloned()
+        .collect();
+    for mut other in referrers {
+        let children_ids = other.children_ids.iter()
+            .filter_map(|child| match child == id {
+                true => node.next_id.clone().filter(|_| rewire),
+                false => Some(child.clone()),
+            })
+            .collect();
+        other.set_children_ids(children_ids);
+        if other.prev_id.as_deref() == Some(id) {
+            other.prev_id = node.prev_id.clone().filter(|_| rewire);
+        }
+        other.updated_at = now;
+        tx.insert(&other.id.clone(), other);
+    }
+    Some(node)
+}
+
+/**
+ * POST /node/{id}/run 从该节点开始链式执行
+ * 
+ * 返回执行记录 `{ "steps": [{ "id": "...", "branch": "then"|"else"|null, "output": ... }] }`
+ * 
+ * 请求体带 `timeout_ms` 时限制执行时间，超时返回 `408` 及
+ * `{ "error": "timed out", "executed_nodes": [...], "timed_out_at": "正在执行的节点ID" }`
+ * 
+ * - `id` 路径中的ID
+ * - `db` 共享数据库状态
+ * - `input` JSON请求体，`{ "input": ... }`
+ */
+async fn node_id_run(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    State(runs): State<Runs>,
+    Json(input): Json<RunRequest>,
+) -> impl IntoResponse {
+    if !data._get_is(&id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if let Some(cycle_at) = find_cycle(&data, &id) {
+        return (StatusCode::CONFLICT, Json(json!({ "error": "cycle detected", "cycle_at": cycle_at }))).into_response();
+    }
+
+    let timeout = async move {
+        match input.timeout_ms {
+            Some(timeout_ms) => tokio::time::sleep(Duration::from_millis(timeout_ms)).await,
+            None => std::future::pending().await,
+        }
+    };
+    let trace = match run_chain(&data, &runs, &id, input.input.unwrap_or_default(), timeout, |_, _| {}).await {
+        Ok(trace) => trace,
+        Err(interrupted) => return (StatusCode::REQUEST_TIMEOUT, Json(json!({
+            "error": "timed out",
+            "executed_nodes": interrupted.executed_nodes,
+            "timed_out_at": interrupted.running.first(),
+        }))).into_response(),
+    };
+    if trace.error.is_some() {
+        (StatusCode::UNPROCESSABLE_ENTITY, Json(trace)).into_response()
+    } else {
+        Json(trace).into_response()
+    }
+}
+
+/**
+ * POST /node/{id}/run/async 在后台链式执行
+ * 
+ * 立即返回 `202` 及任务 `{ "job_id": "...", "status": "queued" }`，用 `GET /node/jobs/{job_id}` 查询结果。
+ * 起点不存在或存在环时直接返回错误，不提交
+ * 
+ * - `id` 路径中的ID
+ * - `input` JSON请求体，同 `POST /node/{id}/run`
+ */
+async fn node_id_run_async(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    Json(input): Json<RunRequest>,
+) -> impl IntoResponse {
+    if !data._get_is(&id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if let Some(cycle_at) = find_cycle(&data, &id) {
+        return (StatusCode::CONFLICT, Json(json!({ "error": "cycle detected", "cycle_at": cycle_at }))).into_response();
+    }
+
+    match jobs::submit(&id, input.input.unwrap_or_default()) {
+        Ok(job) => (
+            StatusCode::ACCEPTED,
+            [(header::LOCATION, format!("/node/jobs/{}", job.id))],
+            Json(json!({ "job_id": job.id, "status": job.status })),
+        ).into_response(),
+        Err(err) => (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "error": err }))).into_response(),
+    }
+}
+
+/**
+ * 未匹配任何路由的请求: 查找 HTTP 端点节点注册的路由 (见 `NodeKind::HttpEndpoint`)
+ * 
+ * 以请求体 (JSON，为空时为 `null`) 为输入从该节点开始执行，返回最后一步的输出。
+ * 没有对应的节点返回 `404`，请求体不是JSON返回 `400`，存在环返回 `409`，执行出错返回 `422` 及执行记录
+ * 
+ * - `method` 请求方法
+ * - `uri` 请求路径
+ * - `db` 共享数据库状态
+ * - `endpoints` 已注册的路由
+ * - `body` 请求体
+ */
+async fn node_endpoint_fallback(
+    method: Method,
+    uri: Uri,
+    State(data): State<ItemContainer>,
+    State(endpoints): State<EndpointIndex>,
+    State(runs): State<Runs>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let Some(id) = endpoints.get_by_id(&endpoint_key(method.as_str(), uri.path())) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let input = match body.is_empty() {
+        true => Value::Null,
+        false => match serde_json::from_slice(&body) {
+            Ok(input) => input,
+            Err(err) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": format!("invalid json body: {}", err) }))).into_response(),
+        },
+    };
+    if let Some(cycle_at) = find_cycle(&data, &id) {
+        return (StatusCode::CONFLICT, Json(json!({ "error": "cycle detected", "cycle_at": cycle_at }))).into_response();
+    }
+
+    let Ok(trace) = run_chain(&data, &runs, &id, input, std::future::pending(), |_, _| {}).await else {
+        unreachable!("pending() never completes");
+    };
+    match (&trace.error, trace.steps.last()) {
+        (None, Some(step)) => Json(step.output.clone()).into_response(),
+        _ => (StatusCode::UNPROCESSABLE_ENTITY, Json(trace)).into_response(),
+    }
+}
+
+/**
+ * GET /node/{id}/metadata 节点的注解
+ * 
+ * - `id` 路径中的ID
+ * - `db` 共享数据库状态
+ */
+async fn node_id_metadata_get(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+) -> impl IntoResponse {
+    match data.get_by_id(&id) {
+        Some(node) => Json(node.metadata).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/**
+ * PATCH /node/{id}/metadata/{key} 设置一个注解，其余注解不变
+ * 
+ * 请求体为该键的新值 (任意JSON，`null` 时删除该键)，返回修改后的全部注解
+ * 
+ * - `id` 路径中的ID
+ * - `key` 注解的键
+ * - `db` 共享数据库状态
+ * - `value` JSON请求体
+ */
+async fn node_id_metadata_patch(
+    Path((id, key)): Path<(String, String)>,
+    State(data): State<ItemContainer>,
+    Json(value): Json<Value>,
+) -> impl IntoResponse {
+    let updated = data.update_by_id(&id, |node| {
+        let mut node = node.clone();
+        match value {
+            Value::Null => node.metadata.remove(&key),
+            value => node.metadata.insert(key, value),
+        };
+        node.updated_at = Utc::now();
+        Ok::<_, ()>(node)
+    });
+    match updated {
+        Some(Ok((_, node))) => Json(node.metadata).into_response(),
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/**
+ * GET /node/{id}/history 节点最近的执行历史 (最多100条，按完成顺序)
+ * 
+ * 每条为 `{ "node_id", "run_id", "timestamp", "input", "output", "duration_ms", "error" }`
+ * 
+ * - `id` 路径中的ID
+ * - `db` 共享数据库状态
+ * - `runs` 执行统计与历史
+ */
+async fn node_id_history(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    State(runs): State<Runs>,
+) -> impl IntoResponse {
+    if !data._get_is(&id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Json(runs.history(&id)).into_response()
+}
+
+/**
+ * GET /node/{id}/schedule/next 接下来的5次定时执行时间
+ * 
+ * 返回 `{ "schedule": "0 0 * * * *", "last_run_at": ..., "next": ["...", ...] }`，节点没有定时时返回 `404`
+ * 
+ * - `id` 路径中的ID
+ */
+async fn node_id_schedule_next(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    State(runs): State<Runs>,
+) -> impl IntoResponse {
+    let Some(node) = data.get_by_id(&id) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Some(schedule) = node.schedule.as_deref().and_then(|schedule| parse_schedule(schedule).ok()) else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "node has no schedule" }))).into_response();
+    };
+    let next: Vec<DateTime<Utc>> = schedule.upcoming(Utc).take(5).collect();
+    Json(json!({
+        "schedule": node.schedule,
+        "last_run_at": runs.stats(&id).last_run_at,
+        "next": next,
+    })).into_response()
+}
+
+/**
+ * GET /node/jobs/{job_id} 查询后台执行的任务
+ * 
+ * - `job_id` 任务ID
+ */
+async fn node_job_get(Path(job_id): Path<String>) -> impl IntoResponse {
+    match jobs::get(&job_id) {
+        Some(job) => Json(job).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/**
+ * DELETE /node/jobs/{job_id} 取消后台执行的任务
+ * 
+ * 排队中的任务不再执行，执行中的任务被中断，状态变为 `cancelled`。返回取消后的任务，已结束的任务返回 `409`
+ * 
+ * - `job_id` 任务ID
+ */
+async fn node_job_delete(Path(job_id): Path<String>) -> impl IntoResponse {
+    match jobs::cancel(&job_id) {
+        Some(Ok(job)) => Json(job).into_response(),
+        Some(Err(status)) => (StatusCode::CONFLICT, Json(json!({ "error": "job already finished", "status": status }))).into_response(),
         None => StatusCode::NOT_FOUND.into_response(),
     }
 }
 
+/**
+ * GET /node/{id}/visualize 以 Graphviz DOT 格式输出从该节点出发的图
+ * 
+ * `children_ids` 每个后继一条实线，`else_id` 为虚线
+ * 
+ * - `id` 路径中的ID
+ * - `query` 查询参数，`depth` 最大跳数 (默认10)
+ * - `db` 共享数据库状态
+ */
+async fn node_id_visualize(
+    Path(id): Path<String>,
+    Query(query): Query<VisualizeQuery>,
+    State(data): State<ItemContainer>,
+) -> impl IntoResponse {
+    let all = data.get_all();
+    if !all.contains_key(&id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let depth = query.depth.unwrap_or(10);
+
+    let mut graph = DotGraph::new(&id);
+    for (node, hops) in bfs(&all, &id, Direction::Forward, depth) {
+        graph.add_node(&node.id, &format!("{}\n({})", node.id, node.kind.name()));
+        if hops >= depth {
+            continue;
+        }
+        for (next_id, style) in node.neighbors(Direction::Forward) {
+            if all.contains_key(next_id) {
+                graph.add_edge(&node.id, next_id, style);
+            }
+        }
+    }
+
+    ([(header::CONTENT_TYPE, "text/vnd.graphviz")], graph.to_dot()).into_response()
+}
+
+/**
+ * GET /node/{id}/subgraph 导出从该节点可达的子图
+ * 
+ * 返回节点数组，可直接用于 `POST /node/subgraph/import`
+ * 
+ * - `id` 路径中的ID
+ * - `query` 查询参数，`direction` 方向 (`forward`/`backward`，默认前者)，`depth` 最大跳数 (默认50)
+ * - `db` 共享数据库状态
+ */
+async fn node_id_subgraph(
+    Path(id): Path<String>,
+    Query(query): Query<SubgraphQuery>,
+    State(data): State<ItemContainer>,
+) -> impl IntoResponse {
+    let all = data.get_all();
+    if !all.contains_key(&id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let result: Vec<Item> = bfs(&all, &id, query.direction.unwrap_or_default(), query.depth.unwrap_or(50))
+        .into_iter()
+        .map(|(node, _)| node.clone())
+        .collect();
+    Json(result).into_response()
+}
+
+/**
+ * GET /node/{id}/ancestors 沿 `prev_id` 向上直到根 (没有 `prev_id` 的节点)
+ * 
+ * 返回 `{ "chain": ["根ID", ..., "父ID", "{id}"], "nodes": [...] }`，`nodes` 为对应的节点，顺序同 `chain`。
+ * `prev_id` 指向不存在的节点时在此停止，成环时在重复处停止
+ * 
+ * - `id` 路径中的ID
+ * - `db` 共享数据库状态
+ */
+async fn node_id_ancestors(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+) -> impl IntoResponse {
+    let all = data.get_all();
+    if !all.contains_key(&id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let nodes: Vec<&Item> = bfs(&all, &id, Direction::Backward, usize::MAX)
+        .into_iter()
+        .rev()
+        .map(|(node, _)| node)
+        .collect();
+    let chain: Vec<&str> = nodes.iter().map(|node| node.id.as_str()).collect();
+    Json(json!({ "chain": chain, "nodes": nodes })).into_response()
+}
+
+/**
+ * POST /node/subgraph/import 批量导入子图
+ * 
+ * 所有节点分配新的ID以避免冲突，子图内部的 `children_ids`/`prev_id`/`else_id` 随之重映射，
+ * 指向子图外部的链接被清除。只有 `next_id` 的旧数据由其补全 `children_ids`
+ * 
+ * 返回 `{ "mapping": { "旧ID": "新ID" }, "nodes": [...] }`
+ * 
+ * - `db` 共享数据库状态
+ * - `input` JSON请求体，节点数组 (同 `GET /node/{id}/subgraph` 的返回值)
+ */
+async fn node_subgraph_import(
+    State(data): State<ItemContainer>,
+    State(endpoints): State<EndpointIndex>,
+    Json(input): Json<Vec<Item>>,
+) -> impl IntoResponse {
+    let mapping: HashMap<String, String> = input.iter()
+        .map(|node| (node.id.clone(), Uuid::new_v4().to_string()))
+        .collect();
+    if mapping.len() != input.len() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "duplicate node id" }))).into_response();
+    }
+    let mut routes = HashSet::new();
+    for route in input.iter().filter_map(BasicNode::endpoint_key) {
+        if endpoints.get_by_id(&route).is_some() || !routes.insert(route.clone()) {
+            return endpoint_conflict_response(&route);
+        }
+    }
+
+    let nodes = remap_nodes(input, &mapping);
+    for node in &nodes {
+        data.put_by_id(&node.id, node.clone());
+    }
+
+    (StatusCode::CREATED, Json(json!({ "mapping": mapping, "nodes": nodes }))).into_response()
+}
+
+/// 按 `mapping` (旧ID -> 新ID) 给节点换上新ID，并重设创建时间
+/// 
+/// 组内的 `children_ids`/`prev_id`/`else_id` 随之重映射，指向组外的链接被清除。
+/// 只有 `next_id` 的旧数据由其补全 `children_ids`
+fn remap_nodes(nodes: Vec<Item>, mapping: &HashMap<String, String>) -> Vec<Item> {
+    let remap = |id: Option<String>| id.and_then(|id| mapping.get(&id).cloned());
+    let now = Utc::now();
+    nodes.into_iter()
+        .map(|mut node| {
+            let children_ids = match node.children_ids.is_empty() {
+                true => node.next_id.take().into_iter().collect(),
+                false => std::mem::take(&mut node.children_ids),
+            };
+            node.set_children_ids(children_ids.into_iter().filter_map(|id| mapping.get(&id).cloned()).collect());
+            Item {
+                id: mapping[&node.id].clone(),
+                prev_id: remap(node.prev_id),
+                else_id: remap(node.else_id),
+                created_at: now,
+                updated_at: now,
+                ..node
+            }
+        })
+        .collect()
+}
+
+/**
+ * POST /node/{id}/clone 复制节点
+ * 
+ * 副本分配新的ID，不与任何节点相连 (清除 `children_ids`/`prev_id`/`else_id`)。
+ * `?with_chain=true` 时一并复制所有可达的下游节点，副本之间保持原有的连接。
+ * `?metadata=false` 时不复制注解
+ * 
+ * 返回 `{ "original_id": "...", "clone_id": "...", "mapping": { "旧ID": "新ID" }, "nodes": [...] }`
+ * 
+ * - `id` 路径中的ID
+ * - `query` 查询参数
+ * - `db` 共享数据库状态
+ */
+async fn node_id_clone(
+    Path(id): Path<String>,
+    Query(query): Query<CloneQuery>,
+    State(data): State<ItemContainer>,
+) -> impl IntoResponse {
+    let all = data.get_all();
+    if !all.contains_key(&id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let depth = match query.with_chain.unwrap_or(false) {
+        true => usize::MAX,
+        false => 0,
+    };
+    let originals: Vec<Item> = bfs(&all, &id, Direction::Forward, depth)
+        .into_iter()
+        .map(|(node, _)| node.clone())
+        .collect();
+    let mapping: HashMap<String, String> = originals.iter()
+        .map(|node| (node.id.clone(), Uuid::new_v4().to_string()))
+        .collect();
+    let nodes: Vec<Item> = remap_nodes(originals, &mapping)
+        .into_iter()
+        .map(|node| Item {
+            metadata: if query.metadata.unwrap_or(true) { node.metadata } else { Map::new() },
+            ..node
+        })
+        .collect();
+    for node in &nodes {
+        data.put_by_id(&node.id, node.clone());
+    }
+
+    (StatusCode::CREATED, Json(json!({
+        "original_id": id,
+        "clone_id": mapping[&id],
+        "mapping": mapping,
+        "nodes": nodes,
+    }))).into_response()
+}
+
+/**
+ * POST /node/{id}/move 在链表 (`prev_id`/`next_id`) 中移动节点
+ * 
+ * 先从原位置摘除 (前后节点互相链接)，再插入到目标节点之后 (`after_id`) 或之前 (`before_id`)。
+ * 只调整第一个后继 (`next_id`)，`children_ids` 的其余项不变。
+ * 涉及的所有节点在同一事务内修改，其他请求看不到中间状态
+ * 
+ * - `id` 路径中的ID
+ * - `db` 共享数据库状态
+ * - `input` JSON请求体，`after_id` 与 `before_id` 恰好给出其一
+ */
+async fn node_id_move(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    Json(input): Json<MoveRequest>,
+) -> impl IntoResponse {
+    let (target_id, after) = match (input.after_id, input.before_id) {
+        (Some(after_id), None) => (after_id, true),
+        (None, Some(before_id)) => (before_id, false),
+        _ => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "exactly one of after_id and before_id is required" }))).into_response(),
+    };
+    if target_id == id {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "cannot move a node relative to itself" }))).into_response();
+    }
+
+    let now = Utc::now();
+    let result = data.transaction(|tx| {
+        let Some(mut node) = tx.get_by_id(&id) else {
+            return Err(None); // 节点不存在 (404)，Some 为参数错误 (400)
+        };
+        if !tx.contains_key(&target_id) {
+            return Err(Some("target node not found"));
+        }
+
+        // 摘除: 前后节点互相链接 (仅修改确实指向本节点的链接)
+        relink(tx, node.prev_id.as_deref(), now, |prev| {
+            if prev.next_id.as_deref() == Some(id.as_str()) {
+                prev.set_next_id(node.next_id.clone());
+            }
+        });
+        relink(tx, node.next_id.as_deref(), now, |next| {
+            if next.prev_id.as_deref() == Some(id.as_str()) {
+                next.prev_id = node.prev_id.clone();
+            }
+        });
+
+        // 插入: 摘除后重新读取目标节点 (它可能是原来的邻居)
+        let target = tx.get_by_id(&target_id).unwrap_or_default();
+        let next_id;
+        (node.prev_id, next_id) = match after {
+            true => (Some(target_id.clone()), target.next_id),
+            false => (target.prev_id, Some(target_id.clone())),
+        };
+        node.set_next_id(next_id);
+        relink(tx, node.prev_id.as_deref(), now, |prev| prev.set_next_id(Some(id.clone())));
+        relink(tx, node.next_id.as_deref(), now, |next| next.prev_id = Some(id.clone()));
+        node.updated_at = now;
+        tx.insert(&id, node.clone());
+        Ok(node)
+    });
+
+    match result {
+        Ok(node) => Json(node).into_response(),
+        Err(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(Some(err)) => (StatusCode::BAD_REQUEST, Json(json!({ "error": err }))).into_response(),
+    }
+}
+
+/// 修改事务中的一个节点 (不存在时忽略)
+fn relink(tx: &mut Transaction<'_, Item, HashMap<String, Item>>, id: Option<&str>, now: DateTime<Utc>, update: impl FnOnce(&mut Item)) {
+    let Some(mut node) = id.and_then(|id| tx.get_by_id(id)) else { return };
+    update(&mut node);
+    node.updated_at = now;
+    tx.insert(&node.id.clone(), node);
+}
+
+/**
+ * POST /node/validate-script 校验脚本 (仅编译，不执行)
+ * 
+ * - `input` JSON请求体，`{ "script": "..." }`
+ */
+#[cfg(feature = "scripting")]
+async fn node_validate_script(
+    Json(input): Json<ValidateScriptRequest>,
+) -> impl IntoResponse {
+    match script::validate_script(&input.script) {
+        Ok(()) => (StatusCode::OK, Json(json!({ "valid": true }))),
+        Err(err) => (StatusCode::BAD_REQUEST, Json(json!({ "valid": false, "error": err }))),
+    }
+}
+
 // #region api struct
 
 #[derive(Debug, Deserialize, Default)]
@@ -274,11 +1208,145 @@ struct GetPagination {
     offset: Option<usize>,
     /// 数量限制
     limit: Option<usize>,
+    /// 仅返回该创建者的项
+    owner: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OwnerDeleteQuery {
+    /// 删除该创建者的所有项
+    owner: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
+struct DeleteQuery {
+    /// 同时清除其他节点指向被删节点的链接
+    cascade_unlink: Option<bool>,
+    /// 把被删节点的前驱与后继相连 (隐含 `cascade_unlink`)
+    rewire: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_config"))]
 struct RequestType {
+    #[validate(custom(function = "validate_data_size"))]
+    data: Option<Value>,
+    kind: Option<NodeKind>,
+    config: Option<Value>,
+    /// 附加的注解，PATCH 时合并到原有的注解中
+    metadata: Option<Map<String, Value>>,
+    next_id: Option<String>,
+    /// 所有后继节点，给出时忽略 `next_id`
+    children_ids: Option<Vec<String>>,
+    prev_id: Option<String>,
+    else_id: Option<String>,
+    #[validate(custom(function = "validate_schedule"))]
+    schedule: Option<String>,
+}
+
+/// `PATCH /node/{id}` 的请求体，字段同 [`RequestType`]，缺失的字段不修改
+/// 
+/// 可清除的字段为两层 `Option`: 缺失为 `None`，`null` 为 `Some(None)`。
+/// `config` 需与修改后的 `kind` 一起校验，在处理函数中进行
+#[derive(Debug, Deserialize, Validate)]
+struct PatchRequestType {
+    #[validate(custom(function = "validate_data_size"))]
     data: Option<Value>,
+    kind: Option<NodeKind>,
+    config: Option<Value>,
+    /// 合并到原有的注解中
+    metadata: Option<Map<String, Value>>,
+    #[serde(default, deserialize_with = "nullable")]
+    next_id: Option<Option<String>>,
+    /// 所有后继节点，给出时忽略 `next_id`
+    children_ids: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    prev_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    else_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(custom(function = "validate_schedule"))]
+    schedule: Option<Option<String>>,
+}
+
+/// 反序列化可清除的字段: 给出的值 (含 `null`) 包一层 `Some`，以区分缺失与 `null`
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// 请求体中 HTTP 端点节点的路由已注册到其他节点时返回 `409` (同一路由只能属于一个节点)
+fn endpoint_conflict(endpoints: &EndpointIndex, id: &str, input: &RequestType) -> Option<Response> {
+    let config = input.config.as_ref().unwrap_or(&Value::Null);
+    let route = input.kind.unwrap_or_default().endpoint_key(config)?;
+    endpoints.get_by_id(&route)
+        .filter(|owner| owner != id)
+        .map(|_| endpoint_conflict_response(&route))
+}
+
+fn endpoint_conflict_response(route: &str) -> Response {
+    let error = format!("endpoint {} is already registered by another node", route);
+    (StatusCode::CONFLICT, Json(json!({ "error": error }))).into_response()
+}
+
+/// 校验: `config` 符合 `kind` 的格式 (见 [`NodeKindMetadata::config_schema`])
+fn validate_config(input: &RequestType) -> Result<(), ValidationError> {
+    let config = input.config.as_ref().unwrap_or(&Value::Null);
+    input.kind.unwrap_or_default()
+        .validate_config(config)
+        .map_err(|err| ValidationError::new("config_schema").with_message(err.into()))
+}
+
+/// 校验: `schedule` 为合法的 cron 表达式
+fn validate_schedule(schedule: &str) -> Result<(), ValidationError> {
+    parse_schedule(schedule)
+        .map(|_| ())
+        .map_err(|err| ValidationError::new("cron").with_message(format!("invalid cron expression: {}", err).into()))
+}
+
+#[derive(Debug, Deserialize)]
+struct VisualizeQuery {
+    /// 最大跳数
+    depth: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubgraphQuery {
+    /// 遍历方向
+    direction: Option<Direction>,
+    /// 最大跳数
+    depth: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CloneQuery {
+    /// 一并复制所有可达的下游节点
+    with_chain: Option<bool>,
+    /// 是否复制注解 (默认是)
+    metadata: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RunRequest {
+    input: Option<Value>,
+    /// 执行时间限制 (毫秒)，仅 `POST /node/{id}/run`
+    timeout_ms: Option<u64>,
+}
+
+/// 移动节点的目标位置，见 `POST /node/{id}/move`
+#[derive(Debug, Deserialize)]
+struct MoveRequest {
+    after_id: Option<String>,
+    before_id: Option<String>,
+}
+
+#[cfg(feature = "scripting")]
+#[derive(Debug, Deserialize)]
+struct ValidateScriptRequest {
+    script: String,
 }
 
 // #endregion
@@ -1,58 +1,183 @@
 //! 通用的 RESTful API
 //!
-//! (分单/多人版本，多人版本会需要秘钥、隔离。目前仅支持单人版本。
-//! 不管哪个版本，api都是通用的，多人版本还需要auth/cookies)
+//! 需登录 (`Authorization: Bearer <token>`)，各用户的数据相互隔离: 只能看到自己的项，访问他人的项返回 `403`
 //!
 //! API接口设计：
 //!
-//! - `GET /rest`: 返回所有存储项
+//! - `GET /rest`: 返回所有存储项 (按ID排序)，`?after_id=<id>&limit=10` 为游标分页
+//! - `GET /rest/search?field=data.name&q=...`: 按 `data` 中的字段过滤 (`q` 相等，`q_gt` 大于)
+//! - `GET /rest/aggregate?field=data.status&op=count_by_value`: 按字段统计 (`count_by_value`/`sum`/`avg`/`min`/`max`)
+//! - `POST /rest/query`: 按条件树过滤、排序与分页，见 [`crate::api::query_engine`]
 //! - `POST /rest`: 创建新的存储项
-//! - `PATCH /rest/{id}`: 更新指定ID的存储项
+//! - `PATCH /rest/{id}`: 更新指定ID的存储项，`?set.data.count=42` 只修改个别字段 (值为JSON)
+//!
+//! 单项的响应带 `X-Version` (版本号，每次修改递增)。`PUT`/`PATCH` 可带 `X-Expected-Version`，
+//! 仅当当前版本号与之相等时写入，否则返回 `409 { "current_version": N }` (乐观并发控制)。
+//! `GET` 带 `Last-Modified`，`PUT` 可带 `If-Unmodified-Since`，项在此之后被修改过则返回 `412` (精确到秒)
 //! - `DELETE /rest/{id}`: 删除指定ID的存储项
+//! - `POST /rest/{id}/increment`: 原子地增减 `data` 中的数字 (计数器)
+//! - `GET /rest/{id}/history`: 历史版本 (最近 `ITEM_VERSION_HISTORY` 个，默认10)
+//! - `POST /rest/{id}/history/{version}/restore`: 恢复到历史版本 (作为一次新的修改)
+//! - `POST /rest/import`: 从远程地址导入 (后台执行)，`GET /rest/import/{job_id}` 查询进度
 
 use axum::{
     // error_handling::HandleErrorLayer,// 错误处理中间件
-    extract::{Path, Query, State},      // 请求提取器（路径参数、查询参数、状态）
-    http::StatusCode,                   // HTTP状态码
-    response::IntoResponse,             // 响应转换trait
-    routing::{get},                     // HTTP方法路由
-    Json, Router,                       // JSON处理、路由器
+    extract::{FromRequest, OriginalUri, Path, Query, Request, State}, // 请求提取器（路径参数、查询参数、状态）
+    http::{header::{IF_UNMODIFIED_SINCE, LAST_MODIFIED}, HeaderMap, HeaderName, StatusCode}, // 请求头、HTTP状态码
+    response::{IntoResponse, Response}, // 响应转换trait
+    Extension, Json, Router,            // 请求扩展 (当前用户)、JSON处理、路由器
 };
+use chrono::{DateTime, Utc};            // 时间戳
 use serde::{Deserialize, Serialize};    // JSON序列化/反序列化
-use serde_json::Value;                  // 支持任意JSON数据
-use std::sync::Arc;                     // 线程安全共享指针
+use once_cell::sync::Lazy;              // 全局导入任务
+use serde_json::{json, Value};          // 支持任意JSON数据
+use std::{collections::BTreeMap, sync::Arc, time::Duration}; // 线程安全共享指针
 use uuid::Uuid;                         // 生成唯一ID
+use validator::Validate;                // 请求体校验
 
-use crate::container::rest_store::Container;
+use crate::api::{events, extractors::{validate_data_size, ValidatedJson}, health, json_pointer, pagination::{cursor_response, list_response, page_response}, query_engine::{QueryEngine, QueryRequest}};
+use crate::api::middleware::{jwt::{Claims, JwtAuthLayer}, user_rate_limit::UserRateLimitLayer};
+use crate::config::CONFIG;
+use crate::container::{CasError, Container, SortedContainer, Timestamped, VersionHistory};
+use crate::documented_route;
 
 // #region 相关类型
 
 /// 存储项
 /// - `id` 唯一标识符 (uuid或其他字符串，一般前者配合hashmap会更好，字符串长度应限制?)
 /// - `data` 事项内容 (可以是任意json项(object/string/...))
-#[derive(Debug, Serialize, Clone)]
-struct Item {
+/// - `owner_id` 创建者的用户ID (JWT 的 `sub`)，各用户只能访问自己的项
+/// - `created_at`/`updated_at` 创建/最后修改时间
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Item {
     id: String,
+    #[serde(default)] // 旧数据 (隔离前) 没有此字段，不属于任何用户
+    pub(crate) owner_id: String,
     data: Value,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+pub type ItemContainer = Arc<SortedContainer<Item>>; // 按键排序，支持 `after_id` 游标分页
+/// 各项的历史版本，作为请求扩展注入
+type ItemHistory = Arc<VersionHistory<Item>>;
+
+impl Timestamped for Item {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
 }
-type ItemContainer = Arc<Container<Item>>;
 
 const API_ROOT_STR: &str = "rest/";
 
+/// 期望的版本号 (请求头)
+const EXPECTED_VERSION_HEADER: HeaderName = HeaderName::from_static("x-expected-version");
+/// 当前的版本号 (响应头)
+const VERSION_HEADER: HeaderName = HeaderName::from_static("x-version");
+
+/// 每项保留的历史版本数的环境变量，为0时不记录历史
+const VERSION_HISTORY_ENV: &str = "ITEM_VERSION_HISTORY";
+/// 默认保留的历史版本数
+const DEFAULT_VERSION_HISTORY: usize = 10;
+
+/// 导入的项数上限
+const IMPORT_MAX_ITEMS: usize = 10_000;
+/// 导入的响应体上限
+const IMPORT_MAX_BYTES: usize = 10 * 1024 * 1024;
+/// 导入结果中最多记录的错误数
+const IMPORT_MAX_ERRORS: usize = 100;
+/// 已结束的导入任务的保留时间 (秒)
+const IMPORT_JOB_TTL_SECS: i64 = 60 * 60;
+
+/// 导入用的HTTP客户端
+///
+/// 不跟随重定向: 重定向目标不经过协议检查，可能从 https 跳到 http 或内网地址
+static IMPORT_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("failed to build http client")
+});
+
+/// 导入任务，以任务ID为索引 (重启后清空)。结束超过 [`IMPORT_JOB_TTL_SECS`] 的任务在创建新任务时清理
+static IMPORT_JOBS: Lazy<Arc<Container<ImportJob>>> = Lazy::new(Container::new_arc);
+
+/// 导入任务的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ImportStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// 导入任务
+/// - `imported` 已导入的项数
+/// - `errors` 单项的错误 (如ID属于其他用户)，最多记录100条。整体失败 (如下载失败) 时 `status` 为 `failed`，原因在 `errors` 中
+#[derive(Debug, Clone, Serialize)]
+struct ImportJob {
+    id: String,
+    #[serde(skip)]
+    owner_id: String,
+    url: String,
+    status: ImportStatus,
+    imported: usize,
+    errors: Vec<String>,
+    created_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
 // #endregion
 
 /// 创建 RESTful API 路由
-pub async fn factory_rest_router() -> Router {
-    let data = Container::<Item>::new_arc();
+/// 
+/// - `data` 存储，由调用方创建 (见 [`crate::api::Stores`])。启用 `redis` 且设置了 `REDIS_URL` 时从 Redis 加载并持久化
+pub async fn factory_rest_router(data: ItemContainer) -> Router {
+    #[cfg(feature = "redis")]
+    if let Some(redis) = crate::container::redis_store::RedisContainer::connect_from_env("rest").await {
+        let redis = redis.unwrap_or_else(|err| panic!("failed to connect to redis: {}", err));
+        let check = redis.clone();
+        redis.attach(&data).await.unwrap_or_else(|err| panic!("failed to load from redis: {}", err));
+        health::register(health::FnCheck::new("redis", move || check.check()));
+    }
+    events::publish_changes(&data, "rest", "rest");
+    health::register_storage(&data, "rest");
+    let history = VersionHistory::new_arc(version_history_limit());
+    history.attach(&data);
 
     // axum
     let app = Router::new()
-        .route("/rest", get(rest_id_get).put(rest_id_put).post(rest_id_post).delete(rest_id_delete))
-        .route("/rest/{id}", get(rest_id_get).put(rest_id_put).post(rest_id_post).patch(rest_id_patch).delete(rest_id_delete))
+        .merge(documented_route!(get, "/rest", rest_id_get, "列出存储项"))
+        .merge(documented_route!(put, "/rest", rest_id_put, "以随机ID创建存储项"))
+        .merge(documented_route!(post, "/rest", rest_id_post, "创建存储项"))
+        .merge(documented_route!(delete, "/rest", rest_id_delete, "清空 (禁止，返回 403)"))
+        .merge(documented_route!(get, "/rest/{id}", rest_id_get, "获取存储项"))
+        .merge(documented_route!(put, "/rest/{id}", rest_id_put, "幂等创建/覆盖存储项"))
+        .merge(documented_route!(post, "/rest/{id}", rest_id_post, "以指定ID创建存储项 (已存在时 409)"))
+        .merge(documented_route!(patch, "/rest/{id}", rest_id_patch, "更新存储项"))
+        .merge(documented_route!(delete, "/rest/{id}", rest_id_delete, "删除存储项"))
+        .merge(documented_route!(post, "/rest/{id}/increment", rest_increment_post, "原子地增减 data 中的数字"))
+        .merge(documented_route!(get, "/rest/{id}/history", rest_history_get, "历史版本"))
+        .merge(documented_route!(post, "/rest/{id}/history/{version}/restore", rest_history_restore_post, "恢复到历史版本"))
+        .merge(documented_route!(get, "/rest/search", rest_search_get, "按字段过滤"))
+        .merge(documented_route!(get, "/rest/aggregate", rest_aggregate_get, "按字段统计"))
+        .merge(documented_route!(post, "/rest/query", rest_query_post, "按条件树查询"))
+        .merge(documented_route!(post, "/rest/import", rest_import_post, "从远程地址导入 (后台执行)"))
+        .merge(documented_route!(get, "/rest/import/{job_id}", rest_import_get, "查询导入任务"))
+        .route_layer(UserRateLimitLayer) // 在鉴权内层，按用户限流
+        .route_layer(JwtAuthLayer) // 需登录，各用户的数据相互隔离
+        .layer(Extension(history))
         .with_state(data); // 注入共享状态（数据库）
     app
 }
 
+/// 每项保留的历史版本数，见 [`VERSION_HISTORY_ENV`]
+fn version_history_limit() -> usize {
+    std::env::var(VERSION_HISTORY_ENV).ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_VERSION_HISTORY)
+}
+
 /**
  * GET /rest/{id?} 获取项
  * 
@@ -63,28 +188,193 @@ pub async fn factory_rest_router() -> Router {
 async fn rest_id_get(
     id: Option<Path<String>>,
     pagination: Query<GetPagination>,
+    OriginalUri(uri): OriginalUri,
     State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
 ) -> impl IntoResponse {
     match id {
         // 有id，则查找特定ID项
         Some(Path(id)) => {
-            tracing::debug!("GET /{}{}", API_ROOT_STR, id); // TODO 用统一的中间件来处理
-            data.get_by_id(&id)
-                .map_or_else(
-                    || StatusCode::NOT_FOUND.into_response(),
-                    |result| Json(result.clone()).into_response()
-                )
-        }
-        // 无id，返回所有项
+            match data.versioned_get(&id) {
+                None => StatusCode::NOT_FOUND.into_response(),
+                Some((result, _)) if result.owner_id != claims.sub => StatusCode::FORBIDDEN.into_response(),
+                Some((result, version)) => (
+                    [(VERSION_HEADER, version.to_string()), (LAST_MODIFIED, httpdate::fmt_http_date(result.updated_at.into()))],
+                    Json(result),
+                ).into_response(),
+            }
+        }
+        // 无id，返回所有项 (按ID排序)
         None => {
-            tracing::debug!("GET /{}", API_ROOT_STR);
-            let result: Vec<Item> = data.get_all()
-                .values()
-                .skip(pagination.offset.unwrap_or(0))
-                .take(pagination.limit.unwrap_or(usize::MAX))
-                .cloned()
-                .collect::<Vec<_>>();
-            Json(result).into_response()
+            if let Some(after_id) = &pagination.after_id {
+                let limit = pagination.limit.unwrap_or(usize::MAX);
+                let page = data.range_after(after_id, limit, |item| item.owner_id == claims.sub);
+                let last_key = page.last().map(|item| item.id.clone());
+                return cursor_response(&uri, page, pagination.limit, last_key.as_deref());
+            }
+            let (page, total) = data.get_page_sorted_by(
+                |item| item.owner_id == claims.sub,
+                |item| item.id.clone(), // 与游标分页的顺序一致
+                pagination.offset.unwrap_or(0),
+                pagination.limit.unwrap_or(usize::MAX),
+            );
+            page_response(&uri, page, total, pagination.offset, pagination.limit)
+        }
+    }
+}
+
+/**
+ * GET /rest/search 按字段过滤项
+ * 
+ * - `field` 字段路径，相对于整个项，如 `data.name` 或 JSON Pointer `/data/name`
+ * - `q` 字段值等于 (字符串原样比较，数字/布尔/null 按其文本)
+ * - `q_gt` 字段为数字且大于该值
+ * - 分页同 `GET /rest`
+ */
+async fn rest_search_get(
+    Query(query): Query<SearchQuery>,
+    OriginalUri(uri): OriginalUri,
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response();
+    let pointer = match json_pointer::parse_field(&query.field) {
+        Ok(pointer) => pointer,
+        Err(err) => return bad_request(format!("invalid field: {}", err)),
+    };
+    if query.q.is_none() && query.q_gt.is_none() {
+        return bad_request("q or q_gt is required".to_string());
+    }
+    let q_gt = match query.q_gt.as_deref().map(str::parse::<f64>) {
+        None => None,
+        Some(Ok(q_gt)) if q_gt.is_finite() => Some(q_gt),
+        Some(_) => return bad_request("q_gt must be a number".to_string()),
+    };
+
+    let result = data.find_where(|item| {
+        if item.owner_id != claims.sub {
+            return false;
+        }
+        let Ok(root) = serde_json::to_value(item) else { return false };
+        let Some(value) = json_pointer::value_at_pointer(&root, &pointer) else { return false };
+        query.q.as_deref().is_none_or(|q| value_equals(value, q))
+            && q_gt.is_none_or(|q_gt| value.as_f64().is_some_and(|value| value > q_gt))
+    });
+    list_response(&uri, result, query.offset, query.limit)
+}
+
+/// 字段值与查询参数的文本是否相等
+fn value_equals(value: &Value, q: &str) -> bool {
+    match value {
+        Value::String(value) => value == q,
+        Value::Number(value) => q.parse::<f64>().is_ok_and(|q| value.as_f64() == Some(q)),
+        Value::Bool(value) => q == if *value { "true" } else { "false" },
+        Value::Null => q == "null",
+        _ => false,
+    }
+}
+
+/**
+ * GET /rest/aggregate 按字段统计
+ * 
+ * - `field` 字段路径，同 `GET /rest/search`
+ * - `op` 统计方式
+ *   - `count_by_value` 按字段值分组计数，返回 `{ "completed": 5, "pending": 3 }`
+ *   - `sum`/`avg`/`min`/`max` 对数字字段计算，返回单个数字 (忽略非数字的值)
+ */
+async fn rest_aggregate_get(
+    Query(query): Query<AggregateQuery>,
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response();
+    let pointer = match json_pointer::parse_field(&query.field) {
+        Ok(pointer) => pointer,
+        Err(err) => return bad_request(format!("invalid field: {}", err)),
+    };
+    let Some(op) = AggregateOp::parse(&query.op) else {
+        return bad_request(format!("unsupported op: {}", query.op));
+    };
+
+    // 一次遍历，同时累计各种统计值
+    let mut found = false;
+    let mut groups: BTreeMap<String, usize> = BTreeMap::new();
+    let (mut count, mut sum, mut min, mut max) = (0usize, 0.0f64, f64::INFINITY, f64::NEG_INFINITY);
+    for item in data.values() {
+        if item.owner_id != claims.sub {
+            continue;
+        }
+        let Ok(root) = serde_json::to_value(&item) else { continue };
+        let Some(value) = json_pointer::value_at_pointer(&root, &pointer) else { continue };
+        found = true;
+        match value {
+            Value::String(value) => *groups.entry(value.clone()).or_default() += 1,
+            Value::Number(_) | Value::Bool(_) | Value::Null => *groups.entry(value.to_string()).or_default() += 1,
+            _ => {}
+        }
+        if let Some(number) = value.as_f64() {
+            count += 1;
+            sum += number;
+            min = min.min(number);
+            max = max.max(number);
+        }
+    }
+
+    if !found {
+        return bad_request(format!("field not found: {}", query.field));
+    }
+    match op {
+        AggregateOp::CountByValue => Json(groups).into_response(),
+        _ if count == 0 => bad_request(format!("no numeric values at field: {}", query.field)),
+        AggregateOp::Sum => Json(sum).into_response(),
+        AggregateOp::Avg => Json(sum / count as f64).into_response(),
+        AggregateOp::Min => Json(min).into_response(),
+        AggregateOp::Max => Json(max).into_response(),
+    }
+}
+
+/**
+ * POST /rest/query 按条件树查询
+ * 
+ * 请求体如 `{ "filter": { "and": [{ "field": "data.count", "op": "gt", "value": 5 }] }, "sort": { "field": "data.name", "order": "asc" }, "limit": 20 }`，
+ * 格式见 [`crate::api::query_engine`]。返回 `{ "items": [...], "total": N }`，`total` 为分页前的匹配数。
+ * 字段路径有误或 `in` 的值不是数组时返回 `400`
+ * 
+ * - `input` JSON请求体
+ */
+async fn rest_query_post(
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<QueryRequest>,
+) -> impl IntoResponse {
+    let engine = match QueryEngine::new(input) {
+        Ok(engine) => engine,
+        Err(err) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": err }))).into_response(),
+    };
+    let items = data.find_where(|item| item.owner_id == claims.sub); // 按ID排序，排序值相同时保持此顺序
+    Json(engine.execute(items)).into_response()
+}
+
+/// 统计方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AggregateOp {
+    CountByValue,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateOp {
+    /// 从查询参数解析 (手动解析以返回JSON错误)
+    fn parse(op: &str) -> Option<Self> {
+        match op {
+            "count_by_value" => Some(Self::CountByValue),
+            "sum" => Some(Self::Sum),
+            "avg" => Some(Self::Avg),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            _ => None,
         }
     }
 }
@@ -94,33 +384,76 @@ async fn rest_id_get(
  * 
  * - `id` 路径中的ID (可选, 无则随机id)
  * - `db` 共享数据库状态
+ * - `headers` 可带 `X-Expected-Version` (为0表示仅在不存在时创建)，
+ *   可带 `If-Unmodified-Since` (HTTP-date)，项在此之后被修改过则返回 `412`。
+ *   HTTP-date 只精确到秒，与读取同一秒内的修改检测不到，需要可靠的并发控制时用 `X-Expected-Version`
  * - `input` JSON请求体
  */
 async fn rest_id_put(
     id: Option<Path<String>>,
     State(data): State<ItemContainer>,
-    Json(input): Json<RequestType>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    ValidatedJson(input): ValidatedJson<RequestType>,
 ) -> impl IntoResponse {
-    let id = id
-        .map_or_else(
-            || {
-                let id = Uuid::new_v4().to_string();
-                tracing::debug!("PUT /{}, create id:{}", API_ROOT_STR, id);
-                id
-            },
-            |p| {
-                tracing::debug!("PUT /{}{}", API_ROOT_STR, p.0);
-                p.0
+    let id = id.map_or_else(|| Uuid::new_v4().to_string(), |p| p.0);
+    let expected_version = match expected_version(&headers) {
+        Ok(expected_version) => expected_version,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+    let unmodified_since = match if_unmodified_since(&headers) {
+        Ok(unmodified_since) => unmodified_since,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+
+    let now = Utc::now();
+    let value = input.data.unwrap_or(Value::Null);
+    let item = |created_at| Item { id: id.clone(), owner_id: claims.sub.clone(), data: value.clone(), created_at, updated_at: now };
+    loop {
+        // 所有者、修改时间与版本号的检查和写入在同一次写锁内，检查之后的并发修改不会被覆盖
+        let result = data.versioned_update_by_id(&id, |old, version| {
+            if old.owner_id != claims.sub {
+                return Err(PutError::Forbidden);
+            }
+            if let Some(unmodified_since) = unmodified_since
+                && old.updated_at.timestamp() > unmodified_since.timestamp()
+            {
+                return Err(PutError::Modified(old.updated_at));
             }
-        );
+            if let Some(expected_version) = expected_version
+                && expected_version != version
+            {
+                return Err(PutError::VersionMismatch(version));
+            }
+            Ok(item(old.created_at)) // 覆盖时保留创建时间
+        });
+        match result {
+            Some(Ok((item, version))) => return put_response(item, version),
+            Some(Err(PutError::Forbidden)) => return StatusCode::FORBIDDEN.into_response(),
+            Some(Err(PutError::Modified(updated_at))) => return precondition_failed(updated_at),
+            Some(Err(PutError::VersionMismatch(current_version))) => return version_mismatch(current_version),
+            None => {}
+        }
+        // 不存在的项没有修改时间，`If-Unmodified-Since` 不适用
+        if expected_version.is_some_and(|expected_version| expected_version != 0) {
+            return version_mismatch(0);
+        }
+        let created = item(now);
+        if data.put_if_absent(&id, created.clone()).is_ok() {
+            let version = data.versioned_get(&id).map_or(0, |(_, version)| version);
+            return put_response(created, version);
+        }
+        // 检查之后被其他请求创建，按修改重试
+    }
+}
 
-    let item = Item {
-        id: id.clone(),
-        data: input.data.unwrap_or(Value::Null),
-    };
-    
-    data.put_by_id(&id, item.clone());
-    (StatusCode::CREATED, Json(item))
+/// 覆盖写入失败的原因
+enum PutError {
+    Forbidden,
+    /// 在 `If-Unmodified-Since` 之后被修改过，附最后修改时间
+    Modified(DateTime<Utc>),
+    /// 版本号不匹配，附当前版本号
+    VersionMismatch(u64),
 }
 
 /**
@@ -133,61 +466,258 @@ async fn rest_id_put(
 async fn rest_id_post(
     id: Option<Path<String>>,
     State(data): State<ItemContainer>,
-    Json(input): Json<RequestType>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<RequestType>,
 ) -> impl IntoResponse {
-    let id = id
-        .map_or_else(
-            || {
-                let id = Uuid::new_v4().to_string();
-                tracing::debug!("POST /{}, create id:{}", API_ROOT_STR, id);
-                id
-            },
-            |p| {
-                tracing::debug!("POST /{}{}", API_ROOT_STR, p.0);
-                p.0
-            }
-        );
-
-    data.get_by_id(&id)
-        .map_or_else(
-            || {
-                let item = Item {
-                    id: id.clone(),
-                    data: input.data.unwrap_or(Value::Null),
-                };
-                data.put_by_id(&id, item.clone());
-                (StatusCode::CREATED, Json(item))
-            },
-            |result| (StatusCode::CONFLICT, Json(result))
-        )
+    let id = id.map_or_else(|| Uuid::new_v4().to_string(), |p| p.0);
+
+    let now = Utc::now();
+    let item = Item {
+        id: id.clone(),
+        owner_id: claims.sub.clone(),
+        data: input.data.unwrap_or(Value::Null),
+        created_at: now,
+        updated_at: now,
+    };
+    // 检查与插入在同一次写锁内，并发创建同一ID时只有一个成功
+    match data.put_if_absent(&id, item.clone()) {
+        Ok(()) => (StatusCode::CREATED, Json(item)).into_response(),
+        Err(existing) if existing.owner_id != claims.sub => StatusCode::FORBIDDEN.into_response(),
+        Err(existing) => (StatusCode::CONFLICT, Json(existing)).into_response(),
+    }
 }
 
 /**
  * PATCH /rest/{id} 更新项 (缺失策略: 404, 而非新建)
  * 
+ * 带 `?set.<字段>=<JSON值>` 时只修改这些字段 (如 `?set.data.count=42`)，不读取请求体，见 [`rest_fields_patch`]
+ * 
  * - `id` 路径中的ID (可选, 无则随机id)
  * - `db` 共享数据库状态
- * - `input` JSON请求体
+ * - `query` 查询参数
+ * - `req` 请求，可带 `X-Expected-Version`，请求体为JSON
  */
 async fn rest_id_patch(
     Path(id): Path<String>,
     State(data): State<ItemContainer>,
-    Json(input): Json<RequestType>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<Vec<(String, String)>>,
+    req: Request,
 ) -> impl IntoResponse {
-    tracing::debug!("PATCH /{}{}", API_ROOT_STR, id);
+    let expected_version = match expected_version(req.headers()) {
+        Ok(expected_version) => expected_version,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+    let fields = match set_params(&query) {
+        Ok(fields) => fields,
+        Err(err) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": err }))).into_response(),
+    };
+    if !fields.is_empty() {
+        if expected_version.is_some() {
+            let err = "X-Expected-Version is not supported with set. parameters";
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": err }))).into_response();
+        }
+        return rest_fields_patch(&data, &id, &claims, fields);
+    }
+    let ValidatedJson(input) = match ValidatedJson::<RequestType>::from_request(req, &()).await {
+        Ok(input) => input,
+        Err(rejection) => return rejection,
+    };
 
-    let old_value = data.get_by_id(&id);
-    if old_value.is_none() {
+    let Some(old_value) = data.get_by_id(&id) else {
         return StatusCode::NOT_FOUND.into_response()
     };
+    if old_value.owner_id != claims.sub {
+        return StatusCode::FORBIDDEN.into_response();
+    }
 
     let new_value = Item {
         id: id.clone(),
-        data: input.data.unwrap_or(Value::default())
+        owner_id: old_value.owner_id,
+        data: input.data.unwrap_or(Value::default()),
+        created_at: old_value.created_at,
+        updated_at: Utc::now(),
+    };
+
+    put_item(&data, new_value, expected_version, StatusCode::OK)
+}
+
+/// 查询参数中 `set.` 开头的项: 字段 (转为 JSON Pointer) 与 JSON 值，按出现顺序
+///
+/// 字段须在 `data` 下 (其余字段不可修改)，格式有误或值不是合法的JSON时返回错误信息
+fn set_params(query: &[(String, String)]) -> Result<Vec<(String, Value)>, String> {
+    query.iter()
+        .filter_map(|(key, value)| Some((key.strip_prefix("set.")?, value)))
+        .map(|(field, value)| {
+            let pointer = json_pointer::parse_field(field)
+                .map_err(|err| format!("invalid field set.{}: {}", field, err))?;
+            if pointer != "/data" && !pointer.starts_with("/data/") {
+                return Err(format!("invalid field set.{}: only fields under data can be set", field));
+            }
+            let value = serde_json::from_str(value)
+                .map_err(|_| format!("invalid JSON value for set.{}: {}", field, value))?;
+            Ok((pointer, value))
+        })
+        .collect()
+}
+
+/**
+ * PATCH /rest/{id}?set.<字段>=<JSON值> 修改个别字段
+ * 
+ * 各字段按顺序在同一次 `update_by_id` 内写入 (原子)，中间缺失的对象会补全。
+ * 写入失败 (如经过的值不是对象或数组、下标越界) 时返回 `400`，不做任何修改
+ * 
+ * - `fields` 见 [`set_params`]
+ */
+fn rest_fields_patch(data: &ItemContainer, id: &str, claims: &Claims, fields: Vec<(String, Value)>) -> Response {
+    let result = data.update_by_id(id, |old| {
+        if old.owner_id != claims.sub {
+            return Err(SetFieldsError::Forbidden);
+        }
+        let mut root = json!({ "data": old.data });
+        for (pointer, value) in &fields {
+            json_pointer::set_at_pointer(&mut root, pointer, value.clone())
+                .map_err(|err| SetFieldsError::Invalid(format!("cannot set {}: {}", pointer, err)))?;
+        }
+        let mut new = old.clone();
+        new.data = root["data"].take();
+        new.updated_at = Utc::now();
+        Ok(new)
+    });
+    match result {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(Err(SetFieldsError::Forbidden)) => StatusCode::FORBIDDEN.into_response(),
+        Some(Err(SetFieldsError::Invalid(err))) => (StatusCode::BAD_REQUEST, Json(json!({ "error": err }))).into_response(),
+        Some(Ok((_, new))) => {
+            let version = data.versioned_get(id).map_or(0, |(_, version)| version);
+            ([(VERSION_HEADER, version.to_string())], Json(new)).into_response()
+        }
+    }
+}
+
+/// 修改个别字段失败的原因
+enum SetFieldsError {
+    Forbidden,
+    Invalid(String),
+}
+
+/// 解析 `X-Expected-Version` (可选)，格式错误时返回错误信息 (`400`)
+fn expected_version(headers: &HeaderMap) -> Result<Option<u64>, Json<Value>> {
+    let Some(value) = headers.get(EXPECTED_VERSION_HEADER) else {
+        return Ok(None);
+    };
+    match value.to_str().ok().and_then(|value| value.trim().parse().ok()) {
+        Some(version) => Ok(Some(version)),
+        None => Err(Json(json!({ "error": "invalid expected version" }))),
+    }
+}
+
+/// 从 `If-Unmodified-Since` 请求头解析 HTTP-date，没有时为 `None`
+fn if_unmodified_since(headers: &HeaderMap) -> Result<Option<DateTime<Utc>>, Json<Value>> {
+    let Some(value) = headers.get(IF_UNMODIFIED_SINCE) else {
+        return Ok(None);
+    };
+    match value.to_str().ok().and_then(|value| httpdate::parse_http_date(value.trim()).ok()) {
+        Some(time) => Ok(Some(time.into())),
+        None => Err(Json(json!({ "error": "invalid If-Unmodified-Since" }))),
+    }
+}
+
+/// `412`: 项在客户端给出的时间之后被修改过，带当前的 `Last-Modified`
+fn precondition_failed(updated_at: DateTime<Utc>) -> Response {
+    (
+        StatusCode::PRECONDITION_FAILED,
+        [(LAST_MODIFIED, httpdate::fmt_http_date(updated_at.into()))],
+        Json(json!({ "error": "precondition failed", "updated_at": updated_at })),
+    ).into_response()
+}
+
+/// 写入项，响应带新的版本号。有期望的版本号时比较并替换，不匹配返回 `409`
+fn put_item(data: &ItemContainer, item: Item, expected_version: Option<u64>, status: StatusCode) -> Response {
+    let result = match expected_version {
+        Some(expected_version) => data.versioned_put(&item.id, item.clone(), expected_version),
+        None => {
+            data.put_by_id(&item.id, item.clone());
+            Ok(data.versioned_get(&item.id).map_or(0, |(_, version)| version))
+        }
     };
+    match result {
+        Ok(version) => (status, [(VERSION_HEADER, version.to_string())], Json(item)).into_response(),
+        Err(CasError { current_version }) => version_mismatch(current_version),
+    }
+}
+
+/// PUT 的响应: `201`，带新的版本号
+fn put_response(item: Item, version: u64) -> Response {
+    (StatusCode::CREATED, [(VERSION_HEADER, version.to_string())], Json(item)).into_response()
+}
+
+fn version_mismatch(current_version: u64) -> Response {
+    (
+        StatusCode::CONFLICT,
+        Json(json!({ "error": "version mismatch", "current_version": current_version })),
+    ).into_response()
+}
+
+/**
+ * POST /rest/{id}/increment 原子地增减数字
+ * 
+ * 读取、修改、写回在同一次写锁内完成，并发的增减不会丢失
+ * 
+ * - `id` 路径中的ID
+ * - `input` JSON请求体，`path` 为 `data` 中的 JSON Pointer (如 `/count`)，`by` 为增量 (默认1，可为负数或小数)
+ */
+async fn rest_increment_post(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<IncrementRequest>,
+) -> impl IntoResponse {
+    let bad_request = |message: &str| (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response();
+    if let Err(err) = json_pointer::parse_pointer(&input.path) {
+        return bad_request(&format!("invalid path: {}", err));
+    }
+
+    let result = data.update_by_id(&id, |old| {
+        if old.owner_id != claims.sub {
+            return Err(IncrementError::Forbidden);
+        }
+        let mut new = old.clone();
+        let counter = new.data.pointer_mut(&input.path).ok_or(IncrementError::NotNumber)?;
+        *counter = add_number(counter, &input.by)?;
+        new.updated_at = Utc::now();
+        Ok(new)
+    });
+    match result {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(Err(IncrementError::Forbidden)) => StatusCode::FORBIDDEN.into_response(),
+        Some(Err(IncrementError::NotNumber)) => bad_request("value at path is not a number"),
+        Some(Err(IncrementError::Overflow)) => bad_request("counter overflow"),
+        Some(Ok((old, new))) => Json(json!({
+            "id": id,
+            "previous": old.data.pointer(&input.path),
+            "current": new.data.pointer(&input.path),
+        })).into_response(),
+    }
+}
+
+/// 增减失败的原因
+enum IncrementError {
+    Forbidden,
+    NotNumber,
+    Overflow,
+}
 
-    data.put_by_id(&id, new_value.clone());
-    Json(new_value).into_response()
+/// 数字相加: 两者都是整数时按整数 (溢出报错)，否则按浮点数
+fn add_number(value: &Value, by: &serde_json::Number) -> Result<Value, IncrementError> {
+    let Value::Number(value) = value else {
+        return Err(IncrementError::NotNumber);
+    };
+    if let (Some(value), Some(by)) = (value.as_i64(), by.as_i64()) {
+        return value.checked_add(by).map(Value::from).ok_or(IncrementError::Overflow);
+    }
+    let sum = value.as_f64().unwrap_or_default() + by.as_f64().unwrap_or_default();
+    serde_json::Number::from_f64(sum).map(Value::Number).ok_or(IncrementError::Overflow)
 }
 
 /**
@@ -199,9 +729,9 @@ async fn rest_id_patch(
 async fn rest_id_delete(
     id: Option<Path<String>>,
     State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
 ) -> impl IntoResponse {
     let id = if let Some(id) = id {
-        tracing::debug!("DELETE /{}{}", API_ROOT_STR, id.0);
         id.0
     } else {
         tracing::warn!("DELETE /{}, clearing is a high-risk operation", API_ROOT_STR);
@@ -209,13 +739,234 @@ async fn rest_id_delete(
         return StatusCode::FORBIDDEN.into_response();
     };
 
-    let result = data.delete_by_id(&id);
-    match result {
-        Some(_) => StatusCode::NO_CONTENT.into_response(),
+    match data.get_by_id(&id) {
         None => StatusCode::NOT_FOUND.into_response(),
+        Some(old_value) if old_value.owner_id != claims.sub => StatusCode::FORBIDDEN.into_response(),
+        Some(_) => {
+            data.delete_by_id(&id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+    }
+}
+
+// #region 历史版本
+
+/**
+ * GET /rest/{id}/history 历史版本
+ * 
+ * 返回 `[{ "version": 1, "data": ..., "updated_at": "..." }]`，按版本号升序，不含当前值 (当前值的版本号为最后一项加一)。
+ * `updated_at` 为该版本写入的时间
+ * 
+ * - `id` 路径中的ID
+ */
+async fn rest_history_get(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    Extension(history): Extension<ItemHistory>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    match data.get_by_id(&id) {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(item) if item.owner_id != claims.sub => StatusCode::FORBIDDEN.into_response(),
+        Some(_) => {
+            let versions: Vec<Value> = history.versions(&id).into_iter()
+                .map(|record| json!({
+                    "version": record.version,
+                    "data": record.value.data,
+                    "updated_at": record.value.updated_at,
+                }))
+                .collect();
+            Json(versions).into_response()
+        }
     }
 }
 
+/**
+ * POST /rest/{id}/history/{version}/restore 恢复到历史版本
+ * 
+ * 以该版本的 `data` 写入 (当前值照常存为新的历史版本，因此恢复本身也可撤销)，返回写入后的项。
+ * 版本不存在或已丢弃时返回 `404 { "error": "version not found" }`
+ * 
+ * - `id` 路径中的ID
+ * - `version` 版本号
+ * - `headers` 可带 `X-Expected-Version`，同 `PUT`
+ */
+async fn rest_history_restore_post(
+    Path((id, version)): Path<(String, u64)>,
+    State(data): State<ItemContainer>,
+    Extension(history): Extension<ItemHistory>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let expected_version = match expected_version(&headers) {
+        Ok(expected_version) => expected_version,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+
+    let Some(old_value) = data.get_by_id(&id) else {
+        return StatusCode::NOT_FOUND.into_response()
+    };
+    if old_value.owner_id != claims.sub {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let Some(record) = history.get(&id, version) else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "version not found" }))).into_response();
+    };
+
+    let item = Item {
+        data: record.value.data,
+        updated_at: Utc::now(),
+        ..old_value
+    };
+    put_item(&data, item, expected_version, StatusCode::OK)
+}
+
+// #endregion
+
+// #region 导入
+
+/**
+ * POST /rest/import 从远程地址导入
+ * 
+ * 下载 `url` (须为 JSON 数组，元素为对象)，以 `id_field` 字段为ID (缺失时随机生成) 存为当前用户的项，
+ * 已存在的本人的项被覆盖。在后台执行，立即返回 `202` 及任务ID
+ * 
+ * - `input` JSON请求体
+ */
+async fn rest_import_post(
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<ImportRequest>,
+) -> impl IntoResponse {
+    let scheme_allowed = input.url.starts_with("https://") || (CONFIG.import_allow_http && input.url.starts_with("http://"));
+    if !scheme_allowed {
+        let message = if CONFIG.import_allow_http { "url must use http or https" } else { "url must use https" };
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response();
+    }
+
+    let now = Utc::now();
+    IMPORT_JOBS.delete_where(|job| job.finished_at.is_some_and(|at| (now - at).num_seconds() > IMPORT_JOB_TTL_SECS)); // 顺带清理
+    let job = ImportJob {
+        id: Uuid::new_v4().to_string(),
+        owner_id: claims.sub,
+        url: input.url,
+        status: ImportStatus::Running,
+        imported: 0,
+        errors: Vec::new(),
+        created_at: now,
+        finished_at: None,
+    };
+    IMPORT_JOBS.put_by_id(&job.id, job.clone());
+    let location = format!("/rest/import/{}", job.id);
+    let response = (StatusCode::ACCEPTED, [(axum::http::header::LOCATION, location)], Json(job.clone())).into_response();
+
+    tokio::spawn(async move {
+        let mut job = job;
+        match fetch_import(&job.url).await {
+            Ok(values) => {
+                import_items(&data, &mut job, values, &input.id_field);
+                job.status = ImportStatus::Completed;
+            }
+            Err(err) => {
+                tracing::warn!("import {} failed: {}", job.id, err);
+                job.errors.push(err);
+                job.status = ImportStatus::Failed;
+            }
+        }
+        job.finished_at = Some(Utc::now());
+        tracing::info!("import {} finished, {} items imported", job.id, job.imported);
+        IMPORT_JOBS.put_by_id(&job.id.clone(), job);
+    });
+    response
+}
+
+/**
+ * GET /rest/import/{job_id} 查询导入任务
+ * 
+ * - `job_id` 任务ID，其他用户的任务返回 `403`
+ */
+async fn rest_import_get(
+    Path(job_id): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    match IMPORT_JOBS.get_by_id(&job_id) {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(job) if job.owner_id != claims.sub => StatusCode::FORBIDDEN.into_response(),
+        Some(job) => Json(job).into_response(),
+    }
+}
+
+/// 下载并解析为数组，超过大小或项数上限时失败
+async fn fetch_import(url: &str) -> Result<Vec<Value>, String> {
+    let response = IMPORT_CLIENT.get(url).send().await
+        .map_err(|err| format!("failed to fetch: {}", err))?;
+    if response.status().is_redirection() {
+        return Err(format!("failed to fetch: redirects are not followed ({})", response.status()));
+    }
+    let response = response.error_for_status().map_err(|err| format!("failed to fetch: {}", err))?;
+    if response.content_length().is_some_and(|len| len as usize > IMPORT_MAX_BYTES) {
+        return Err("response exceeds 10 MiB".to_string());
+    }
+    // 分块读取，Content-Length 缺失或不实时也不会超出上限
+    let mut response = response;
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|err| format!("failed to fetch: {}", err))? {
+        if body.len() + chunk.len() > IMPORT_MAX_BYTES {
+            return Err("response exceeds 10 MiB".to_string());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let values: Vec<Value> = serde_json::from_slice(&body)
+        .map_err(|err| format!("response is not a JSON array: {}", err))?;
+    if values.len() > IMPORT_MAX_ITEMS {
+        return Err(format!("too many items ({}, max {})", values.len(), IMPORT_MAX_ITEMS));
+    }
+    Ok(values)
+}
+
+/// 把下载的各项存为任务所属用户的项，单项的错误记入任务
+fn import_items(data: &ItemContainer, job: &mut ImportJob, values: Vec<Value>, id_field: &str) {
+    let push_error = |errors: &mut Vec<String>, error: String| {
+        if errors.len() < IMPORT_MAX_ERRORS {
+            errors.push(error);
+        }
+    };
+    for (index, value) in values.into_iter().enumerate() {
+        if !value.is_object() {
+            push_error(&mut job.errors, format!("item {}: not an object", index));
+            continue;
+        }
+        let id = match value.get(id_field) {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            _ => Uuid::new_v4().to_string(),
+        };
+        let old_value = data.get_by_id(&id);
+        if old_value.as_ref().is_some_and(|old| old.owner_id != job.owner_id) {
+            push_error(&mut job.errors, format!("item {}: id {} belongs to another user", index, id));
+            continue;
+        }
+        if let Err(err) = validate_data_size(&value) {
+            push_error(&mut job.errors, format!("item {}: {}", index, err));
+            continue;
+        }
+
+        let now = Utc::now();
+        let item = Item {
+            id: id.clone(),
+            owner_id: job.owner_id.clone(),
+            data: value,
+            created_at: old_value.map_or(now, |old| old.created_at),
+            updated_at: now,
+        };
+        data.put_by_id(&id, item);
+        job.imported += 1;
+    }
+}
+
+// #endregion
+
 // #region api struct
 
 #[derive(Debug, Deserialize, Default)]
@@ -224,10 +975,59 @@ struct GetPagination {
     offset: Option<usize>,
     /// 数量限制
     limit: Option<usize>,
+    /// 游标: 从该ID之后开始 (不含)，空串表示从头开始。指定时忽略 `offset`
+    after_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct IncrementRequest {
+    /// `data` 中的 JSON Pointer
+    path: String,
+    /// 增量
+    #[serde(default = "default_increment")]
+    by: serde_json::Number,
+}
+
+fn default_increment() -> serde_json::Number {
+    1.into()
 }
 
 #[derive(Debug, Deserialize)]
+struct AggregateQuery {
+    /// 字段路径
+    field: String,
+    /// 统计方式
+    op: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    /// 字段路径
+    field: String,
+    /// 等于
+    q: Option<String>,
+    /// 大于 (数字)，手动解析以返回JSON错误
+    q_gt: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct ImportRequest {
+    #[validate(url(message = "url must be a valid URL"))]
+    url: String,
+    /// 作为ID的字段
+    #[serde(default = "default_id_field")]
+    id_field: String,
+}
+
+fn default_id_field() -> String {
+    "id".to_string()
+}
+
+#[derive(Debug, Deserialize, Validate)]
 struct RequestType {
+    #[validate(custom(function = "validate_data_size"))]
     data: Option<Value>,
 }
 
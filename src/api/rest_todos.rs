@@ -2,24 +2,42 @@
 //!
 //! API接口设计：
 //!
-//! - `GET /todos`: 返回所有待办事项的JSON列表
+//! - `GET /todos`: 返回所有待办事项的JSON列表 (可用 `?completed=true` 过滤，总数见 `X-Total-Count`)。
+//!   `?after=<id>&limit=20` 为游标分页，返回 `{ "items": [...], "total": 3, "next_cursor": "<id>" }`
+//! - `GET /todos/{id}`: 获取指定ID的待办事项 (进程内缓存5秒，修改时失效，见 [`crate::api::cache`])
 //! - `POST /todos`: 创建新的待办事项
 //! - `PATCH /todos/{id}`: 更新指定ID的待办事项
+//! - `PATCH /todos/{id}/toggle`: 切换完成状态 (无需请求体)
+//! - `PATCH /todos/toggle-all?completed=true`: 把本人的所有项设为该完成状态
+//! - `PATCH /todos/{id}/move`: 调整顺序 (拖放排序)，列表按 `position` 升序返回
 //! - `DELETE /todos/{id}`: 删除指定ID的待办事项
+//! - `POST /todos/{id}/share`: 生成只读分享链接 (7天有效)，`DELETE /todos/{id}/share` 撤销该项的所有分享
+//! - `GET /shared/todos/{share_token}`: 通过分享令牌查看 (无需登录)
+//!
+//! 需登录 (`Authorization: Bearer <token>`)，各用户只能看到自己的项，访问他人的项返回 `403`。
+//! 分享接口除外，见 [`factory_shared_todos_router`]
 
 use axum::{
     // error_handling::HandleErrorLayer,// 错误处理中间件
-    extract::{Path, Query, State},      // 请求提取器（路径参数、查询参数、状态）
-    http::StatusCode,                   // HTTP状态码
-    response::{IntoResponse},           // 响应转换trait
-    routing::{get},                     // HTTP方法路由
-    Json, Router,                       // JSON处理、路由器
+    extract::{OriginalUri, Path, Query, State},      // 请求提取器（路径参数、查询参数、状态）
+    http::{StatusCode, Uri},            // HTTP状态码、请求地址 (生成翻页链接)
+    response::{IntoResponse, Response}, // 响应转换trait
+    Extension, Json, Router,            // 请求扩展 (当前用户)、JSON处理、路由器
 };
+use chrono::{DateTime, Utc};            // 时间戳
 use serde::{Deserialize, Serialize};    // JSON序列化/反序列化
-use std::sync::Arc;                     // 线程安全共享指针
+use serde_json::json;
+use password_hash::rand_core::{OsRng, RngCore}; // 分享令牌
+use sha2::{Digest, Sha256};             // 分享令牌只存哈希
+use once_cell::sync::Lazy;              // 全局缓存
+use std::{sync::Arc, time::Duration};   // 线程安全共享指针、缓存有效期
 use uuid::Uuid;                         // 生成唯一ID
+use validator::Validate;                // 请求体校验
 
-use crate::container::rest_store::Container;
+use crate::api::{cache::{cached_response, ResponseCache}, events, extractors::ValidatedJson, health, pagination::{cursor_page_response, page_response, CursorPage}};
+use crate::api::middleware::{jwt::{Claims, JwtAuthLayer}, user_rate_limit::UserRateLimitLayer};
+use crate::container::{Container, HookEvent, Timestamped};
+use crate::documented_route;
 
 // #region 相关类型
 
@@ -27,26 +45,112 @@ use crate::container::rest_store::Container;
 /// - `id` 唯一标识符 (uuid或其他字符串，一般前者配合hashmap会更好，字符串长度应限制?)
 /// - `data` 事项内容
 /// - `completed` 完成状态
-#[derive(Debug, Serialize, Clone)]
-struct Item {
+/// - `position` 排序位置，列表按此升序 (相同时按创建时间)。新建的项为 `u32::MAX` (排在最后)，见 [`todos_id_move`]
+/// - `owner_id` 创建者的用户ID (JWT 的 `sub`)，各用户只能访问自己的项
+/// - `created_at`/`updated_at` 创建/最后修改时间
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Item {
     id: String,
+    #[serde(default)] // 旧数据 (隔离前) 没有此字段，不属于任何用户
+    pub(crate) owner_id: String,
     text: String,
     completed: bool,
+    #[serde(default = "default_position")]
+    position: u32,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+pub type ItemContainer = Arc<Container<Item>>;
+
+fn default_position() -> u32 {
+    u32::MAX
+}
+
+impl Timestamped for Item {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
 }
-type ItemContainer = Arc<Container<Item>>;
 
 const API_ROOT_STR: &str = "todos/";
 
+/// 单项查询的缓存有效期
+const CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// 单项查询的响应缓存
+static CACHE: Lazy<ResponseCache> = Lazy::new(ResponseCache::new);
+
+/// 重新编号后相邻项的间隔
+const POSITION_GAP: u32 = 1024;
+/// 移动后与相邻项的间隔小于此值时，在后台重新编号
+const POSITION_MIN_GAP: u32 = 4;
+
+/// 调整顺序涉及多个项，互斥执行
+static MOVE_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// 分享链接的有效期 (天)
+const SHARE_TTL_DAYS: i64 = 7;
+/// 每项的分享链接数上限，超出时最早的失效
+const MAX_SHARES_PER_TODO: usize = 10;
+
+/// 分享记录，以令牌哈希为索引 (重启后清空)
+static SHARES: Lazy<Arc<Container<ShareRecord>>> = Lazy::new(Container::new_arc);
+
+/// 分享记录
+///
+/// - `token_hash` 令牌的 SHA-256 (十六进制)，同时是存储的键。令牌原文只在创建时返回一次
+#[derive(Debug, Clone)]
+pub struct ShareRecord {
+    pub token_hash: String,
+    pub todo_id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
 // #endregion
 
 /// 创建 RESTful API 路由
-pub async fn factory_todos_router() -> Router {
-    let data = Container::<Item>::new_arc();
+/// 
+/// - `data` 存储，由调用方创建 (见 [`crate::api::Stores`])。启用 `sqlite` 时从数据库加载并持久化
+pub async fn factory_todos_router(data: ItemContainer) -> Router {
+    #[cfg(feature = "sqlite")]
+    {
+        use crate::container::sqlite_store::SqliteContainer;
+        let attached = match SqliteContainer::connect_from_env().await {
+            Ok(sqlite) => sqlite.attach(&data).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = attached {
+            panic!("failed to open sqlite database: {}", err);
+        }
+    }
+    events::publish_changes(&data, "todos", "todo");
+    CACHE.invalidate_on_change(&data);
+    data.add_hook(|event| {
+        if let HookEvent::Delete { key, .. } = event {
+            SHARES.delete_where(|share| share.todo_id == *key); // 项删除后分享随之失效
+        }
+    });
+    health::register_storage(&data, "todos");
 
     // axum
     let app = Router::new()
-        .route("/todos", get(todos_id_get).put(todos_id_put).post(todos_id_post).delete(todos_id_delete))
-        .route("/todos/{id}", get(todos_id_get).put(todos_id_put).post(todos_id_post).patch(todos_id_patch).delete(todos_id_delete))
+        .merge(documented_route!(get, "/todos", todos_id_get, "列出待办事项"))
+        .merge(documented_route!(put, "/todos", todos_id_put, "以随机ID创建待办事项"))
+        .merge(documented_route!(post, "/todos", todos_id_post, "创建待办事项"))
+        .merge(documented_route!(delete, "/todos", todos_id_delete, "清空 (禁止，返回 403)"))
+        .merge(documented_route!(get, "/todos/{id}", todos_id_get, "获取待办事项"))
+        .merge(documented_route!(put, "/todos/{id}", todos_id_put, "幂等创建/覆盖待办事项"))
+        .merge(documented_route!(post, "/todos/{id}", todos_id_post, "以指定ID创建待办事项 (已存在时 409)"))
+        .merge(documented_route!(patch, "/todos/{id}", todos_id_patch, "更新待办事项"))
+        .merge(documented_route!(delete, "/todos/{id}", todos_id_delete, "删除待办事项"))
+        .merge(documented_route!(patch, "/todos/{id}/toggle", todos_id_toggle, "切换完成状态"))
+        .merge(documented_route!(patch, "/todos/{id}/move", todos_id_move, "调整顺序"))
+        .merge(documented_route!(patch, "/todos/toggle-all", todos_toggle_all, "把所有项设为指定的完成状态"))
+        .merge(documented_route!(post, "/todos/{id}/share", todos_id_share_post, "生成只读分享链接"))
+        .merge(documented_route!(delete, "/todos/{id}/share", todos_id_share_delete, "撤销该项的所有分享链接"))
+        .route_layer(UserRateLimitLayer) // 在鉴权内层，按用户限流
+        .route_layer(JwtAuthLayer) // 需登录，各用户的数据相互隔离
         .with_state(data); // 注入共享状态（数据库）
     app
 }
@@ -61,32 +165,62 @@ pub async fn factory_todos_router() -> Router {
 async fn todos_id_get(
     id: Option<Path<String>>,
     pagination: Query<GetPagination>, 
-    State(data): State<ItemContainer>
+    OriginalUri(uri): OriginalUri,
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
 ) -> impl IntoResponse {
     match id {
         // 有id，则查找特定ID项
         Some(Path(id)) => {
-            tracing::debug!("GET /{}{}", API_ROOT_STR, id); // TODO 用统一的中间件来处理
-            data.get_by_id(&id)
-                .map_or_else(
-                    || StatusCode::NOT_FOUND.into_response(),
-                    |result| Json(result.clone()).into_response()
-                )
+            // 只缓存 200 (即本人的项)，因此以用户区分缓存
+            let key = format!("{}:{}", claims.sub, id);
+            cached_response(&CACHE, &key, &id, CACHE_TTL, || async {
+                match data.get_by_id(&id) {
+                    None => StatusCode::NOT_FOUND.into_response(),
+                    Some(result) if result.owner_id != claims.sub => StatusCode::FORBIDDEN.into_response(),
+                    Some(result) => Json(result).into_response(),
+                }
+            }).await
         }
-        // 无id，返回所有项
+        // 无id，返回所有项 (可按完成状态过滤)
         None => {
-            tracing::debug!("GET /{}", API_ROOT_STR);
-            let result: Vec<Item> = data.get_all()
-                .values()
-                .skip(pagination.offset.unwrap_or(0))
-                .take(pagination.limit.unwrap_or(usize::MAX))
-                .cloned()
-                .collect::<Vec<_>>();
-            Json(result).into_response()
+            if let Some(after) = &pagination.after {
+                return todos_cursor_page(&uri, &data, &claims.sub, after, &pagination);
+            }
+            let (page, total) = data.get_page_sorted_by(
+                |item| item.owner_id == claims.sub && pagination.completed.is_none_or(|completed| item.completed == completed),
+                |item| (item.position, item.created_at),
+                pagination.offset.unwrap_or(0),
+                pagination.limit.unwrap_or(usize::MAX),
+            );
+            page_response(&uri, page, total, pagination.offset, pagination.limit)
         }
     }
 }
 
+/// GET /todos?after=<id>&limit=20, 游标分页
+///
+/// 与 offset 分页的顺序一致 (`position`，相同时按创建时间)。新建的项排在最后，翻页期间新增的项不会使已翻过的项重复出现。
+/// `after` 为空串时从头开始，找不到该项 (如已删除) 时返回 `400`
+fn todos_cursor_page(uri: &Uri, data: &ItemContainer, owner_id: &str, after: &str, pagination: &GetPagination) -> Response {
+    let items: Vec<Item> = data.get_all_sorted_by(|item| (item.position, item.created_at))
+        .into_iter()
+        .filter(|item| item.owner_id == owner_id && pagination.completed.is_none_or(|completed| item.completed == completed))
+        .collect();
+    let start = match after {
+        "" => 0,
+        after => match items.iter().position(|item| item.id == after) {
+            Some(index) => index + 1,
+            None => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "cursor not found" }))).into_response(),
+        },
+    };
+    let total = items.len();
+    let limit = pagination.limit.unwrap_or(usize::MAX);
+    let page: Vec<Item> = items.into_iter().skip(start).take(limit).collect();
+    let next_cursor = page.last().filter(|_| start.saturating_add(limit) < total).map(|item| item.id.clone());
+    cursor_page_response(uri, CursorPage { items: page, total, next_cursor }, pagination.limit)
+}
+
 /**
  * PUT /todos/{id?} 幂等创建/修改项 (重复策略：覆盖，而非报错)
  * 
@@ -97,29 +231,36 @@ async fn todos_id_get(
 async fn todos_id_put(
     id: Option<Path<String>>,
     State(data): State<ItemContainer>,
-    Json(input): Json<RequestType>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<RequestType>,
 ) -> impl IntoResponse {
-    let id = id
-        .map_or_else(
-            || {
-                let id = Uuid::new_v4().to_string();
-                tracing::debug!("PUT /{}, create id:{}", API_ROOT_STR, id);
-                id
-            },
-            |p| {
-                tracing::debug!("PUT /{}{}", API_ROOT_STR, p.0);
-                p.0
-            }
-        );
+    let id = id.map_or_else(|| Uuid::new_v4().to_string(), |p| p.0);
 
-    let item = Item {
+    let now = Utc::now();
+    let item = |old: Option<&Item>| Item {
         id: id.clone(),
-        text: input.text.unwrap_or(String::new()),
+        owner_id: claims.sub.clone(),
+        text: input.text.clone().unwrap_or(String::new()),
         completed: input.completed.unwrap_or(false),
+        position: old.map_or(u32::MAX, |old| old.position),
+        created_at: old.map_or(now, |old| old.created_at), // 覆盖时保留创建时间
+        updated_at: now,
     };
-    
-    data.put_by_id(&id, item.clone());
-    (StatusCode::CREATED, Json(item))
+
+    loop {
+        // 所有者的检查与写入在同一次写锁内
+        let result = data.update_by_id(&id, |old| if old.owner_id == claims.sub { Ok(item(Some(old))) } else { Err(()) });
+        match result {
+            Some(Ok((_, item))) => return (StatusCode::CREATED, Json(item)).into_response(),
+            Some(Err(())) => return StatusCode::FORBIDDEN.into_response(),
+            None => {}
+        }
+        let created = item(None);
+        if data.put_if_absent(&id, created.clone()).is_ok() {
+            return (StatusCode::CREATED, Json(created)).into_response();
+        }
+        // 检查之后被其他请求创建，按修改重试
+    }
 }
 
 /**
@@ -132,34 +273,27 @@ async fn todos_id_put(
 async fn todos_id_post(
     id: Option<Path<String>>,
     State(data): State<ItemContainer>,
-    Json(input): Json<RequestType>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<RequestType>,
 ) -> impl IntoResponse {
-    let id = id
-        .map_or_else(
-            || {
-                let id = Uuid::new_v4().to_string();
-                tracing::debug!("POST /{}, create id:{}", API_ROOT_STR, id);
-                id
-            },
-            |p| {
-                tracing::debug!("POST /{}{}", API_ROOT_STR, p.0);
-                p.0
-            }
-        );
-
-    data.get_by_id(&id)
-        .map_or_else(
-            || {
-                let item = Item {
-                    id: id.clone(),
-                    text: input.text.unwrap_or(String::new()),
-                    completed: input.completed.unwrap_or(false),
-                };
-                data.put_by_id(&id, item.clone());
-                (StatusCode::CREATED, Json(item))
-            },
-            |result| (StatusCode::CONFLICT, Json(result))
-        )
+    let id = id.map_or_else(|| Uuid::new_v4().to_string(), |p| p.0);
+
+    let now = Utc::now();
+    let item = Item {
+        id: id.clone(),
+        owner_id: claims.sub.clone(),
+        text: input.text.unwrap_or(String::new()),
+        completed: input.completed.unwrap_or(false),
+        position: u32::MAX,
+        created_at: now,
+        updated_at: now,
+    };
+    // 检查与插入在同一次写锁内，并发创建同一ID时只有一个成功
+    match data.put_if_absent(&id, item.clone()) {
+        Ok(()) => (StatusCode::CREATED, Json(item)).into_response(),
+        Err(existing) if existing.owner_id != claims.sub => StatusCode::FORBIDDEN.into_response(),
+        Err(existing) => (StatusCode::CONFLICT, Json(existing)).into_response(),
+    }
 }
 
 /**
@@ -172,25 +306,183 @@ async fn todos_id_post(
 async fn todos_id_patch(
     Path(id): Path<String>,
     State(data): State<ItemContainer>,
-    Json(input): Json<RequestType>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<RequestType>,
 ) -> impl IntoResponse {
-    tracing::debug!("PATCH /{}{}", API_ROOT_STR, id);
-
-    let old_value = data.get_by_id(&id);
-    if old_value.is_none() {
+    let Some(old_value) = data.get_by_id(&id) else {
         return StatusCode::NOT_FOUND.into_response()
     };
+    if old_value.owner_id != claims.sub {
+        return StatusCode::FORBIDDEN.into_response();
+    }
 
     let new_value = Item {
         id: id.clone(),
+        owner_id: old_value.owner_id,
         text: input.text.unwrap_or(String::default()),
-        completed: input.completed.unwrap_or(false)
+        completed: input.completed.unwrap_or(false),
+        position: old_value.position,
+        created_at: old_value.created_at,
+        updated_at: Utc::now(),
     };
 
     data.put_by_id(&id, new_value.clone());
     Json(new_value).into_response()
 }
 
+/**
+ * PATCH /todos/{id}/toggle 切换完成状态
+ * 
+ * 读取与写回在同一次写锁内完成，并发切换不会丢失
+ * 
+ * - `id` 路径中的ID
+ */
+async fn todos_id_toggle(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    let result = data.update_by_id(&id, |item| {
+        if item.owner_id != claims.sub {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        Ok(Item { completed: !item.completed, updated_at: Utc::now(), ..item.clone() })
+    });
+    match result {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(Err(status)) => status.into_response(),
+        Some(Ok((_, new_value))) => Json(new_value).into_response(),
+    }
+}
+
+/**
+ * PATCH /todos/toggle-all 把本人的所有项设为指定的完成状态
+ * 
+ * 返回修改的项数 `{ "updated": 3 }` (已是该状态的项不修改)
+ * 
+ * - `query` 查询参数，`completed` 目标状态
+ */
+async fn todos_toggle_all(
+    Query(query): Query<ToggleAllQuery>,
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    let now = Utc::now();
+    let updated = data.update_where(
+        |item| item.owner_id == claims.sub && item.completed != query.completed,
+        |item| Item { completed: query.completed, updated_at: now, ..item.clone() },
+    );
+    Json(json!({ "updated": updated.len() }))
+}
+
+/**
+ * PATCH /todos/{id}/move 调整顺序
+ * 
+ * 取相邻两项 `position` 的中间值，只修改被移动的项。
+ * 间隔用尽时先把该用户的所有项重新编号 (间隔 1024) 再移动; 间隔所剩不多时在后台重新编号
+ * 
+ * - `id` 路径中的ID
+ * - `input` JSON请求体，`before_id`、`after_id` (移到该项之前/之后) 或 `position` (移到第几位，从0开始) 三选一
+ */
+async fn todos_id_move(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<MoveRequest>,
+) -> impl IntoResponse {
+    let bad_request = |message: &str| (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response();
+    let given = [input.before_id.is_some(), input.after_id.is_some(), input.position.is_some()];
+    if given.iter().filter(|&&given| given).count() != 1 {
+        return bad_request("exactly one of before_id, after_id and position is required");
+    }
+
+    let _guard = MOVE_LOCK.lock().await;
+    let Some(item) = data.get_by_id(&id) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if item.owner_id != claims.sub {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    // 两次尝试: 间隔用尽时重新编号后再算
+    for attempt in 0..2 {
+        let others = sorted_items(&data, &claims.sub, Some(&id));
+        let index = match (&input.before_id, &input.after_id, input.position) {
+            (Some(before_id), _, _) => others.iter().position(|other| &other.id == before_id),
+            (_, Some(after_id), _) => others.iter().position(|other| &other.id == after_id).map(|index| index + 1),
+            (_, _, Some(position)) => Some((position as usize).min(others.len())),
+            _ => None,
+        };
+        let Some(index) = index else {
+            return bad_request("reference item not found");
+        };
+        let lower = index.checked_sub(1).map(|index| others[index].position);
+        let upper = others.get(index).map(|other| other.position);
+
+        let Some(position) = position_between(lower, upper) else {
+            if attempt == 0 {
+                renumber(&data, &claims.sub);
+            }
+            continue;
+        };
+        let near_lower = lower.is_some_and(|lower| position - lower < POSITION_MIN_GAP);
+        let near_upper = upper.is_some_and(|upper| upper - position < POSITION_MIN_GAP);
+        if near_lower || near_upper {
+            let (data, owner_id) = (data.clone(), claims.sub.clone());
+            tokio::spawn(async move {
+                let _guard = MOVE_LOCK.lock().await;
+                renumber(&data, &owner_id);
+            });
+        }
+
+        // 只改位置，不覆盖期间其他请求对该项的修改
+        return match data.update_by_id(&id, |item| Ok::<_, ()>(Item { position, updated_at: Utc::now(), ..item.clone() })) {
+            Some(Ok((_, moved))) => Json(moved).into_response(),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        };
+    }
+    StatusCode::INTERNAL_SERVER_ERROR.into_response() // 重新编号后必有间隔，不会到达
+}
+
+/// 用户的所有项，按顺序排列
+/// 
+/// - `except` 排除的项 (被移动的项)
+fn sorted_items(data: &ItemContainer, owner_id: &str, except: Option<&str>) -> Vec<Item> {
+    let mut items = data.find_where(|item| item.owner_id == owner_id && except != Some(item.id.as_str()));
+    items.sort_by_key(|item| (item.position, item.created_at));
+    items
+}
+
+/// 两个位置之间的新位置 (均不含)，没有空隙时返回 `None`
+/// 
+/// - `lower` 为 `None` 表示移到最前，`upper` 为 `None` 表示移到最后
+fn position_between(lower: Option<u32>, upper: Option<u32>) -> Option<u32> {
+    match (lower, upper) {
+        (None, None) => Some(POSITION_GAP),
+        (Some(lower), None) => lower.checked_add(POSITION_GAP)
+            .filter(|&position| position < u32::MAX)
+            .or_else(|| position_between(Some(lower), Some(u32::MAX))),
+        (lower, Some(upper)) => {
+            let lower = lower.map_or(0, |lower| lower as u64 + 1); // 可用的最小值
+            (lower < upper as u64).then(|| (lower + (upper as u64 - lower) / 2) as u32)
+        }
+    }
+}
+
+/// 把用户的所有项按当前顺序重新编号，间隔为 [`POSITION_GAP`]
+/// 
+/// 调用方需持有 [`MOVE_LOCK`]
+fn renumber(data: &ItemContainer, owner_id: &str) {
+    let items = sorted_items(data, owner_id, None);
+    tracing::debug!("renumbering {} todos of user {}", items.len(), owner_id);
+    for (index, item) in items.into_iter().enumerate() {
+        let position = (index as u32 + 1).saturating_mul(POSITION_GAP);
+        if item.position != position {
+            data.update_by_id(&item.id, |item| Ok::<_, ()>(Item { position, ..item.clone() }));
+        }
+    }
+}
+
 /**
  * DELETE /todos/{id} 删除待办事项
  * 
@@ -200,9 +492,9 @@ async fn todos_id_patch(
 async fn todos_id_delete (
     id: Option<Path<String>>,
     State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
 ) -> impl IntoResponse {
     let id = if let Some(id) = id {
-        tracing::debug!("DELETE /{}{}", API_ROOT_STR, id.0);
         id.0
     } else {
         tracing::warn!("DELETE /{}, clearing is a high-risk operation", API_ROOT_STR);
@@ -210,13 +502,130 @@ async fn todos_id_delete (
         return StatusCode::FORBIDDEN.into_response();
     };
 
-    let result = data.delete_by_id(&id);
-    match result {
-        Some(_) => StatusCode::NO_CONTENT.into_response(),
+    match data.get_by_id(&id) {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(old_value) if old_value.owner_id != claims.sub => StatusCode::FORBIDDEN.into_response(),
+        Some(_) => {
+            data.delete_by_id(&id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+    }
+}
+
+// #region 分享
+
+/// 分享链接的路由 (无需登录)
+///
+/// 不经过 [`JwtAuthLayer`]，由 main 合并到公开路由中
+pub fn factory_shared_todos_router(data: ItemContainer) -> Router {
+    Router::new()
+        .merge(documented_route!(get, "/shared/todos/{share_token}", shared_todos_get, "通过分享令牌查看 (无需登录)"))
+        .with_state(data)
+}
+
+/**
+ * POST /todos/{id}/share 生成只读分享链接
+ * 
+ * 返回 `{ "share_token": "...", "expires_at": "..." }`，令牌只在此时可见。
+ * 可多次生成，各令牌独立有效 (每项最多10个，超出时最早的失效)
+ * 
+ * - `id` 路径中的ID
+ */
+async fn todos_id_share_post(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    match data.get_by_id(&id) {
+        None => return StatusCode::NOT_FOUND.into_response(),
+        Some(item) if item.owner_id != claims.sub => return StatusCode::FORBIDDEN.into_response(),
+        Some(_) => {}
+    }
+
+    let now = Utc::now();
+    SHARES.delete_where(|share| share.expires_at <= now); // 顺带清理
+    let mut existing = SHARES.find_where(|share| share.todo_id == id);
+    if existing.len() >= MAX_SHARES_PER_TODO {
+        existing.sort_by_key(|share| share.created_at);
+        for share in &existing[..=existing.len() - MAX_SHARES_PER_TODO] {
+            SHARES.delete_by_id(&share.token_hash);
+        }
+    }
+    let token = hex::encode(rand_bytes());
+    let share = ShareRecord {
+        token_hash: hash_share_token(&token),
+        todo_id: id,
+        created_at: now,
+        expires_at: now + chrono::Duration::days(SHARE_TTL_DAYS),
+    };
+    SHARES.put_by_id(&share.token_hash.clone(), share.clone());
+    (StatusCode::CREATED, Json(json!({ "share_token": token, "expires_at": share.expires_at }))).into_response()
+}
+
+/**
+ * DELETE /todos/{id}/share 撤销该项的所有分享链接
+ * 
+ * - `id` 路径中的ID
+ */
+async fn todos_id_share_delete(
+    Path(id): Path<String>,
+    State(data): State<ItemContainer>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    match data.get_by_id(&id) {
+        None => StatusCode::NOT_FOUND,
+        Some(item) if item.owner_id != claims.sub => StatusCode::FORBIDDEN,
+        Some(_) => {
+            let revoked = SHARES.delete_where(|share| share.todo_id == id).len();
+            tracing::debug!("revoked {} share links of todo {}", revoked, id);
+            StatusCode::NO_CONTENT
+        }
+    }
+}
+
+/**
+ * GET /shared/todos/{share_token} 通过分享令牌查看
+ * 
+ * 只返回公开的字段 (不含所有者与排序位置)。令牌无效、过期或项已删除时返回 `404`
+ * 
+ * - `share_token` 路径中的分享令牌
+ */
+async fn shared_todos_get(
+    Path(share_token): Path<String>,
+    State(data): State<ItemContainer>,
+) -> impl IntoResponse {
+    let token_hash = hash_share_token(&share_token);
+    let Some(share) = SHARES.get_by_id(&token_hash) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if share.expires_at <= Utc::now() {
+        SHARES.delete_by_id(&token_hash);
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match data.get_by_id(&share.todo_id) {
         None => StatusCode::NOT_FOUND.into_response(),
+        Some(item) => Json(json!({
+            "id": item.id,
+            "text": item.text,
+            "completed": item.completed,
+            "created_at": item.created_at,
+            "updated_at": item.updated_at,
+        })).into_response(),
     }
 }
 
+fn rand_bytes() -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn hash_share_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// #endregion
+
 // #region api struct
 
 #[derive(Debug, Deserialize, Default)]
@@ -225,10 +634,31 @@ struct GetPagination {
     offset: Option<usize>,
     /// 数量限制
     limit: Option<usize>,
+    /// 仅返回该完成状态的项
+    completed: Option<bool>,
+    /// 游标分页: 返回该ID之后的项 (空串表示从头开始)
+    after: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct MoveRequest {
+    /// 移到该项之前
+    before_id: Option<String>,
+    /// 移到该项之后
+    after_id: Option<String>,
+    /// 移到第几位 (从0开始，超出时移到最后)
+    position: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
+struct ToggleAllQuery {
+    /// 目标完成状态
+    completed: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
 struct RequestType {
+    #[validate(length(min = 1, max = 2000, message = "text must be 1-2000 characters"))]
     text: Option<String>,
     completed: Option<bool>,
 }
@@ -0,0 +1,63 @@
+//! 已注册的路由
+//!
+//! axum 的 `Router` 无法列出其中的路由，因此在构造路由时另行记录: 用 [`documented_route!`] 注册的路由
+//! 会同时记入全局列表 (方法、路径模板、所在模块与说明)，由 `GET /admin/debug/routes` 返回。
+//! 用于快速排查有哪些路由，完整的接口说明见 `/openapi.json`
+//!
+//! ```ignore
+//! Router::new()
+//!     .merge(documented_route!(get, "/todos", todos_id_get, "列出待办事项"))
+//!     .merge(documented_route!(post, "/todos", todos_id_post, "创建待办事项"))
+//! ```
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// 已注册的路由，按注册顺序
+static ROUTES: Lazy<Mutex<Vec<RouteInfo>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// 路由信息
+///
+/// - `method` HTTP 方法 (大写)
+/// - `path_pattern` 路径模板，如 `/todos/{id}`。资源路由另挂载于 `/v1` 下，此处为不带前缀的路径
+/// - `module` 所在模块，如 `api::rest_todos`
+/// - `description` 说明
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RouteInfo {
+    pub method: String,
+    pub path_pattern: &'static str,
+    pub module: &'static str,
+    pub description: &'static str,
+}
+
+/// 记录路由，同一方法与路径重复注册时 (如路由被构造多次) 只保留一条
+///
+/// - `module` 为 `module_path!()`，去掉开头的 crate 名
+pub fn register(method: &str, path_pattern: &'static str, module: &'static str, description: &'static str) {
+    let module = module.split_once("::").map_or(module, |(_, module)| module);
+    let info = RouteInfo { method: method.to_uppercase(), path_pattern, module, description };
+    let mut routes = ROUTES.lock().unwrap();
+    if !routes.iter().any(|route| route.method == info.method && route.path_pattern == info.path_pattern) {
+        routes.push(info);
+    }
+}
+
+/// 已注册的路由，按路径与方法排序
+pub fn list() -> Vec<RouteInfo> {
+    let mut routes = ROUTES.lock().unwrap().clone();
+    routes.sort_by(|a, b| (a.path_pattern, &a.method).cmp(&(b.path_pattern, &b.method)));
+    routes
+}
+
+/// 注册单个方法的路由并记入路由列表，返回只含该路由的 `Router`，用 `Router::merge` 合并
+///
+/// `documented_route!(方法, "路径", 处理函数, "说明")`，方法为 `axum::routing` 中的函数名 (`get`/`post`/...)。
+/// 同一路径的多个方法分别注册，合并后与 `get(a).post(b)` 相同
+#[macro_export]
+macro_rules! documented_route {
+    ($method:ident, $path:literal, $handler:expr, $description:literal $(,)?) => {{
+        $crate::api::route_registry::register(stringify!($method), $path, module_path!(), $description);
+        ::axum::Router::new().route($path, ::axum::routing::$method($handler))
+    }};
+}
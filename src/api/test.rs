@@ -6,7 +6,6 @@ use axum::{
 
 /// GET / 测试是否服务器正常
 pub async fn root() -> Html<&'static str> {
-    tracing::debug!("GET /");
     Html("<h1>Hello, World!</h1>")
 }
 
@@ -0,0 +1,36 @@
+//! 服务器版本与构建信息
+//!
+//! 构建信息由 `build.rs` 在编译时写入。`GET /version` 返回全部信息，
+//! 版本号与构建时间另由 [`BuildInfoLayer`](crate::api::middleware::build_info::BuildInfoLayer) 加入每个响应的响应头
+
+use axum::response::{IntoResponse, Json};
+use serde_json::json;
+
+/// 服务器版本 (Cargo.toml 中的版本号)
+pub const SERVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// 构建时间 (UTC，RFC 3339)
+pub const BUILD_DATE: &str = env!("BUILD_DATE");
+/// 编译器版本
+pub const RUSTC_VERSION: &str = env!("BUILD_RUSTC_VERSION");
+/// 构建时的 git 提交，不在 git 仓库中构建时为 None
+pub const GIT_COMMIT_HASH: Option<&str> = option_env!("VERGEN_GIT_SHA");
+/// 启用的 Cargo 特性，逗号分隔
+const FEATURES: &str = env!("BUILD_FEATURES");
+
+/// 启用的 Cargo 特性 (不含 `default`)，按名称排序
+pub fn features() -> Vec<&'static str> {
+    FEATURES.split(',').filter(|feature| !feature.is_empty()).collect()
+}
+
+/// GET /version, 服务器版本与构建信息
+///
+/// `{ "version": "0.1.0", "build_date": "...", "rustc_version": "rustc 1.x.x ...", "git_commit_hash": "...", "features": ["scripting"] }`
+pub async fn get_version() -> impl IntoResponse {
+    Json(json!({
+        "version": SERVER_VERSION,
+        "build_date": BUILD_DATE,
+        "rustc_version": RUSTC_VERSION,
+        "git_commit_hash": GIT_COMMIT_HASH,
+        "features": features(),
+    }))
+}
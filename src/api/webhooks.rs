@@ -0,0 +1,354 @@
+//! Webhook (资源变更时回调)
+//!
+//! 需登录，各用户只能管理自己的 webhook，也只会收到自己的数据的变更。
+//!
+//! - `POST /webhooks`: 注册，`{ url, events: ["todo.created", ...], secret }`
+//! - `GET /webhooks`: 列出自己的 webhook
+//! - `DELETE /webhooks/{id}`: 删除
+//! - `GET /webhooks/{id}/deliveries`: 最近的投递记录 (最多50条)
+//!
+//! 事件来源于各容器的事件钩子 (与 `GET /events` 相同)，见 [`events::publish_changes`]。
+//! 事件名为 `<todo|rest|node>.<created|updated|deleted>`，`*` 表示全部。
+//!
+//! 投递为 `POST` JSON，带 `X-Webhook-Signature: sha256=<hex>` (以 `secret` 对请求体做 HMAC-SHA256)。
+//! 非 `2xx` 或网络错误时依次在 1s、5s、25s 后重试
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::api::{events::{self, SseEvent}, extractors::ValidatedJson};
+use crate::api::middleware::{jwt::{Claims, JwtAuthLayer}, user_rate_limit::UserRateLimitLayer};
+use crate::config::CONFIG;
+use crate::container::Container;
+use crate::supervisor::TaskSupervisor;
+use crate::documented_route;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 签名头
+const SIGNATURE_HEADER: &str = "x-webhook-signature";
+/// 事件名头
+const EVENT_HEADER: &str = "x-webhook-event";
+/// 投递ID头 (重试时不变，接收方可据此去重)
+const DELIVERY_HEADER: &str = "x-webhook-delivery";
+/// 失败后的重试间隔
+const RETRY_DELAYS: [Duration; 3] = [Duration::from_secs(1), Duration::from_secs(5), Duration::from_secs(25)];
+/// 每个 webhook 保留的投递记录数
+const MAX_DELIVERIES: usize = 50;
+/// 每个用户的 webhook 数上限
+const MAX_WEBHOOKS_PER_USER: usize = 20;
+/// 可订阅的资源与动作
+const EVENT_RESOURCES: &[&str] = &["todo", "rest", "node"];
+const EVENT_ACTIONS: &[&str] = &["created", "updated", "deleted"];
+
+/// 投递用的HTTP客户端 (不跟随重定向)
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("failed to build http client")
+});
+
+/// 已注册的 webhook，以ID为索引 (重启后清空)
+static WEBHOOKS: Lazy<Arc<Container<WebhookRecord>>> = Lazy::new(Container::new_arc);
+/// 各 webhook 最近的投递记录，以 webhook ID 为索引
+static DELIVERIES: Lazy<Arc<Container<VecDeque<DeliveryRecord>>>> = Lazy::new(Container::new_arc);
+
+// #region 类型
+
+/// 已注册的 webhook
+///
+/// - `secret` 签名密钥，不在响应中返回
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookRecord {
+    pub id: String,
+    #[serde(skip)]
+    pub owner_id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    #[serde(skip)]
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WebhookRecord {
+    fn subscribes(&self, event_type: &str) -> bool {
+        self.events.iter().any(|event| event == "*" || event == event_type)
+    }
+}
+
+/// 一次投递尝试
+///
+/// - `delivery_id` 同一事件的各次重试相同
+/// - `attempt` 第几次尝试，从 1 开始
+/// - `status_code` 对方的响应码，网络错误时为 `None`
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryRecord {
+    pub delivery_id: String,
+    pub event: String,
+    pub attempt: u32,
+    pub success: bool,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub attempted_at: DateTime<Utc>,
+}
+
+// #endregion
+
+/// webhook 路由，并启动投递任务
+pub fn factory_webhooks_router() -> Router {
+    TaskSupervisor::spawn("webhook_dispatcher", dispatch_loop);
+
+    Router::new()
+        .merge(documented_route!(get, "/webhooks", get_webhooks, "列出自己的 webhook"))
+        .merge(documented_route!(post, "/webhooks", post_webhooks, "注册 webhook"))
+        .merge(documented_route!(delete, "/webhooks/{id}", delete_webhook, "删除 webhook"))
+        .merge(documented_route!(get, "/webhooks/{id}/deliveries", get_webhook_deliveries, "最近的投递记录"))
+        .route_layer(UserRateLimitLayer) // 在鉴权内层，按用户限流
+        .route_layer(JwtAuthLayer)
+}
+
+/// 删除用户的全部 webhook (注销账号时调用)，返回删除的数量
+pub(crate) fn delete_webhooks_by_owner(owner_id: &str) -> usize {
+    let removed = WEBHOOKS.delete_where(|webhook| webhook.owner_id == owner_id);
+    for webhook in &removed {
+        DELIVERIES.delete_by_id(&webhook.id);
+    }
+    removed.len()
+}
+
+// #region 接口
+
+/**
+ * POST /webhooks 注册 webhook
+ *
+ * - `url` 回调地址，默认只允许 https (见配置 `webhook_allow_http`)
+ * - `events` 订阅的事件，如 `todo.created`，`*` 表示全部
+ * - `secret` 签名密钥，至少16个字符
+ */
+async fn post_webhooks(
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<WebhookRequest>,
+) -> Response {
+    let scheme_allowed = input.url.starts_with("https://") || (CONFIG.webhook_allow_http && input.url.starts_with("http://"));
+    if !scheme_allowed {
+        let message = if CONFIG.webhook_allow_http { "url must use http or https" } else { "url must use https" };
+        return error_response(StatusCode::BAD_REQUEST, message);
+    }
+    if let Some(event) = input.events.iter().find(|event| !is_valid_event(event)) {
+        return error_response(StatusCode::BAD_REQUEST, &format!("unknown event: {}", event));
+    }
+    if WEBHOOKS.find_where(|webhook| webhook.owner_id == claims.sub).len() >= MAX_WEBHOOKS_PER_USER {
+        return error_response(StatusCode::CONFLICT, "too many webhooks");
+    }
+
+    let mut events = input.events;
+    events.sort();
+    events.dedup();
+    let webhook = WebhookRecord {
+        id: Uuid::new_v4().to_string(),
+        owner_id: claims.sub,
+        url: input.url,
+        events,
+        secret: input.secret,
+        created_at: Utc::now(),
+    };
+    WEBHOOKS.put_by_id(&webhook.id, webhook.clone());
+    tracing::info!("webhook {} registered for {:?}", webhook.id, webhook.events);
+    (StatusCode::CREATED, Json(webhook)).into_response()
+}
+
+/// GET /webhooks, 列出自己的 webhook，按创建时间排序
+async fn get_webhooks(Extension(claims): Extension<Claims>) -> impl IntoResponse {
+    let mut webhooks = WEBHOOKS.find_where(|webhook| webhook.owner_id == claims.sub);
+    webhooks.sort_by_key(|webhook| webhook.created_at);
+    Json(webhooks)
+}
+
+/**
+ * DELETE /webhooks/{id} 删除 webhook
+ *
+ * 其他用户的 webhook 返回 `403`。已在重试中的投递不再继续
+ */
+async fn delete_webhook(
+    Path(id): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> StatusCode {
+    match WEBHOOKS.get_by_id(&id) {
+        None => StatusCode::NOT_FOUND,
+        Some(webhook) if webhook.owner_id != claims.sub => StatusCode::FORBIDDEN,
+        Some(_) => {
+            WEBHOOKS.delete_by_id(&id);
+            DELIVERIES.delete_by_id(&id);
+            StatusCode::NO_CONTENT
+        }
+    }
+}
+
+/**
+ * GET /webhooks/{id}/deliveries 最近的投递记录
+ *
+ * 每次尝试 (含重试) 一条，新的在前，最多50条
+ */
+async fn get_webhook_deliveries(
+    Path(id): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> Response {
+    match WEBHOOKS.get_by_id(&id) {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(webhook) if webhook.owner_id != claims.sub => StatusCode::FORBIDDEN.into_response(),
+        Some(_) => {
+            let deliveries = DELIVERIES.get_by_id(&id).unwrap_or_default();
+            Json(deliveries.into_iter().rev().collect::<Vec<_>>()).into_response()
+        }
+    }
+}
+
+// #endregion
+
+// #region 投递
+
+/// 订阅变更事件，为匹配的 webhook 发起投递
+async fn dispatch_loop() {
+    let mut receiver = events::subscribe();
+    loop {
+        match receiver.recv().await {
+            Ok((_, event)) => dispatch(&event),
+            Err(RecvError::Lagged(skipped)) => tracing::warn!("webhook dispatcher lagged, {} events skipped", skipped),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// 只投递给数据所有者的 webhook
+fn dispatch(event: &SseEvent) {
+    let Some(owner_id) = event.owner_id.as_deref() else { return };
+    let webhooks = WEBHOOKS.find_where(|webhook| webhook.owner_id == owner_id && webhook.subscribes(&event.event_type));
+    if webhooks.is_empty() {
+        return;
+    }
+    let body = json!({
+        "event": event.event_type,
+        "resource": event.resource,
+        "resource_id": event.resource_id,
+        "data": event.payload,
+        "timestamp": Utc::now(),
+    }).to_string();
+    for webhook in webhooks {
+        tokio::spawn(deliver(webhook, event.event_type.clone(), body.clone()));
+    }
+}
+
+/// 投递一个事件，失败时按 [`RETRY_DELAYS`] 重试。webhook 被删除后停止
+async fn deliver(webhook: WebhookRecord, event: String, body: String) {
+    let delivery_id = Uuid::new_v4().to_string();
+    let signature = sign(&webhook.secret, body.as_bytes());
+    let mut delays = RETRY_DELAYS.iter();
+    for attempt in 1.. {
+        let started = Instant::now();
+        let result = CLIENT.post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, &signature)
+            .header(EVENT_HEADER, &event)
+            .header(DELIVERY_HEADER, &delivery_id)
+            .body(body.clone())
+            .send()
+            .await;
+        let (status_code, error) = match result {
+            Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+            Ok(response) => (Some(response.status().as_u16()), Some(format!("unexpected status {}", response.status()))),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        let success = error.is_none();
+        record_delivery(&webhook.id, DeliveryRecord {
+            delivery_id: delivery_id.clone(),
+            event: event.clone(),
+            attempt,
+            success,
+            status_code,
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+            attempted_at: Utc::now(),
+        });
+        if success {
+            return;
+        }
+        let Some(delay) = delays.next() else {
+            tracing::warn!("webhook {} delivery {} failed after {} attempts", webhook.id, delivery_id, attempt);
+            return;
+        };
+        tokio::time::sleep(*delay).await;
+        if WEBHOOKS.get_by_id(&webhook.id).is_none() {
+            return;
+        }
+    }
+}
+
+/// 追加投递记录，只保留最近的 [`MAX_DELIVERIES`] 条
+fn record_delivery(webhook_id: &str, record: DeliveryRecord) {
+    if WEBHOOKS.get_by_id(webhook_id).is_none() {
+        return; // 已删除
+    }
+    let _ = DELIVERIES.put_if_absent(webhook_id, VecDeque::new());
+    DELIVERIES.update_by_id(webhook_id, |deliveries| {
+        let mut deliveries = deliveries.clone();
+        if deliveries.len() >= MAX_DELIVERIES {
+            deliveries.pop_front();
+        }
+        deliveries.push_back(record);
+        Ok::<_, ()>(deliveries)
+    });
+}
+
+/// `sha256=<hex>`
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn is_valid_event(event: &str) -> bool {
+    event == "*" || event.split_once('.').is_some_and(|(resource, action)| {
+        EVENT_RESOURCES.contains(&resource) && EVENT_ACTIONS.contains(&action)
+    })
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+// #endregion
+
+// #region api struct
+
+#[derive(Debug, Deserialize, Validate)]
+struct WebhookRequest {
+    #[validate(url(message = "url must be a valid URL"))]
+    url: String,
+    #[validate(length(min = 1, max = 16, message = "events must have 1 to 16 entries"))]
+    events: Vec<String>,
+    #[validate(length(min = 16, max = 256, message = "secret must be 16 to 256 characters"))]
+    secret: String,
+}
+
+// #endregion
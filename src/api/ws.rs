@@ -0,0 +1,221 @@
+//! WebSocket 接口，用于双向控制节点执行
+//!
+//! `GET /ws` (需登录) 升级为 WebSocket 后，客户端可发送。浏览器可用 `new WebSocket(url, ["bearer", token])`
+//! 或 `?access_token=<token>` 携带令牌 (见 [`BrowserJwtAuthLayer`])
+//!
+//! - `{ "type": "run_node", "id": "...", "input": ... }`
+//!   执行节点链，每完成一个节点回传 `{ "type": "node_result", "id": "...", "output": ..., "error": null }`
+//! - `{ "type": "subscribe", "resource": "todos" }`
+//!   订阅数据变更事件 (同 SSE 接口，只收到当前用户可见的事件)，回传 `{ "type": "event", ... }`
+//!
+//! 同一连接同时执行的节点链数有上限，超出时回传 `{ "type": "error", ... }`。再次订阅替换之前的订阅 (不会重复收到事件)
+//!
+//! 服务端定时发送 ping，长时间收不到任何消息则断开。客户端读取过慢、发送缓冲已满时也断开。
+//! 断开时取消该连接上所有未完成的执行
+
+use axum::{
+    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, Extension},
+    response::IntoResponse,
+    Router,
+};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tokio::{sync::mpsc, task::{JoinHandle, JoinSet}};
+use tokio_util::sync::CancellationToken;
+
+use crate::api::{events, middleware::jwt::{BrowserJwtAuthLayer, Claims, TOKEN_SUBPROTOCOL}, rest_node};
+use crate::documented_route;
+
+/// ping 间隔
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+/// 超过该时间未收到任何消息 (含 pong) 则视为断线
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(45);
+/// 发送缓冲的消息数，满了说明客户端读取过慢，断开连接
+const SEND_BUFFER: usize = 256;
+/// 每个连接同时执行的节点链数上限
+const MAX_RUNNING: usize = 16;
+
+/// WebSocket 路由
+pub fn factory_ws_router() -> Router {
+    Router::new()
+        .merge(documented_route!(get, "/ws", get_ws, "升级为 WebSocket"))
+        .route_layer(BrowserJwtAuthLayer) // 浏览器无法设置请求头，可用查询参数或子协议携带令牌
+}
+
+/// GET /ws, 升级为 WebSocket
+///
+/// 客户端以子协议携带令牌时需应答该子协议，否则浏览器会断开
+pub async fn get_ws(Extension(claims): Extension<Claims>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    ws.protocols([TOKEN_SUBPROTOCOL])
+        .on_upgrade(move |socket| handle_socket(socket, claims.sub))
+}
+
+/// 发往客户端的消息，经有界通道交给写任务
+#[derive(Clone)]
+struct Outbox {
+    tx: mpsc::Sender<Message>,
+    /// 缓冲已满时取消，连接随之断开
+    overflow: CancellationToken,
+}
+
+impl Outbox {
+    /// 放入发送缓冲 (不等待)。连接已关闭时忽略，缓冲已满时断开连接
+    fn send(&self, message: Message) {
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.tx.try_send(message) {
+            self.overflow.cancel();
+        }
+    }
+
+    /// 发送JSON文本消息
+    fn send_json(&self, value: Value) {
+        self.send(Message::Text(value.to_string().into()));
+    }
+}
+
+/// 单个连接的状态
+struct Connection {
+    /// 当前用户，订阅的事件按其过滤
+    user_id: String,
+    outbox: Outbox,
+    /// 未完成的执行
+    runs: JoinSet<()>,
+    /// 当前的订阅，再次订阅时替换
+    subscription: Option<JoinHandle<()>>,
+}
+
+/// 处理单个连接
+///
+/// 写操作统一经由有界的 mpsc 通道交给写任务，各个执行/订阅任务只需持有发送端。
+/// 连接结束时取消所有执行与订阅任务
+///
+/// - `user_id` 当前用户，订阅的事件按其过滤
+async fn handle_socket(socket: WebSocket, user_id: String) {
+    let (mut sink, mut stream) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<Message>(SEND_BUFFER);
+
+    let writer = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            if sink.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let outbox = Outbox { tx, overflow: CancellationToken::new() };
+    let mut connection = Connection { user_id, outbox: outbox.clone(), runs: JoinSet::new(), subscription: None };
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    let mut last_seen = Instant::now();
+
+    loop {
+        tokio::select! {
+            message = stream.next() => {
+                let Some(Ok(message)) = message else { break };
+                last_seen = Instant::now();
+                match message {
+                    Message::Text(text) => connection.handle_message(&text),
+                    Message::Close(_) => break,
+                    _ => {} // ping 由底层自动回复 pong，pong 仅用于刷新活跃时间
+                }
+            }
+            _ = ping.tick() => {
+                if last_seen.elapsed() > CLIENT_TIMEOUT {
+                    tracing::debug!("WS /ws, client timeout");
+                    break;
+                }
+                outbox.send(Message::Ping(Vec::new().into()));
+            }
+            _ = outbox.overflow.cancelled() => {
+                tracing::debug!("WS /ws, send buffer full");
+                break;
+            }
+        }
+    }
+
+    connection.runs.abort_all();
+    if let Some(subscription) = connection.subscription {
+        subscription.abort();
+    }
+    writer.abort();
+    tracing::debug!("WS /ws, disconnected");
+}
+
+impl Connection {
+    /// 处理客户端消息
+    fn handle_message(&mut self, text: &str) {
+        let message: ClientMessage = match serde_json::from_str(text) {
+            Ok(message) => message,
+            Err(err) => {
+                self.outbox.send_json(json!({ "type": "error", "error": err.to_string() }));
+                return;
+            }
+        };
+
+        match message {
+            ClientMessage::RunNode { id, input } => {
+                tracing::debug!("WS /ws, run_node id:{}", id);
+                while self.runs.try_join_next().is_some() {} // 回收已完成的执行
+                if self.runs.len() >= MAX_RUNNING {
+                    let error = format!("too many running nodes (max {})", MAX_RUNNING);
+                    self.outbox.send_json(json!({ "type": "error", "id": id, "error": error }));
+                    return;
+                }
+                let outbox = self.outbox.clone();
+                self.runs.spawn(async move {
+                    let report = |node_id: &str, result: Result<&Value, &str>| {
+                        let (output, error) = match result {
+                            Ok(output) => (output.clone(), Value::Null),
+                            Err(err) => (Value::Null, Value::from(err)),
+                        };
+                        outbox.send_json(json!({ "type": "node_result", "id": node_id, "output": output, "error": error }));
+                    };
+                    let result = rest_node::run_chain_reporting(&id, input.unwrap_or_default(), report).await;
+                    match result {
+                        Ok(()) => outbox.send_json(json!({ "type": "run_finished", "id": id })),
+                        Err(err) => outbox.send_json(json!({ "type": "node_result", "id": id, "output": null, "error": err })),
+                    }
+                });
+            }
+            ClientMessage::Subscribe { resource } => {
+                tracing::debug!("WS /ws, subscribe resource:{:?}", resource);
+                let outbox = self.outbox.clone();
+                let user_id = self.user_id.clone();
+                let mut receiver = events::subscribe();
+                let subscription = tokio::spawn(async move {
+                    loop {
+                        let (id, event) = match receiver.recv().await {
+                            Ok(received) => received,
+                            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                            Err(_) => break,
+                        };
+                        if !event.visible_to(&user_id) || resource.as_deref().is_some_and(|r| r != event.resource) {
+                            continue;
+                        }
+                        outbox.send_json(json!({ "type": "event", "event_id": id, "event": event }));
+                    }
+                });
+                if let Some(previous) = self.subscription.replace(subscription) {
+                    previous.abort();
+                }
+            }
+        }
+    }
+}
+
+// #region api struct
+
+/// 客户端消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    RunNode {
+        id: String,
+        input: Option<Value>,
+    },
+    Subscribe {
+        resource: Option<String>,
+    },
+}
+
+// #endregion
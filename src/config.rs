@@ -0,0 +1,174 @@
+//! 服务器配置
+//!
+//! 启动时从 TOML 文件读取，路径由环境变量 `RUST_HTTP_DEMO_CONFIG` 指定，默认为 `config.toml`。
+//! 文件不存在时全部使用默认值
+//!
+//! ```toml
+//! require_signature = false
+//! tcp_keepalive_idle_secs = 60
+//! tcp_keepalive_interval_secs = 10
+//! tcp_keepalive_retries = 3
+//! import_allow_http = false
+//! webhook_allow_http = false
+//! circuit_breaker_failure_threshold = 5
+//! circuit_breaker_open_secs = 30
+//! trusted_proxy_depth = 0
+//! client_ip_allow_private = false
+//! http_node_allow_private = false
+//! user_rate_limit_capacity = 120
+//! user_rate_limit_refill_per_sec = 30.0
+//! cors_allowed_origins = ["https://app.example.com"]
+//! cors_allowed_methods = ["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"]
+//! cors_allowed_headers = ["content-type", "authorization"]
+//! cors_allow_credentials = true
+//!
+//! [[api_keys]]
+//! key = "client-a"
+//! secret = "change-me"
+//! ```
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::api::api_keys::ApiKeyRecord;
+use crate::api::middleware::cors::{CorsConfig, CORS_ANY};
+
+/// 配置文件路径的环境变量
+const CONFIG_PATH_ENV: &str = "RUST_HTTP_DEMO_CONFIG";
+/// 默认配置文件路径
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// 全局配置，首次访问时加载
+pub static CONFIG: Lazy<ServerConfig> = Lazy::new(ServerConfig::load);
+
+/// 服务器配置
+///
+/// - `api_keys` 机器客户端的 API Key 及签名密钥
+/// - `require_signature` 是否要求所有请求带签名。否则仅校验带签名头的请求
+/// - `tcp_keepalive_*` TCP keepalive: 连接空闲多久后开始探测、探测间隔、失败几次后断开。
+///   用于及早发现 SSE/WebSocket 等长连接的对端已失联
+/// - `import_allow_http` `POST /rest/import` 是否允许 http 地址 (默认只允许 https)
+/// - `webhook_allow_http` `POST /webhooks` 是否允许 http 回调地址 (默认只允许 https)
+/// - `circuit_breaker_*` Http 节点的熔断: 连续失败几次后熔断、熔断多少秒后试探，见 [`crate::node::circuit_breaker`]
+/// - `trusted_proxy_depth` 前面的可信反向代理层数，客户端IP取 `X-Forwarded-For` 右起第这么多项。
+///   为0时视为没有代理，限流不信任转发的请求头，见 [`crate::api::client_ip`]
+/// - `client_ip_allow_private` 是否接受私有、回环等地址作为客户端IP (内网部署)
+/// - `http_node_allow_private` Http 节点是否可以访问 localhost 及私有、回环等地址 (内网部署)，见 [`crate::node::http`]
+/// - `user_rate_limit_*` 登录用户的默认限流配额: 桶容量、每秒补充的令牌数，见 [`crate::api::middleware::user_rate_limit`]
+/// - `cors_allowed_*` 跨域允许的来源、方法、请求头，`["*"]` 表示任意 (来源默认任意，生产建议指定域名)
+/// - `cors_allow_credentials` 是否允许跨域携带凭证 (cookies等)，开启时以上三项都不能为 `*`。
+///   以上为全局的策略，见 [`crate::api::middleware::cors`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub api_keys: Vec<ApiKeyRecord>,
+    pub require_signature: bool,
+    pub tcp_keepalive_idle_secs: u64,
+    pub tcp_keepalive_interval_secs: u64,
+    pub tcp_keepalive_retries: u32,
+    pub import_allow_http: bool,
+    pub webhook_allow_http: bool,
+    pub circuit_breaker_failure_threshold: u32,
+    pub circuit_breaker_open_secs: u64,
+    pub trusted_proxy_depth: usize,
+    pub client_ip_allow_private: bool,
+    pub http_node_allow_private: bool,
+    pub user_rate_limit_capacity: u32,
+    pub user_rate_limit_refill_per_sec: f64,
+    pub cors_allowed_origins: Vec<String>,
+    pub cors_allowed_methods: Vec<String>,
+    pub cors_allowed_headers: Vec<String>,
+    pub cors_allow_credentials: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            api_keys: Vec::new(),
+            require_signature: false,
+            tcp_keepalive_idle_secs: 60,
+            tcp_keepalive_interval_secs: 10,
+            tcp_keepalive_retries: 3,
+            import_allow_http: false,
+            webhook_allow_http: false,
+            circuit_breaker_failure_threshold: 5,
+            circuit_breaker_open_secs: 30,
+            trusted_proxy_depth: 0,
+            client_ip_allow_private: false,
+            http_node_allow_private: false,
+            user_rate_limit_capacity: 120,
+            user_rate_limit_refill_per_sec: 30.0,
+            cors_allowed_origins: vec![CORS_ANY.to_string()],
+            cors_allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"].map(String::from).to_vec(),
+            cors_allowed_headers: [
+                "content-type",
+                "authorization",
+                "x-requested-with",
+                "x-api-version",
+                "x-api-key",
+                "x-signature-sha256",
+                "x-csrf-token",
+                "idempotency-key",
+                "x-request-id",
+            ].map(String::from).to_vec(),
+            cors_allow_credentials: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// 读取配置文件
+    ///
+    /// panic: 文件存在但无法读取、解析或校验不通过时 (配置错误应在启动时暴露)
+    fn load() -> Self {
+        let path = std::env::var(CONFIG_PATH_ENV).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!("config file {} not found, using defaults", path);
+                return Self::default();
+            }
+            Err(err) => panic!("failed to read config file {}: {}", path, err),
+        };
+        let config: Self = toml::from_str(&text)
+            .unwrap_or_else(|err| panic!("failed to parse config file {}: {}", path, err));
+        if let Err(err) = config.validate() {
+            panic!("invalid config file {}: {}", path, err);
+        }
+        tracing::info!("loaded config from {}", path);
+        config
+    }
+
+    /// 校验各项的取值
+    ///
+    /// CORS 见 [`CorsConfig::validate`]
+    pub fn validate(&self) -> Result<(), String> {
+        CorsConfig::from_server_config(self).validate().map_err(|err| format!("cors_{}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cors_validation() {
+        assert_eq!(ServerConfig::default().validate(), Ok(()));
+
+        let credentials_with_any = ServerConfig { cors_allow_credentials: true, ..Default::default() };
+        assert!(credentials_with_any.validate().unwrap_err().starts_with("cors_allowed_origins"));
+
+        let credentials = ServerConfig {
+            cors_allow_credentials: true,
+            cors_allowed_origins: vec!["https://app.example.com".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(credentials.validate(), Ok(()));
+
+        let mixed = ServerConfig { cors_allowed_methods: vec!["GET".to_string(), "*".to_string()], ..Default::default() };
+        assert!(mixed.validate().is_err());
+        let invalid = ServerConfig { cors_allowed_headers: vec!["bad header".to_string()], ..Default::default() };
+        assert_eq!(invalid.validate(), Err("cors_allowed_headers: invalid header bad header".to_string()));
+        assert!(credentials_with_any.validate().unwrap_err().ends_with("when allow_credentials is true"));
+    }
+}
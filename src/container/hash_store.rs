@@ -0,0 +1,867 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError}; // 线程安全共享指针和读写锁
+use std::sync::atomic::{AtomicU64, Ordering}; // 计数器
+use std::time::Instant;
+use tokio::sync::broadcast; // 变更订阅 (watch)
+use tracing::{field::Empty, Span}; // 操作的 span (键、结果、等锁时间)
+// use std::thread;
+
+/// 一个线程安全的容器，封装了一个具有字符串键和泛型值的HashMap。
+/// 
+/// 特性：
+/// - 使用RwLock确保线程安全操作
+/// - 字符串类型的键，泛型类型的值
+/// - 基本操作：get、put、delete、...
+/// 
+/// 为安全性，禁止直接编辑返回的元素。这样只需要保证容器是多线程安全的就行了
+/// 
+/// 底层映射 `M` 默认为 HashMap (遍历顺序不定)。需按键排序时用 [`super::sorted_store::SortedContainer`]
+/// 
+/// `get_by_id`/`put_by_id`/`delete_by_id`/`get_all` 带 debug 级别的 span，记录键、是否找到与等锁时间 (`lock_wait_us`)，
+/// 锁被占用时另记录一个事件
+#[derive(Clone)]
+pub struct Container<T, M = HashMap<String, T>> {
+    data: Arc<RwLock<M>>,
+    hooks: Arc<RwLock<Vec<Hook<T>>>>,
+    watchers: Arc<broadcast::Sender<ContainerEvent<T>>>,
+    counters: Arc<Mutex<HashMap<String, Arc<AtomicU64>>>>,
+    versions: Arc<Mutex<Versions>>,
+}
+
+/// 订阅的通道容量，订阅者落后超过此数量时丢失最早的事件
+const WATCH_CAPACITY: usize = 256;
+
+/// 容器的底层映射 (键为字符串)
+/// 
+/// 只包含容器用到的操作，由 HashMap 与 BTreeMap 实现
+pub trait MapBackend<T>: Default {
+    fn get(&self, key: &str) -> Option<&T>;
+    fn insert(&mut self, key: String, value: T) -> Option<T>;
+    fn remove(&mut self, key: &str) -> Option<T>;
+    fn contains_key(&self, key: &str) -> bool;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn values<'a>(&'a self) -> impl Iterator<Item = &'a T> where T: 'a;
+    fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (&'a String, &'a mut T)> where T: 'a;
+    /// 移除并返回所有满足条件的项
+    fn extract_if(&mut self, predicate: impl FnMut(&T) -> bool) -> Vec<(String, T)>;
+}
+
+impl<T> MapBackend<T> for HashMap<String, T> {
+    fn get(&self, key: &str) -> Option<&T> {
+        HashMap::get(self, key)
+    }
+    fn insert(&mut self, key: String, value: T) -> Option<T> {
+        HashMap::insert(self, key, value)
+    }
+    fn remove(&mut self, key: &str) -> Option<T> {
+        HashMap::remove(self, key)
+    }
+    fn contains_key(&self, key: &str) -> bool {
+        HashMap::contains_key(self, key)
+    }
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+    fn values<'a>(&'a self) -> impl Iterator<Item = &'a T> where T: 'a {
+        HashMap::values(self)
+    }
+    fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (&'a String, &'a mut T)> where T: 'a {
+        HashMap::iter_mut(self)
+    }
+    fn extract_if(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Vec<(String, T)> {
+        HashMap::extract_if(self, |_, value| predicate(value)).collect()
+    }
+}
+
+/// 容器事件，传给事件钩子
+/// 
+/// - `Put` 中的 `old` 为被覆盖的旧值 (若有)
+pub enum HookEvent<'a, T> {
+    Put { key: &'a str, old: Option<&'a T>, new: &'a T },
+    Delete { key: &'a str, value: &'a T },
+}
+
+/// 容器变更，由 [`Container::watch`] 订阅
+/// 
+/// 与 [`HookEvent`] 不同，值是复制出来的，订阅者在锁外异步处理
+#[derive(Debug, Clone)]
+pub enum ContainerEvent<T> {
+    Inserted { key: String, value: T },
+    Updated { key: String, old: T, new: T },
+    Deleted { key: String, value: T },
+}
+
+impl<T: Clone> ContainerEvent<T> {
+    fn from_hook(event: &HookEvent<T>) -> Self {
+        match *event {
+            HookEvent::Put { key, old: None, new } => Self::Inserted { key: key.to_string(), value: new.clone() },
+            HookEvent::Put { key, old: Some(old), new } => Self::Updated { key: key.to_string(), old: old.clone(), new: new.clone() },
+            HookEvent::Delete { key, value } => Self::Deleted { key: key.to_string(), value: value.clone() },
+        }
+    }
+}
+
+/// 计数器，见 [`Container::atomic_counter`]
+/// 
+/// 可 clone，各副本指向同一个计数。增减无锁
+#[derive(Debug, Clone)]
+pub struct AtomicCounterHandle(Arc<AtomicU64>);
+
+impl AtomicCounterHandle {
+    /// 增加，返回增加前的值
+    pub fn fetch_add(&self, value: u64, ordering: Ordering) -> u64 {
+        self.0.fetch_add(value, ordering)
+    }
+
+    /// 减少，返回减少前的值 (不检查下溢)
+    pub fn fetch_sub(&self, value: u64, ordering: Ordering) -> u64 {
+        self.0.fetch_sub(value, ordering)
+    }
+
+    /// 当前值
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// 设为指定值
+    pub fn reset(&self, value: u64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+}
+
+/// 各键的版本号，见 [`Container::versioned_put`]
+/// 
+/// - `latest` 已分配的最大版本号，每次增加/修改时加一后分配给该键
+/// - `keys` 各键当前的版本号，删除时移除
+#[derive(Debug, Default)]
+struct Versions {
+    latest: u64,
+    keys: HashMap<String, u64>,
+}
+
+/// 从快照恢复的项没有版本记录，视为此版本
+const RESTORED_VERSION: u64 = 1;
+
+/// [`Container::versioned_put`] 的版本不匹配
+/// 
+/// - `current_version` 当前的版本号，键不存在时为0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CasError {
+    pub current_version: u64,
+}
+
+impl std::fmt::Display for CasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "version mismatch, current version is {}", self.current_version)
+    }
+}
+
+impl std::error::Error for CasError {}
+
+/// 带创建时间的存储项
+pub trait Timestamped {
+    fn created_at(&self) -> DateTime<Utc>;
+}
+
+/// 事件钩子
+type Hook<T> = Arc<dyn Fn(&HookEvent<T>) + Send + Sync>;
+
+impl<T, M: std::fmt::Debug> std::fmt::Debug for Container<T, M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Container")
+            .field("data", &self.data)
+            .field("hooks", &self.hooks.read().unwrap().len())
+            .finish()
+    }
+}
+
+impl<T, M: MapBackend<T>> Container<T, M> {
+    /// 创建对象
+    fn new() -> Self {
+        Self::from_map(M::default())
+    }
+
+    /// 创建对象
+    pub fn new_arc() -> Arc<Self> {
+        Arc::new(Self::new())
+    }
+
+    // ---------------- 增删改查 ----------------
+
+    /// 获取
+    #[tracing::instrument(level = "debug", skip(self), fields(found = Empty, lock_wait_us = Empty))]
+    pub fn get_by_id(&self, key: &str) -> Option<T>
+    where
+        T: Clone,
+    {
+        let map = self.read_traced();
+        let value = map.get(key).cloned();
+        Span::current().record("found", value.is_some());
+        value
+    }
+
+    /// 获取 - 全部
+    #[tracing::instrument(level = "debug", skip(self), fields(len = Empty, lock_wait_us = Empty))]
+    pub fn get_all(&self) -> M
+    where
+        M: Clone,
+    {
+        let map = self.read_traced();
+        Span::current().record("len", map.len());
+        map.clone()
+    }
+
+    /// 获取 - 全部的值
+    pub fn values(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let map = self.data.read().unwrap();
+        map.values().cloned().collect()
+    }
+
+    /// 获取 - 条件，返回所有满足条件的项 (一次读锁)
+    pub fn find_where<F>(&self, predicate: F) -> Vec<T>
+    where
+        T: Clone,
+        F: Fn(&T) -> bool,
+    {
+        let map = self.data.read().unwrap();
+        map.values().filter(|value| predicate(value)).cloned().collect()
+    }
+
+    /// 获取 - 全部的值，按 `key_fn` 排序 (一次读锁，排序在锁外)
+    pub fn get_all_sorted_by<K, F>(&self, key_fn: F) -> Vec<T>
+    where
+        T: Clone,
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        let mut values = self.values();
+        values.sort_by_cached_key(|value| key_fn(value));
+        values
+    }
+
+    /// 获取 - 满足条件的项按 `key_fn` 排序后的一页，返回该页与 (过滤后的) 总数
+    /// 
+    /// 在读锁内排序引用，只复制该页的项
+    pub fn get_page_sorted_by<K, F, P>(&self, predicate: P, key_fn: F, offset: usize, limit: usize) -> (Vec<T>, usize)
+    where
+        T: Clone,
+        K: Ord,
+        F: Fn(&T) -> K,
+        P: Fn(&T) -> bool,
+    {
+        let map = self.data.read().unwrap();
+        let mut values: Vec<&T> = map.values().filter(|value| predicate(value)).collect();
+        values.sort_by_cached_key(|value| key_fn(value));
+        let total = values.len();
+        let page = values.into_iter().skip(offset).take(limit).cloned().collect();
+        (page, total)
+    }
+
+    /// 获取 - 键是否存在
+    pub fn _get_is(&self, key: &str) -> bool {
+        let map = self.data.read().unwrap();
+        map.contains_key(key)
+    }
+
+    // /// 增加 - 随机
+    // 略，由上层实现
+
+    /// 增加 - 覆盖
+    #[tracing::instrument(level = "debug", skip(self, value), fields(found = Empty, lock_wait_us = Empty))]
+    pub fn put_by_id(&self, key: &str, value: T) -> Option<T>
+    where
+        T: Clone,
+    {
+        let mut map = self.write_traced();
+        let old = map.insert(key.to_string(), value);
+        Span::current().record("found", old.is_some());
+        if let Some(new) = map.get(key) {
+            self.fire(&HookEvent::Put { key, old: old.as_ref(), new });
+        }
+        old
+    }
+
+    /// 增加 - 新增，键已存在时不修改，返回 `Err` 及现有项 (检查与插入在同一次写锁内)
+    pub fn put_if_absent(&self, key: &str, value: T) -> Result<(), T>
+    where
+        T: Clone,
+    {
+        let mut map = self.data.write().unwrap();
+        if let Some(existing) = map.get(key) {
+            return Err(existing.clone());
+        }
+        map.insert(key.to_string(), value);
+        if let Some(new) = map.get(key) {
+            self.fire(&HookEvent::Put { key, old: None, new });
+        }
+        Ok(())
+    }
+
+    /// 修改 - 原子地读取并替换 (一次写锁，期间其他修改需等待)
+    /// 
+    /// `update` 由旧值计算新值，返回 `Err` 时不修改。键不存在时返回 `None`，否则返回旧值与新值
+    pub fn update_by_id<F, E>(&self, key: &str, update: F) -> Option<Result<(T, T), E>>
+    where
+        T: Clone,
+        F: FnOnce(&T) -> Result<T, E>,
+    {
+        let mut map = self.data.write().unwrap();
+        let old = map.get(key)?.clone();
+        let new = match update(&old) {
+            Ok(new) => new,
+            Err(err) => return Some(Err(err)),
+        };
+        map.insert(key.to_string(), new.clone());
+        self.fire(&HookEvent::Put { key, old: Some(&old), new: &new });
+        Some(Ok((old, new)))
+    }
+
+    /// 修改 - 条件，对所有满足条件的项原子地替换为 `update` 的返回值 (一次写锁)，返回修改后的项
+    pub fn update_where<P, F>(&self, predicate: P, update: F) -> Vec<T>
+    where
+        T: Clone,
+        P: Fn(&T) -> bool,
+        F: Fn(&T) -> T,
+    {
+        let mut map = self.data.write().unwrap();
+        let mut updated = Vec::new();
+        for (key, value) in map.iter_mut().filter(|(_, value)| predicate(value)) {
+            let old = std::mem::replace(value, update(value));
+            self.fire(&HookEvent::Put { key, old: Some(&old), new: value });
+            updated.push(value.clone());
+        }
+        updated
+    }
+
+    /// 删除
+    #[tracing::instrument(level = "debug", skip(self), fields(found = Empty, lock_wait_us = Empty))]
+    pub fn delete_by_id(&self, key: &str) -> Option<T>
+    where
+        T: Clone,
+    {
+        let mut map = self.write_traced();
+        let old = map.remove(key);
+        Span::current().record("found", old.is_some());
+        if let Some(value) = &old {
+            self.fire(&HookEvent::Delete { key, value });
+        }
+        old
+    }
+
+    /// 删除 - 条件，返回被删除的项
+    pub fn delete_where<F>(&self, predicate: F) -> Vec<T>
+    where
+        T: Clone,
+        F: Fn(&T) -> bool,
+    {
+        let mut map = self.data.write().unwrap();
+        let removed = map.extract_if(predicate);
+        for (key, value) in &removed {
+            self.fire(&HookEvent::Delete { key, value });
+        }
+        removed.into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// 删除 - 清空
+    pub fn _delete_all(&self)
+    where
+        T: Clone,
+    {
+        let mut map = self.data.write().unwrap();
+        for (key, value) in map.extract_if(|_| true) {
+            self.fire(&HookEvent::Delete { key: &key, value: &value });
+        }
+    }
+
+    // ---------------- 版本号 ----------------
+    // 每次增加/修改都会为该项分配新的版本号，在容器内单调递增 (删除后重建的项不会复用旧版本号)。
+    // 不存在的键版本号为0。版本号不参与序列化
+
+    /// 获取 - 值与其版本号
+    pub fn versioned_get(&self, key: &str) -> Option<(T, u64)>
+    where
+        T: Clone,
+    {
+        let map = self.data.read().unwrap();
+        let value = map.get(key)?.clone();
+        Some((value, self.version_of(&*map, key)))
+    }
+
+    /// 增加 - 比较并替换: 仅当当前版本号等于 `expected_version` 时写入 (检查与写入在同一次写锁内)
+    /// 
+    /// 成功时返回新的版本号。`expected_version` 为0表示仅在键不存在时创建
+    pub fn versioned_put(&self, key: &str, value: T, expected_version: u64) -> Result<u64, CasError>
+    where
+        T: Clone,
+    {
+        let mut map = self.data.write().unwrap();
+        let current_version = self.version_of(&*map, key);
+        if current_version != expected_version {
+            return Err(CasError { current_version });
+        }
+        let old = map.insert(key.to_string(), value);
+        if let Some(new) = map.get(key) {
+            self.fire(&HookEvent::Put { key, old: old.as_ref(), new });
+        }
+        Ok(self.version_of(&*map, key))
+    }
+
+    /// 修改 - 同 [`Container::update_by_id`]，`update` 另外得到当前版本号 (可据此比较并替换)。
+    /// 键不存在时返回 `None`，否则返回新值与新的版本号
+    pub fn versioned_update_by_id<F, E>(&self, key: &str, update: F) -> Option<Result<(T, u64), E>>
+    where
+        T: Clone,
+        F: FnOnce(&T, u64) -> Result<T, E>,
+    {
+        let mut map = self.data.write().unwrap();
+        let old = map.get(key)?.clone();
+        let new = match update(&old, self.version_of(&*map, key)) {
+            Ok(new) => new,
+            Err(err) => return Some(Err(err)),
+        };
+        map.insert(key.to_string(), new.clone());
+        self.fire(&HookEvent::Put { key, old: Some(&old), new: &new });
+        Some(Ok((new, self.version_of(&*map, key))))
+    }
+
+    /// 当前版本号，需在持有锁时调用
+    fn version_of(&self, map: &M, key: &str) -> u64 {
+        if !map.contains_key(key) {
+            return 0;
+        }
+        self.versions.lock().unwrap().keys.get(key).copied().unwrap_or(RESTORED_VERSION)
+    }
+
+    // ---------------- 非阻塞 ----------------
+    // 锁被占用时立即返回 `Err(TryLockError::WouldBlock)`，而不是等待。用于不应被写竞争拖慢的路径 (如中间件)
+
+    /// 获取 - 非阻塞
+    pub fn try_get_by_id(&self, key: &str) -> Result<Option<T>, TryLockError<()>>
+    where
+        T: Clone,
+    {
+        let map = self.data.try_read().map_err(discard_guard)?;
+        Ok(map.get(key).cloned())
+    }
+
+    /// 增加 - 覆盖，非阻塞
+    pub fn try_put_by_id(&self, key: &str, value: T) -> Result<Option<T>, TryLockError<()>>
+    where
+        T: Clone,
+    {
+        let mut map = self.try_write()?;
+        let old = map.insert(key.to_string(), value);
+        if let Some(new) = map.get(key) {
+            self.fire(&HookEvent::Put { key, old: old.as_ref(), new });
+        }
+        Ok(old)
+    }
+
+    /// 删除 - 非阻塞
+    pub fn try_delete_by_id(&self, key: &str) -> Result<Option<T>, TryLockError<()>>
+    where
+        T: Clone,
+    {
+        let mut map = self.try_write()?;
+        let old = map.remove(key);
+        if let Some(value) = &old {
+            self.fire(&HookEvent::Delete { key, value });
+        }
+        Ok(old)
+    }
+
+    fn try_write(&self) -> Result<RwLockWriteGuard<'_, M>, TryLockError<()>> {
+        self.data.try_write().map_err(discard_guard)
+    }
+
+    // ---------------- 锁的追踪 ----------------
+    // 供带 span 的方法使用: 先尝试获取锁，被占用时记录一个竞争事件再等待，并把等待时间记入 span 的 `lock_wait_us`
+
+    fn read_traced(&self) -> RwLockReadGuard<'_, M> {
+        if let Ok(guard) = self.data.try_read() {
+            Span::current().record("lock_wait_us", 0);
+            return guard;
+        }
+        tracing::debug!("read lock contended");
+        let start = Instant::now();
+        let guard = self.data.read().unwrap(); // 中毒时与其他方法一样 panic
+        Span::current().record("lock_wait_us", start.elapsed().as_micros() as u64);
+        guard
+    }
+
+    fn write_traced(&self) -> RwLockWriteGuard<'_, M> {
+        if let Ok(guard) = self.data.try_write() {
+            Span::current().record("lock_wait_us", 0);
+            return guard;
+        }
+        tracing::debug!("write lock contended");
+        let start = Instant::now();
+        let guard = self.data.write().unwrap();
+        Span::current().record("lock_wait_us", start.elapsed().as_micros() as u64);
+        guard
+    }
+
+    // ---------------- 事务 ----------------
+
+    /// 在一次写锁内执行 `f`，用于需要原子地修改多个键的操作 (如链表节点的重新链接)
+    /// 
+    /// `f` 可读取整个底层映射 (`Transaction` 可解引用为 `M`)，经 [`Transaction::insert`]/[`Transaction::remove`] 修改，
+    /// 每次修改照常更新版本号、触发事件钩子并通知订阅者。
+    /// 
+    /// 注意:
+    /// - `f` 内不能调用本容器的其他方法 (会死锁)，只能经 `Transaction` 访问
+    /// - 没有回滚: `f` 中途返回时已做的修改仍然生效，应先检查完毕再修改
+    pub fn transaction<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Transaction<'_, T, M>) -> R,
+    {
+        let map = self.data.write().unwrap();
+        f(&mut Transaction { container: self, map })
+    }
+
+    // ---------------- 计数器 ----------------
+    // 与存储项分开存放，不触发事件钩子，也不参与序列化。
+    // 用于限流、访问计数等，避免 "读取 → 加一 → 写回" 在并发下丢失计数
+
+    /// 获取计数器，不存在时创建 (初始为0)
+    /// 
+    /// 只在查找时加锁，之后通过句柄增减是无锁的。频繁使用时可保留句柄
+    pub fn atomic_counter(&self, key: &str) -> AtomicCounterHandle {
+        let mut counters = self.counters.lock().unwrap();
+        if let Some(counter) = counters.get(key) {
+            return AtomicCounterHandle(counter.clone());
+        }
+        let counter = Arc::new(AtomicU64::new(0));
+        counters.insert(key.to_string(), counter.clone());
+        AtomicCounterHandle(counter)
+    }
+
+    /// 全部计数器的当前值
+    pub fn counters(&self) -> Vec<(String, u64)> {
+        let counters = self.counters.lock().unwrap();
+        counters.iter().map(|(key, counter)| (key.clone(), counter.load(Ordering::Relaxed))).collect()
+    }
+
+    // ---------------- 事件钩子 ----------------
+
+    /// 注册事件钩子，在增加/删除后调用
+    /// 
+    /// 钩子在持有写锁期间同步执行，以保证事件顺序与实际修改一致。
+    /// 因此钩子内不能再访问本容器 (会死锁)，访问其他容器则没问题
+    pub fn add_hook<F>(&self, hook: F)
+    where
+        F: Fn(&HookEvent<T>) + Send + Sync + 'static,
+    {
+        self.hooks.write().unwrap().push(Arc::new(hook));
+    }
+
+    /// 订阅变更 (增加/修改/删除)
+    /// 
+    /// 事件在写锁内按修改顺序发出，订阅者在锁外异步接收，可与 `tokio::select!` 组合。
+    /// 订阅者落后过多时收到 `RecvError::Lagged` 并丢失部分事件
+    pub fn watch(&self) -> broadcast::Receiver<ContainerEvent<T>> {
+        self.watchers.subscribe()
+    }
+
+    /// 更新版本号，触发事件钩子，并通知订阅者
+    fn fire(&self, event: &HookEvent<T>)
+    where
+        T: Clone,
+    {
+        {
+            let mut versions = self.versions.lock().unwrap();
+            match *event {
+                HookEvent::Put { key, .. } => {
+                    versions.latest += 1;
+                    let version = versions.latest;
+                    versions.keys.insert(key.to_string(), version);
+                }
+                HookEvent::Delete { key, .. } => {
+                    versions.keys.remove(key);
+                }
+            }
+        }
+        for hook in self.hooks.read().unwrap().iter() {
+            hook(event);
+        }
+        if self.watchers.receiver_count() > 0 { // 无订阅者时不复制
+            let _ = self.watchers.send(ContainerEvent::from_hook(event));
+        }
+    }
+
+    // ---------------- 其他 --------------------
+
+    /// 底层映射的读锁，供其他容器变体实现特有的查询 (如按范围查询)
+    pub(super) fn read(&self) -> RwLockReadGuard<'_, M> {
+        self.data.read().unwrap()
+    }
+
+    /// 获取当前元素数量
+    pub fn len(&self) -> usize {
+        let map = self.data.read().unwrap();
+        map.len()
+    }
+
+    /// 估算占用: 序列化为JSON后的字节数 (并非实际内存占用)
+    pub fn estimated_bytes(&self) -> usize
+    where
+        M: Serialize,
+    {
+        let map = self.data.read().unwrap();
+        serde_json::to_vec(&*map).map_or(0, |json| json.len())
+    }
+
+    /// 最早与最晚的创建时间，容器为空时返回None
+    pub fn created_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)>
+    where
+        T: Timestamped,
+    {
+        let map = self.data.read().unwrap();
+        let oldest = map.values().map(Timestamped::created_at).min()?;
+        let newest = map.values().map(Timestamped::created_at).max()?;
+        Some((oldest, newest))
+    }
+
+    /// 检查容器是否为空
+    pub fn is_empty(&self) -> bool {
+        let map = self.data.read().unwrap();
+        map.is_empty()
+    }
+
+    /// 锁是否已中毒 (持锁线程 panic)。中毒后所有操作都会 panic，容器不再可用
+    pub fn is_poisoned(&self) -> bool {
+        self.data.is_poisoned() || self.hooks.is_poisoned()
+    }
+}
+
+/// 事务中对容器的访问，见 [`Container::transaction`]
+pub struct Transaction<'a, T, M> {
+    container: &'a Container<T, M>,
+    map: RwLockWriteGuard<'a, M>,
+}
+
+impl<T, M> Deref for Transaction<'_, T, M> {
+    type Target = M;
+
+    fn deref(&self) -> &M {
+        &self.map
+    }
+}
+
+impl<T: Clone, M: MapBackend<T>> Transaction<'_, T, M> {
+    /// 获取 (复制)
+    pub fn get_by_id(&self, key: &str) -> Option<T> {
+        self.map.get(key).cloned()
+    }
+
+    /// 增加 - 覆盖，返回旧值
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+        let old = self.map.insert(key.to_string(), value);
+        if let Some(new) = self.map.get(key) {
+            self.container.fire(&HookEvent::Put { key, old: old.as_ref(), new });
+        }
+        old
+    }
+
+    /// 删除，返回被删除的值
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+        let old = self.map.remove(key);
+        if let Some(value) = &old {
+            self.container.fire(&HookEvent::Delete { key, value });
+        }
+        old
+    }
+}
+
+// 实现Default trait，提供便利
+impl<V, M> Default for Container<V, M>
+where
+    V: Clone + Send + Sync + 'static,
+    M: MapBackend<V>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 去掉错误中的锁守卫，以便返回给调用方
+fn discard_guard<G>(err: TryLockError<G>) -> TryLockError<()> {
+    match err {
+        TryLockError::WouldBlock => TryLockError::WouldBlock,
+        TryLockError::Poisoned(_) => TryLockError::Poisoned(PoisonError::new(())),
+    }
+}
+
+/// 序列化为 JSON 对象 (键到值)，用于导出快照。序列化期间持有读锁
+impl<T, M: Serialize> Serialize for Container<T, M> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.data.read().unwrap().serialize(serializer)
+    }
+}
+
+/// 从 JSON 对象恢复 (不含事件钩子，需重新注册)
+impl<'de, T, M: Deserialize<'de>> Deserialize<'de> for Container<T, M> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        M::deserialize(deserializer).map(Self::restored)
+    }
+}
+
+impl<T, M: MapBackend<T>> From<M> for Container<T, M> {
+    fn from(map: M) -> Self {
+        Self::restored(map)
+    }
+}
+
+impl<T, M> Container<T, M> {
+    fn from_map(map: M) -> Self {
+        Container {
+            data: Arc::new(RwLock::new(map)),
+            hooks: Arc::new(RwLock::new(Vec::new())),
+            watchers: Arc::new(broadcast::Sender::new(WATCH_CAPACITY)),
+            counters: Arc::new(Mutex::new(HashMap::new())),
+            versions: Arc::new(Mutex::new(Versions::default())),
+        }
+    }
+
+    /// 由已有数据创建，已有的项版本号为 [`RESTORED_VERSION`]，新版本号从其后分配
+    fn restored(map: M) -> Self {
+        let container = Self::from_map(map);
+        container.versions.lock().unwrap().latest = RESTORED_VERSION;
+        container
+    }
+}
+
+/*
+// 添加静态测试方法
+impl Container<i32> {
+    /// 静态测试方法，展示ThreadSafeContainer的基本用法
+    pub fn test() {
+        println!("=== 测试整数类型容器 ===");
+        
+        // 创建一个整数类型的容器
+        let int_container = Container::<i32>::new();
+        
+        // 添加一些值
+        int_container.put_by_id("one".to_string(), 1);
+        int_container.put_by_id("two".to_string(), 2);
+        int_container.put_by_id("three".to_string(), 3);
+        
+        println!("容器包含 {} 个项目", int_container.len());
+        println!("键'one'对应的值: {:?}", int_container.get_by_id("one"));
+        
+        // 删除一个项目
+        int_container.delete_by_id("two");
+        println!("删除后，容器包含'two': {}", int_container.contains_key("two"));
+    }
+}
+
+impl Container<String> {
+    /// 静态测试方法，展示ThreadSafeContainer的字符串用法
+    pub fn test() {
+        println!("=== 测试字符串类型容器 ===");
+        
+        // 使用字符串值
+        let string_container = Container::<String>::new();
+        string_container.put_by_id("greeting".to_string(), "你好，世界！".to_string());
+        println!("greeting: {:?}", string_container.get_by_id("greeting"));
+        
+        // 线程安全性演示
+        println!("\n=== 测试多线程安全性 ===");
+        let container = Container::<String>::new();
+        container.put_by_id("shared".to_string(), "初始值".to_string());
+        
+        let container_ref = &container;
+        
+        let handles: Vec<_> = (0..5).map(|i| {
+            let container = container_ref;
+            thread::spawn(move || {
+                // 读取操作是线程安全的
+                println!("线程 {}: 当前值是 {:?}", i, container.get_by_id("shared"));
+                
+                // 写入操作也是线程安全的
+                container.put_by_id(format!("thread_{}", i), format!("来自线程 {}", i));
+                
+                if i == 3 {
+                    // 一个线程更新共享值
+                    container.put_by_id("shared".to_string(), format!("被线程 {} 更新", i));
+                }
+            })
+        }).collect();
+        
+        // 等待所有线程完成
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        
+        // 打印最终状态
+        println!("'shared'的最终值: {:?}", container.get_by_id("shared"));
+        // println!("所有键: {:?}", container.keys());
+    }
+}*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn try_ops_would_block_while_write_locked() {
+        let container = Container::<i32>::new_arc();
+        container.put_by_id("a", 1);
+
+        // 另一个线程持有写锁，直到收到释放信号
+        let (locked_tx, locked_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let holder = {
+            let container = container.clone();
+            thread::spawn(move || {
+                let _guard = container.data.write().unwrap();
+                locked_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+            })
+        };
+        locked_rx.recv().unwrap();
+
+        let started = Instant::now();
+        assert!(matches!(container.try_get_by_id("a"), Err(TryLockError::WouldBlock)));
+        assert!(matches!(container.try_put_by_id("a", 2), Err(TryLockError::WouldBlock)));
+        assert!(matches!(container.try_delete_by_id("a"), Err(TryLockError::WouldBlock)));
+        assert!(started.elapsed() < Duration::from_secs(1), "try_* must not wait for the lock");
+
+        release_tx.send(()).unwrap();
+        holder.join().unwrap();
+        assert_eq!(container.try_put_by_id("a", 2).unwrap(), Some(1));
+        assert_eq!(container.try_get_by_id("a").unwrap(), Some(2));
+        assert_eq!(container.try_delete_by_id("a").unwrap(), Some(2));
+    }
+
+    #[test]
+    fn try_ops_report_poisoned_lock() {
+        let container = Container::<i32>::new_arc();
+        container.put_by_id("a", 1);
+
+        // 钩子在持有写锁时 panic，锁被毒化
+        container.add_hook(|event| {
+            if let HookEvent::Put { new: 2, .. } = event {
+                panic!("hook failed");
+            }
+        });
+        let writer = container.clone();
+        assert!(thread::spawn(move || writer.put_by_id("a", 2)).join().is_err());
+
+        assert!(matches!(container.try_get_by_id("a"), Err(TryLockError::Poisoned(_))));
+        assert!(matches!(container.try_put_by_id("a", 3), Err(TryLockError::Poisoned(_))));
+        assert!(matches!(container.try_delete_by_id("a"), Err(TryLockError::Poisoned(_))));
+    }
+}
@@ -1,3 +1,22 @@
-// pub mod rest_todos;
-pub mod rest_store;
-// pub mod rest_node;
+//! 存储容器
+//!
+//! 各变体的接口相同，调用方从这里引入 (`use crate::container::Container`)，不依赖具体的文件:
+//!
+//! - [`Container`] 底层为 HashMap，遍历顺序不定 (`hash_store`)
+//! - [`SortedContainer`] 底层为 BTreeMap，按键排序 (`sorted_store`)
+//!
+//! 持久化后端 (sled/redis/sqlite) 由对应的 feature 启用，作为容器的钩子同步写入
+
+pub mod hash_store;
+pub mod sorted_store;
+pub mod versioned_store;
+#[cfg(feature = "sled")]
+pub mod sled_store;
+#[cfg(feature = "redis")]
+pub mod redis_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+
+pub use hash_store::{CasError, Container, ContainerEvent, HookEvent, MapBackend, Timestamped, Transaction};
+pub use sorted_store::SortedContainer;
+pub use versioned_store::VersionHistory;
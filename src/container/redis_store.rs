@@ -0,0 +1,227 @@
+//! Redis 存储 (feature `redis`)
+//!
+//! 地址由环境变量 `REDIS_URL` 指定 (如 `redis://127.0.0.1/`)，未设置时仍只用内存存储。
+//! 可选的 `REDIS_TTL_SECS` 为写入的键设置过期时间。
+//!
+//! 各项以JSON存于键 `{namespace}:{id}`。与 [`super::sqlite_store`] 相同，处理函数仍使用内存中的 [`Container`]，
+//! 由 [`RedisContainer::attach`] 在启动时加载并写回修改。
+//! 因此多个实例共享同一份持久数据，但其他实例的修改要在重启后才可见。
+//! 连接为多路复用的 [`ConnectionManager`]，各请求共用，断开后自动重连
+
+use redis::{aio::ConnectionManager, AsyncCommands, Client};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+use super::{Container, HookEvent, MapBackend};
+use crate::supervisor::TaskSupervisor;
+
+/// Redis 地址的环境变量
+const REDIS_URL_ENV: &str = "REDIS_URL";
+/// 过期时间 (秒) 的环境变量
+const REDIS_TTL_ENV: &str = "REDIS_TTL_SECS";
+/// `SCAN` 每批的数量
+const SCAN_BATCH: usize = 100;
+/// 健康检查的 `PING` 间隔
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 以 Redis 为后端的容器，接口与 [`Container`] 一致 (但为异步)
+#[derive(Clone)]
+pub struct RedisContainer<T> {
+    conn: ConnectionManager,
+    namespace: String,
+    /// 过期时间 (秒)，None 为不过期
+    ttl: Option<u64>,
+    /// 最近一次 `PING` 是否成功
+    healthy: Arc<AtomicBool>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// 待写回的修改
+enum WriteOp<T> {
+    Put(String, T),
+    Delete(String),
+}
+
+impl<T> RedisContainer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// 连接 `REDIS_URL` 指定的 Redis，未设置时返回 None
+    pub async fn connect_from_env(namespace: &str) -> Option<Result<Self, String>> {
+        let url = std::env::var(REDIS_URL_ENV).ok()?;
+        let ttl = std::env::var(REDIS_TTL_ENV).ok().and_then(|ttl| ttl.parse().ok());
+        Some(Self::connect(&url, namespace, ttl).await)
+    }
+
+    /// 连接 Redis，并 `PING` 一次以尽早发现配置错误
+    pub async fn connect(url: &str, namespace: &str, ttl: Option<u64>) -> Result<Self, String> {
+        let client = Client::open(url).map_err(|err| format!("invalid redis url {}: {}", url, err))?;
+        let conn = ConnectionManager::new(client).await
+            .map_err(|err| format!("failed to connect to {}: {}", url, err))?;
+        let container = Self {
+            conn,
+            namespace: namespace.to_string(),
+            ttl,
+            healthy: Arc::new(AtomicBool::new(false)),
+            _marker: PhantomData,
+        };
+        container.ping().await?;
+        container.healthy.store(true, Ordering::Relaxed);
+        tracing::info!("connected to {} (namespace {})", url, namespace);
+        Ok(container)
+    }
+
+    // ---------------- 增删改查 ----------------
+
+    /// 获取
+    pub async fn _get_by_id(&self, key: &str) -> Result<Option<T>, String> {
+        let data: Option<String> = self.conn().get(self.key(key)).await.map_err(|err| err.to_string())?;
+        data.map(|data| decode(&data)).transpose()
+    }
+
+    /// 获取 - 全部 (`SCAN` 逐批取键，再 `MGET`)
+    pub async fn get_all(&self) -> Result<HashMap<String, T>, String> {
+        let mut conn = self.conn();
+        let prefix = format!("{}:", self.namespace);
+        let mut result = HashMap::new();
+        let mut cursor: u64 = 0;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(format!("{}*", prefix))
+                .arg("COUNT")
+                .arg(SCAN_BATCH)
+                .query_async(&mut conn)
+                .await
+                .map_err(|err| err.to_string())?;
+            if !keys.is_empty() {
+                let values: Vec<Option<String>> = redis::cmd("MGET")
+                    .arg(&keys)
+                    .query_async(&mut conn)
+                    .await
+                    .map_err(|err| err.to_string())?;
+                for (key, value) in keys.iter().zip(values) {
+                    // 在 SCAN 与 MGET 之间被删除或过期的键为 None
+                    if let (Some(id), Some(value)) = (key.strip_prefix(&prefix), value) {
+                        result.insert(id.to_string(), decode(&value)?);
+                    }
+                }
+            }
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        Ok(result)
+    }
+
+    /// 增加 - 覆盖
+    pub async fn put_by_id(&self, key: &str, value: &T) -> Result<(), String> {
+        let data = serde_json::to_string(value).map_err(|err| err.to_string())?;
+        let mut conn = self.conn();
+        match self.ttl {
+            Some(ttl) => conn.set_ex::<_, _, ()>(self.key(key), data, ttl).await,
+            None => conn.set::<_, _, ()>(self.key(key), data).await,
+        }
+        .map_err(|err| err.to_string())
+    }
+
+    /// 删除，返回是否存在
+    pub async fn delete_by_id(&self, key: &str) -> Result<bool, String> {
+        let removed: u64 = self.conn().del(self.key(key)).await.map_err(|err| err.to_string())?;
+        Ok(removed > 0)
+    }
+
+    // ---------------- 与内存容器同步 ----------------
+
+    /// 把 Redis 中的项加载到 `container`，并在之后把 `container` 的修改写回 Redis
+    ///
+    /// 应在注册其他事件钩子之前调用，以免加载时触发事件
+    pub async fn attach<M: MapBackend<T>>(self, container: &Container<T, M>) -> Result<(), String>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let items = self.get_all().await?;
+        tracing::info!("loaded {} items from redis namespace {}", items.len(), self.namespace);
+        for (key, value) in items {
+            container.put_by_id(&key, value);
+        }
+
+        // 钩子在容器的写锁内同步执行，不能等待 Redis，因此经由通道交给单个写入任务，保证写入顺序
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        container.add_hook(move |event| {
+            let op = match event {
+                HookEvent::Put { key, new, .. } => WriteOp::Put(key.to_string(), (*new).clone()),
+                HookEvent::Delete { key, .. } => WriteOp::Delete(key.to_string()),
+            };
+            let _ = sender.send(op);
+        });
+        let writer = self.clone();
+        tokio::spawn(async move {
+            while let Some(op) = receiver.recv().await {
+                let result = match &op {
+                    WriteOp::Put(key, value) => writer.put_by_id(key, value).await,
+                    WriteOp::Delete(key) => writer.delete_by_id(key).await.map(|_| ()),
+                };
+                if let Err(err) = result {
+                    tracing::error!("failed to write to redis: {}", err);
+                }
+            }
+        });
+
+        let pinger = self.clone();
+        TaskSupervisor::spawn(&format!("redis_ping.{}", self.namespace), move || {
+            let pinger = pinger.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(PING_INTERVAL).await;
+                    let healthy = pinger.ping().await.is_ok();
+                    pinger.healthy.store(healthy, Ordering::Relaxed);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// 健康检查，使用最近一次 `PING` 的结果
+    pub fn check(&self) -> Result<(), String> {
+        if self.healthy.load(Ordering::Relaxed) {
+            Ok(())
+        } else {
+            Err(format!("redis unreachable (namespace {})", self.namespace))
+        }
+    }
+
+    async fn ping(&self) -> Result<(), String> {
+        redis::cmd("PING")
+            .query_async::<String>(&mut self.conn())
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+
+    /// 共用的连接 (克隆开销很小)
+    fn conn(&self) -> ConnectionManager {
+        self.conn.clone()
+    }
+
+    /// 带命名空间的键
+    fn key(&self, id: &str) -> String {
+        format!("{}:{}", self.namespace, id)
+    }
+}
+
+/// 解析存储的JSON
+fn decode<T: DeserializeOwned>(data: &str) -> Result<T, String> {
+    serde_json::from_str(data).map_err(|err| err.to_string())
+}
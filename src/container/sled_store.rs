@@ -0,0 +1,120 @@
+//! sled 存储 (feature `sled`)
+//!
+//! 嵌入式数据库，无需外部服务。目录由环境变量 `SLED_PATH` 指定，默认为 `sled_db`，首次使用时打开。
+//! 每个命名空间对应一个 `Tree`，各项以JSON存储。
+//!
+//! 与 [`super::sqlite_store`] 相同，处理函数仍使用内存中的 [`Container`]，由 [`SledContainer::attach`]
+//! 在启动时加载并写回修改。sled 的接口是同步的，修改直接在事件钩子中写入
+
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::HashMap, marker::PhantomData};
+
+use super::{Container, HookEvent, MapBackend};
+
+/// 数据库目录的环境变量
+const SLED_PATH_ENV: &str = "SLED_PATH";
+/// 默认数据库目录
+const DEFAULT_SLED_PATH: &str = "sled_db";
+
+/// 全局数据库
+///
+/// 打开时执行迁移，见 [`crate::migrations`]
+///
+/// panic: 无法打开或迁移失败时 (如目录被另一个进程占用)
+static SLED_DB: Lazy<sled::Db> = Lazy::new(|| {
+    let path = std::env::var(SLED_PATH_ENV).unwrap_or_else(|_| DEFAULT_SLED_PATH.to_string());
+    let db = sled::open(&path).unwrap_or_else(|err| panic!("failed to open sled database {}: {}", path, err));
+    let version = crate::migrations::run_sled_migrations(&db)
+        .unwrap_or_else(|err| panic!("failed to migrate sled database {}: {}", path, err));
+    tracing::info!("opened sled database {} (schema version {})", path, version);
+    db
+});
+
+/// 把未落盘的修改写入磁盘 (sled 默认每 500ms 落盘一次)，退出前调用
+pub fn flush() {
+    if let Err(err) = SLED_DB.flush() {
+        tracing::error!("failed to flush sled database: {}", err);
+    }
+}
+
+/// 以 sled 为后端的容器，接口与 [`Container`] 一致
+#[derive(Debug, Clone)]
+pub struct SledContainer<T> {
+    tree: sled::Tree,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> SledContainer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// 打开命名空间对应的 `Tree`
+    pub fn open(namespace: &str) -> Result<Self, String> {
+        let tree = SLED_DB.open_tree(namespace).map_err(|err| err.to_string())?;
+        Ok(Self { tree, _marker: PhantomData })
+    }
+
+    // ---------------- 增删改查 ----------------
+
+    /// 获取
+    pub fn _get_by_id(&self, key: &str) -> Result<Option<T>, String> {
+        let data = self.tree.get(key).map_err(|err| err.to_string())?;
+        data.map(|data| decode(&data)).transpose()
+    }
+
+    /// 获取 - 全部
+    pub fn get_all(&self) -> Result<HashMap<String, T>, String> {
+        self.tree.iter()
+            .map(|entry| {
+                let (key, data) = entry.map_err(|err| err.to_string())?;
+                Ok((String::from_utf8_lossy(&key).into_owned(), decode(&data)?))
+            })
+            .collect()
+    }
+
+    /// 增加 - 覆盖
+    pub fn put_by_id(&self, key: &str, value: &T) -> Result<(), String> {
+        let data = serde_json::to_vec(value).map_err(|err| err.to_string())?;
+        self.tree.insert(key, data).map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// 删除，返回是否存在
+    pub fn delete_by_id(&self, key: &str) -> Result<bool, String> {
+        let old = self.tree.remove(key).map_err(|err| err.to_string())?;
+        Ok(old.is_some())
+    }
+
+    // ---------------- 与内存容器同步 ----------------
+
+    /// 把 `Tree` 中的项加载到 `container`，并在之后把 `container` 的修改写回
+    ///
+    /// 加载会触发已注册的钩子 (如索引)，事件推送等钩子应在之后注册
+    pub fn attach<M: MapBackend<T>>(self, container: &Container<T, M>) -> Result<(), String>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let items = self.get_all()?;
+        tracing::info!("loaded {} items from sled tree {}", items.len(), String::from_utf8_lossy(&self.tree.name()));
+        for (key, value) in items {
+            container.put_by_id(&key, value);
+        }
+
+        container.add_hook(move |event| {
+            let result = match event {
+                HookEvent::Put { key, new, .. } => self.put_by_id(key, new),
+                HookEvent::Delete { key, .. } => self.delete_by_id(key).map(|_| ()),
+            };
+            if let Err(err) = result {
+                tracing::error!("failed to write to sled: {}", err);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// 解析存储的JSON
+fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, String> {
+    serde_json::from_slice(data).map_err(|err| err.to_string())
+}
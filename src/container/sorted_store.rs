@@ -0,0 +1,66 @@
+//! 按键排序的容器
+//!
+//! 与 [`Container`] 的接口相同，底层为 BTreeMap: `get_all`/`values`/`find_where` 等按键的字典序返回，
+//! 结果是确定的。另可按键的范围查询 ([`SortedContainer::range`])，用于基于键的游标分页
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use super::{Container, MapBackend};
+
+/// 按键排序的容器，见模块文档
+pub type SortedContainer<T> = Container<T, BTreeMap<String, T>>;
+
+impl<T> MapBackend<T> for BTreeMap<String, T> {
+    fn get(&self, key: &str) -> Option<&T> {
+        BTreeMap::get(self, key)
+    }
+    fn insert(&mut self, key: String, value: T) -> Option<T> {
+        BTreeMap::insert(self, key, value)
+    }
+    fn remove(&mut self, key: &str) -> Option<T> {
+        BTreeMap::remove(self, key)
+    }
+    fn contains_key(&self, key: &str) -> bool {
+        BTreeMap::contains_key(self, key)
+    }
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+    fn values<'a>(&'a self) -> impl Iterator<Item = &'a T> where T: 'a {
+        BTreeMap::values(self)
+    }
+    fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (&'a String, &'a mut T)> where T: 'a {
+        BTreeMap::iter_mut(self)
+    }
+    fn extract_if(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Vec<(String, T)> {
+        BTreeMap::extract_if(self, .., |_, value| predicate(value)).collect()
+    }
+}
+
+impl<T: Clone> SortedContainer<T> {
+    /// 获取 - 键在 `[start, end)` 内的项，按键排序
+    pub fn range(&self, start: &str, end: &str) -> Vec<T> {
+        if start >= end {
+            return Vec::new(); // BTreeMap::range 在 start > end 时会 panic
+        }
+        let map = self.read();
+        map.range::<str, _>((Bound::Included(start), Bound::Excluded(end))).map(|(_, value)| value.clone()).collect()
+    }
+
+    /// 获取 - 键在 `after` 之后 (不含) 且满足条件的前 `limit` 项，按键排序 (游标分页)
+    ///
+    /// 定位为 O(log n)，之后逐项过滤直到取满
+    pub fn range_after<F>(&self, after: &str, limit: usize, predicate: F) -> Vec<T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let map = self.read();
+        map.range::<str, _>((Bound::Excluded(after), Bound::Unbounded))
+            .map(|(_, value)| value)
+            .filter(|value| predicate(value))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
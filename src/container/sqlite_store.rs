@@ -0,0 +1,150 @@
+//! SQLite 存储 (feature `sqlite`)
+//!
+//! 数据库由环境变量 `DATABASE_URL` 指定 (如 `sqlite://todos.db`)，未设置时使用内存数据库 (重启后丢失)。
+//!
+//! 各项以JSON存于表 `items`，表结构见 [`crate::migrations`]:
+//!
+//! ```sql
+//! CREATE TABLE items (id TEXT PRIMARY KEY, data JSON NOT NULL, created_at INTEGER NOT NULL)
+//! ```
+//!
+//! 处理函数仍使用内存中的 [`Container`]，由 [`SqliteContainer::attach`] 在启动时从数据库加载，
+//! 之后通过事件钩子把修改按顺序写回数据库
+
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
+    Row,
+};
+use std::{collections::HashMap, marker::PhantomData, str::FromStr};
+use tokio::sync::mpsc;
+
+use super::{Container, HookEvent, MapBackend, Timestamped};
+
+/// 数据库地址的环境变量
+const DATABASE_URL_ENV: &str = "DATABASE_URL";
+/// 默认数据库 (内存)
+const DEFAULT_DATABASE_URL: &str = "sqlite::memory:";
+
+/// 以 SQLite 为后端的容器，接口与 [`Container`] 一致 (但为异步)
+#[derive(Debug, Clone)]
+pub struct SqliteContainer<T> {
+    pool: SqlitePool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// 待写回的修改
+enum WriteOp<T> {
+    Put(String, T),
+    Delete(String),
+}
+
+impl<T> SqliteContainer<T>
+where
+    T: Serialize + DeserializeOwned + Timestamped,
+{
+    /// 连接 `DATABASE_URL` 指定的数据库，并执行迁移
+    pub async fn connect_from_env() -> Result<Self, sqlx::Error> {
+        let url = std::env::var(DATABASE_URL_ENV).unwrap_or_else(|_| DEFAULT_DATABASE_URL.to_string());
+        Self::connect(&url).await
+    }
+
+    /// 连接数据库 (文件不存在时创建)，并执行迁移
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        // 内存数据库每个连接各自独立，只能用一个连接
+        let max_connections = if url.contains(":memory:") { 1 } else { 4 };
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await?;
+        let version = crate::migrations::run_migrations(&pool).await?;
+        tracing::info!("connected to {} (schema version {})", url, version);
+        Ok(Self { pool, _marker: PhantomData })
+    }
+
+    // ---------------- 增删改查 ----------------
+
+    /// 获取
+    pub async fn _get_by_id(&self, key: &str) -> Result<Option<T>, sqlx::Error> {
+        let row = sqlx::query("SELECT data FROM items WHERE id = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| decode(row.get("data"))).transpose()
+    }
+
+    /// 获取 - 全部
+    pub async fn get_all(&self) -> Result<HashMap<String, T>, sqlx::Error> {
+        let rows = sqlx::query("SELECT id, data FROM items")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter()
+            .map(|row| Ok((row.get("id"), decode(row.get("data"))?)))
+            .collect()
+    }
+
+    /// 增加 - 覆盖
+    pub async fn put_by_id(&self, key: &str, value: &T) -> Result<(), sqlx::Error> {
+        let data = serde_json::to_string(value).map_err(|err| sqlx::Error::Encode(Box::new(err)))?;
+        sqlx::query("INSERT OR REPLACE INTO items (id, data, created_at) VALUES (?, ?, ?)")
+            .bind(key)
+            .bind(data)
+            .bind(value.created_at().timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 删除，返回是否存在
+    pub async fn delete_by_id(&self, key: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM items WHERE id = ?")
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ---------------- 与内存容器同步 ----------------
+
+    /// 把数据库中的项加载到 `container`，并在之后把 `container` 的修改写回数据库
+    ///
+    /// 应在注册其他事件钩子之前调用，以免加载时触发事件
+    pub async fn attach<M: MapBackend<T>>(self, container: &Container<T, M>) -> Result<(), sqlx::Error>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let items = self.get_all().await?;
+        tracing::info!("loaded {} items from sqlite", items.len());
+        for (key, value) in items {
+            container.put_by_id(&key, value);
+        }
+
+        // 钩子在容器的写锁内同步执行，不能等待数据库，因此经由通道交给单个写入任务，保证写入顺序
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        container.add_hook(move |event| {
+            let op = match event {
+                HookEvent::Put { key, new, .. } => WriteOp::Put(key.to_string(), (*new).clone()),
+                HookEvent::Delete { key, .. } => WriteOp::Delete(key.to_string()),
+            };
+            let _ = sender.send(op);
+        });
+        tokio::spawn(async move {
+            while let Some(op) = receiver.recv().await {
+                let result = match &op {
+                    WriteOp::Put(key, value) => self.put_by_id(key, value).await,
+                    WriteOp::Delete(key) => self.delete_by_id(key).await.map(|_| ()),
+                };
+                if let Err(err) = result {
+                    tracing::error!("failed to write to sqlite: {}", err);
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// 解析存储的JSON
+fn decode<T: DeserializeOwned>(data: String) -> Result<T, sqlx::Error> {
+    serde_json::from_str(&data).map_err(|err| sqlx::Error::Decode(Box::new(err)))
+}
@@ -0,0 +1,85 @@
+//! 各项的历史版本
+//!
+//! 与主容器并存，通过事件钩子记录: 每次覆盖 (`put_by_id`、`update_by_id` 等) 时把旧值存为一个历史版本，
+//! 每项最多保留最近的 `limit` 个，更早的丢弃。删除项时一并删除其历史 (重建的项从版本1重新开始)。
+//!
+//! 版本号按项从1开始计数，第 N 次写入的值为版本 N，与容器的全局版本号 ([`Container::versioned_get`]) 无关
+//!
+//! ```ignore
+//! let history = VersionHistory::new_arc(10);
+//! history.attach(&container);
+//! container.put_by_id("a", 1);
+//! container.put_by_id("a", 2);
+//! assert_eq!(history.versions("a")[0].value, 1);
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{collections::VecDeque, sync::Arc};
+
+use super::{Container, HookEvent, MapBackend};
+
+/// 历史版本
+///
+/// - `version` 版本号
+/// - `value` 该版本的值
+/// - `replaced_at` 被新版本覆盖的时间
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionRecord<T> {
+    pub version: u64,
+    pub value: T,
+    pub replaced_at: DateTime<Utc>,
+}
+
+/// 历史版本的存储，见模块文档
+#[derive(Debug)]
+pub struct VersionHistory<T> {
+    limit: usize,
+    /// 键同主容器，按版本号升序
+    records: Container<VecDeque<VersionRecord<T>>>,
+}
+
+impl<T: Clone + Send + Sync + 'static> VersionHistory<T> {
+    /// `limit` 每项保留的版本数
+    pub fn new_arc(limit: usize) -> Arc<Self> {
+        Arc::new(Self { limit, records: Container::default() })
+    }
+
+    /// 开始记录 `container` 的修改 (之前的修改没有历史)。`limit` 为0时不记录
+    pub fn attach<M: MapBackend<T>>(self: &Arc<Self>, container: &Container<T, M>) {
+        if self.limit == 0 {
+            return;
+        }
+        let history = self.clone();
+        container.add_hook(move |event| match *event {
+            HookEvent::Put { key, old: Some(old), .. } => history.push(key, old.clone()),
+            HookEvent::Put { old: None, .. } => {}
+            HookEvent::Delete { key, .. } => {
+                history.records.delete_by_id(key);
+            }
+        });
+    }
+
+    /// 记录被覆盖的旧值，其版本号为上一个历史版本加一
+    fn push(&self, key: &str, value: T) {
+        self.records.transaction(|tx| {
+            let mut records = tx.get_by_id(key).unwrap_or_default();
+            let version = records.back().map_or(1, |record| record.version + 1);
+            records.push_back(VersionRecord { version, value, replaced_at: Utc::now() });
+            while records.len() > self.limit {
+                records.pop_front();
+            }
+            tx.insert(key, records);
+        });
+    }
+
+    /// 项的历史版本，按版本号升序 (不含当前值)
+    pub fn versions(&self, key: &str) -> Vec<VersionRecord<T>> {
+        self.records.get_by_id(key).map_or_else(Vec::new, Vec::from)
+    }
+
+    /// 项的指定历史版本，已丢弃或不存在时为 `None`
+    pub fn get(&self, key: &str, version: u64) -> Option<VersionRecord<T>> {
+        self.records.get_by_id(key)?.into_iter().find(|record| record.version == version)
+    }
+}
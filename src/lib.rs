@@ -0,0 +1,14 @@
+//! 服务的各模块
+//!
+//! 入口 (`main.rs`) 负责组装中间件与监听，路由、存储等都在这里，集成测试与基准测试同样由此引入
+
+pub mod node;
+pub mod container;
+pub mod api;
+pub mod config;
+pub mod supervisor;
+pub mod migrations;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+#[cfg(test)]
+mod test_utils;
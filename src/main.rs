@@ -2,20 +2,38 @@
 //! 
 //! 负责服务器配置和启动
 
-use axum::{
-    http::{HeaderName, Method},
-    routing::get,
-    Router
+use axum::{Router, ServiceExt};
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+use tokio::task::JoinSet;
+use tower::Layer;
+
+use rust_http_demo::api::middleware::{
+    build_info::BuildInfoLayer,
+    cache_control::CacheControlLayer,
+    cors::{CorsConfig, PerRouteCorsLayer},
+    hmac::{HmacSignatureLayer, SignatureMode},
+    idempotency::IdempotencyLayer,
+    options::OptionsMethodLayer,
+    security_headers::SecurityHeadersLayer,
+    stats::{track_requests, RequestCounter},
+    MiddlewareStack, DEFAULT_CACHE_CONTROL,
 };
-use tower_http::cors::{Any, CorsLayer};
+use rust_http_demo::config::CONFIG;
+use rust_http_demo::documented_route;
+#[cfg(feature = "otel")]
+use rust_http_demo::telemetry;
+use rust_http_demo::{api, config};
+#[cfg(feature = "sled")]
+use rust_http_demo::container;
 use tracing_subscriber::{ // 日志订阅系统
     layer::SubscriberExt,
     util::SubscriberInitExt
 };
 
-mod node;
-mod container;
-mod api;
+/// 是否启用 TLS (目前仅支持明文 HTTP)。启用时才发送 HSTS 头
+const TLS_ENABLED: bool = false;
+/// 默认监听地址
+const DEFAULT_BIND_ADDR: &str = "127.0.0.1:24042";
 
 /// 主异步函数，使用tokio运行时
 #[tokio::main]
@@ -23,63 +41,264 @@ async fn main() {
     api::test::test_fn();
 
     // 初始化日志追踪
-    tracing_subscriber::registry()
+    let registry = tracing_subscriber::registry()
         .with( // 过滤规则: 默认显示debug级别
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
                 format!("{}=debug,tower_http=debug", env!("CARGO_CRATE_NAME")).into()
             }),
         )
-        .with(tracing_subscriber::fmt::layer()) // 默认输出格式
-        .init(); // 初始化
+        .with(tracing_subscriber::fmt::layer()); // 默认输出格式
+    #[cfg(feature = "otel")]
+    let tracer_provider = telemetry::init_tracer_provider();
+    #[cfg(feature = "otel")]
+    let registry = registry.with(tracer_provider.as_ref().map(telemetry::layer)); // 设置了 OTEL_EXPORTER_OTLP_ENDPOINT 时导出
+    registry.init(); // 初始化
 
     // axum
-    let cors = CorsLayer::new()
-        .allow_origin(
-            Any,
-            // #[cfg(debug_assertions)]
-            // Any,
-            
-            // #[cfg(not(debug_assertions))]
-            // [
-            //     "http://localhost".parse::<HeaderValue>().unwrap(),
-            //     "http://localhost:3060".parse::<HeaderValue>().unwrap(),
-            // ],
-        ) // Any 允许任意来源，开发阶段可用，生产建议指定域名
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::PATCH,
-            Method::DELETE,
-            Method::OPTIONS,
-        ])
-        .allow_headers([
-            HeaderName::from_static("content-type"),
-            HeaderName::from_static("authorization"),
-            HeaderName::from_static("x-requested-with"),
-        ])
-        .allow_credentials(
-            false,
-            // 允许凭证 (cookies等)。但若开了，限制不再允许用 `allow_origin(Any)`，因为这会带来严重的安全风险
-            // #[cfg(debug_assertions)]
-            // false,
-
-            // #[cfg(not(debug_assertions))]
-            // true,
-        )
-        ;
+    // 各路由的跨域策略，按顺序匹配。目前都使用配置文件中的全局策略
+    let cors = PerRouteCorsLayer::new()
+        .route("/**", CorsConfig::from_server_config(&CONFIG))
+        .build();
+    let stores = api::Stores::new();
+    let v1 = api::v1_router(&stores).await;
+    let counter = RequestCounter::new_arc();
+    // 公开路由: 无需登录，不经过 JwtAuthLayer
+    let public_routes = Router::new()
+        .merge(api::rest_todos::factory_shared_todos_router(stores.todos.clone()));
     let app = Router::new()
-        .route("/", get(api::test::root))
+        .merge(documented_route!(get, "/", api::test::root, "测试服务器是否正常"))
+        .merge(documented_route!(get, "/api-versions", api::get_api_versions, "API 版本信息"))
+        .merge(documented_route!(get, "/version", api::version::get_version, "服务器版本与构建信息"))
         .merge(api::heartbeat::factory_utils_router())
-        .merge(api::rest_todos::factory_todos_router().await)
-        .merge(api::rest_store::factory_rest_router().await)
-        .merge(api::rest_node::factory_node_router().await)
-        .layer(cors);
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:24042") // 绑定TCP监听端口
-        .await
-        .unwrap();
-    tracing::info!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap(); // 启动HTTP服务器
+        .merge(api::auth::factory_auth_router(stores.clone()))
+        .merge(api::events::factory_events_router())
+        .merge(api::webhooks::factory_webhooks_router())
+        .merge(api::ws::factory_ws_router())
+        .merge(api::openapi::factory_openapi_router())
+        .merge(api::admin::factory_admin_router(counter.clone(), stores.memory_reports()))
+        .merge(public_routes)
+        .merge(v1.clone()) // 无前缀，作为 v1 的别名
+        .nest("/v1", v1)
+        .nest("/v2", api::v2_router().await)
+        .layer(IdempotencyLayer); // 在 cors 内侧，重放的响应也带跨域头
+    #[cfg(feature = "msgpack")]
+    let app = app.layer(api::middleware::content_negotiation::ContentNegotiationLayer); // 在幂等键外侧，重放的响应也按 Accept 编码
+    let signature_mode = if CONFIG.require_signature { SignatureMode::Required } else { SignatureMode::Optional };
+    let app = app
+        .layer(axum::middleware::from_fn_with_state(counter, track_requests)) // 需在路由内部，以取得 MatchedPath
+        .layer(cors)
+        .layer(HmacSignatureLayer::new(signature_mode)); // 在请求体大小限制内层，超限的请求体不会被读入校验
+    #[cfg(feature = "csrf")]
+    let app = app.layer(api::middleware::csrf::CsrfLayer);
+    // TODO: 加入 ETag 中间件时放在 Cache-Control 内侧，使客户端可用 If-None-Match 重新验证
+    let mut cache_control = CacheControlLayer::new(DEFAULT_CACHE_CONTROL);
+    for version in ["", "/v1"] { // 无前缀的路径是 v1 的别名
+        cache_control = cache_control.prefix(&format!("{}/todos", version), "max-age=0");
+    }
+    let cache_control = cache_control
+        .prefix("/heartbeat", "max-age=2")
+        .prefix("/health", "no-store") // 探针结果不可缓存
+        .prefix("/admin", "no-store")
+        .prefix("/heartbeat/config", "no-store")
+        .prefix("/auth", "no-store");
+    let mut security_headers = SecurityHeadersLayer::builder().csp("default-src 'self'");
+    if TLS_ENABLED {
+        security_headers = security_headers.hsts(Duration::from_secs(31536000));
+    }
+    let middleware = MiddlewareStack::default()
+        .with_cache_control(cache_control)
+        .with_security_headers(security_headers.build());
+    #[cfg(debug_assertions)]
+    let app = app.layer(api::middleware::body_log::BodyLogLayer); // 仅调试构建，需在请求ID内层
+    let app = app.layer(middleware.layer()); // 需在路由内部，追踪需取得 MatchedPath
+    // 需包裹整个 Router 而非 Router::layer (后者作用于各路由内部，拿不到 axum 生成的 Allow 头)，且需在 cors 外层
+    let app = OptionsMethodLayer.layer(app);
+    let app = BuildInfoLayer.layer(app); // 最外层，所有响应 (含 OPTIONS) 都带版本头
+
+    let addrs = bind_addrs().unwrap_or_else(|err| {
+        tracing::error!("{}", err);
+        std::process::exit(1);
+    });
+    let mut listeners = Vec::new();
+    for addr in addrs {
+        let listener = bind_tcp(addr, &CONFIG) // 绑定TCP监听端口
+            .unwrap_or_else(|err| {
+                tracing::error!("failed to bind {}: {}", addr, err);
+                std::process::exit(1);
+            });
+        let local_addr = listener.local_addr().unwrap(); // 实际地址 (BIND_PORT=0 时端口由系统分配)
+        tracing::info!("listening on {}", local_addr);
+        listeners.push((local_addr, listener));
+    }
+    #[cfg(unix)]
+    let (unix_socket, unix_listener) = match std::env::var("UNIX_SOCKET_PATH") {
+        Ok(path) => {
+            let (guard, listener) = UnixSocketGuard::bind(PathBuf::from(path)).unwrap_or_else(|err| {
+                tracing::error!("{}", err);
+                std::process::exit(1);
+            });
+            tracing::info!("listening on unix:{}", guard.path.display());
+            (Some(guard), Some(listener))
+        }
+        Err(_) => (None, None),
+    };
+    #[cfg(not(unix))]
+    let unix_socket: Option<()> = None;
+    if listeners.is_empty() && unix_socket.is_none() {
+        tracing::error!("no address to listen on (set BIND_ADDRS or UNIX_SOCKET_PATH)");
+        std::process::exit(1);
+    }
+
+    let mut listen_addrs: Vec<String> = listeners.iter().map(|(addr, _)| addr.to_string()).collect();
+    #[cfg(unix)]
+    listen_addrs.extend(unix_socket.as_ref().map(|guard| format!("unix:{}", guard.path.display())));
+    api::health::set_listen_addrs(listen_addrs);
+
+    // 每个监听地址一个服务任务，Ctrl-C 时全部取消。
+    // 各任务持有的 app 是克隆，但其中的路由与状态都在 Arc 里，克隆不会复制处理函数的状态
+    let mut servers = JoinSet::new();
+    for (addr, listener) in listeners {
+        let app = app.clone();
+        servers.spawn(async move {
+            let result = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await; // 启动HTTP服务器 (限流需取得客户端地址)
+            (addr.to_string(), result)
+        });
+    }
+    #[cfg(unix)]
+    if let (Some(guard), Some(listener)) = (&unix_socket, unix_listener) {
+        let name = format!("unix:{}", guard.path.display());
+        servers.spawn(async move {
+            let result = axum::serve(listener, app.into_make_service()).await;
+            (name, result)
+        });
+    }
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {
+            tracing::info!("shutting down");
+            servers.abort_all();
+            #[cfg(feature = "sled")]
+            container::sled_store::flush();
+        }
+        Some(Ok((addr, result))) = servers.join_next() => {
+            tracing::error!("server on {} stopped: {:?}", addr, result);
+            servers.abort_all();
+        }
+    }
+    #[cfg(feature = "otel")]
+    if let Some(provider) = tracer_provider {
+        telemetry::shutdown(provider).await;
+    }
+}
+
+/// 监听地址
+/// 
+/// - `BIND_ADDRS` 逗号分隔的多个地址，如 `127.0.0.1:24042,[::1]:24042`。设置时忽略 `BIND_ADDR`。
+///   设为空则不监听 TCP (仅用 `UNIX_SOCKET_PATH`)
+/// - `BIND_ADDR` 单个地址，如 `0.0.0.0:8080` (容器中部署时需绑定 `0.0.0.0`)。默认 `127.0.0.1:24042`
+/// - `BIND_PORT` 覆盖以上所有地址的端口，`0` 表示由系统分配
+fn bind_addrs() -> Result<Vec<SocketAddr>, String> {
+    let (name, raw) = match std::env::var("BIND_ADDRS") {
+        Ok(raw) => ("BIND_ADDRS", raw),
+        Err(_) => ("BIND_ADDR", std::env::var("BIND_ADDR").unwrap_or_else(|_| DEFAULT_BIND_ADDR.to_string())),
+    };
+    let mut addrs = raw.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<SocketAddr>()
+            .map_err(|err| format!("invalid {} entry {:?}: {} (expected e.g. 0.0.0.0:24042)", name, s, err)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Ok(port) = std::env::var("BIND_PORT") {
+        let port = port.parse::<u16>()
+            .map_err(|err| format!("invalid BIND_PORT {:?}: {} (expected 0-65535)", port, err))?;
+        addrs.iter_mut().for_each(|addr| addr.set_port(port));
+    }
+    Ok(addrs)
+}
+
+/// 绑定 TCP 监听端口
+///
+/// 在监听套接字上设置 keepalive (见 [`config::ServerConfig`])，接受的连接会继承该设置。
+/// Linux 下还设置 `SO_REUSEPORT`，新旧进程可同时监听同一端口，以便不停机重启
+fn bind_tcp(addr: SocketAddr, config: &config::ServerConfig) -> std::io::Result<tokio::net::TcpListener> {
+    use socket2::{Domain, Protocol, Socket, TcpKeepalive, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(target_os = "linux")]
+    socket.set_reuse_port(true)?;
+
+    let keepalive = TcpKeepalive::new().with_time(Duration::from_secs(config.tcp_keepalive_idle_secs));
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd"))]
+    let keepalive = keepalive
+        .with_interval(Duration::from_secs(config.tcp_keepalive_interval_secs))
+        .with_retries(config.tcp_keepalive_retries);
+    socket.set_tcp_keepalive(&keepalive)?;
+
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    tokio::net::TcpListener::from_std(socket.into())
+}
+
+/// Unix 域套接字 (`UNIX_SOCKET_PATH`)，供 Nginx/Caddy 等反向代理使用
+/// 
+/// 析构时删除套接字文件
+#[cfg(unix)]
+struct UnixSocketGuard {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl UnixSocketGuard {
+    /// 绑定套接字。路径上已有文件 (如上次异常退出遗留) 时先删除
+    fn bind(path: PathBuf) -> Result<(Self, tokio::net::UnixListener), String> {
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .map_err(|err| format!("failed to remove stale socket {}: {}", path.display(), err))?;
+        }
+        let listener = tokio::net::UnixListener::bind(&path)
+            .map_err(|err| format!("failed to bind unix socket {}: {}", path.display(), err))?;
+        Ok((Self { path }, listener))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UnixSocketGuard {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            tracing::warn!("failed to remove socket {}: {}", self.path.display(), err);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use axum::{body::{to_bytes, Body}, http::{header::HOST, Request, StatusCode}, routing::get};
+    use hyper_util::rt::TokioIo;
+
+    #[tokio::test]
+    async fn serves_over_unix_socket() {
+        let path = std::env::temp_dir().join(format!("rust-http-demo-{}.sock", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"").unwrap(); // 遗留的文件会被替换
+        let (guard, listener) = UnixSocketGuard::bind(path.clone()).unwrap();
+        let app = Router::new().route("/", get(api::test::root));
+        let server = tokio::spawn(async move { axum::serve(listener, app.into_make_service()).await });
+
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await.unwrap();
+        tokio::spawn(connection);
+        let request = Request::get("/").header(HOST, "localhost").body(Body::empty()).unwrap();
+        let response = sender.send_request(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(Body::new(response.into_body()), usize::MAX).await.unwrap();
+        assert_eq!(body, "<h1>Hello, World!</h1>");
+
+        server.abort();
+        assert!(path.exists());
+        drop(guard);
+        assert!(!path.exists());
+    }
 }
 
 // /// 自定义日志的格式化器
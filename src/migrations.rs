@@ -0,0 +1,168 @@
+//! 持久化后端的数据库迁移
+//!
+//! 每个迁移带递增的版本号。启动时读取数据库当前版本，依次执行更新的迁移，并记录新版本:
+//!
+//! - SQLite: 版本存于表 `schema_version`，迁移与版本更新在同一个事务中
+//! - sled: 版本存于默认 `Tree` 的键 `__schema_version`
+//!
+//! 迁移后的版本可通过 `GET /admin/schema-version` 查看
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// 各后端迁移后的版本
+static VERSIONS: Lazy<Mutex<BTreeMap<&'static str, SchemaVersion>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// 后端的版本
+///
+/// - `current` 数据库当前版本
+/// - `latest` 程序已知的最新版本 (低于 `current` 说明数据库由更新的程序写入)
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SchemaVersion {
+    pub current: u32,
+    pub latest: u32,
+}
+
+/// 所有已迁移后端的版本，键为后端名
+pub fn versions() -> BTreeMap<&'static str, SchemaVersion> {
+    VERSIONS.lock().unwrap().clone()
+}
+
+#[cfg(any(feature = "sqlite", feature = "sled"))]
+fn record(backend: &'static str, version: SchemaVersion) {
+    VERSIONS.lock().unwrap().insert(backend, version);
+}
+
+// #region SQLite
+
+/// SQLite 迁移
+///
+/// - `up` 要执行的SQL，可含多条语句
+#[cfg(feature = "sqlite")]
+pub struct Migration {
+    pub version: u32,
+    pub up: &'static str,
+}
+
+/// SQLite 迁移列表，按版本递增。已发布的迁移不可修改，只能追加
+#[cfg(feature = "sqlite")]
+fn sqlite_migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            // todos
+            up: "CREATE TABLE IF NOT EXISTS items (id TEXT PRIMARY KEY, data JSON NOT NULL, created_at INTEGER NOT NULL)",
+        },
+    ]
+}
+
+/// 执行 SQLite 迁移，返回迁移后的版本
+///
+/// 所有待执行的迁移在同一个事务中，任一失败则全部回滚
+#[cfg(feature = "sqlite")]
+pub async fn run_migrations(pool: &sqlx::SqlitePool) -> Result<u32, sqlx::Error> {
+    sqlx::query("CREATE TABLE IF NOT EXISTS schema_version (version INTEGER)")
+        .execute(pool)
+        .await?;
+
+    let mut tx = pool.begin().await?;
+    let current = sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(version) FROM schema_version")
+        .fetch_one(&mut *tx)
+        .await?
+        .map_or(0, |version| version as u32);
+
+    let migrations = sqlite_migrations();
+    let latest = migrations.last().map_or(0, |migration| migration.version);
+    let mut version = current;
+    for migration in migrations.iter().filter(|migration| migration.version > current) {
+        tracing::info!("applying sqlite migration {}", migration.version);
+        sqlx::raw_sql(migration.up).execute(&mut *tx).await?;
+        version = migration.version;
+    }
+    if version != current {
+        sqlx::query("DELETE FROM schema_version").execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_version (version) VALUES (?)")
+            .bind(version as i64)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    record("sqlite", SchemaVersion { current: version, latest });
+    Ok(version)
+}
+
+// #endregion
+
+// #region sled
+
+/// sled 迁移
+///
+/// - `up` 迁移函数，在数据库上直接执行
+#[cfg(feature = "sled")]
+pub struct SledMigration {
+    pub version: u32,
+    pub up: fn(&sled::Db) -> sled::Result<()>,
+}
+
+/// sled 版本所在的键
+#[cfg(feature = "sled")]
+const SLED_VERSION_KEY: &str = "__schema_version";
+
+/// sled 迁移列表，按版本递增。已发布的迁移不可修改，只能追加
+#[cfg(feature = "sled")]
+fn sled_migrations() -> Vec<SledMigration> {
+    vec![
+        SledMigration {
+            version: 1,
+            // node
+            up: |db| db.open_tree("node").map(|_| ()),
+        },
+        SledMigration {
+            version: 2,
+            // node: 由 next_id 补全 children_ids
+            up: migrate_node_children,
+        },
+    ]
+}
+
+/// 只有 `next_id` 的节点补全 `children_ids: [next_id]`
+#[cfg(feature = "sled")]
+fn migrate_node_children(db: &sled::Db) -> sled::Result<()> {
+    let tree = db.open_tree("node")?;
+    for entry in tree.iter() {
+        let (key, data) = entry?;
+        let Ok(serde_json::Value::Object(mut node)) = serde_json::from_slice(&data) else { continue };
+        let has_children = node.get("children_ids").and_then(serde_json::Value::as_array).is_some_and(|ids| !ids.is_empty());
+        let Some(next_id) = node.get("next_id").filter(|id| id.is_string() && !has_children).cloned() else { continue };
+        node.insert("children_ids".to_string(), serde_json::Value::Array(vec![next_id]));
+        tree.insert(key, serde_json::to_vec(&node).expect("json object is serializable"))?;
+    }
+    Ok(())
+}
+
+/// 执行 sled 迁移，返回迁移后的版本
+///
+/// sled 不支持跨 `Tree` 的事务，每个迁移完成后立即记录版本，中途失败时下次从失败处继续
+#[cfg(feature = "sled")]
+pub fn run_sled_migrations(db: &sled::Db) -> sled::Result<u32> {
+    let current = db.get(SLED_VERSION_KEY)?
+        .and_then(|value| <[u8; 4]>::try_from(value.as_ref()).ok())
+        .map_or(0, u32::from_be_bytes);
+
+    let migrations = sled_migrations();
+    let latest = migrations.last().map_or(0, |migration| migration.version);
+    let mut version = current;
+    for migration in migrations.iter().filter(|migration| migration.version > current) {
+        tracing::info!("applying sled migration {}", migration.version);
+        (migration.up)(db)?;
+        db.insert(SLED_VERSION_KEY, &migration.version.to_be_bytes())?;
+        version = migration.version;
+    }
+
+    record("sled", SchemaVersion { current: version, latest });
+    Ok(version)
+}
+
+// #endregion
@@ -0,0 +1,198 @@
+//! 熔断器
+//!
+//! Http 节点调用的外部服务不可用时，每次调用都要等到超时，整条链被拖慢。
+//! 为每个 URL 维护一个熔断器:
+//!
+//! - `Closed` 正常放行，连续失败达到阈值后转为 `Open`
+//! - `Open` 直接返回错误，不发出请求。到期后转为 `HalfOpen`
+//! - `HalfOpen` 放行一个试探请求，成功则恢复 `Closed`，失败则重新 `Open`
+//!
+//! 阈值与熔断时长在配置文件中设置 (`circuit_breaker_failure_threshold`、`circuit_breaker_open_secs`)。
+//! 网络错误、超时与 `5xx` 计为失败，`4xx` 不计 (服务本身是可用的)
+
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::config::CONFIG;
+use crate::container::Container;
+
+/// 各 URL 的熔断器。恢复正常的熔断器会被移除，只保留有失败记录的
+static BREAKERS: Lazy<Arc<Container<CircuitBreaker>>> = Lazy::new(Container::new_arc);
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    /// 熔断中，直到该时刻
+    Open(Instant),
+    /// 试探中，试探请求于该时刻放行
+    HalfOpen(Instant),
+}
+
+/// 单个 URL 的熔断器
+///
+/// - `consecutive_failures` 连续失败次数
+/// - `last_error` 最近一次失败的原因
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    pub url: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+impl CircuitBreaker {
+    fn new(url: &str) -> Self {
+        Self { url: url.to_string(), state: CircuitState::Closed, consecutive_failures: 0, last_error: None }
+    }
+
+    /// 管理接口展示用
+    pub fn summary(&self) -> Value {
+        let now = Instant::now();
+        let (state, retry_after_secs) = match self.state {
+            CircuitState::Closed => ("closed", None),
+            CircuitState::Open(until) => ("open", Some(until.saturating_duration_since(now).as_secs())),
+            CircuitState::HalfOpen(_) => ("half_open", None),
+        };
+        json!({
+            "url": self.url,
+            "state": state,
+            "consecutive_failures": self.consecutive_failures,
+            "retry_after_secs": retry_after_secs,
+            "last_error": self.last_error,
+        })
+    }
+
+    /// 于 `now` 时刻请求，熔断中返回 `Err`，放行试探时返回转为 `HalfOpen` 的熔断器
+    fn acquire_at(&self, now: Instant, open_for: Duration) -> Result<Option<Self>, String> {
+        let trial_allowed = match self.state {
+            CircuitState::Closed => return Ok(None),
+            CircuitState::Open(until) => now >= until,
+            CircuitState::HalfOpen(since) => now.duration_since(since) >= open_for,
+        };
+        if !trial_allowed {
+            return Err(format!("circuit open for {}", self.url));
+        }
+        Ok(Some(Self { state: CircuitState::HalfOpen(now), ..self.clone() }))
+    }
+
+    /// 于 `now` 时刻失败一次后的熔断器
+    fn fail_at(&self, error: &str, now: Instant, threshold: u32, open_for: Duration) -> Self {
+        let consecutive_failures = self.consecutive_failures + 1;
+        let trip = matches!(self.state, CircuitState::HalfOpen(_)) || consecutive_failures >= threshold;
+        let state = match trip {
+            true => {
+                tracing::warn!("circuit opened for {} after {} consecutive failures", self.url, consecutive_failures);
+                CircuitState::Open(now + open_for)
+            }
+            false => self.state,
+        };
+        Self { url: self.url.clone(), state, consecutive_failures, last_error: Some(error.to_string()) }
+    }
+}
+
+/// 熔断时长
+fn open_duration() -> Duration {
+    Duration::from_secs(CONFIG.circuit_breaker_open_secs)
+}
+
+/// 请求前检查，熔断中返回 `Err`
+///
+/// 熔断到期时转为 `HalfOpen` 并放行本次请求作为试探，试探期间的其他请求仍被拒绝。
+/// 试探请求超过熔断时长仍未报告结果 (如被取消) 时，允许再次试探
+pub fn acquire(url: &str) -> Result<(), String> {
+    let result = BREAKERS.update_by_id(url, |breaker| {
+        match breaker.acquire_at(Instant::now(), open_duration()) {
+            Ok(Some(breaker)) => Ok(breaker),
+            Ok(None) => Err(None),
+            Err(err) => Err(Some(err)),
+        }
+    });
+    match result {
+        Some(Err(Some(err))) => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// 请求成功，恢复正常
+pub fn record_success(url: &str) {
+    BREAKERS.delete_by_id(url);
+}
+
+/// 请求失败，连续失败达到阈值或试探失败时熔断
+pub fn record_failure(url: &str, error: &str) {
+    let _ = BREAKERS.put_if_absent(url, CircuitBreaker::new(url));
+    BREAKERS.update_by_id(url, |breaker| {
+        Ok::<_, ()>(breaker.fail_at(error, Instant::now(), CONFIG.circuit_breaker_failure_threshold, open_duration()))
+    });
+}
+
+/// 全部熔断器，按 URL 排序
+pub fn list() -> Vec<CircuitBreaker> {
+    BREAKERS.get_all_sorted_by(|breaker| breaker.url.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPEN_FOR: Duration = Duration::from_secs(30);
+    const THRESHOLD: u32 = 3;
+
+    #[test]
+    fn opens_after_threshold_and_recovers() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new("http://example.com");
+        assert!(matches!(breaker.acquire_at(start, OPEN_FOR), Ok(None)));
+
+        // Closed: 未达阈值前仍放行
+        for failures in 1..THRESHOLD {
+            breaker = breaker.fail_at("boom", start, THRESHOLD, OPEN_FOR);
+            assert_eq!(breaker.state, CircuitState::Closed);
+            assert_eq!(breaker.consecutive_failures, failures);
+        }
+        breaker = breaker.fail_at("boom", start, THRESHOLD, OPEN_FOR);
+        assert_eq!(breaker.state, CircuitState::Open(start + OPEN_FOR));
+        assert_eq!(breaker.last_error.as_deref(), Some("boom"));
+
+        // Open: 到期前拒绝，到期后放行一个试探
+        assert!(breaker.acquire_at(start + OPEN_FOR - Duration::from_secs(1), OPEN_FOR).is_err());
+        let trial_at = start + OPEN_FOR;
+        breaker = breaker.acquire_at(trial_at, OPEN_FOR).unwrap().unwrap();
+        assert_eq!(breaker.state, CircuitState::HalfOpen(trial_at));
+
+        // HalfOpen: 试探期间的其他请求被拒绝，试探超过熔断时长未报告时允许再次试探
+        assert!(breaker.acquire_at(trial_at + Duration::from_secs(1), OPEN_FOR).is_err());
+        let retrial_at = trial_at + OPEN_FOR;
+        let retrial = breaker.acquire_at(retrial_at, OPEN_FOR).unwrap().unwrap();
+        assert_eq!(retrial.state, CircuitState::HalfOpen(retrial_at));
+
+        // 试探成功: 熔断器被移除，即恢复 Closed
+        let url = "http://circuit-breaker-test.invalid/recover";
+        BREAKERS.put_by_id(url, CircuitBreaker { url: url.to_string(), ..breaker });
+        record_success(url);
+        assert!(BREAKERS.get_by_id(url).is_none());
+        assert_eq!(acquire(url), Ok(()));
+    }
+
+    #[test]
+    fn failed_trial_reopens() {
+        let start = Instant::now();
+        let breaker = CircuitBreaker {
+            state: CircuitState::HalfOpen(start),
+            consecutive_failures: THRESHOLD,
+            ..CircuitBreaker::new("http://example.com")
+        };
+        // 试探失败立即重新熔断，不论阈值
+        let failed_at = start + Duration::from_secs(2);
+        let breaker = breaker.fail_at("still down", failed_at, u32::MAX, OPEN_FOR);
+        assert_eq!(breaker.state, CircuitState::Open(failed_at + OPEN_FOR));
+        assert_eq!(breaker.consecutive_failures, THRESHOLD + 1);
+        assert!(breaker.acquire_at(failed_at + Duration::from_secs(1), OPEN_FOR).is_err());
+        assert!(breaker.acquire_at(failed_at + OPEN_FOR, OPEN_FOR).unwrap().is_some());
+    }
+}
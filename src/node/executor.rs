@@ -0,0 +1,332 @@
+//! 链式执行
+//!
+//! 从某个节点开始依次执行，上一节点的输出作为下一节点的输入。节点有多个后继时并发执行各后继的子链，
+//! 分支节点按条件只走其一。每个节点执行后更新其执行统计并追加到执行历史 ([`RunLog`])
+
+use chrono::{DateTime, Utc};
+use futures_util::future::{join_all, BoxFuture};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::container::Container;
+use super::types::{BasicNode, Node, NodeKind};
+#[cfg(feature = "scripting")]
+use super::script::eval_condition;
+
+/// 未启用 `scripting` feature 时的占位实现
+#[cfg(not(feature = "scripting"))]
+async fn eval_condition(_node_id: &str, _condition: &str, _input: &Value) -> Result<bool, String> {
+    Err("scripting feature is not enabled".to_string())
+}
+
+/// 每个节点保留的执行历史条数
+pub const HISTORY_LIMIT: usize = 100;
+
+/// 节点的一次执行
+///
+/// - `run_id` 所属的链式执行，同一次执行的各节点相同
+/// - `output` 成功时的输出，`error` 失败时的错误信息
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeRunRecord {
+    pub node_id: String,
+    pub run_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub input: Value,
+    pub output: Option<Value>,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+/// 节点的执行统计
+///
+/// - `last_output` 最近一次成功执行的输出
+/// - `last_run_at` 最近一次执行的时间
+/// - `run_count` 执行次数 (含失败)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunStats {
+    pub last_output: Option<Value>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub run_count: u64,
+}
+
+/// 单个节点的执行统计与最近的执行历史
+#[derive(Debug, Default)]
+struct NodeRuns {
+    stats: RunStats,
+    /// 按完成顺序，最多 [`HISTORY_LIMIT`] 条
+    history: VecDeque<NodeRunRecord>,
+}
+
+/// 各节点的执行统计与执行历史 (仅在内存中，重启后清空)
+///
+/// 与节点存储分开: 执行不修改节点，不会触发节点的变更事件 (SSE、webhook)、缓存失效与持久化
+#[derive(Debug, Default)]
+pub struct RunLog {
+    nodes: Mutex<HashMap<String, NodeRuns>>,
+}
+
+impl RunLog {
+    /// 记录一次节点执行: 更新统计，并追加到该节点的历史 (超出 [`HISTORY_LIMIT`] 时丢弃最早的)
+    pub fn record(&self, record: NodeRunRecord) {
+        let mut nodes = self.nodes.lock().unwrap();
+        let runs = nodes.entry(record.node_id.clone()).or_default();
+        if let Some(output) = &record.output {
+            runs.stats.last_output = Some(output.clone());
+        }
+        runs.stats.last_run_at = Some(record.timestamp);
+        runs.stats.run_count += 1;
+        if runs.history.len() >= HISTORY_LIMIT {
+            runs.history.pop_front();
+        }
+        runs.history.push_back(record);
+    }
+
+    /// 节点的执行统计 (未执行过时为默认值)
+    pub fn stats(&self, node_id: &str) -> RunStats {
+        self.nodes.lock().unwrap().get(node_id).map(|runs| runs.stats.clone()).unwrap_or_default()
+    }
+
+    /// 节点最近的执行历史，按完成顺序
+    pub fn history(&self, node_id: &str) -> Vec<NodeRunRecord> {
+        self.nodes.lock().unwrap().get(node_id).map(|runs| runs.history.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /// 删除节点的执行统计与历史 (节点删除时调用)
+    pub fn forget(&self, node_id: &str) {
+        self.nodes.lock().unwrap().remove(node_id);
+    }
+}
+
+/// 分支节点选择的分支
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Branch {
+    Then,
+    Else,
+}
+
+/// 执行记录中的一步
+///
+/// - `branch` 仅分支节点有值
+#[derive(Debug, Serialize)]
+pub struct RunStep {
+    pub id: String,
+    pub branch: Option<Branch>,
+    pub output: Value,
+}
+
+/// 链式执行的记录
+///
+/// - `error` 出错时为错误信息，`steps` 为出错前已完成的步骤
+#[derive(Debug, Serialize)]
+pub struct ChainTrace {
+    pub steps: Vec<RunStep>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 执行被中断 (超时或取消)
+///
+/// - `executed_nodes` 已完成的节点，按完成顺序
+/// - `running` 中断时正在执行的节点
+#[derive(Debug, Serialize)]
+pub struct Interrupted {
+    pub executed_nodes: Vec<String>,
+    pub running: Vec<String>,
+}
+
+/// 执行进度，由并发的子链共享
+///
+/// - `run_id` 本次执行的ID，见 [`NodeRunRecord`]
+struct Progress<F> {
+    run_id: String,
+    on_step: F,
+    executed: Vec<String>,
+    running: Vec<String>,
+}
+
+impl<F: FnMut(&str, Result<&Value, &str>)> Progress<F> {
+    fn start(&mut self, id: &str) {
+        self.running.push(id.to_string());
+    }
+
+    fn finish(&mut self, id: &str, result: Result<&Value, &str>) {
+        if let Some(index) = self.running.iter().position(|running| running == id) {
+            self.running.remove(index);
+        }
+        if result.is_ok() {
+            self.executed.push(id.to_string());
+        }
+        (self.on_step)(id, result);
+    }
+}
+
+impl BasicNode {
+    /// 根据输入决定下一批节点
+    ///
+    /// Branch 类型按条件在 `next_id`/`else_id` 间选择其一，其余类型走所有 `children_ids`
+    async fn next(&self, input: &Value) -> Result<(Option<Branch>, Vec<String>), String> {
+        if self.kind != NodeKind::Branch {
+            return Ok((None, self.children_ids.clone()));
+        }
+
+        let condition = self.config.get("condition")
+            .and_then(Value::as_str)
+            .ok_or_else(|| "config.condition is required".to_string())?;
+        if eval_condition(&self.id, condition, input).await? {
+            Ok((Some(Branch::Then), self.next_id.iter().cloned().collect()))
+        } else {
+            Ok((Some(Branch::Else), self.else_id.iter().cloned().collect()))
+        }
+    }
+}
+
+/// 从 `start_id` 开始依次执行节点，上一节点的输出作为下一节点的输入
+///
+/// 节点有多个后继时，以其输出为输入并发执行各后继的子链。每个节点执行后记录执行结果，见 [`RunLog::record`]
+///
+/// - `runs` 执行统计与历史
+/// - `interrupt` 完成时中断执行 (如超时、取消)，返回 `Err`。不需要时传 [`std::future::pending`]
+/// - `on_step` 每个节点完成 (`Ok(输出)`) 或失败 (`Err(错误信息)`) 时回调，用于实时回报进度
+pub async fn run_chain<F>(
+    data: &Container<BasicNode>,
+    runs: &RunLog,
+    start_id: &str,
+    input: Value,
+    interrupt: impl Future<Output = ()>,
+    on_step: F,
+) -> Result<ChainTrace, Interrupted>
+where
+    F: FnMut(&str, Result<&Value, &str>) + Send,
+{
+    let progress = Mutex::new(Progress { run_id: Uuid::new_v4().to_string(), on_step, executed: Vec::new(), running: Vec::new() });
+    tokio::select! {
+        (steps, error) = run_from(data, runs, start_id.to_string(), input, &progress) => Ok(ChainTrace { steps, error }),
+        () = interrupt => {
+            let mut progress = progress.lock().unwrap();
+            Err(Interrupted {
+                executed_nodes: std::mem::take(&mut progress.executed),
+                running: std::mem::take(&mut progress.running),
+            })
+        }
+    }
+}
+
+/// 从 `start_id` 开始执行，见 [`run_chain`]
+///
+/// 返回执行的步骤与错误。并发的子链按 `children_ids` 的顺序拼接各自的步骤，错误取第一个出错的子链的。
+/// 某个子链出错不影响其他子链
+fn run_from<'a, F>(
+    data: &'a Container<BasicNode>,
+    runs: &'a RunLog,
+    start_id: String,
+    input: Value,
+    progress: &'a Mutex<Progress<F>>,
+) -> BoxFuture<'a, (Vec<RunStep>, Option<String>)>
+where
+    F: FnMut(&str, Result<&Value, &str>) + Send,
+{
+    Box::pin(async move {
+        let report = |id: &str, result: Result<&Value, &str>| progress.lock().unwrap().finish(id, result);
+        let mut steps = Vec::new();
+        let mut id = start_id;
+        let mut value = input;
+
+        loop {
+            progress.lock().unwrap().start(&id);
+            let Some(node) = data.get_by_id(&id) else {
+                let err = format!("node not found: {}", id);
+                report(&id, Err(&err));
+                return (steps, Some(err));
+            };
+            let (branch, next_ids) = match node.next(&value).await {
+                Ok(next) => next,
+                Err(err) => {
+                    report(&id, Err(&err));
+                    return (steps, Some(err));
+                }
+            };
+            let run_id = progress.lock().unwrap().run_id.clone();
+            let (timestamp, started) = (Utc::now(), Instant::now());
+            let result = node._run(value.clone()).await;
+            runs.record(NodeRunRecord {
+                node_id: id.clone(),
+                run_id,
+                timestamp,
+                input: value,
+                output: result.as_ref().ok().cloned(),
+                duration_ms: started.elapsed().as_millis() as u64,
+                error: result.as_ref().err().cloned(),
+            });
+            value = match result {
+                Ok(output) => output,
+                Err(err) => {
+                    report(&id, Err(&err));
+                    return (steps, Some(err));
+                }
+            };
+            report(&id, Ok(&value));
+            steps.push(RunStep { id, branch, output: value.clone() });
+
+            match <[String; 1]>::try_from(next_ids) {
+                Ok([next_id]) => id = next_id,
+                Err(next_ids) if next_ids.is_empty() => return (steps, None),
+                Err(next_ids) => {
+                    let branches = next_ids.into_iter().map(|next_id| run_from(data, runs, next_id, value.clone(), progress));
+                    let mut error = None;
+                    for (branch_steps, branch_error) in join_all(branches).await {
+                        steps.extend(branch_steps);
+                        error = error.or(branch_error);
+                    }
+                    return (steps, error);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(node_id: &str, input: usize, error: Option<&str>) -> NodeRunRecord {
+        NodeRunRecord {
+            node_id: node_id.to_string(),
+            run_id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            input: json!(input),
+            output: error.is_none().then(|| json!(input)),
+            duration_ms: 0,
+            error: error.map(String::from),
+        }
+    }
+
+    #[test]
+    fn run_log_keeps_latest_history() {
+        let log = RunLog::default();
+        for i in 0..HISTORY_LIMIT + 5 {
+            log.record(record("a", i, None));
+        }
+        log.record(record("a", 0, Some("failed")));
+        log.record(record("b", 0, None));
+
+        let history = log.history("a");
+        assert_eq!(history.len(), HISTORY_LIMIT);
+        assert_eq!(history[0].input, json!(6));
+        assert_eq!(history.last().unwrap().error.as_deref(), Some("failed"));
+        let stats = log.stats("a");
+        assert_eq!(stats.run_count, HISTORY_LIMIT as u64 + 6);
+        assert_eq!(stats.last_output, Some(json!(HISTORY_LIMIT + 4))); // 失败不覆盖最近的输出
+
+        log.forget("a");
+        assert!(log.history("a").is_empty());
+        assert_eq!(log.stats("a").run_count, 0);
+        assert_eq!(log.stats("b").run_count, 1);
+    }
+}
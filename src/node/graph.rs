@@ -0,0 +1,156 @@
+//! 节点图的遍历
+//!
+//! 节点经 `children_ids`/`else_id` (向前) 与 `prev_id` (向后) 相连，组成有向图。
+//! 供导出子图、可视化、复制下游与执行前的环检测使用
+
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::api::graph_viz::EdgeStyle;
+use crate::container::Container;
+use super::types::BasicNode;
+
+/// 遍历方向
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// 沿 `children_ids`/`else_id`
+    #[default]
+    Forward,
+    /// 沿 `prev_id`
+    Backward,
+}
+
+impl BasicNode {
+    /// 指定方向上的相邻节点ID
+    ///
+    /// - 向前: `children_ids` (实线) 与 `else_id` (虚线)
+    /// - 向后: `prev_id`
+    pub(crate) fn neighbors(&self, direction: Direction) -> Vec<(&str, EdgeStyle)> {
+        match direction {
+            Direction::Forward => self.children_ids.iter()
+                .map(|id| (id.as_str(), EdgeStyle::Solid))
+                .chain(self.else_id.as_deref().map(|id| (id, EdgeStyle::Dashed)))
+                .collect(),
+            Direction::Backward => self.prev_id.as_deref().map(|id| (id, EdgeStyle::Solid)).into_iter().collect(),
+        }
+    }
+}
+
+/// 沿指定方向BFS，返回可达节点及其跳数 (含起点，按访问顺序)
+///
+/// - `depth` 最大跳数，超出的节点不收集
+/// - 不存在的节点ID被忽略
+pub fn bfs<'a>(all: &'a HashMap<String, BasicNode>, start_id: &str, direction: Direction, depth: usize) -> Vec<(&'a BasicNode, usize)> {
+    let Some(start) = all.get(start_id) else {
+        return Vec::new();
+    };
+
+    let mut result = Vec::new();
+    let mut visited: HashSet<&str> = HashSet::from([start.id.as_str()]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((node, hops)) = queue.pop_front() {
+        result.push((node, hops));
+        if hops >= depth {
+            continue;
+        }
+        for (next_id, _) in node.neighbors(direction) {
+            if let Some(next) = all.get(next_id)
+                && visited.insert(next.id.as_str())
+            {
+                queue.push_back((next, hops + 1));
+            }
+        }
+    }
+    result
+}
+
+/// 检测从 `start_id` 出发可达的部分是否有环 (`children_ids` 与 `else_id` 均视为边)
+///
+/// 返回环上的某个节点ID。不存在的节点视为终点，不算环。
+/// 用显式的栈做DFS (而非递归)，很长的链也不会栈溢出
+pub fn find_cycle(data: &Container<BasicNode>, start_id: &str) -> Option<String> {
+    let all = data.get_all();
+    let start = all.get(start_id)?;
+
+    // 栈中为当前路径上的节点，及其尚未访问的后继
+    let mut stack = vec![(start, start.neighbors(Direction::Forward).into_iter())];
+    let mut on_path: HashSet<&str> = HashSet::from([start.id.as_str()]);
+    let mut done: HashSet<&str> = HashSet::new();
+    while let Some((node, next_ids)) = stack.last_mut() {
+        let (node, next) = (*node, next_ids.next());
+        match next {
+            Some((next_id, _)) => {
+                if on_path.contains(next_id) {
+                    return Some(next_id.to_string());
+                }
+                if done.contains(next_id) {
+                    continue;
+                }
+                if let Some(next) = all.get(next_id) {
+                    on_path.insert(next.id.as_str());
+                    stack.push((next, next.neighbors(Direction::Forward).into_iter()));
+                }
+            }
+            None => {
+                on_path.remove(node.id.as_str());
+                done.insert(node.id.as_str());
+                stack.pop();
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, children_ids: &[String]) -> BasicNode {
+        BasicNode {
+            id: id.to_string(),
+            next_id: children_ids.first().cloned(),
+            children_ids: children_ids.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// `n0 -> n1 -> ... -> n{len-1}`
+    fn chain(data: &Container<BasicNode>, len: usize) {
+        for i in 0..len {
+            let next: Vec<String> = (i + 1 < len).then(|| format!("n{}", i + 1)).into_iter().collect();
+            data.put_by_id(&format!("n{}", i), node(&format!("n{}", i), &next));
+        }
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let data = Container::new_arc();
+        chain(&data, 3);
+        assert_eq!(find_cycle(&data, "n0"), None);
+        assert_eq!(find_cycle(&data, "missing"), None);
+
+        // 菱形 (两条路径汇合) 不是环
+        data.put_by_id("n0", node("n0", &["n1".to_string(), "n2".to_string()]));
+        assert_eq!(find_cycle(&data, "n0"), None);
+
+        data.put_by_id("n2", node("n2", &["n0".to_string()]));
+        assert_eq!(find_cycle(&data, "n0").as_deref(), Some("n0"));
+        assert_eq!(find_cycle(&data, "n1").as_deref(), Some("n1"));
+
+        let mut branch = node("b", &[]);
+        branch.else_id = Some("b".to_string());
+        data.put_by_id("b", branch);
+        assert_eq!(find_cycle(&data, "b").as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn long_chain_does_not_overflow() {
+        let data = Container::new_arc();
+        chain(&data, 50_000);
+        assert_eq!(find_cycle(&data, "n0"), None);
+
+        data.put_by_id("n49999", node("n49999", &["n0".to_string()]));
+        assert_eq!(find_cycle(&data, "n0").as_deref(), Some("n0"));
+    }
+}
@@ -0,0 +1,148 @@
+//! HTTP 请求支持
+//!
+//! 供 `NodeKind::Http` 类型的节点使用，可用于 webhook 等集成场景。配置格式:
+//!
+//! ```json
+//! { "method": "GET", "url": "https://...", "headers": {}, "body_template": "..." }
+//! ```
+//!
+//! `body_template` 中的 `{input}` 会被替换为序列化后的输入值
+//!
+//! 同一 URL 连续失败时熔断，期间直接返回错误，见 [`super::circuit_breaker`]
+//!
+//! 节点由用户创建，为防止借此访问内网 (SSRF)，目标不能是 localhost 或私有、回环、链路本地等地址:
+//! 保存节点时校验 URL，请求时再校验域名解析出的地址，且不跟随重定向。
+//! 内网部署可用配置项 `http_node_allow_private` 放开
+
+use once_cell::sync::Lazy;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+    Client, Method, Url,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use super::circuit_breaker;
+use crate::api::client_ip::is_public;
+use crate::config::CONFIG;
+
+/// 请求超时时间
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 全局共享的HTTP客户端 (内部有连接池，应复用而非每次创建)
+static CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(TIMEOUT)
+        .redirect(Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("failed to build http client")
+});
+
+/// 是否允许访问该地址
+fn ip_allowed(ip: IpAddr) -> bool {
+    CONFIG.http_node_allow_private || is_public(ip)
+}
+
+/// 校验节点的目标 URL: 只允许 http/https，主机不能是 localhost 或非公网的IP
+///
+/// 域名解析出的地址在请求时由 [`PublicResolver`] 校验
+pub fn validate_url(url: &str) -> Result<(), String> {
+    let url = Url::parse(url).map_err(|e| format!("invalid url: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("url must use http or https".to_string());
+    }
+    let host = url.host_str().ok_or("url must have a host")?;
+    let allowed = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => ip_allowed(ip),
+        Err(_) => {
+            let domain = host.trim_end_matches('.').to_ascii_lowercase();
+            CONFIG.http_node_allow_private || !(domain == "localhost" || domain.ends_with(".localhost"))
+        }
+    };
+    match allowed {
+        true => Ok(()),
+        false => Err("url must not target a private or loopback address".to_string()),
+    }
+}
+
+/// 只返回公网地址的域名解析 (防止域名指向内网地址绕过 [`validate_url`])
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<_> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| ip_allowed(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} does not resolve to a public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Http 节点的配置
+#[derive(Debug, Deserialize)]
+struct HttpConfig {
+    #[serde(default = "default_method")]
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Map<String, Value>,
+    body_template: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// 发送节点配置的请求，返回响应体
+///
+/// - 响应体若是JSON则解析为对应值，否则作为字符串返回
+/// - 非2xx状态返回 `Err("HTTP 503: ...")`
+/// - 熔断中返回 `Err("circuit open for ...")`，不发出请求
+/// - 目标不允许访问时返回错误 (见 [`validate_url`])，重定向 (3xx) 不跟随，同非2xx
+pub async fn run_http(config: &Value, input: Value) -> Result<Value, String> {
+    let config = HttpConfig::deserialize(config).map_err(|e| format!("invalid config: {}", e))?;
+    validate_url(&config.url)?;
+    let method = Method::from_bytes(config.method.to_uppercase().as_bytes())
+        .map_err(|_| format!("invalid method: {}", config.method))?;
+
+    circuit_breaker::acquire(&config.url)?;
+
+    let mut request = CLIENT.request(method, &config.url);
+    for (name, value) in &config.headers {
+        let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+        request = request.header(name, value);
+    }
+    if let Some(template) = &config.body_template {
+        let input = serde_json::to_string(&input).map_err(|e| e.to_string())?;
+        request = request.body(template.replace("{input}", &input));
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(err) => {
+            circuit_breaker::record_failure(&config.url, &err.to_string());
+            return Err(err.to_string());
+        }
+    };
+    let status = response.status();
+    let body = response.text().await.map_err(|e| e.to_string());
+    if status.is_server_error() || body.is_err() {
+        let error = body.as_ref().err().cloned().unwrap_or_else(|| format!("HTTP {}", status.as_u16()));
+        circuit_breaker::record_failure(&config.url, &error);
+    } else {
+        circuit_breaker::record_success(&config.url);
+    }
+    let body = body?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}: {}", status.as_u16(), body));
+    }
+    Ok(serde_json::from_str(&body).unwrap_or(Value::String(body)))
+}
@@ -1,3 +1,18 @@
-//! 一些独立任务
+//! 节点子系统
+//!
+//! - [`types`] 节点类型 ([`types::NodeKind`]) 与存储的节点 ([`types::BasicNode`])
+//! - [`executor`] 链式执行与执行历史
+//! - [`graph`] 图遍历 (BFS、环检测)
+//! - [`registry`] 预设任务的注册表
+//! - [`http`]/[`script`] 各类型节点的执行支持，[`circuit_breaker`] Http 节点的熔断器
+//!
+//! 节点的存储与 `/node` 路由见 [`crate::api::rest_node`]
 
-pub mod utils;
+pub mod types;
+pub mod executor;
+pub mod graph;
+pub mod registry;
+pub mod http;
+pub mod circuit_breaker;
+#[cfg(feature = "scripting")]
+pub mod script;
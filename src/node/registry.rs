@@ -1,3 +1,7 @@
+//! 预设任务的注册表
+//!
+//! 以名称索引的无参任务，`GET /nodelist` 列出其名称
+
 use std::collections::HashMap;
 use once_cell::sync::Lazy;
 
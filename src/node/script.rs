@@ -0,0 +1,146 @@
+//! Rhai 脚本支持
+//!
+//! 供 `NodeKind::Script`/`NodeKind::Branch` 类型的节点使用。脚本需定义入口函数:
+//!
+//! ```rhai
+//! fn run(input) { input }
+//! ```
+//!
+//! 条件表达式则直接求值为bool。编译结果按节点ID缓存，源码变化时自动重新编译
+//!
+//! 脚本由用户提交，引擎限制了操作数、调用深度与数据大小 (死循环、无限递归、无限增长会以错误结束)，
+//! 并在阻塞线程池中执行，不占用异步运行时的工作线程
+
+use once_cell::sync::Lazy;
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::container::Container;
+
+/// 脚本入口函数名
+const ENTRY_FN: &str = "run";
+/// 单次执行的最大操作数，超出时中止
+const MAX_OPERATIONS: u64 = 1_000_000;
+/// 最大函数调用深度
+const MAX_CALL_LEVELS: usize = 32;
+/// 字符串的最大长度 (字节)
+const MAX_STRING_SIZE: usize = 1024 * 1024;
+/// 数组、对象的最大元素数
+const MAX_COLLECTION_SIZE: usize = 64 * 1024;
+/// 表达式的最大嵌套深度 (全局、函数内)
+const MAX_EXPR_DEPTHS: (usize, usize) = (64, 32);
+
+/// 缓存项: (脚本源码, 编译结果)。源码用于判断缓存是否过期
+type CachedScript = (String, Arc<AST>);
+
+/// 编译缓存，key: 节点ID
+static AST_CACHE: Lazy<Arc<Container<CachedScript>>> = Lazy::new(Container::new_arc);
+
+/// 脚本错误 (编译期)
+///
+/// - `line`/`position` 出错位置 (从1开始，可能缺失)
+#[derive(Debug, Serialize)]
+pub struct ScriptError {
+    pub message: String,
+    pub line: Option<usize>,
+    pub position: Option<usize>,
+}
+
+/// 执行脚本的 `run(input)` 函数
+///
+/// - `node_id` 节点ID，用作缓存键
+/// - `script` 脚本源码
+/// - `input` 输入值，会被转换为 Rhai 的 `Dynamic`
+pub async fn run_script(node_id: &str, script: &str, input: Value) -> Result<Value, String> {
+    let (node_id, script) = (node_id.to_string(), script.to_string());
+    tokio::task::spawn_blocking(move || run_script_blocking(&node_id, &script, input))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn run_script_blocking(node_id: &str, script: &str, input: Value) -> Result<Value, String> {
+    let engine = engine();
+    let ast = compile_cached(&engine, node_id, script)?;
+
+    let input: Dynamic = rhai::serde::to_dynamic(&input).map_err(|e| e.to_string())?;
+    let output: Dynamic = engine
+        .call_fn(&mut Scope::new(), &ast, ENTRY_FN, (input,))
+        .map_err(|e| e.to_string())?;
+    rhai::serde::from_dynamic(&output).map_err(|e| e.to_string())
+}
+
+/// 计算条件表达式 (如 `input > 0`)
+///
+/// - `node_id` 节点ID，用作缓存键
+/// - `condition` 表达式源码，可通过变量 `input` 访问输入值，结果必须为bool
+pub async fn eval_condition(node_id: &str, condition: &str, input: &Value) -> Result<bool, String> {
+    let (node_id, condition, input) = (node_id.to_string(), condition.to_string(), input.clone());
+    tokio::task::spawn_blocking(move || eval_condition_blocking(&node_id, &condition, &input))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn eval_condition_blocking(node_id: &str, condition: &str, input: &Value) -> Result<bool, String> {
+    let engine = engine();
+    let ast = compile_cached(&engine, node_id, condition)?;
+
+    let mut scope = Scope::new();
+    scope.push_dynamic("input", rhai::serde::to_dynamic(input).map_err(|e| e.to_string())?);
+    engine
+        .eval_ast_with_scope::<bool>(&mut scope, &ast)
+        .map_err(|e| e.to_string())
+}
+
+/// 创建引擎，限制脚本的操作数、调用深度、数据大小与表达式深度
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTHS.0, MAX_EXPR_DEPTHS.1);
+    engine
+}
+
+/// 获取编译结果，缓存未命中或源码变化时重新编译
+fn compile_cached(engine: &Engine, node_id: &str, source: &str) -> Result<Arc<AST>, String> {
+    if let Some((cached, ast)) = AST_CACHE.get_by_id(node_id)
+        && cached == source
+    {
+        return Ok(ast);
+    }
+    let ast = Arc::new(engine.compile(source).map_err(|e| e.to_string())?);
+    AST_CACHE.put_by_id(node_id, (source.to_string(), ast.clone()));
+    Ok(ast)
+}
+
+/// 校验脚本 (仅编译，不执行)
+///
+/// 除语法错误外，还要求脚本定义了单参数的 `run` 函数
+pub fn validate_script(script: &str) -> Result<(), ScriptError> {
+    let ast = engine().compile(script).map_err(|e| ScriptError {
+        message: e.0.to_string(),
+        line: e.1.line(),
+        position: e.1.position(),
+    })?;
+
+    let has_entry = ast
+        .iter_functions()
+        .any(|f| f.name == ENTRY_FN && f.params.len() == 1);
+    if !has_entry {
+        return Err(ScriptError {
+            message: format!("missing entry function `fn {}(input)`", ENTRY_FN),
+            line: None,
+            position: None,
+        });
+    }
+    Ok(())
+}
+
+/// 移除节点的编译缓存 (节点删除时调用)
+pub fn forget_script(node_id: &str) {
+    AST_CACHE.delete_by_id(node_id);
+}
@@ -0,0 +1,239 @@
+//! 节点类型
+//!
+//! - [`Node`] 节点的执行接口
+//! - [`NodeKind`] 节点类型，决定执行的行为与 `config` 的格式
+//! - [`BasicNode`] 存储的节点，由 `/node` 接口增删改 (见 [`crate::api::rest_node`])
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::api::json_schema;
+use crate::container::Timestamped;
+use super::http;
+#[cfg(feature = "scripting")]
+use super::script::run_script;
+
+/// 未启用 `scripting` feature 时的占位实现
+#[cfg(not(feature = "scripting"))]
+async fn run_script(_node_id: &str, _script: &str, _input: Value) -> Result<Value, String> {
+    Err("scripting feature is not enabled".to_string())
+}
+
+/// Node特征
+///
+/// 必须实现线程安全约束
+pub(crate) trait Node: Send + Sync {
+    /// 执行节点自身
+    ///
+    /// - `input` 上一节点的输出 (或调用者给的初始输入)
+    /// - 返回本节点的输出，失败时返回错误信息
+    async fn _run(&self, input: Value) -> Result<Value, String>;
+}
+
+/// 节点类型
+///
+/// 决定 `_run` 的行为，以及 `config` 的格式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// 基础节点，原样输出输入值
+    #[default]
+    Basic,
+    /// Rhai 脚本节点，`config: { "script": "fn run(input) { input }" }`
+    ///
+    /// 需要启用 `scripting` feature
+    Script,
+    /// HTTP请求节点，`config: { "method": "GET", "url": "https://...", "headers": {}, "body_template": "..." }`
+    ///
+    /// 响应体作为输出，`body_template` 中的 `{input}` 会被替换为输入值
+    Http,
+    /// 条件分支节点，`config: { "condition": "input > 0" }`
+    ///
+    /// 条件为 Rhai 表达式，为真走 `next_id`，为假走 `else_id`。输出即输入
+    Branch,
+    /// HTTP 端点节点，`config: { "method": "POST", "path": "/my-endpoint" }`
+    ///
+    /// 对该路径的请求以请求体为输入从本节点开始执行，最后一步的输出作为响应。输出即输入
+    HttpEndpoint,
+}
+
+impl NodeKind {
+    /// 所有类型，供 `GET /node/types` 列出
+    pub fn all_variants() -> &'static [NodeKind] {
+        &[NodeKind::Basic, NodeKind::Script, NodeKind::Http, NodeKind::Branch, NodeKind::HttpEndpoint]
+    }
+
+    /// 类型名 (与序列化的值一致)
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeKind::Basic => "basic",
+            NodeKind::Script => "script",
+            NodeKind::Http => "http",
+            NodeKind::Branch => "branch",
+            NodeKind::HttpEndpoint => "http_endpoint",
+        }
+    }
+
+    /// 该类型的节点以 `config` 注册的路由 (仅 HTTP 端点节点)，见 [`endpoint_key`]
+    pub(crate) fn endpoint_key(&self, config: &Value) -> Option<String> {
+        if *self != NodeKind::HttpEndpoint {
+            return None;
+        }
+        let method = config.get("method").and_then(Value::as_str).unwrap_or("GET");
+        let path = config.get("path").and_then(Value::as_str)?;
+        Some(endpoint_key(method, path))
+    }
+
+    /// 按该类型的 schema 校验 `config`
+    pub fn validate_config(&self, config: &Value) -> Result<(), String> {
+        json_schema::validate(&self.config_schema(), config, "config")?;
+        if *self == NodeKind::HttpEndpoint && !config["path"].as_str().is_some_and(|path| path.starts_with('/')) {
+            return Err("config.path: must start with /".to_string());
+        }
+        if *self == NodeKind::Http {
+            let url = config["url"].as_str().unwrap_or_default();
+            http::validate_url(url).map_err(|e| format!("config.url: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// 节点类型的说明，使 API 可自描述 (前端的节点编辑器据此生成表单)
+pub trait NodeKindMetadata {
+    /// 说明
+    fn description(&self) -> &'static str;
+    /// `config` 的格式 (JSON Schema)，创建/修改节点时按此校验
+    fn config_schema(&self) -> Value;
+}
+
+impl NodeKindMetadata for NodeKind {
+    fn description(&self) -> &'static str {
+        match self {
+            NodeKind::Basic => "原样输出输入值",
+            NodeKind::Script => "执行 Rhai 脚本的 run(input) 函数，返回值作为输出 (需启用 scripting)",
+            NodeKind::Http => "发送HTTP请求，响应体作为输出。body_template 中的 {input} 替换为输入值",
+            NodeKind::Branch => "按 Rhai 条件表达式选择下一节点: 为真走 next_id，为假走 else_id。输出即输入 (需启用 scripting)",
+            NodeKind::HttpEndpoint => "把 method + path 注册为HTTP端点，请求体作为输入从本节点开始执行，最后一步的输出作为响应。输出即输入",
+        }
+    }
+
+    fn config_schema(&self) -> Value {
+        match self {
+            NodeKind::Basic => json!({ "description": "不使用" }),
+            NodeKind::Script => json!({
+                "type": "object",
+                "required": ["script"],
+                "properties": {
+                    "script": { "type": "string", "description": "需定义 fn run(input)" },
+                },
+            }),
+            NodeKind::Http => json!({
+                "type": "object",
+                "required": ["url"],
+                "properties": {
+                    "method": { "type": "string", "default": "GET" },
+                    "url": { "type": "string" },
+                    "headers": { "type": "object" },
+                    "body_template": { "type": "string" },
+                },
+            }),
+            NodeKind::Branch => json!({
+                "type": "object",
+                "required": ["condition"],
+                "properties": {
+                    "condition": { "type": "string", "description": "Rhai 表达式，input 为输入值，如 input > 0" },
+                },
+            }),
+            NodeKind::HttpEndpoint => json!({
+                "type": "object",
+                "required": ["path"],
+                "properties": {
+                    "method": { "enum": ["GET", "POST", "PUT", "PATCH", "DELETE"], "default": "GET" },
+                    "path": { "type": "string", "description": "以 / 开头，不能与已有的路由重复 (重复时已有的路由优先)" },
+                },
+            }),
+        }
+    }
+}
+
+/// 基础节点结构体，实现Node trait
+///
+/// 存储项
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BasicNode {
+    pub(crate) id: String,
+    pub(crate) kind: NodeKind,
+    pub(crate) content: Value, // type(预设)/运行脚本，或指向对应的对象
+    pub(crate) config: Value, // 类型相关的配置，格式由 `kind` 决定
+    pub(crate) metadata: Map<String, Value>, // 附加的注解，仅供客户端使用，不影响执行
+    pub(crate) next_id: Option<String>, // 兼容旧数据，总是等于 `children_ids` 的第一项
+    pub(crate) children_ids: Vec<String>, // 所有后继节点 (分支节点只使用第一项)
+    pub(crate) prev_id: Option<String>,
+    pub(crate) else_id: Option<String>, // 分支节点条件为假时的下一节点
+    pub(crate) owner_id: Option<String>, // 创建者，删除创建者时级联删除其所有节点
+    pub(crate) schedule: Option<String>, // cron 表达式 (秒 分 时 日 月 周 [年]，UTC)，到点时以 null 为输入在后台执行
+    // 以下为执行统计，不保存在节点中 (见 `RunLog`)，只在返回节点时填入
+    #[serde(skip_deserializing)]
+    pub(crate) last_output: Option<Value>, // 最近一次成功执行的输出
+    #[serde(skip_deserializing)]
+    pub(crate) last_run_at: Option<DateTime<Utc>>, // 最近一次执行的时间
+    #[serde(skip_deserializing)]
+    pub(crate) run_count: u64, // 执行次数 (含失败)
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) updated_at: DateTime<Utc>,
+}
+
+impl Timestamped for BasicNode {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+impl Node for BasicNode {
+    async fn _run(&self, input: Value) -> Result<Value, String> {
+        match self.kind {
+            NodeKind::Basic => Ok(input),
+            NodeKind::Script => {
+                let script = self.config.get("script")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "config.script is required".to_string())?;
+                run_script(&self.id, script, input).await
+            }
+            NodeKind::Http => http::run_http(&self.config, input).await,
+            NodeKind::Branch => Ok(input),
+            NodeKind::HttpEndpoint => Ok(input),
+        }
+    }
+}
+
+impl BasicNode {
+    /// HTTP 端点节点注册的路由，如 `POST /my-endpoint`，见 [`NodeKind::endpoint_key`]
+    pub(crate) fn endpoint_key(&self) -> Option<String> {
+        self.kind.endpoint_key(&self.config)
+    }
+
+    /// 设置后继节点，`next_id` 随之更新
+    pub(crate) fn set_children_ids(&mut self, children_ids: Vec<String>) {
+        self.next_id = children_ids.first().cloned();
+        self.children_ids = children_ids;
+    }
+
+    /// 替换第一个后继节点 (`None` 时移除)，其余后继不变
+    pub(crate) fn set_next_id(&mut self, next_id: Option<String>) {
+        let mut children_ids = std::mem::take(&mut self.children_ids);
+        match (next_id, children_ids.is_empty()) {
+            (Some(next_id), true) => children_ids.push(next_id),
+            (Some(next_id), false) => children_ids[0] = next_id,
+            (None, true) => {}
+            (None, false) => { children_ids.remove(0); }
+        }
+        self.set_children_ids(children_ids);
+    }
+}
+
+/// HTTP 端点节点注册的路由的键，`方法 路径`
+pub(crate) fn endpoint_key(method: &str, path: &str) -> String {
+    format!("{} {}", method.to_uppercase(), path)
+}
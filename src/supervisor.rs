@@ -0,0 +1,145 @@
+//! 后台任务监管
+//!
+//! 通过 [`TaskSupervisor::spawn`] 启动的任务若意外退出 (panic 或返回)，会记录错误并以指数退避重启
+//! (初始1秒，最长60秒)。任务状态可通过 `GET /admin/tasks` 查看
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::task::JoinError;
+
+/// 初始重启间隔
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// 最长重启间隔。任务持续运行超过该时间后，重启间隔恢复为初始值
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Running,
+    /// 已停止，不再重启 (运行时关闭时)
+    Stopped,
+    /// 已退出，等待重启
+    Restarting,
+}
+
+/// 任务信息
+///
+/// - `restart_count` 重启次数
+/// - `last_exit_reason` 最近一次退出的原因
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    pub name: String,
+    pub status: TaskStatus,
+    pub restart_count: u32,
+    pub last_exit_reason: Option<String>,
+}
+
+/// 任务监管器
+pub struct TaskSupervisor {
+    tasks: Mutex<BTreeMap<String, TaskInfo>>,
+}
+
+/// 全局监管器
+static SUPERVISOR: Lazy<TaskSupervisor> = Lazy::new(|| TaskSupervisor {
+    tasks: Mutex::new(BTreeMap::new()),
+});
+
+impl TaskSupervisor {
+    /// 启动受监管的任务
+    ///
+    /// `factory` 每次 (重新) 启动时调用，生成任务的 future。同名任务会覆盖之前的记录
+    pub fn spawn<F, Fut>(name: &str, factory: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let name = name.to_string();
+        SUPERVISOR.update(&name, |info| *info = TaskInfo {
+            name: name.clone(),
+            status: TaskStatus::Running,
+            restart_count: 0,
+            last_exit_reason: None,
+        });
+
+        tokio::spawn(async move {
+            let mut backoff = INITIAL_BACKOFF;
+            loop {
+                let started = Instant::now();
+                let result = tokio::spawn(factory()).await;
+                let reason = match result {
+                    Ok(()) => "returned".to_string(),
+                    Err(err) if err.is_panic() => format!("panicked: {}", panic_message(err)),
+                    Err(_) => {
+                        // 被取消，一般是运行时正在关闭
+                        SUPERVISOR.update(&name, |info| info.status = TaskStatus::Stopped);
+                        break;
+                    }
+                };
+
+                if started.elapsed() > MAX_BACKOFF {
+                    backoff = INITIAL_BACKOFF;
+                }
+                tracing::error!("task {} exited unexpectedly ({}), restarting in {:?}", name, reason, backoff);
+                SUPERVISOR.update(&name, |info| {
+                    info.status = TaskStatus::Restarting;
+                    info.last_exit_reason = Some(reason);
+                });
+
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                SUPERVISOR.update(&name, |info| {
+                    info.status = TaskStatus::Running;
+                    info.restart_count += 1;
+                });
+            }
+        });
+    }
+
+    /// 所有任务的信息 (按名称排序)
+    pub fn list() -> Vec<TaskInfo> {
+        SUPERVISOR.tasks.lock().unwrap().values().cloned().collect()
+    }
+
+    /// 检查所有任务是否都在运行，否则返回未运行的任务名
+    pub fn check_all_running() -> Result<(), String> {
+        let stopped: Vec<String> = Self::list().into_iter()
+            .filter(|info| info.status != TaskStatus::Running)
+            .map(|info| info.name)
+            .collect();
+        if stopped.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("tasks not running: {}", stopped.join(", ")))
+        }
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut TaskInfo)) {
+        let mut tasks = self.tasks.lock().unwrap();
+        let info = tasks.entry(name.to_string()).or_insert_with(|| TaskInfo {
+            name: name.to_string(),
+            status: TaskStatus::Running,
+            restart_count: 0,
+            last_exit_reason: None,
+        });
+        f(info);
+    }
+}
+
+/// 取出 panic 信息
+fn panic_message(err: JoinError) -> String {
+    let payload = err.into_panic();
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
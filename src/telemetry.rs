@@ -0,0 +1,109 @@
+//! 链路追踪导出 (OpenTelemetry)
+//!
+//! 设置了 `OTEL_EXPORTER_OTLP_ENDPOINT` (如 `http://localhost:4318`) 时，将 tracing 的 span 以 OTLP (HTTP/protobuf) 导出。
+//! 服务名取自 `OTEL_SERVICE_NAME`，默认为包名。其他 `OTEL_EXPORTER_OTLP_*` 环境变量 (如 `_HEADERS`、`_TIMEOUT`) 同样生效。
+//!
+//! 请求的 span (见 [`crate::api::middleware::tracing`]) 以传入的 W3C `traceparent` 头为父 span，
+//! 响应带 `traceresponse` 头，处理函数可用 `Extension<TraceContext>` 取得当前请求的追踪上下文。
+//! 需启用 `otel` feature
+
+use axum::{extract::Request, http::HeaderMap};
+use opentelemetry::{
+    global,
+    propagation::Extractor,
+    trace::{TraceContextExt, TracerProvider},
+    Context,
+};
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::{SdkTracerProvider, Tracer}, Resource};
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// 导出地址的环境变量
+const ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+/// 服务名的环境变量
+const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+
+/// 请求的追踪上下文，由请求追踪中间件放入请求扩展
+#[derive(Debug, Clone)]
+pub struct TraceContext(pub Context);
+
+impl TraceContext {
+    /// `traceresponse` 头的值 (W3C Trace Context Level 2)，未导出 (span 无效) 时为 `None`
+    pub fn traceresponse(&self) -> Option<String> {
+        let span = self.0.span();
+        let span_context = span.span_context();
+        span_context.is_valid().then(|| format!(
+            "00-{}-{}-{:02x}",
+            span_context.trace_id(),
+            span_context.span_id(),
+            span_context.trace_flags().to_u8(),
+        ))
+    }
+}
+
+/// 创建导出器并设为全局，未设置导出地址时返回 `None`
+///
+/// 导出器使用阻塞的 HTTP 客户端 (在批量导出的专用线程中运行)，它不能在异步运行时中创建，因此在单独的线程中初始化。
+/// 此时日志尚未初始化，错误输出到 stderr
+pub fn init_tracer_provider() -> Option<SdkTracerProvider> {
+    std::env::var(ENDPOINT_ENV).ok().filter(|endpoint| !endpoint.is_empty())?;
+    let exporter = std::thread::spawn(|| SpanExporter::builder().with_http().build())
+        .join()
+        .expect("otlp exporter init panicked");
+    let exporter = match exporter {
+        Ok(exporter) => exporter,
+        Err(err) => {
+            eprintln!("failed to create otlp exporter: {}", err);
+            return None;
+        }
+    };
+    let service_name = std::env::var(SERVICE_NAME_ENV).unwrap_or_else(|_| env!("CARGO_PKG_NAME").to_string());
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build();
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    global::set_tracer_provider(provider.clone());
+    Some(provider)
+}
+
+/// tracing 的订阅层，将 span 交给导出器
+pub fn layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, Tracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_CRATE_NAME")))
+}
+
+/// 关闭导出器，导出尚未发出的 span (阻塞，在专用线程中执行)
+pub async fn shutdown(provider: SdkTracerProvider) {
+    match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => tracing::warn!("failed to shut down otlp exporter: {}", err),
+        Err(err) => tracing::warn!("failed to shut down otlp exporter: {}", err),
+    }
+}
+
+/// 以请求的 `traceparent` 头为 `span` 的父 span，并将追踪上下文放入请求扩展
+pub fn attach(span: &Span, req: &mut Request) -> TraceContext {
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(req.headers())));
+    let _ = span.set_parent(parent); // 未启用导出时 span 没有 OpenTelemetry 数据，忽略
+    let trace_context = TraceContext(span.context());
+    req.extensions_mut().insert(trace_context.clone());
+    trace_context
+}
+
+/// 从请求头读取传播字段
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
@@ -0,0 +1,145 @@
+//! 测试工具
+//!
+//! 构造测试数据的工厂函数与断言，仅在测试时编译。
+//! 存储项的字段不对外公开，这里经由 serde 构造 (与从文件加载的路径一致)，不必为测试放宽可见性
+
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api::{rest_store, rest_todos},
+    container::Container,
+    node::types::BasicNode,
+};
+
+/// 工厂函数创建的项的所有者
+pub const TEST_OWNER_ID: &str = "test-user";
+
+/// 由服务端设置的时间戳字段，比较时忽略
+const TIMESTAMP_FIELDS: [&str; 2] = ["created_at", "updated_at"];
+
+/// 待办事项，ID 随机，属于 [`TEST_OWNER_ID`]
+pub fn build_todo(text: &str, completed: bool) -> rest_todos::Item {
+    let now = Utc::now();
+    from_json(json!({
+        "id": Uuid::new_v4().to_string(),
+        "owner_id": TEST_OWNER_ID,
+        "text": text,
+        "completed": completed,
+        "created_at": now,
+        "updated_at": now,
+    }))
+}
+
+/// 通用存储项，属于 [`TEST_OWNER_ID`]
+pub fn build_rest_item(id: &str, data: Value) -> rest_store::Item {
+    let now = Utc::now();
+    from_json(json!({
+        "id": id,
+        "owner_id": TEST_OWNER_ID,
+        "data": data,
+        "created_at": now,
+        "updated_at": now,
+    }))
+}
+
+/// 基础节点 (`basic`)，没有前后节点
+pub fn build_node(id: &str, content: Value) -> BasicNode {
+    let now = Utc::now();
+    from_json(json!({
+        "id": id,
+        "content": content,
+        "created_at": now,
+        "updated_at": now,
+    }))
+}
+
+/// 以 `key_fn` 取键，装入新的容器
+pub fn filled_container<T: Clone>(items: Vec<T>, key_fn: impl Fn(&T) -> &str) -> Arc<Container<T>> {
+    let container = Container::new_arc();
+    for item in items {
+        let key = key_fn(&item).to_string();
+        container.put_by_id(&key, item);
+    }
+    container
+}
+
+/// 比较两个待办事项，忽略 `created_at`/`updated_at`
+#[track_caller]
+pub fn assert_todo_eq(a: &rest_todos::Item, b: &rest_todos::Item) {
+    assert_eq_ignoring_timestamps(a, b);
+}
+
+/// 比较两个存储项 (按序列化结果)，忽略 `created_at`/`updated_at`
+#[track_caller]
+pub fn assert_eq_ignoring_timestamps<T: Serialize>(a: &T, b: &T) {
+    assert_eq!(without_timestamps(a), without_timestamps(b));
+}
+
+fn without_timestamps<T: Serialize>(item: &T) -> Value {
+    let mut value = serde_json::to_value(item).expect("test item should serialize");
+    if let Some(object) = value.as_object_mut() {
+        for field in TIMESTAMP_FIELDS {
+            object.remove(field);
+        }
+    }
+    value
+}
+
+fn from_json<T: DeserializeOwned>(value: Value) -> T {
+    serde_json::from_value(value).expect("test item should deserialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn todo_factory_sets_fields() {
+        let todo = build_todo("buy milk", true);
+        let value = serde_json::to_value(&todo).unwrap();
+        assert_eq!(value["text"], "buy milk");
+        assert_eq!(value["completed"], true);
+        assert_eq!(value["position"], u32::MAX);
+        assert_eq!(todo.owner_id, TEST_OWNER_ID);
+    }
+
+    #[test]
+    fn todo_eq_ignores_timestamps() {
+        let todo = build_todo("buy milk", false);
+        let mut value = serde_json::to_value(&todo).unwrap();
+        value["updated_at"] = json!(Utc::now() + chrono::Duration::hours(1));
+        assert_todo_eq(&todo, &from_json(value));
+    }
+
+    #[test]
+    #[should_panic]
+    fn todo_eq_compares_other_fields() {
+        assert_todo_eq(&build_todo("a", false), &build_todo("a", false)); // ID 不同
+    }
+
+    #[test]
+    fn rest_item_and_node_factories() {
+        let item = build_rest_item("k1", json!({ "n": 1 }));
+        assert_eq!(serde_json::to_value(&item).unwrap()["data"], json!({ "n": 1 }));
+        assert_eq_ignoring_timestamps(&item, &build_rest_item("k1", json!({ "n": 1 })));
+
+        let node = build_node("n1", json!("hello"));
+        let value = serde_json::to_value(&node).unwrap();
+        assert_eq!(value["kind"], "basic");
+        assert_eq!(value["content"], "hello");
+        assert!(value["next_id"].is_null());
+    }
+
+    #[test]
+    fn filled_container_uses_key_fn() {
+        let todos = vec![("a".to_string(), build_todo("a", false)), ("b".to_string(), build_todo("b", true))];
+        let container = filled_container(todos.clone(), |(key, _)| key.as_str());
+        assert_eq!(container.len(), 2);
+        assert_todo_eq(&container.get_by_id("b").unwrap().1, &todos[1].1);
+        assert!(container.get_by_id("c").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! `/admin` 的集成测试
+
+use axum::{http::{Method, StatusCode}, Router};
+use rust_http_demo::api::{
+    admin::factory_admin_router, auth::{self, factory_auth_router, UserRole}, heartbeat::factory_utils_router,
+    middleware::{jwt, stats::RequestCounter}, rest_node::factory_node_router, rest_store::factory_rest_router, Stores,
+};
+use rust_http_demo::container::Container;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{new_user_token, send};
+
+/// 注册新用户，返回用户ID与用户名
+async fn register(auth: &Router) -> (String, String) {
+    let username = format!("u{}", Uuid::new_v4().simple()).chars().take(20).collect::<String>();
+    let registered = send(auth, Method::POST, "/auth/register", None, Some(json!({ "username": username, "password": "password123" }))).await;
+    assert_eq!(registered.status, StatusCode::CREATED);
+    (registered.body["id"].as_str().unwrap().to_string(), username)
+}
+
+/// 登录，返回令牌 (角色为当前角色)
+async fn login(auth: &Router, username: &str) -> String {
+    let logged_in = send(auth, Method::POST, "/auth/login", None, Some(json!({ "username": username, "password": "password123" }))).await;
+    assert_eq!(logged_in.status, StatusCode::OK);
+    logged_in.body["access_token"].as_str().unwrap().to_string()
+}
+
+fn admin_token() -> String {
+    jwt::issue(&Uuid::new_v4().to_string(), "admin", UserRole::Admin).expect("token")
+}
+
+#[tokio::test]
+async fn admin_routes_check_role() {
+    let app = factory_admin_router(RequestCounter::new_arc(), Vec::new())
+        .merge(factory_utils_router())
+        .merge(factory_node_router().await);
+    let (user, admin) = (new_user_token(), admin_token());
+    let role = json!({ "role": "user" });
+    let quota = json!({ "capacity": 10 });
+    // (方法, 路径, 请求体, 管理员请求时的状态码)
+    let routes: [(Method, &str, Option<&Value>, StatusCode); 14] = [
+        (Method::GET, "/admin/tasks", None, StatusCode::OK),
+        (Method::GET, "/admin/stats", None, StatusCode::OK),
+        (Method::GET, "/admin/memory", None, StatusCode::OK),
+        (Method::GET, "/admin/snapshot", None, StatusCode::OK),
+        (Method::GET, "/admin/schema-version", None, StatusCode::OK),
+        (Method::GET, "/admin/sessions", None, StatusCode::OK),
+        (Method::DELETE, "/admin/sessions/nobody", None, StatusCode::NOT_FOUND),
+        (Method::PATCH, "/admin/users/nobody/role", Some(&role), StatusCode::NOT_FOUND),
+        (Method::PATCH, "/admin/users/nobody/rate-limit", Some(&quota), StatusCode::NOT_FOUND),
+        (Method::GET, "/admin/node/circuit-breakers", None, StatusCode::OK),
+        (Method::GET, "/admin/debug/routes", None, StatusCode::OK),
+        (Method::POST, "/admin/fingerprint/rotate-secret", None, StatusCode::OK),
+        (Method::GET, "/heartbeat/config", None, StatusCode::OK),
+        (Method::DELETE, "/node?owner=nobody", None, StatusCode::OK),
+    ];
+    for (method, uri, body, admin_status) in routes {
+        let anonymous = send(&app, method.clone(), uri, None, body.cloned()).await;
+        assert_eq!(anonymous.status, StatusCode::UNAUTHORIZED, "{} {} without token", method, uri);
+        let invalid = send(&app, method.clone(), uri, Some("invalid"), body.cloned()).await;
+        assert_eq!(invalid.status, StatusCode::UNAUTHORIZED, "{} {} with invalid token", method, uri);
+        let as_user = send(&app, method.clone(), uri, Some(&user), body.cloned()).await;
+        assert_eq!(as_user.status, StatusCode::FORBIDDEN, "{} {} as user", method, uri);
+        assert_eq!(as_user.body, json!({ "error": "admin role required" }));
+        let as_admin = send(&app, method.clone(), uri, Some(&admin), body.cloned()).await;
+        assert_eq!(as_admin.status, admin_status, "{} {} as admin", method, uri);
+    }
+}
+
+#[tokio::test]
+async fn promote_and_demote() {
+    let auth = factory_auth_router(Stores::new());
+    let app = factory_admin_router(RequestCounter::new_arc(), Vec::new());
+    let admin = admin_token();
+    let (user_id, username) = register(&auth).await;
+    let uri = format!("/admin/users/{}/role", user_id);
+    let old_token = login(&auth, &username).await;
+    assert_eq!(send(&app, Method::GET, "/admin/tasks", Some(&old_token), None).await.status, StatusCode::FORBIDDEN);
+
+    // 普通用户不能提升自己
+    let own = send(&app, Method::PATCH, &uri, Some(&old_token), Some(json!({ "role": "admin" }))).await;
+    assert_eq!(own.status, StatusCode::FORBIDDEN);
+    assert_eq!(auth::find_user(&user_id).unwrap().role, UserRole::User);
+
+    let promoted = send(&app, Method::PATCH, &uri, Some(&admin), Some(json!({ "role": "admin" }))).await;
+    assert_eq!(promoted.status, StatusCode::OK);
+    assert_eq!(promoted.body["role"], "admin");
+    assert_eq!(auth::find_user(&user_id).unwrap().role, UserRole::Admin);
+    // 角色记录在令牌中，旧令牌仍为普通用户，重新登录后生效
+    assert_eq!(send(&app, Method::GET, "/admin/tasks", Some(&old_token), None).await.status, StatusCode::FORBIDDEN);
+    let admin_token = login(&auth, &username).await;
+    assert_eq!(send(&app, Method::GET, "/admin/tasks", Some(&admin_token), None).await.status, StatusCode::OK);
+
+    let demoted = send(&app, Method::PATCH, &uri, Some(&admin), Some(json!({ "role": "user" }))).await;
+    assert_eq!(demoted.status, StatusCode::OK);
+    assert_eq!(demoted.body["role"], "user");
+    let user_token = login(&auth, &username).await;
+    assert_eq!(send(&app, Method::GET, "/admin/tasks", Some(&user_token), None).await.status, StatusCode::FORBIDDEN);
+
+    let invalid = send(&app, Method::PATCH, &uri, Some(&admin), Some(json!({ "role": "root" }))).await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn admin_cannot_demote_self() {
+    let auth = factory_auth_router(Stores::new());
+    let app = factory_admin_router(RequestCounter::new_arc(), Vec::new());
+    let (admin_id, username) = register(&auth).await;
+    auth::set_role(&admin_id, UserRole::Admin).unwrap();
+    let admin = login(&auth, &username).await;
+    let uri = format!("/admin/users/{}/role", admin_id);
+
+    let demoted = send(&app, Method::PATCH, &uri, Some(&admin), Some(json!({ "role": "user" }))).await;
+    assert_eq!(demoted.status, StatusCode::CONFLICT);
+    assert_eq!(demoted.body, json!({ "error": "cannot demote yourself" }));
+    assert_eq!(auth::find_user(&admin_id).unwrap().role, UserRole::Admin);
+    assert_eq!(send(&app, Method::PATCH, &uri, Some(&admin), Some(json!({ "role": "admin" }))).await.status, StatusCode::OK);
+
+    // 可由其他管理员降级
+    let other = send(&app, Method::PATCH, &uri, Some(&admin_token()), Some(json!({ "role": "user" }))).await;
+    assert_eq!(other.status, StatusCode::OK);
+    assert_eq!(auth::find_user(&admin_id).unwrap().role, UserRole::User);
+}
+
+#[tokio::test]
+async fn debug_routes() {
+    let _ = factory_rest_router(Container::new_arc()).await; // 构造时注册路由
+    let _ = factory_rest_router(Container::new_arc()).await; // 重复构造不会重复记录
+    let app = factory_admin_router(RequestCounter::new_arc(), Vec::new());
+    let admin = admin_token();
+
+    let routes = send(&app, Method::GET, "/admin/debug/routes", Some(&admin), None).await;
+    assert_eq!(routes.status, StatusCode::OK);
+    let routes = routes.body.as_array().expect("array").clone();
+    assert!(routes.contains(&json!({
+        "method": "GET",
+        "path_pattern": "/rest/{id}/history",
+        "module": "api::rest_store",
+        "description": "历史版本",
+    })));
+    assert!(routes.iter().any(|route| route["method"] == "PATCH" && route["path_pattern"] == "/rest/{id}"));
+    assert!(routes.iter().any(|route| route["path_pattern"] == "/admin/debug/routes"));
+    assert_eq!(routes.iter().filter(|route| route["method"] == "GET" && route["path_pattern"] == "/rest/{id}").count(), 1);
+
+    let user = new_user_token();
+    assert_eq!(send(&app, Method::GET, "/admin/debug/routes", Some(&user), None).await.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn user_rate_limit() {
+    let auth = factory_auth_router(Stores::new());
+    let (user_id, username) = register(&auth).await;
+    let user = jwt::issue(&user_id, &username, UserRole::User).expect("token");
+
+    let app = factory_admin_router(RequestCounter::new_arc(), Vec::new());
+    let admin = admin_token();
+    let uri = format!("/admin/users/{}/rate-limit", user_id);
+    let quota = send(&app, Method::PATCH, &uri, Some(&admin), Some(json!({ "capacity": 2, "refill_per_sec": 0.01 }))).await;
+    assert_eq!(quota.status, StatusCode::OK);
+    assert_eq!(quota.body, json!({ "capacity": 2, "refill_per_sec": 0.01, "unlimited": false }));
+    let invalid = send(&app, Method::PATCH, &uri, Some(&admin), Some(json!({ "capacity": 0 }))).await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let missing = send(&app, Method::PATCH, "/admin/users/nobody/rate-limit", Some(&admin), Some(json!({ "capacity": 2 }))).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+
+    // 按用户计数，与客户端IP无关
+    let rest = factory_rest_router(Container::new_arc()).await;
+    assert_eq!(send(&rest, Method::GET, "/rest", Some(&user), None).await.status, StatusCode::OK);
+    assert_eq!(send(&rest, Method::GET, "/rest", Some(&user), None).await.status, StatusCode::OK);
+    let limited = send(&rest, Method::GET, "/rest", Some(&user), None).await;
+    assert_eq!(limited.status, StatusCode::TOO_MANY_REQUESTS);
+    assert!(limited.headers.contains_key("retry-after"));
+    assert_eq!(limited.body, json!({ "error": "too many requests" }));
+
+    // 其他用户不受影响，放开后恢复
+    assert_eq!(send(&rest, Method::GET, "/rest", Some(&new_user_token()), None).await.status, StatusCode::OK);
+    send(&app, Method::PATCH, &uri, Some(&admin), Some(json!({ "unlimited": true }))).await;
+    assert_eq!(send(&rest, Method::GET, "/rest", Some(&user), None).await.status, StatusCode::OK);
+}
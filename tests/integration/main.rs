@@ -0,0 +1,88 @@
+//! 集成测试
+//!
+//! 直接调用资源路由 (`tower::ServiceExt::oneshot`)，不监听端口。WebSocket 需要真实的连接，在随机端口上启动服务 (见 [`serve`])。
+//! 节点存储是全局的，各测试以随机ID区分数据
+
+mod admin;
+mod node;
+mod rest_store;
+mod todos;
+mod ws;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    Router,
+};
+use rust_http_demo::api::{auth::UserRole, middleware::jwt};
+use serde_json::Value;
+use std::net::SocketAddr;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// 响应: 状态码、响应头与 JSON 响应体 (无响应体时为 `null`)
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+/// 测试用的请求
+///
+/// - `token` 有则带 `Authorization: Bearer <token>`
+/// - `body` 有则作为 JSON 请求体
+pub async fn send(app: &Router, method: Method, uri: &str, token: Option<&str>, body: Option<Value>) -> TestResponse {
+    send_with_headers(app, method, uri, token, &[], body).await
+}
+
+/// 同 [`send`]，另带请求头
+pub async fn send_with_headers(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+    headers: &[(&str, &str)],
+    body: Option<Value>,
+) -> TestResponse {
+    let mut builder = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    let request = match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    }.expect("valid request");
+
+    let response = app.clone().oneshot(request).await.expect("router is infallible");
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.expect("readable body");
+    let body = match bytes.is_empty() {
+        true => Value::Null,
+        false => serde_json::from_slice(&bytes).expect("json body"),
+    };
+    TestResponse { status, headers, body }
+}
+
+/// 在随机端口上启动服务，返回地址。测试结束 (运行时关闭) 时停止
+pub async fn serve(app: Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    addr
+}
+
+/// 新用户的令牌 (用户ID随机，数据与其他测试隔离)
+pub fn new_user_token() -> String {
+    jwt::issue(&Uuid::new_v4().to_string(), "tester", UserRole::User).expect("token")
+}
+
+/// 随机ID
+pub fn unique_id(prefix: &str) -> String {
+    format!("{}-{}", prefix, Uuid::new_v4())
+}
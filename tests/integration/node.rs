@@ -0,0 +1,541 @@
+//! `/node` 的集成测试
+
+use axum::{http::{Method, StatusCode}, Router};
+use rust_http_demo::api::{auth::UserRole, middleware::jwt, rest_node::factory_node_router};
+use serde_json::{json, Value};
+
+use crate::{send, unique_id};
+
+async fn app() -> Router {
+    factory_node_router().await
+}
+
+/// 创建节点，返回其路径
+async fn put_node(app: &Router, id: &str, body: Value) -> String {
+    let uri = format!("/node/{}", id);
+    let response = send(app, Method::PUT, &uri, None, Some(body)).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    uri
+}
+
+/// 从 `id` 开始执行，返回经过的节点ID
+async fn run_ids(app: &Router, id: &str, input: Value) -> Vec<Value> {
+    let response = send(app, Method::POST, &format!("/node/{}/run", id), None, Some(json!({ "input": input }))).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    response.body["steps"].as_array().unwrap().iter().map(|step| step["id"].clone()).collect()
+}
+
+#[tokio::test]
+async fn crud_lifecycle() {
+    let app = app().await;
+    let id = unique_id("node");
+    let uri = format!("/node/{}", id);
+
+    let created = send(&app, Method::POST, &uri, None, Some(json!({ "data": "hello" }))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.body["content"], "hello");
+    assert_eq!(created.body["kind"], "basic");
+    let conflict = send(&app, Method::POST, &uri, None, Some(json!({ "data": "again" }))).await;
+    assert_eq!(conflict.status, StatusCode::CONFLICT);
+    assert_eq!(conflict.body["content"], "hello");
+
+    let fetched = send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    assert_eq!(fetched.body, created.body);
+
+    let patched = send(&app, Method::PATCH, &uri, None, Some(json!({ "data": "world" }))).await;
+    assert_eq!(patched.status, StatusCode::OK);
+    assert_eq!(patched.body["content"], "world");
+    assert_eq!(patched.body["created_at"], created.body["created_at"]);
+    assert_eq!(send(&app, Method::GET, &uri, None, None).await.body, patched.body);
+
+    assert_eq!(send(&app, Method::DELETE, &uri, None, None).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(send(&app, Method::GET, &uri, None, None).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, Method::PATCH, &uri, None, Some(json!({ "data": 1 }))).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, Method::DELETE, &uri, None, None).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, Method::DELETE, "/node", None, None).await.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn runs_chain() {
+    let app = app().await;
+    let (a, b, c) = (unique_id("a"), unique_id("b"), unique_id("c"));
+    put_node(&app, &a, json!({ "next_id": b })).await;
+    put_node(&app, &b, json!({ "prev_id": a, "next_id": c })).await;
+    put_node(&app, &c, json!({ "prev_id": b })).await;
+
+    let response = send(&app, Method::POST, &format!("/node/{}/run", a), None, Some(json!({ "input": 42 }))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, json!({ "steps": [
+        { "id": a, "branch": null, "output": 42 },
+        { "id": b, "branch": null, "output": 42 },
+        { "id": c, "branch": null, "output": 42 },
+    ] }));
+    assert_eq!(run_ids(&app, &b, Value::Null).await, [json!(b), json!(c)]);
+
+    let missing = send(&app, Method::POST, "/node/missing/run", None, Some(json!({}))).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+/// 死循环的脚本，直到用完操作数 (远超下面的超时时间) 才以错误结束
+#[cfg(feature = "scripting")]
+const BUSY_SCRIPT: &str = "fn run(input) { let s = \"\"; loop { s = s + \"x\"; if s.len() > 10000 { s = \"\"; } } }";
+
+#[cfg(feature = "scripting")]
+#[tokio::test]
+async fn run_timeout() {
+    let app = app().await;
+    let (start, slow) = (unique_id("start"), unique_id("slow"));
+    put_node(&app, &start, json!({ "next_id": slow })).await;
+    put_node(&app, &slow, json!({ "kind": "script", "config": { "script": BUSY_SCRIPT } })).await;
+
+    let run_uri = format!("/node/{}/run", start);
+    let response = send(&app, Method::POST, &run_uri, None, Some(json!({ "timeout_ms": 50 }))).await;
+    assert_eq!(response.status, StatusCode::REQUEST_TIMEOUT);
+    assert_eq!(response.body, json!({ "error": "timed out", "executed_nodes": [start], "timed_out_at": slow }));
+
+    assert_eq!(send(&app, Method::DELETE, "/node/jobs/missing", None, None).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn run_history() {
+    let app = app().await;
+    let (a, b) = (unique_id("a"), unique_id("b"));
+    put_node(&app, &a, json!({ "next_id": b })).await;
+    put_node(&app, &b, json!({})).await;
+    run_ids(&app, &a, json!(1)).await;
+    run_ids(&app, &a, json!(2)).await;
+
+    let node = send(&app, Method::GET, &format!("/node/{}", b), None, None).await;
+    assert_eq!(node.body["run_count"], 2);
+    assert_eq!(node.body["last_output"], 2);
+    assert!(node.body["last_run_at"].is_string());
+
+    let history = send(&app, Method::GET, &format!("/node/{}/history", a), None, None).await;
+    assert_eq!(history.status, StatusCode::OK);
+    let records = history.body.as_array().unwrap();
+    let inputs: Vec<_> = records.iter().map(|record| record["input"].clone()).collect();
+    assert_eq!(inputs, [json!(1), json!(2)]);
+    assert_eq!(records[1]["output"], 2);
+    assert_eq!(records[1]["error"], Value::Null);
+    let b_history = send(&app, Method::GET, &format!("/node/{}/history", b), None, None).await;
+    assert_eq!(b_history.body[0]["run_id"], records[0]["run_id"]);
+    assert_ne!(records[0]["run_id"], records[1]["run_id"]);
+
+    send(&app, Method::DELETE, &format!("/node/{}", a), None, None).await;
+    assert_eq!(send(&app, Method::GET, &format!("/node/{}/history", a), None, None).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn broken_chain_and_cycle() {
+    let app = app().await;
+    let (a, b, missing) = (unique_id("a"), unique_id("b"), unique_id("missing"));
+    put_node(&app, &a, json!({ "next_id": missing })).await;
+    let response = send(&app, Method::POST, &format!("/node/{}/run", a), None, Some(json!({}))).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.body["steps"].as_array().unwrap().len(), 1);
+    assert_eq!(response.body["error"], format!("node not found: {}", missing));
+
+    put_node(&app, &a, json!({ "next_id": b })).await;
+    put_node(&app, &b, json!({ "next_id": a })).await;
+    let response = send(&app, Method::POST, &format!("/node/{}/run", a), None, Some(json!({}))).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert_eq!(response.body["error"], "cycle detected");
+}
+
+#[tokio::test]
+async fn runs_all_children() {
+    let app = app().await;
+    let (root, left, right, tail) = (unique_id("root"), unique_id("left"), unique_id("right"), unique_id("tail"));
+    let root_node = send(&app, Method::PUT, &format!("/node/{}", root), None, Some(json!({ "children_ids": [left, right] }))).await;
+    assert_eq!(root_node.body["next_id"], left);
+    put_node(&app, &left, json!({ "next_id": tail })).await;
+    put_node(&app, &right, json!({})).await;
+    put_node(&app, &tail, json!({})).await;
+    let left_node = send(&app, Method::GET, &format!("/node/{}", left), None, None).await;
+    assert_eq!(left_node.body["children_ids"], json!([tail]));
+
+    assert_eq!(run_ids(&app, &root, json!(1)).await, [json!(root), json!(left), json!(tail), json!(right)]);
+
+    let subgraph = send(&app, Method::GET, &format!("/node/{}/subgraph", root), None, None).await;
+    let ids: Vec<_> = subgraph.body.as_array().unwrap().iter().map(|node| node["id"].clone()).collect();
+    assert_eq!(ids, [json!(root), json!(left), json!(right), json!(tail)]);
+
+    // 第二个后继上的环同样被检测
+    put_node(&app, &right, json!({ "next_id": root })).await;
+    let response = send(&app, Method::POST, &format!("/node/{}/run", root), None, Some(json!({}))).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn move_relinks_neighbours() {
+    let app = app().await;
+    let (a, b, c) = (unique_id("a"), unique_id("b"), unique_id("c"));
+    put_node(&app, &a, json!({ "next_id": b })).await;
+    put_node(&app, &b, json!({ "prev_id": a, "next_id": c })).await;
+    put_node(&app, &c, json!({ "prev_id": b })).await;
+
+    let moved = send(&app, Method::POST, &format!("/node/{}/move", c), None, Some(json!({ "before_id": a }))).await;
+    assert_eq!(moved.status, StatusCode::OK);
+    assert_eq!(moved.body["prev_id"], Value::Null);
+    assert_eq!(moved.body["next_id"], a);
+    assert_eq!(run_ids(&app, &c, Value::Null).await, [json!(c), json!(a), json!(b)]);
+    let b_node = send(&app, Method::GET, &format!("/node/{}", b), None, None).await;
+    assert_eq!(b_node.body["next_id"], Value::Null);
+
+    let moved = send(&app, Method::POST, &format!("/node/{}/move", c), None, Some(json!({ "after_id": b }))).await;
+    assert_eq!(moved.status, StatusCode::OK);
+    assert_eq!(run_ids(&app, &a, Value::Null).await, [json!(a), json!(b), json!(c)]);
+
+    let uri = format!("/node/{}/move", a);
+    let both = send(&app, Method::POST, &uri, None, Some(json!({ "after_id": b, "before_id": c }))).await;
+    assert_eq!(both.status, StatusCode::BAD_REQUEST);
+    let itself = send(&app, Method::POST, &uri, None, Some(json!({ "after_id": a }))).await;
+    assert_eq!(itself.body, json!({ "error": "cannot move a node relative to itself" }));
+    let no_target = send(&app, Method::POST, &uri, None, Some(json!({ "after_id": "missing" }))).await;
+    assert_eq!(no_target.body, json!({ "error": "target node not found" }));
+    let no_node = send(&app, Method::POST, "/node/missing/move", None, Some(json!({ "after_id": a }))).await;
+    assert_eq!(no_node.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn metadata_merge_and_key_update() {
+    let app = app().await;
+    let uri = put_node(&app, &unique_id("meta"), json!({ "data": 1, "metadata": { "name": "start", "color": "red" } })).await;
+
+    let patched = send(&app, Method::PATCH, &uri, None, Some(json!({ "data": 2, "metadata": { "color": null, "x": 10 } }))).await;
+    assert_eq!(patched.body["content"], 2);
+    assert_eq!(patched.body["metadata"], json!({ "name": "start", "x": 10 }));
+
+    let metadata_uri = format!("{}/metadata", uri);
+    let updated = send(&app, Method::PATCH, &format!("{}/name", metadata_uri), None, Some(json!("renamed"))).await;
+    assert_eq!(updated.status, StatusCode::OK);
+    assert_eq!(updated.body, json!({ "name": "renamed", "x": 10 }));
+    let removed = send(&app, Method::PATCH, &format!("{}/x", metadata_uri), None, Some(Value::Null)).await;
+    assert_eq!(removed.body, json!({ "name": "renamed" }));
+    assert_eq!(send(&app, Method::GET, &metadata_uri, None, None).await.body, json!({ "name": "renamed" }));
+
+    let replaced = send(&app, Method::PUT, &uri, None, Some(json!({ "metadata": { "y": 1 } }))).await;
+    assert_eq!(replaced.body["metadata"], json!({ "y": 1 }));
+    assert_eq!(send(&app, Method::GET, "/node/missing/metadata", None, None).await.status, StatusCode::NOT_FOUND);
+    let missing = send(&app, Method::PATCH, "/node/missing/metadata/name", None, Some(json!(1))).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn delete_unlinks_and_rewires() {
+    let app = app().await;
+    let (a, b, c) = (unique_id("a"), unique_id("b"), unique_id("c"));
+    put_node(&app, &a, json!({ "next_id": b })).await;
+    put_node(&app, &b, json!({ "prev_id": a, "next_id": c })).await;
+    put_node(&app, &c, json!({ "prev_id": b })).await;
+
+    let deleted = send(&app, Method::DELETE, &format!("/node/{}?rewire=true", b), None, None).await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+    assert_eq!(run_ids(&app, &a, Value::Null).await, [json!(a), json!(c)]);
+    assert_eq!(send(&app, Method::GET, &format!("/node/{}", c), None, None).await.body["prev_id"], a);
+
+    let deleted = send(&app, Method::DELETE, &format!("/node/{}?cascade_unlink=true", c), None, None).await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+    let a_node = send(&app, Method::GET, &format!("/node/{}", a), None, None).await;
+    assert_eq!(a_node.body["next_id"], Value::Null);
+    assert_eq!(a_node.body["children_ids"], json!([]));
+    let missing = send(&app, Method::DELETE, &format!("/node/{}?cascade_unlink=true", c), None, None).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn owner_listing_and_cascade_delete() {
+    let app = app().await;
+    let owner = unique_id("owner");
+    let token = jwt::issue(&owner, &owner, UserRole::User).unwrap();
+    let (a, b) = (unique_id("a"), unique_id("b"));
+    for id in [&a, &b] {
+        // 请求体中的 owner_id 被忽略，创建者取自令牌
+        let created = send(&app, Method::PUT, &format!("/node/{}", id), Some(&token), Some(json!({ "owner_id": "someone-else" }))).await;
+        assert_eq!(created.status, StatusCode::CREATED);
+        assert_eq!(created.body["owner_id"], owner);
+    }
+    let anonymous = unique_id("anonymous");
+    put_node(&app, &anonymous, json!({})).await;
+    assert_eq!(send(&app, Method::GET, &format!("/node/{}", anonymous), None, None).await.body["owner_id"], Value::Null);
+    let invalid = send(&app, Method::PUT, &format!("/node/{}", unique_id("c")), Some("invalid"), Some(json!({}))).await;
+    assert_eq!(invalid.status, StatusCode::UNAUTHORIZED);
+    // 覆盖时创建者不变
+    let other = jwt::issue("other-user", "other", UserRole::User).unwrap();
+    let overwritten = send(&app, Method::PUT, &format!("/node/{}", a), Some(&other), Some(json!({ "data": 1 }))).await;
+    assert_eq!(overwritten.body["owner_id"], owner);
+
+    let listed = send(&app, Method::GET, &format!("/node?owner={}", owner), None, None).await;
+    let ids: Vec<_> = listed.body.as_array().unwrap().iter().map(|node| node["id"].clone()).collect();
+    assert_eq!(ids, [json!(a), json!(b)]);
+
+    // 仅限管理员
+    let uri = format!("/node?owner={}", owner);
+    assert_eq!(send(&app, Method::DELETE, &uri, None, None).await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(send(&app, Method::DELETE, &uri, Some(&token), None).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::GET, &format!("/node/{}", a), None, None).await.status, StatusCode::OK);
+    let admin = jwt::issue(&unique_id("admin"), "admin", UserRole::Admin).unwrap();
+    assert_eq!(send(&app, Method::DELETE, "/node", Some(&admin), None).await.status, StatusCode::FORBIDDEN);
+    let deleted = send(&app, Method::DELETE, &uri, Some(&admin), None).await;
+    assert_eq!(deleted.body, json!({ "deleted": 2 }));
+    assert_eq!(send(&app, Method::GET, &format!("/node/{}", a), None, None).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, Method::GET, &format!("/node?owner={}", owner), None, None).await.body, json!([]));
+}
+
+#[tokio::test]
+async fn subgraph_export() {
+    let app = app().await;
+    let (a, b) = (unique_id("a"), unique_id("b"));
+    put_node(&app, &a, json!({ "next_id": b })).await;
+    put_node(&app, &b, json!({ "prev_id": a })).await;
+
+    let response = send(&app, Method::GET, &format!("/node/{}/subgraph", a), None, None).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let ids: Vec<_> = response.body.as_array().unwrap().iter().map(|node| node["id"].clone()).collect();
+    assert_eq!(ids, [json!(a), json!(b)]);
+}
+
+#[tokio::test]
+async fn ancestors_chain() {
+    let app = app().await;
+    let (a, b, c) = (unique_id("a"), unique_id("b"), unique_id("c"));
+    put_node(&app, &a, json!({ "next_id": b })).await;
+    put_node(&app, &b, json!({ "prev_id": a, "next_id": c })).await;
+    put_node(&app, &c, json!({ "prev_id": b })).await;
+
+    let response = send(&app, Method::GET, &format!("/node/{}/ancestors", c), None, None).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["chain"], json!([a, b, c]));
+    assert_eq!(response.body["nodes"][1]["id"], b);
+    let root = send(&app, Method::GET, &format!("/node/{}/ancestors", a), None, None).await;
+    assert_eq!(root.body["chain"], json!([a]));
+
+    // 成环时在重复处停止
+    put_node(&app, &a, json!({ "prev_id": c, "next_id": b })).await;
+    let cycle = send(&app, Method::GET, &format!("/node/{}/ancestors", c), None, None).await;
+    assert_eq!(cycle.body["chain"], json!([a, b, c]));
+    assert_eq!(send(&app, Method::GET, "/node/missing/ancestors", None, None).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn clone_node_and_chain() {
+    let app = app().await;
+    let (a, b, c) = (unique_id("a"), unique_id("b"), unique_id("c"));
+    put_node(&app, &a, json!({ "children_ids": [b, c], "metadata": { "name": "template" } })).await;
+    put_node(&app, &b, json!({ "prev_id": a })).await;
+    put_node(&app, &c, json!({ "prev_id": a })).await;
+
+    let single = send(&app, Method::POST, &format!("/node/{}/clone?metadata=false", a), None, None).await;
+    assert_eq!(single.status, StatusCode::CREATED);
+    assert_eq!(single.body["original_id"], a);
+    let clone_id = single.body["clone_id"].as_str().unwrap().to_string();
+    let clone = send(&app, Method::GET, &format!("/node/{}", clone_id), None, None).await;
+    assert_eq!(clone.body["children_ids"], json!([]));
+    assert_eq!(clone.body["next_id"], Value::Null);
+    assert_eq!(clone.body["metadata"], json!({}));
+
+    let chain = send(&app, Method::POST, &format!("/node/{}/clone?with_chain=true", a), None, None).await;
+    let mapping = &chain.body["mapping"];
+    assert_eq!(mapping.as_object().unwrap().len(), 3);
+    let (new_a, new_b, new_c) = (mapping[&a].clone(), mapping[&b].clone(), mapping[&c].clone());
+    assert_eq!(chain.body["clone_id"], new_a);
+    let root = send(&app, Method::GET, &format!("/node/{}", new_a.as_str().unwrap()), None, None).await;
+    assert_eq!(root.body["children_ids"], json!([new_b, new_c]));
+    assert_eq!(root.body["metadata"], json!({ "name": "template" }));
+    let child = send(&app, Method::GET, &format!("/node/{}", new_b.as_str().unwrap()), None, None).await;
+    assert_eq!(child.body["prev_id"], new_a);
+
+    let missing = send(&app, Method::POST, "/node/missing/clone", None, None).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn http_endpoint_nodes() {
+    let app = app().await;
+    let (endpoint, next) = (unique_id("endpoint"), unique_id("next"));
+    let path = format!("/{}", unique_id("hook"));
+    let uri = put_node(&app, &endpoint, json!({ "kind": "http_endpoint", "config": { "method": "POST", "path": path }, "next_id": next })).await;
+    put_node(&app, &next, json!({})).await;
+
+    let response = send(&app, Method::POST, &path, None, Some(json!({ "value": 1 }))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, json!({ "value": 1 }));
+    assert_eq!(send(&app, Method::GET, &path, None, None).await.status, StatusCode::NOT_FOUND);
+
+    // 修改路由后旧路由注销
+    let new_path = format!("{}-v2", path);
+    send(&app, Method::PATCH, &uri, None, Some(json!({ "kind": "http_endpoint", "config": { "path": new_path } }))).await;
+    assert_eq!(send(&app, Method::POST, &path, None, Some(json!(1))).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, Method::GET, &new_path, None, None).await.body, Value::Null);
+
+    send(&app, Method::DELETE, &uri, None, None).await;
+    assert_eq!(send(&app, Method::GET, &new_path, None, None).await.status, StatusCode::NOT_FOUND);
+
+    let invalid = send(&app, Method::PUT, &uri, None, Some(json!({ "kind": "http_endpoint", "config": { "path": "relative" } }))).await;
+    assert_eq!(invalid.body["fields"]["__all__"][0]["message"], "config.path: must start with /");
+}
+
+#[tokio::test]
+async fn http_endpoint_route_conflict() {
+    let app = app().await;
+    let (first, second) = (unique_id("first"), unique_id("second"));
+    let path = format!("/{}", unique_id("hook"));
+    let config = json!({ "method": "POST", "path": path });
+    let first_uri = put_node(&app, &first, json!({ "kind": "http_endpoint", "config": config, "data": "first" })).await;
+
+    // 其他节点注册同一路由被拒绝，路由仍属于原节点
+    let second_uri = format!("/node/{}", second);
+    let body = json!({ "kind": "http_endpoint", "config": config });
+    for method in [Method::PUT, Method::POST] {
+        let conflict = send(&app, method, &second_uri, None, Some(body.clone())).await;
+        assert_eq!(conflict.status, StatusCode::CONFLICT);
+        assert_eq!(conflict.body["error"], format!("endpoint POST {} is already registered by another node", path));
+    }
+    assert_eq!(send(&app, Method::GET, &second_uri, None, None).await.status, StatusCode::NOT_FOUND);
+    put_node(&app, &second, json!({})).await;
+    assert_eq!(send(&app, Method::PATCH, &second_uri, None, Some(body.clone())).await.status, StatusCode::CONFLICT);
+    let import = send(&app, Method::POST, "/node/subgraph/import", None, Some(json!([{ "id": "a", "kind": "http_endpoint", "config": config }]))).await;
+    assert_eq!(import.status, StatusCode::CONFLICT);
+
+    // 原节点重新保存不受影响，复制出的节点不抢占路由
+    assert_eq!(send(&app, Method::PUT, &first_uri, None, Some(json!({ "kind": "http_endpoint", "config": config, "data": "first" }))).await.status, StatusCode::CREATED);
+    assert_eq!(send(&app, Method::POST, &format!("{}/clone", first_uri), None, None).await.status, StatusCode::CREATED);
+    assert_eq!(send(&app, Method::POST, &path, None, Some(json!(1))).await.body, json!(1));
+
+    // 原节点删除后路由可被注册
+    send(&app, Method::DELETE, &first_uri, None, None).await;
+    assert_eq!(send(&app, Method::PUT, &second_uri, None, Some(body)).await.status, StatusCode::CREATED);
+    assert_eq!(send(&app, Method::POST, &path, None, Some(json!(2))).await.body, json!(2));
+}
+
+#[tokio::test]
+async fn types_and_config_validation() {
+    let app = app().await;
+    let types = send(&app, Method::GET, "/node/types", None, None).await;
+    assert_eq!(types.status, StatusCode::OK);
+    let names: Vec<_> = types.body.as_array().unwrap().iter().map(|kind| kind["name"].clone()).collect();
+    assert_eq!(names, [json!("basic"), json!("script"), json!("http"), json!("branch"), json!("http_endpoint")]);
+    assert_eq!(types.body[2]["config_schema"]["required"], json!(["url"]));
+
+    let uri = format!("/node/{}", unique_id("http"));
+    let invalid = send(&app, Method::POST, &uri, None, Some(json!({ "kind": "http", "config": { "url": 1 } }))).await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(invalid.body["fields"]["__all__"][0]["message"], "config.url: expected string");
+    let missing = send(&app, Method::PUT, &uri, None, Some(json!({ "kind": "script" }))).await;
+    assert_eq!(missing.body["fields"]["__all__"][0]["message"], "config: expected object");
+
+    let valid = send(&app, Method::POST, &uri, None, Some(json!({ "kind": "http", "config": { "url": "https://example.com/hook" } }))).await;
+    assert_eq!(valid.status, StatusCode::CREATED);
+    let invalid = send(&app, Method::PATCH, &uri, None, Some(json!({ "kind": "branch", "config": {} }))).await;
+    assert_eq!(invalid.body["fields"]["__all__"][0]["message"], "config.condition: required");
+}
+
+#[cfg(feature = "scripting")]
+#[tokio::test]
+async fn patch_keeps_missing_fields() {
+    let app = app().await;
+    let (id, next, other, fallback) = (unique_id("script"), unique_id("next"), unique_id("other"), unique_id("else"));
+    let script = "fn run(input) { input + 1 }";
+    let uri = put_node(&app, &id, json!({
+        "kind": "script",
+        "config": { "script": script },
+        "children_ids": [next, other],
+        "else_id": fallback,
+        "schedule": "0 0 * * * *",
+        "metadata": { "owner": "team-a" },
+    })).await;
+
+    let patched = send(&app, Method::PATCH, &uri, None, Some(json!({ "metadata": { "label": "v2" } }))).await;
+    assert_eq!(patched.status, StatusCode::OK);
+    assert_eq!(patched.body["kind"], "script");
+    assert_eq!(patched.body["config"], json!({ "script": script }));
+    assert_eq!(patched.body["children_ids"], json!([next, other]));
+    assert_eq!(patched.body["else_id"], fallback);
+    assert_eq!(patched.body["schedule"], "0 0 * * * *");
+    assert_eq!(patched.body["metadata"], json!({ "owner": "team-a", "label": "v2" }));
+
+    // next_id 只替换第一个后继，null 清除可选字段，config 按原有的 kind 校验
+    let replacement = unique_id("replacement");
+    let patched = send(&app, Method::PATCH, &uri, None, Some(json!({ "next_id": replacement, "else_id": null, "schedule": null }))).await;
+    assert_eq!(patched.body["children_ids"], json!([replacement, other]));
+    assert_eq!(patched.body["else_id"], Value::Null);
+    assert_eq!(patched.body["schedule"], Value::Null);
+    assert_eq!(patched.body["kind"], "script");
+    let invalid = send(&app, Method::PATCH, &uri, None, Some(json!({ "config": {} }))).await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(invalid.body["fields"]["__all__"][0]["message"], "config.script: required");
+}
+
+#[tokio::test]
+async fn http_node_rejects_private_targets() {
+    let app = app().await;
+    let uri = format!("/node/{}", unique_id("http"));
+    for url in [
+        "http://localhost:8080/",
+        "http://api.localhost/",
+        "http://127.0.0.1/",
+        "http://169.254.169.254/latest/meta-data/",
+        "http://10.0.0.1/",
+        "http://192.168.1.1/",
+        "http://[::1]/",
+        "http://[::ffff:127.0.0.1]/",
+        "file:///etc/passwd",
+    ] {
+        let response = send(&app, Method::POST, &uri, None, Some(json!({ "kind": "http", "config": { "url": url } }))).await;
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", url);
+        assert!(response.body["fields"]["__all__"][0]["message"].as_str().unwrap().starts_with("config.url: "), "{}", url);
+    }
+}
+
+#[cfg(feature = "scripting")]
+#[tokio::test]
+async fn branch_and_script_nodes() {
+    let app = app().await;
+    let (branch, double, negate) = (unique_id("branch"), unique_id("double"), unique_id("negate"));
+    put_node(&app, &branch, json!({ "kind": "branch", "config": { "condition": "input > 0" }, "next_id": double, "else_id": negate })).await;
+    put_node(&app, &double, json!({ "kind": "script", "config": { "script": "fn run(input) { input * 2 }" } })).await;
+    put_node(&app, &negate, json!({ "kind": "script", "config": { "script": "fn run(input) { -input }" } })).await;
+
+    let response = send(&app, Method::POST, &format!("/node/{}/run", branch), None, Some(json!({ "input": 21 }))).await;
+    assert_eq!(response.body, json!({ "steps": [
+        { "id": branch, "branch": "then", "output": 21 },
+        { "id": double, "branch": null, "output": 42 },
+    ] }));
+    let response = send(&app, Method::POST, &format!("/node/{}/run", branch), None, Some(json!({ "input": -5 }))).await;
+    assert_eq!(response.body["steps"][0]["branch"], "else");
+    assert_eq!(response.body["steps"][1]["output"], 5);
+
+    let valid = send(&app, Method::POST, "/node/validate-script", None, Some(json!({ "script": "fn run(input) { input }" }))).await;
+    assert_eq!(valid.status, StatusCode::OK);
+    let invalid = send(&app, Method::POST, "/node/validate-script", None, Some(json!({ "script": "fn run(input) {" }))).await;
+    assert_ne!(invalid.status, StatusCode::OK);
+}
+
+#[cfg(feature = "scripting")]
+#[tokio::test]
+async fn script_limits() {
+    let app = app().await;
+    let (endless, recursive) = (unique_id("endless"), unique_id("recursive"));
+    put_node(&app, &endless, json!({ "kind": "script", "config": { "script": "fn run(input) { loop {} }" } })).await;
+    put_node(&app, &recursive, json!({ "kind": "script", "config": { "script": "fn run(input) { run(input) }" } })).await;
+
+    let response = send(&app, Method::POST, &format!("/node/{}/run", endless), None, Some(json!({ "input": 1 }))).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.body["error"].as_str().unwrap().contains("Too many operations"), "{}", response.body);
+    let response = send(&app, Method::POST, &format!("/node/{}/run", recursive), None, Some(json!({ "input": 1 }))).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.body["error"].as_str().unwrap().contains("Stack overflow"), "{}", response.body);
+
+    // 每次翻倍，几十次操作即可耗尽内存，需由数据大小限制终止
+    let (strings, arrays) = (unique_id("strings"), unique_id("arrays"));
+    put_node(&app, &strings, json!({ "kind": "script", "config": { "script": "fn run(input) { let s = \"x\"; loop { s += s; } }" } })).await;
+    put_node(&app, &arrays, json!({ "kind": "script", "config": { "script": "fn run(input) { let a = [1]; loop { a += a; } }" } })).await;
+    let response = send(&app, Method::POST, &format!("/node/{}/run", strings), None, Some(json!({ "input": 1 }))).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.body["error"].as_str().unwrap().contains("Length of string"), "{}", response.body);
+    let response = send(&app, Method::POST, &format!("/node/{}/run", arrays), None, Some(json!({ "input": 1 }))).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.body["error"].as_str().unwrap().contains("Size of array"), "{}", response.body);
+}
+
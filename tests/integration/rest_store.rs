@@ -0,0 +1,347 @@
+//! `/rest` 的集成测试
+
+use axum::{http::{Method, StatusCode}, Router};
+use rust_http_demo::{api::rest_store::factory_rest_router, container::Container};
+use serde_json::{json, Value};
+
+use crate::{new_user_token, send, send_with_headers, unique_id};
+
+async fn app() -> Router {
+    factory_rest_router(Container::new_arc()).await
+}
+
+#[tokio::test]
+async fn crud_lifecycle() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+
+    let created = send(&app, Method::POST, "/rest", token, Some(json!({ "data": { "name": "a", "n": 1 } }))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.body["data"], json!({ "name": "a", "n": 1 }));
+    let uri = format!("/rest/{}", created.body["id"].as_str().expect("id"));
+
+    let fetched = send(&app, Method::GET, &uri, token, None).await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    assert_eq!(fetched.body, created.body);
+
+    let patched = send(&app, Method::PATCH, &uri, token, Some(json!({ "data": [1, 2, 3] }))).await;
+    assert_eq!(patched.status, StatusCode::OK);
+    assert_eq!(patched.body["data"], json!([1, 2, 3]));
+    assert_eq!(send(&app, Method::GET, &uri, token, None).await.body, patched.body);
+
+    assert_eq!(send(&app, Method::DELETE, &uri, token, None).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(send(&app, Method::GET, &uri, token, None).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, Method::PATCH, &uri, token, Some(json!({ "data": 1 }))).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, Method::DELETE, "/rest", token, None).await.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn users_are_isolated() {
+    let app = app().await;
+    let (alice, bob) = (new_user_token(), new_user_token());
+    let uri = format!("/rest/{}", unique_id("item"));
+    send(&app, Method::PUT, &uri, Some(&alice), Some(json!({ "data": "secret" }))).await;
+
+    assert_eq!(send(&app, Method::GET, &uri, Some(&bob), None).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::PUT, &uri, Some(&bob), Some(json!({ "data": 1 }))).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::POST, &uri, Some(&bob), Some(json!({ "data": 1 }))).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::DELETE, &uri, Some(&bob), None).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::GET, "/rest", Some(&bob), None).await.body, json!([]));
+    assert_eq!(send(&app, Method::GET, "/rest", None, None).await.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn post_conflicts_put_overwrites() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    let uri = format!("/rest/{}", unique_id("item"));
+
+    let created = send(&app, Method::POST, &uri, token, Some(json!({ "data": 1 }))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    let conflict = send(&app, Method::POST, &uri, token, Some(json!({ "data": 2 }))).await;
+    assert_eq!(conflict.status, StatusCode::CONFLICT);
+    assert_eq!(conflict.body["data"], 1);
+
+    let replaced = send(&app, Method::PUT, &uri, token, Some(json!({ "data": 2 }))).await;
+    assert_eq!(replaced.status, StatusCode::CREATED);
+    assert_eq!(replaced.body["data"], 2);
+    assert_eq!(replaced.body["created_at"], created.body["created_at"]);
+}
+
+#[tokio::test]
+async fn optimistic_concurrency() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    let uri = format!("/rest/{}", unique_id("item"));
+
+    let created = send_with_headers(&app, Method::PUT, &uri, token, &[("x-expected-version", "0")], Some(json!({ "data": 1 }))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    let version: u64 = created.headers["x-version"].to_str().unwrap().parse().unwrap();
+    assert_eq!(send(&app, Method::GET, &uri, token, None).await.headers["x-version"], version.to_string().as_str());
+
+    let stale = send_with_headers(&app, Method::PUT, &uri, token, &[("x-expected-version", "0")], Some(json!({ "data": 2 }))).await;
+    assert_eq!(stale.status, StatusCode::CONFLICT);
+    assert_eq!(stale.body, json!({ "error": "version mismatch", "current_version": version }));
+
+    let expected = version.to_string();
+    let updated = send_with_headers(&app, Method::PATCH, &uri, token, &[("x-expected-version", &expected)], Some(json!({ "data": 3 }))).await;
+    assert_eq!(updated.status, StatusCode::OK);
+    let new_version: u64 = updated.headers["x-version"].to_str().unwrap().parse().unwrap();
+    assert!(new_version > version);
+
+    let retried = send_with_headers(&app, Method::PATCH, &uri, token, &[("x-expected-version", &expected)], Some(json!({ "data": 4 }))).await;
+    assert_eq!(retried.status, StatusCode::CONFLICT);
+    assert_eq!(retried.body["current_version"], new_version);
+
+    let invalid = send_with_headers(&app, Method::PATCH, &uri, token, &[("x-expected-version", "abc")], Some(json!({ "data": 5 }))).await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+    assert_eq!(invalid.body, json!({ "error": "invalid expected version" }));
+}
+
+#[tokio::test]
+async fn if_unmodified_since_prevents_lost_update() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    let uri = format!("/rest/{}", unique_id("item"));
+    send(&app, Method::PUT, &uri, token, Some(json!({ "data": "v1" }))).await;
+
+    // 客户端A在T1读取
+    let read = send(&app, Method::GET, &uri, token, None).await;
+    let last_modified = read.headers["last-modified"].to_str().unwrap().to_string();
+    let unchanged = send_with_headers(&app, Method::PUT, &uri, token, &[("if-unmodified-since", &last_modified)], Some(json!({ "data": "v1" }))).await;
+    assert_eq!(unchanged.status, StatusCode::CREATED);
+
+    // 客户端B随后修改。HTTP-date 精确到秒，A 以读取前一秒的时间写入，不依赖 B 的修改跨过秒的边界
+    let version = unchanged.headers["x-version"].to_str().unwrap().to_string();
+    send(&app, Method::PUT, &uri, token, Some(json!({ "data": "from B" }))).await;
+    let read_at = httpdate::parse_http_date(&last_modified).unwrap();
+    let before_read = httpdate::fmt_http_date(read_at - std::time::Duration::from_secs(1));
+
+    // 客户端A的写入被拒绝，B的修改保留
+    let stale = send_with_headers(&app, Method::PUT, &uri, token, &[("if-unmodified-since", &before_read)], Some(json!({ "data": "from A" }))).await;
+    assert_eq!(stale.status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(stale.body["error"], "precondition failed");
+    assert_ne!(stale.headers["last-modified"], before_read.as_str());
+    assert_eq!(send(&app, Method::GET, &uri, token, None).await.body["data"], "from B");
+
+    // 同一秒内的修改由 X-Expected-Version 检测
+    let stale = send_with_headers(&app, Method::PUT, &uri, token, &[("x-expected-version", &version)], Some(json!({ "data": "from A" }))).await;
+    assert_eq!(stale.status, StatusCode::CONFLICT);
+    assert_eq!(send(&app, Method::GET, &uri, token, None).await.body["data"], "from B");
+
+    let invalid = send_with_headers(&app, Method::PUT, &uri, token, &[("if-unmodified-since", "yesterday")], Some(json!({ "data": 1 }))).await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+
+    // 不存在的项没有修改时间，直接创建
+    let new_uri = format!("/rest/{}", unique_id("item"));
+    let created = send_with_headers(&app, Method::PUT, &new_uri, token, &[("if-unmodified-since", &last_modified)], Some(json!({ "data": 1 }))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn patch_fields_via_query() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    let uri = format!("/rest/{}", unique_id("item"));
+    send(&app, Method::PUT, &uri, token, Some(json!({ "data": { "count": 1, "tags": ["a"], "name": "x" } }))).await;
+
+    let patched = send(&app, Method::PATCH, &format!("{}?set.data.count=42&set.data.active=true&set./data/tags/1=%22b%22&set.data.meta.note=null", uri), token, None).await;
+    assert_eq!(patched.status, StatusCode::OK);
+    assert!(patched.headers.contains_key("x-version"));
+    assert_eq!(patched.body["data"], json!({ "count": 42, "active": true, "tags": ["a", "b"], "name": "x", "meta": { "note": null } }));
+
+    // 任一字段有误时不做修改
+    for query in ["set.data.count=abc", "set.owner_id=%22me%22", "set.data..x=1", "set.data.count=1&set.data.name.first=1", "set.data.tags.5=1"] {
+        let invalid = send(&app, Method::PATCH, &format!("{}?{}", uri, query), token, None).await;
+        assert_eq!(invalid.status, StatusCode::BAD_REQUEST, "{}", query);
+    }
+    assert_eq!(send(&app, Method::GET, &uri, token, None).await.body["data"]["count"], 42);
+
+    assert_eq!(send(&app, Method::PATCH, &format!("{}?set.data.count=1", uri), Some(&new_user_token()), None).await.status, StatusCode::FORBIDDEN);
+    let missing = format!("/rest/{}?set.data.count=1", unique_id("item"));
+    assert_eq!(send(&app, Method::PATCH, &missing, token, None).await.status, StatusCode::NOT_FOUND);
+
+    // 不带 set. 参数时仍按请求体替换
+    let replaced = send(&app, Method::PATCH, &uri, token, Some(json!({ "data": 7 }))).await;
+    assert_eq!(replaced.body["data"], 7);
+}
+
+#[tokio::test]
+async fn increment() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    let id = unique_id("counter");
+    send(&app, Method::PUT, &format!("/rest/{}", id), token, Some(json!({ "data": { "count": 1, "name": "x" } }))).await;
+    let uri = format!("/rest/{}/increment", id);
+
+    let result = send(&app, Method::POST, &uri, token, Some(json!({ "path": "/count", "by": 5 }))).await;
+    assert_eq!(result.status, StatusCode::OK);
+    assert_eq!(result.body, json!({ "id": id, "previous": 1, "current": 6 }));
+    let result = send(&app, Method::POST, &uri, token, Some(json!({ "path": "/count", "by": -0.5 }))).await;
+    assert_eq!(result.body["current"], 5.5);
+
+    let not_number = send(&app, Method::POST, &uri, token, Some(json!({ "path": "/name", "by": 1 }))).await;
+    assert_eq!(not_number.status, StatusCode::BAD_REQUEST);
+    assert_eq!(not_number.body, json!({ "error": "value at path is not a number" }));
+    let missing = send(&app, Method::POST, "/rest/missing/increment", token, Some(json!({ "path": "/count", "by": 1 }))).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn search_and_aggregate() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    for (id, status, n) in [("a", "done", 1), ("b", "todo", 2), ("c", "done", 3)] {
+        send(&app, Method::PUT, &format!("/rest/{}", id), token, Some(json!({ "data": { "status": status, "n": n } }))).await;
+    }
+    let ids = |body: &Value| body.as_array().unwrap().iter().map(|item| item["id"].clone()).collect::<Vec<_>>();
+
+    let found = send(&app, Method::GET, "/rest/search?field=data.status&q=done", token, None).await;
+    assert_eq!(found.status, StatusCode::OK);
+    let mut found = ids(&found.body);
+    found.sort_by_key(|id| id.to_string());
+    assert_eq!(found, [json!("a"), json!("c")]);
+    let found = send(&app, Method::GET, "/rest/search?field=/data/n&q_gt=2", token, None).await;
+    assert_eq!(ids(&found.body), [json!("c")]);
+    let invalid = send(&app, Method::GET, "/rest/search?field=data.n", token, None).await;
+    assert_eq!(invalid.body, json!({ "error": "q or q_gt is required" }));
+
+    let counts = send(&app, Method::GET, "/rest/aggregate?field=data.status&op=count_by_value", token, None).await;
+    assert_eq!(counts.body, json!({ "done": 2, "todo": 1 }));
+    let sum = send(&app, Method::GET, "/rest/aggregate?field=data.n&op=sum", token, None).await;
+    assert_eq!(sum.body, json!(6.0));
+    let max = send(&app, Method::GET, "/rest/aggregate?field=data.n&op=max", token, None).await;
+    assert_eq!(max.body, json!(3.0));
+    let unknown = send(&app, Method::GET, "/rest/aggregate?field=data.n&op=median", token, None).await;
+    assert_eq!(unknown.status, StatusCode::BAD_REQUEST);
+    let missing = send(&app, Method::GET, "/rest/aggregate?field=data.missing&op=sum", token, None).await;
+    assert_eq!(missing.body, json!({ "error": "field not found: data.missing" }));
+}
+
+#[tokio::test]
+async fn query_filter_dsl() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    for (id, status, count, name) in [("a", "active", 3, "zed"), ("b", "active", 8, "amy"), ("c", "idle", 9, "bob"), ("d", "active", 6, "carl")] {
+        send(&app, Method::PUT, &format!("/rest/{}", id), token, Some(json!({ "data": { "status": status, "count": count, "name": name } }))).await;
+    }
+    let ids = |body: &Value| body["items"].as_array().unwrap().iter().map(|item| item["id"].clone()).collect::<Vec<_>>();
+
+    let query = json!({
+        "filter": { "and": [
+            { "field": "data.status", "op": "eq", "value": "active" },
+            { "field": "data.count", "op": "gt", "value": 5 },
+        ] },
+        "sort": { "field": "data.name", "order": "asc" },
+        "limit": 1,
+    });
+    let found = send(&app, Method::POST, "/rest/query", token, Some(query)).await;
+    assert_eq!(found.status, StatusCode::OK);
+    assert_eq!(found.body["total"], 2);
+    assert_eq!(ids(&found.body), [json!("b")]);
+
+    let query = json!({
+        "filter": { "or": [
+            { "field": "/data/name", "op": "contains", "value": "ar" },
+            { "field": "data.status", "op": "in", "value": ["idle"] },
+        ] },
+        "sort": { "field": "data.count", "order": "desc" },
+    });
+    let found = send(&app, Method::POST, "/rest/query", token, Some(query)).await;
+    assert_eq!(ids(&found.body), [json!("c"), json!("d")]);
+
+    let all = send(&app, Method::POST, "/rest/query", token, Some(json!({}))).await;
+    assert_eq!(all.body["total"], 4);
+    let not_array = json!({ "filter": { "field": "data.status", "op": "in", "value": "idle" } });
+    assert_eq!(send(&app, Method::POST, "/rest/query", token, Some(not_array)).await.status, StatusCode::BAD_REQUEST);
+    let unknown_op = json!({ "filter": { "field": "data.status", "op": "like", "value": "a" } });
+    assert_eq!(send(&app, Method::POST, "/rest/query", token, Some(unknown_op)).await.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn list_pagination() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    for id in ["c", "a", "b"] {
+        send(&app, Method::PUT, &format!("/rest/{}", id), token, Some(json!({ "data": id }))).await;
+    }
+    let ids = |body: &Value| body.as_array().unwrap().iter().map(|item| item["id"].clone()).collect::<Vec<_>>();
+
+    let all = send(&app, Method::GET, "/rest", token, None).await;
+    assert_eq!(ids(&all.body), [json!("a"), json!("b"), json!("c")]); // 按ID排序
+    assert_eq!(all.headers["x-total-count"], "3");
+
+    let first = send(&app, Method::GET, "/rest?after_id=&limit=2", token, None).await;
+    assert_eq!(ids(&first.body), [json!("a"), json!("b")]);
+    assert!(first.headers["link"].to_str().unwrap().contains("after_id=b"));
+    let rest = send(&app, Method::GET, "/rest?after_id=b&limit=2", token, None).await;
+    assert_eq!(ids(&rest.body), [json!("c")]);
+    assert!(!rest.headers.contains_key("link")); // 未取满，没有下一页
+}
+
+#[tokio::test]
+async fn history_and_restore() {
+    let app = app().await;
+    let (token, other) = (new_user_token(), new_user_token());
+    let token = Some(token.as_str());
+    let uri = format!("/rest/{}", unique_id("item"));
+    for value in [1, 2, 3] {
+        send(&app, Method::PUT, &uri, token, Some(json!({ "data": value }))).await;
+    }
+    let data = |body: &Value| body.as_array().unwrap().iter().map(|record| record["data"].clone()).collect::<Vec<_>>();
+
+    let history = send(&app, Method::GET, &format!("{}/history", uri), token, None).await;
+    assert_eq!(history.status, StatusCode::OK);
+    assert_eq!(data(&history.body), [json!(1), json!(2)]);
+    assert_eq!(history.body[0]["version"], 1);
+    assert_eq!(history.body[1]["version"], 2);
+
+    let restored = send(&app, Method::POST, &format!("{}/history/1/restore", uri), token, None).await;
+    assert_eq!(restored.status, StatusCode::OK);
+    assert_eq!(restored.body["data"], 1);
+    assert_eq!(send(&app, Method::GET, &uri, token, None).await.body["data"], 1);
+    let history = send(&app, Method::GET, &format!("{}/history", uri), token, None).await;
+    assert_eq!(data(&history.body), [json!(1), json!(2), json!(3)]); // 恢复前的值也成为历史
+
+    let missing = send(&app, Method::POST, &format!("{}/history/9/restore", uri), token, None).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.body, json!({ "error": "version not found" }));
+    assert_eq!(send(&app, Method::GET, &format!("{}/history", uri), Some(&other), None).await.status, StatusCode::FORBIDDEN);
+
+    // 删除后重建的项没有历史
+    send(&app, Method::DELETE, &uri, token, None).await;
+    send(&app, Method::PUT, &uri, token, Some(json!({ "data": 4 }))).await;
+    assert_eq!(send(&app, Method::GET, &format!("{}/history", uri), token, None).await.body, json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_create_keeps_one_owner() {
+    let app = app().await;
+    let (alice, bob) = (new_user_token(), new_user_token());
+    for method in [Method::POST, Method::PUT].into_iter().cycle().take(40) {
+        let uri = format!("/rest/{}", unique_id("race"));
+        let [first, second] = [&alice, &bob].map(|token| {
+            let (app, method, uri, token) = (app.clone(), method.clone(), uri.clone(), token.clone());
+            tokio::spawn(async move { send(&app, method, &uri, Some(&token), Some(json!({ "data": token }))).await })
+        });
+        let (first, second) = (first.await.unwrap(), second.await.unwrap());
+        let mut statuses = [first.status, second.status];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::CREATED, StatusCode::FORBIDDEN], "{} {}", method, uri);
+
+        // 胜者的项未被另一方覆盖
+        let (winner, token) = if first.status == StatusCode::CREATED { (first, &alice) } else { (second, &bob) };
+        let stored = send(&app, Method::GET, &uri, Some(token), None).await;
+        assert_eq!(stored.body["data"], winner.body["data"]);
+        assert_eq!(stored.body["data"], json!(token));
+    }
+}
@@ -0,0 +1,301 @@
+//! `/todos` 的集成测试
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use futures_util::StreamExt;
+use rust_http_demo::{api::{events::factory_events_router, rest_todos::factory_todos_router, ws::factory_ws_router}, container::Container};
+use serde_json::json;
+use std::time::Duration;
+use tower::ServiceExt;
+
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest};
+
+use crate::{new_user_token, send, serve, unique_id};
+
+async fn app() -> Router {
+    factory_todos_router(Container::new_arc()).await
+}
+
+#[tokio::test]
+async fn crud_lifecycle() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+
+    let created = send(&app, Method::POST, "/todos", token, Some(json!({ "text": "buy milk" }))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.body["text"], "buy milk");
+    assert_eq!(created.body["completed"], false);
+    let id = created.body["id"].as_str().expect("id").to_string();
+    let uri = format!("/todos/{}", id);
+
+    let fetched = send(&app, Method::GET, &uri, token, None).await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    assert_eq!(fetched.body, created.body);
+
+    let patched = send(&app, Method::PATCH, &uri, token, Some(json!({ "text": "buy oat milk", "completed": true }))).await;
+    assert_eq!(patched.status, StatusCode::OK);
+    assert_eq!(patched.body["text"], "buy oat milk");
+    assert_eq!(patched.body["completed"], true);
+    assert_eq!(patched.body["created_at"], created.body["created_at"]);
+
+    let fetched = send(&app, Method::GET, &uri, token, None).await;
+    assert_eq!(fetched.body, patched.body); // 修改后缓存随之失效
+
+    let deleted = send(&app, Method::DELETE, &uri, token, None).await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+    assert_eq!(send(&app, Method::GET, &uri, token, None).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, Method::DELETE, &uri, token, None).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, Method::PATCH, &uri, token, Some(json!({ "text": "x" }))).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn requires_token() {
+    let app = app().await;
+    let response = send(&app, Method::GET, "/todos", None, None).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.body, json!({ "error": "invalid or missing token" }));
+
+    let response = send(&app, Method::GET, "/todos", Some("not-a-token"), None).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn post_and_put_with_id() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    let uri = format!("/todos/{}", unique_id("todo"));
+
+    let created = send(&app, Method::POST, &uri, token, Some(json!({ "text": "first" }))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    let conflict = send(&app, Method::POST, &uri, token, Some(json!({ "text": "second" }))).await;
+    assert_eq!(conflict.status, StatusCode::CONFLICT);
+    assert_eq!(conflict.body, created.body); // 返回已有的项
+
+    let replaced = send(&app, Method::PUT, &uri, token, Some(json!({ "text": "second", "completed": true }))).await;
+    assert_eq!(replaced.status, StatusCode::CREATED);
+    assert_eq!(replaced.body["text"], "second");
+    assert_eq!(replaced.body["created_at"], created.body["created_at"]); // 覆盖时保留创建时间
+
+    let created = send(&app, Method::PUT, "/todos", token, Some(json!({ "text": "random id" }))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert!(created.body["id"].as_str().is_some_and(|id| !id.is_empty()));
+}
+
+#[tokio::test]
+async fn rejects_invalid_body() {
+    let app = app().await;
+    let token = new_user_token();
+    let response = send(&app, Method::POST, "/todos", Some(&token), Some(json!({ "text": "" }))).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn users_are_isolated() {
+    let app = app().await;
+    let (alice, bob) = (new_user_token(), new_user_token());
+    let created = send(&app, Method::POST, "/todos", Some(&alice), Some(json!({ "text": "secret" }))).await;
+    let uri = format!("/todos/{}", created.body["id"].as_str().unwrap());
+
+    assert_eq!(send(&app, Method::GET, &uri, Some(&bob), None).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::PATCH, &uri, Some(&bob), Some(json!({ "text": "x" }))).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::PUT, &uri, Some(&bob), Some(json!({ "text": "x" }))).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::POST, &uri, Some(&bob), Some(json!({ "text": "x" }))).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::PATCH, &format!("{}/toggle", uri), Some(&bob), None).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, Method::DELETE, &uri, Some(&bob), None).await.status, StatusCode::FORBIDDEN);
+
+    let list = send(&app, Method::GET, "/todos", Some(&bob), None).await;
+    assert_eq!(list.body, json!([]));
+    assert_eq!(send(&app, Method::GET, &uri, Some(&alice), None).await.body["text"], "secret");
+}
+
+#[tokio::test]
+async fn list_filters_and_paginates() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    for (text, completed) in [("a", false), ("b", true), ("c", false)] {
+        send(&app, Method::POST, "/todos", token, Some(json!({ "text": text, "completed": completed }))).await;
+    }
+
+    let all = send(&app, Method::GET, "/todos", token, None).await;
+    assert_eq!(all.status, StatusCode::OK);
+    assert_eq!(all.headers["x-total-count"], "3");
+    let texts: Vec<_> = all.body.as_array().unwrap().iter().map(|item| item["text"].clone()).collect();
+    assert_eq!(texts, [json!("a"), json!("b"), json!("c")]); // 按创建顺序
+
+    let pending = send(&app, Method::GET, "/todos?completed=false", token, None).await;
+    assert_eq!(pending.headers["x-total-count"], "2");
+
+    let page = send(&app, Method::GET, "/todos?offset=1&limit=1", token, None).await;
+    assert_eq!(page.body.as_array().unwrap().len(), 1);
+    assert_eq!(page.body[0]["text"], "b");
+    assert!(page.headers.contains_key("link"));
+}
+
+#[tokio::test]
+async fn list_cursor_pagination() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    let mut ids = Vec::new();
+    for text in ["a", "b", "c"] {
+        let created = send(&app, Method::POST, "/todos", token, Some(json!({ "text": text }))).await;
+        ids.push(created.body["id"].as_str().unwrap().to_string());
+    }
+
+    let first = send(&app, Method::GET, "/todos?after=&limit=2", token, None).await;
+    assert_eq!(first.status, StatusCode::OK);
+    assert_eq!(first.body["total"], 3);
+    assert_eq!(first.body["items"].as_array().unwrap().len(), 2);
+    assert_eq!(first.body["next_cursor"], ids[1].as_str());
+    assert_eq!(first.headers["link"], format!("</todos?after={}&limit=2>; rel=\"next\"", ids[1]));
+
+    send(&app, Method::POST, "/todos", token, Some(json!({ "text": "d" }))).await; // 新增的项排在最后，不影响已翻过的项
+    let second = send(&app, Method::GET, &format!("/todos?after={}&limit=2", ids[1]), token, None).await;
+    let texts: Vec<_> = second.body["items"].as_array().unwrap().iter().map(|item| item["text"].clone()).collect();
+    assert_eq!(texts, [json!("c"), json!("d")]);
+    assert_eq!(second.body["total"], 4);
+    assert_eq!(second.body["next_cursor"], json!(null));
+    assert!(!second.headers.contains_key("link"));
+
+    let missing = send(&app, Method::GET, "/todos?after=missing", token, None).await;
+    assert_eq!(missing.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn toggle_and_toggle_all() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    let created = send(&app, Method::POST, "/todos", token, Some(json!({ "text": "a" }))).await;
+    send(&app, Method::POST, "/todos", token, Some(json!({ "text": "b" }))).await;
+    let uri = format!("/todos/{}/toggle", created.body["id"].as_str().unwrap());
+
+    let toggled = send(&app, Method::PATCH, &uri, token, None).await;
+    assert_eq!(toggled.status, StatusCode::OK);
+    assert_eq!(toggled.body["completed"], true);
+    assert_eq!(send(&app, Method::PATCH, &uri, token, None).await.body["completed"], false);
+    assert_eq!(send(&app, Method::PATCH, "/todos/missing/toggle", token, None).await.status, StatusCode::NOT_FOUND);
+
+    let result = send(&app, Method::PATCH, "/todos/toggle-all?completed=true", token, None).await;
+    assert_eq!(result.body, json!({ "updated": 2 }));
+    let result = send(&app, Method::PATCH, "/todos/toggle-all?completed=true", token, None).await;
+    assert_eq!(result.body, json!({ "updated": 0 })); // 已是该状态的项不修改
+}
+
+#[tokio::test]
+async fn move_reorders() {
+    let app = app().await;
+    let token = new_user_token();
+    let token = Some(token.as_str());
+    let mut ids = Vec::new();
+    for text in ["a", "b", "c"] {
+        let created = send(&app, Method::POST, "/todos", token, Some(json!({ "text": text }))).await;
+        ids.push(created.body["id"].as_str().unwrap().to_string());
+    }
+    let texts = |body: &serde_json::Value| body.as_array().unwrap().iter().map(|item| item["text"].clone()).collect::<Vec<_>>();
+
+    let moved = send(&app, Method::PATCH, &format!("/todos/{}/move", ids[2]), token, Some(json!({ "before_id": ids[0] }))).await;
+    assert_eq!(moved.status, StatusCode::OK);
+    assert_eq!(texts(&send(&app, Method::GET, "/todos", token, None).await.body), [json!("c"), json!("a"), json!("b")]);
+
+    send(&app, Method::PATCH, &format!("/todos/{}/move", ids[2]), token, Some(json!({ "position": 2 }))).await;
+    assert_eq!(texts(&send(&app, Method::GET, "/todos", token, None).await.body), [json!("a"), json!("b"), json!("c")]);
+
+    let uri = format!("/todos/{}/move", ids[0]);
+    let both = send(&app, Method::PATCH, &uri, token, Some(json!({ "before_id": ids[1], "position": 0 }))).await;
+    assert_eq!(both.status, StatusCode::BAD_REQUEST);
+    let missing = send(&app, Method::PATCH, &uri, token, Some(json!({ "after_id": "missing" }))).await;
+    assert_eq!(missing.body, json!({ "error": "reference item not found" }));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_create_keeps_one_owner() {
+    let app = app().await;
+    let (alice, bob) = (new_user_token(), new_user_token());
+    for method in [Method::POST, Method::PUT].into_iter().cycle().take(40) {
+        let uri = format!("/todos/{}", unique_id("race"));
+        let [first, second] = [&alice, &bob].map(|token| {
+            let (app, method, uri, token) = (app.clone(), method.clone(), uri.clone(), token.clone());
+            tokio::spawn(async move { send(&app, method, &uri, Some(&token), Some(json!({ "text": token }))).await })
+        });
+        let (first, second) = (first.await.unwrap(), second.await.unwrap());
+        let mut statuses = [first.status, second.status];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::CREATED, StatusCode::FORBIDDEN], "{} {}", method, uri);
+
+        let token = if first.status == StatusCode::CREATED { &alice } else { &bob };
+        assert_eq!(send(&app, Method::GET, &uri, Some(token), None).await.body["text"], json!(token));
+    }
+}
+
+#[tokio::test]
+async fn delete_all_is_forbidden() {
+    let app = app().await;
+    let token = new_user_token();
+    assert_eq!(send(&app, Method::DELETE, "/todos", Some(&token), None).await.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn events_only_reach_owner() {
+    let app = app().await.merge(factory_events_router()).merge(factory_ws_router());
+    assert_eq!(send(&app, Method::GET, "/events", None, None).await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(send(&app, Method::GET, "/ws", None, None).await.status, StatusCode::UNAUTHORIZED);
+
+    // 先订阅 (处理函数返回时已订阅)，再由两个用户各创建一项
+    let (owner, other) = (new_user_token(), new_user_token());
+    let request = Request::get("/events?resource=todos")
+        .header(header::AUTHORIZATION, format!("Bearer {}", owner))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let hidden = send(&app, Method::POST, "/todos", Some(&other), Some(json!({ "text": "other user's todo" }))).await;
+    let visible = send(&app, Method::POST, "/todos", Some(&owner), Some(json!({ "text": "own todo" }))).await;
+    let (hidden, visible) = (hidden.body["id"].as_str().unwrap(), visible.body["id"].as_str().unwrap());
+
+    // 事件按顺序到达，收到自己的事件时另一用户的事件 (若可见) 应已收到
+    let mut stream = response.into_body().into_data_stream();
+    let mut received = String::new();
+    while !received.contains(visible) {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), stream.next()).await
+            .expect("own event within 5s")
+            .expect("stream open")
+            .unwrap();
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+    assert!(!received.contains(hidden), "received another user's event: {}", received);
+}
+
+#[tokio::test]
+async fn browser_clients_pass_token_without_header() {
+    let app = app().await.merge(factory_events_router()).merge(factory_ws_router());
+    let token = new_user_token();
+    let status = |uri: String| {
+        let app = app.clone();
+        async move { app.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap().status() }
+    };
+    // EventSource 无法设置请求头，令牌放在查询参数中
+    assert_eq!(status(format!("/events?resource=todos&access_token={}", token)).await, StatusCode::OK);
+    assert_eq!(status("/events?access_token=invalid".to_string()).await, StatusCode::UNAUTHORIZED);
+    // 其他路由仍只接受请求头
+    assert_eq!(status(format!("/todos?access_token={}", token)).await, StatusCode::UNAUTHORIZED);
+
+    let addr = serve(app).await;
+    // new WebSocket(url, ["bearer", token])，服务端需应答子协议
+    let mut request = format!("ws://{}/ws", addr).into_client_request().unwrap();
+    request.headers_mut().insert(header::SEC_WEBSOCKET_PROTOCOL, format!("bearer, {}", token).parse().unwrap());
+    let (_, response) = tokio_tungstenite::connect_async(request).await.expect("subprotocol token accepted");
+    assert_eq!(response.headers()[header::SEC_WEBSOCKET_PROTOCOL], "bearer");
+
+    tokio_tungstenite::connect_async(format!("ws://{}/ws?access_token={}", addr, token)).await.expect("query token accepted");
+    match tokio_tungstenite::connect_async(format!("ws://{}/ws", addr)).await {
+        Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), StatusCode::UNAUTHORIZED),
+        other => panic!("expected 401, got {:?}", other.map(|(_, response)| response.status())),
+    }
+}
@@ -0,0 +1,90 @@
+//! `/ws` 的集成测试 (在随机端口上启动服务，以 WebSocket 客户端连接)
+
+use axum::{http::{Method, StatusCode}, Router};
+use futures_util::{SinkExt, StreamExt};
+use rust_http_demo::{api::{rest_node::factory_node_router, rest_todos::factory_todos_router, ws::factory_ws_router}, container::Container};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::{new_user_token, send, serve, unique_id};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn app() -> Router {
+    factory_ws_router()
+        .merge(factory_node_router().await)
+        .merge(factory_todos_router(Container::new_arc()).await)
+}
+
+/// 启动服务并以 `token` 连接
+async fn connect(app: Router, token: &str) -> Socket {
+    let addr = serve(app).await;
+    let (socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws?access_token={}", addr, token)).await.expect("connected");
+    socket
+}
+
+async fn send_json(socket: &mut Socket, value: Value) {
+    socket.send(Message::text(value.to_string())).await.expect("sent");
+}
+
+/// 接收下一条JSON消息 (跳过 ping 等控制消息)
+async fn recv_json(socket: &mut Socket) -> Value {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next()).await
+            .expect("message within 5s")
+            .expect("socket open")
+            .expect("valid message");
+        if let Message::Text(text) = message {
+            return serde_json::from_str(text.as_str()).expect("json message");
+        }
+    }
+}
+
+/// 执行不存在的节点并等待其错误，此时之前发送的消息都已处理完毕
+async fn sync(socket: &mut Socket) {
+    let id = unique_id("missing");
+    send_json(socket, json!({ "type": "run_node", "id": id })).await;
+    assert_eq!(recv_json(socket).await, json!({ "type": "node_result", "id": id, "output": null, "error": format!("node not found: {}", id) }));
+}
+
+#[tokio::test]
+async fn run_node_reports_each_step() {
+    let app = app().await;
+    let (first, second) = (unique_id("first"), unique_id("second"));
+    for (id, body) in [(&first, json!({ "next_id": second })), (&second, json!({}))] {
+        assert_eq!(send(&app, Method::PUT, &format!("/node/{}", id), None, Some(body)).await.status, StatusCode::CREATED);
+    }
+    let mut socket = connect(app, &new_user_token()).await;
+
+    send_json(&mut socket, json!({ "type": "run_node", "id": first, "input": 7 })).await;
+    assert_eq!(recv_json(&mut socket).await, json!({ "type": "node_result", "id": first, "output": 7, "error": null }));
+    assert_eq!(recv_json(&mut socket).await, json!({ "type": "node_result", "id": second, "output": 7, "error": null }));
+    assert_eq!(recv_json(&mut socket).await, json!({ "type": "run_finished", "id": first }));
+
+    send_json(&mut socket, json!({ "type": "unknown" })).await;
+    assert_eq!(recv_json(&mut socket).await["type"], "error");
+    sync(&mut socket).await;
+}
+
+#[tokio::test]
+async fn resubscribe_replaces_subscription() {
+    let app = app().await;
+    let token = new_user_token();
+    let mut socket = connect(app.clone(), &token).await;
+
+    // 订阅两次，事件只收到一次
+    for _ in 0..2 {
+        send_json(&mut socket, json!({ "type": "subscribe", "resource": "todos" })).await;
+    }
+    sync(&mut socket).await;
+    let created = send(&app, Method::POST, "/todos", Some(&token), Some(json!({ "text": "subscribed" }))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+
+    let event = recv_json(&mut socket).await;
+    assert_eq!(event["type"], "event");
+    assert_eq!(event["event"]["resource"], "todos");
+    assert_eq!(event["event"]["resource_id"], created.body["id"]);
+    sync(&mut socket).await;
+}
@@ -0,0 +1,109 @@
+//! `Container` 的属性测试
+
+use proptest::prelude::*;
+use rust_http_demo::container::Container;
+use std::collections::HashMap;
+
+/// 对容器的一次操作
+#[derive(Debug, Clone)]
+enum Op {
+    Put(String, i64),
+    Delete(String),
+}
+
+/// 键取自很小的集合，使覆盖与删除已有的键经常发生
+fn key() -> impl Strategy<Value = String> {
+    "[a-e]{1,2}"
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (key(), any::<i64>()).prop_map(|(key, value)| Op::Put(key, value)),
+        1 => key().prop_map(Op::Delete),
+    ]
+}
+
+/// 依次执行，同时在模型上执行，返回模型
+fn apply(container: &Container<i64>, ops: &[Op]) -> HashMap<String, i64> {
+    let mut model = HashMap::new();
+    for op in ops {
+        match op {
+            Op::Put(key, value) => assert_eq!(container.put_by_id(key, *value), model.insert(key.clone(), *value)),
+            Op::Delete(key) => assert_eq!(container.delete_by_id(key), model.remove(key)),
+        }
+    }
+    model
+}
+
+proptest! {
+    /// 读到的总是最后一次写入的值
+    #[test]
+    fn get_returns_last_put(ops in prop::collection::vec(op(), 0..64)) {
+        let container = Container::<i64>::new_arc();
+        let model = apply(&container, &ops);
+        for key in ops.iter().map(|op| match op { Op::Put(key, _) | Op::Delete(key) => key }) {
+            prop_assert_eq!(container.get_by_id(key), model.get(key).copied());
+        }
+    }
+
+    /// 从未写入的键删除时返回 `None`，且不影响其他项
+    #[test]
+    fn delete_missing_key_is_none(ops in prop::collection::vec(op(), 0..64), missing in "z[a-e]{0,2}") {
+        let container = Container::<i64>::new_arc();
+        let model = apply(&container, &ops);
+        prop_assert_eq!(container.delete_by_id(&missing), None);
+        prop_assert_eq!(container.len(), model.len());
+    }
+
+    /// 项数等于写入的不同键数减去删除的键数
+    #[test]
+    fn len_matches_model(ops in prop::collection::vec(op(), 0..64)) {
+        let container = Container::<i64>::new_arc();
+        let model = apply(&container, &ops);
+        let all = container.get_all();
+        prop_assert_eq!(all.len(), model.len());
+        prop_assert_eq!(container.len(), model.len());
+        prop_assert_eq!(all, model);
+    }
+
+    /// `is_empty` 当且仅当 `len() == 0`
+    #[test]
+    fn is_empty_iff_len_zero(ops in prop::collection::vec(op(), 0..16)) {
+        let container = Container::<i64>::new_arc();
+        apply(&container, &ops);
+        let len = container.len();
+        prop_assert_eq!(container.is_empty(), len == 0);
+    }
+}
+
+/// 并发的随机读写删除不会 panic (不检查结果)
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_access_does_not_panic() {
+    let container = Container::<i64>::new_arc();
+    let mut tasks = Vec::new();
+    for task in 0..100u64 {
+        let container = container.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut state = task.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1; // xorshift 的种子，不能为0
+            for _ in 0..200 {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let key = format!("k{}", state % 16);
+                match state % 3 {
+                    0 => { container.put_by_id(&key, state as i64); }
+                    1 => { container.get_by_id(&key); }
+                    _ => { container.delete_by_id(&key); }
+                }
+                if state % 8 == 0 {
+                    tokio::task::yield_now().await;
+                }
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.expect("task panicked");
+    }
+    assert!(container.len() <= 16);
+    assert!(!container.is_poisoned());
+}
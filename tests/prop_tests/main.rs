@@ -0,0 +1,5 @@
+//! 基于属性的测试 (proptest)
+//!
+//! 随机生成操作序列，与简单的模型 (`HashMap`) 对照
+
+mod container;